@@ -0,0 +1,228 @@
+use crate::composite::{check_free_axis, read_along_axis, resolve_axis};
+use crate::store::ConsolidatedStore;
+use crate::{decode_view_plane, ngff, open_array, resolve_array_path, ReadOptions, SliceView};
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use zarrs::array::Array;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// Indices along the animated axis, `start:end:stride` with any part left out for the
+/// first index, the last and every index, e.g. `0:100:5`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameRange {
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+    pub stride: u64,
+}
+
+impl Default for FrameRange {
+    fn default() -> Self {
+        Self {
+            start: None,
+            end: None,
+            stride: 1,
+        }
+    }
+}
+
+impl std::str::FromStr for FrameRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |part: &str| match part.trim() {
+            "" => Some(None),
+            part => part.parse().ok().map(Some),
+        };
+        let parts: Vec<&str> = s.split(':').collect();
+        let parsed = match parts.as_slice() {
+            [start, end] => Some((parse(start), parse(end), Some(Some(1)))),
+            [start, end, stride] => Some((parse(start), parse(end), parse(stride))),
+            _ => None,
+        };
+        match parsed {
+            Some((Some(start), Some(end), Some(stride))) if stride != Some(0) => Ok(Self {
+                start,
+                end,
+                stride: stride.unwrap_or(1),
+            }),
+            _ => anyhow::bail!(
+                "Invalid frames '{}', expected start:end or start:end:stride, e.g. 0:100:5",
+                s
+            ),
+        }
+    }
+}
+
+impl FrameRange {
+    /// The indices of the range among `size`, failing if there are none
+    pub fn indices(&self, size: u64, axis: &str) -> Result<Vec<u64>> {
+        let (start, end) = (self.start.unwrap_or(0), self.end.unwrap_or(size));
+        if start >= end || end > size {
+            anyhow::bail!(
+                "The range {}..{} for --frames is empty or outside the {} indices along {}",
+                start,
+                end,
+                size,
+                axis
+            );
+        }
+        Ok((start..end).step_by(self.stride as usize).collect())
+    }
+}
+
+/// The frames of an animation along a non-spatial axis, read one at a time
+pub struct Animation<TStore: ?Sized> {
+    array: Array<ConsolidatedStore<Arc<TStore>>>,
+    options: ReadOptions,
+    dimension: usize,
+    /// Name of the axis, or its dimension if the axes are not named
+    pub axis: String,
+    /// Indices along the axis of the frames, in order
+    pub indices: Vec<u64>,
+    /// The slice at the first index, whose crop the other frames are read at
+    pub first: SliceView,
+}
+
+/// Find the frames at the indices of `frames` along `axis`, a name of the OME-NGFF axes
+/// or a dimension, with the other slice indices of `options`, and read the first one
+pub fn open_animation<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+    axis: &str,
+    frames: FrameRange,
+) -> Result<Animation<TStore>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(ConsolidatedStore::new(store, options.consolidated));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let attributes = ngff::group_attributes(&store, ngff::parent_path(&path))?.unwrap_or_default();
+    let array = open_array(store.clone(), &path)?;
+    let shape = array.shape().to_vec();
+    let ndims = shape.len();
+    let axes = ngff::axis_names(&attributes).filter(|axes| axes.len() == ndims);
+    let (dimension, name) = resolve_axis(&shape, axes.as_deref(), axis, "--animate", "animation")?;
+    check_free_axis(options, dimension, name, "--animate", "animation axis")?;
+    let axis = name.map_or_else(|| dimension.to_string(), str::to_string);
+    let indices = frames.indices(shape[dimension], &axis)?;
+    let mut views = read_along_axis(
+        store,
+        &path,
+        &shape,
+        options,
+        dimension,
+        name,
+        &indices[..1],
+    )?;
+    Ok(Animation {
+        array,
+        options: options.clone(),
+        dimension,
+        axis,
+        indices,
+        first: views.remove(0),
+    })
+}
+
+impl<TStore> Animation<TStore>
+where
+    TStore: ReadableStorageTraits + ?Sized + 'static,
+{
+    /// Read the slice at `index` along the axis at the crop of the first frame,
+    /// through the chunk cache of the store if it has one
+    pub fn read_frame(&self, index: u64) -> Result<SliceView> {
+        let mut start = self.first.start.clone();
+        start[self.dimension] = index;
+        let (data, exact) =
+            decode_view_plane(&self.array, &self.first, start.clone(), &self.options)?;
+        Ok(SliceView {
+            data,
+            start,
+            exact,
+            ..self.first.clone()
+        })
+    }
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catch Ctrl-C from now on instead of exiting, to stop an animation and restore the terminal
+pub fn catch_interrupt() {
+    #[cfg(unix)]
+    {
+        extern "C" fn handle(_: libc::c_int) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+        // Only an atomic store happens in the handler, which is async-signal-safe
+        unsafe {
+            libc::signal(libc::SIGINT, handle as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+/// Whether Ctrl-C was pressed since `catch_interrupt`
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_store;
+    use crate::SliceIndex;
+
+    #[test]
+    fn test_parse_frames() -> Result<()> {
+        let frames: FrameRange = "0:100:5".parse()?;
+        assert_eq!(
+            frames.indices(200, "t")?,
+            (0..100).step_by(5).collect::<Vec<_>>()
+        );
+        assert_eq!("2:".parse::<FrameRange>()?.indices(4, "t")?, vec![2, 3]);
+        assert_eq!("::2".parse::<FrameRange>()?.indices(5, "t")?, vec![0, 2, 4]);
+        assert!("0:10:0".parse::<FrameRange>().is_err());
+        assert!("10".parse::<FrameRange>().is_err());
+        let error = "5:20"
+            .parse::<FrameRange>()?
+            .indices(10, "t")
+            .expect_err("past the end");
+        assert_eq!(
+            error.to_string(),
+            "The range 5..20 for --frames is empty or outside the 10 indices along t"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_frames() -> Result<()> {
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [{"name": "t", "type": "time"}, {"name": "c", "type": "channel"}, {"name": "y"}, {"name": "x"}],
+                "datasets": [{"path": "0"}]
+            }]
+        });
+        let store = image_store(attributes, &[4, 2, 4, 4], &[1, 1, 4, 4])?;
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("c".to_string(), 1.into())]),
+            ..Default::default()
+        };
+        let animation = open_animation(store.clone(), None, &options, "t", "1::2".parse()?)?;
+        assert_eq!(animation.axis, "t");
+        assert_eq!(animation.indices, vec![1, 3]);
+        assert_eq!(animation.first.data[[0, 0]], 11.0);
+        let frame = animation.read_frame(3)?;
+        assert_eq!(frame.start, vec![3, 1, 0, 0]);
+        assert_eq!(frame.data[[0, 0]], 31.0);
+        let error = open_animation(store, None, &options, "c", FrameRange::default())
+            .err()
+            .expect("sliced axis");
+        assert_eq!(
+            error.to_string(),
+            "Cannot combine --animate with a slice index for the animation axis 'c'"
+        );
+        Ok(())
+    }
+}
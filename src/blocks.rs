@@ -0,0 +1,299 @@
+use anyhow::Result;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbaImage};
+use std::fmt::Write;
+
+/// Upper half block, colored with the top pixel as foreground and the bottom as background
+const UPPER_HALF: char = '▀';
+
+/// Lower half block, for cells whose top pixel is transparent
+const LOWER_HALF: char = '▄';
+
+/// Levels of each channel in the 6x6x6 color cube of the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Thresholds of a 4x4 Bayer matrix for ordered dithering, out of 16
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// How colors are written to the terminal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Palette {
+    /// 24-bit colors, exactly as rendered
+    TrueColor,
+    /// The xterm 256-color palette, whose 24 grays and 6 cube levels band smooth ramps
+    Ansi256,
+}
+
+impl Palette {
+    /// True colors if `COLORTERM` says the terminal has them, the 256-color palette otherwise
+    pub fn detect() -> Self {
+        match std::env::var("COLORTERM").as_deref() {
+            Ok("truecolor" | "24bit") => Self::TrueColor,
+            _ => Self::Ansi256,
+        }
+    }
+}
+
+/// Dithering of the 256-color palette, as given to `--dither`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Dither {
+    None,
+    /// A fixed 4x4 pattern, stable from frame to frame
+    #[default]
+    Ordered,
+    /// Error diffusion, smoother but shimmering in animations
+    FloydSteinberg,
+}
+
+impl std::str::FromStr for Dither {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "none" => Ok(Self::None),
+            "ordered" => Ok(Self::Ordered),
+            "floyd-steinberg" => Ok(Self::FloydSteinberg),
+            _ => anyhow::bail!(
+                "Unknown dither '{}', use none, ordered or floyd-steinberg",
+                s
+            ),
+        }
+    }
+}
+
+/// The color of a pixel as written
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Color {
+    Rgb([u8; 3]),
+    Indexed(u8),
+}
+
+/// Index and value of the palette color closest to `rgb`, from the color cube or the grays
+fn nearest_256(rgb: [f32; 3]) -> (u8, [u8; 3]) {
+    let level = |value: f32| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as f32 - value).abs() as u32)
+            .unwrap_or(0)
+    };
+    let [r, g, b] = rgb.map(level);
+    let cube = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+    let mean = (rgb[0] + rgb[1] + rgb[2]) / 3.0;
+    let gray_index = ((mean - 8.0) / 10.0).round().clamp(0.0, 23.0) as u8;
+    let gray = [8 + 10 * gray_index; 3];
+    let distance =
+        |color: [u8; 3]| -> f32 { (0..3).map(|i| (color[i] as f32 - rgb[i]).powi(2)).sum() };
+    match distance(gray) < distance(cube) {
+        true => (232 + gray_index, gray),
+        false => (16 + 36 * r as u8 + 6 * g as u8 + b as u8, cube),
+    }
+}
+
+/// Spacing of the palette colors around `rgb`: the 24 grays are closer than the cube levels
+fn palette_step(rgb: [f32; 3]) -> f32 {
+    let spread =
+        rgb.iter().copied().fold(f32::MIN, f32::max) - rgb.iter().copied().fold(f32::MAX, f32::min);
+    match spread < 16.0 {
+        true => 10.0,
+        false => 40.0,
+    }
+}
+
+/// Colors of the opaque pixels of `image` in `palette`, dithered with `dither` in 256 colors
+fn quantize(image: &RgbaImage, palette: Palette, dither: Dither) -> Vec<Option<Color>> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut values: Vec<[f32; 3]> = image
+        .pixels()
+        .map(|pixel| [0, 1, 2].map(|i| pixel.0[i] as f32))
+        .collect();
+    let opaque = |x: usize, y: usize| image.get_pixel(x as u32, y as u32).0[3] >= 128;
+    let mut colors = vec![None; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            if !opaque(x, y) {
+                continue;
+            }
+            let value = values[y * width + x];
+            if palette == Palette::TrueColor {
+                colors[y * width + x] = Some(Color::Rgb(value.map(|v| v as u8)));
+                continue;
+            }
+            let shifted = match dither {
+                Dither::Ordered => {
+                    let threshold = (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+                    let step = palette_step(value);
+                    value.map(|v| v + threshold * step)
+                }
+                _ => value,
+            };
+            let (index, chosen) = nearest_256(shifted.map(|v| v.clamp(0.0, 255.0)));
+            colors[y * width + x] = Some(Color::Indexed(index));
+            if dither == Dither::FloydSteinberg {
+                let error = [0, 1, 2].map(|i| value[i] - chosen[i] as f32);
+                let mut spread = |dx: isize, dy: usize, weight: f32| {
+                    let (x, y) = (x as isize + dx, y + dy);
+                    if x >= 0 && (x as usize) < width && y < height {
+                        let target = &mut values[y * width + x as usize];
+                        for i in 0..3 {
+                            target[i] += error[i] * weight;
+                        }
+                    }
+                };
+                spread(1, 0, 7.0 / 16.0);
+                spread(-1, 1, 3.0 / 16.0);
+                spread(0, 1, 5.0 / 16.0);
+                spread(1, 1, 1.0 / 16.0);
+            }
+        }
+    }
+    colors
+}
+
+fn sgr(output: &mut String, color: Option<Color>, background: bool) {
+    let layer = if background { 4 } else { 3 };
+    let _ = match color {
+        Some(Color::Rgb([r, g, b])) => write!(output, "\x1b[{}8;2;{};{};{}m", layer, r, g, b),
+        Some(Color::Indexed(index)) => write!(output, "\x1b[{}8;5;{}m", layer, index),
+        None => write!(output, "\x1b[{}9m", layer),
+    };
+}
+
+/// Escape sequences drawing `image` with one upper half block per two pixels stacked,
+/// changing colors only where they differ from the previous cell,
+/// with transparent pixels (alpha below half) left to the terminal background
+pub fn render(image: &RgbaImage, palette: Palette, dither: Dither) -> String {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let colors = quantize(image, palette, dither);
+    let mut output = String::new();
+    for y in (0..height).step_by(2) {
+        let mut previous = None;
+        for x in 0..width {
+            let top = colors[y * width + x];
+            let bottom = match y + 1 < height {
+                true => colors[(y + 1) * width + x],
+                false => None,
+            };
+            // A transparent top shows the bottom in the lower half block instead
+            let (glyph, foreground, background) = match (top, bottom) {
+                (None, None) => (' ', None, None),
+                (None, Some(_)) => (LOWER_HALF, bottom, None),
+                _ => (UPPER_HALF, top, bottom),
+            };
+            let (last_foreground, last_background) = previous.unzip();
+            if last_foreground != Some(foreground) {
+                sgr(&mut output, foreground, false);
+            }
+            if last_background != Some(background) {
+                sgr(&mut output, background, true);
+            }
+            previous = Some((foreground, background));
+            output.push(glyph);
+        }
+        output.push_str("\x1b[0m\n");
+    }
+    output
+}
+
+/// Shrink `image` to fit within `columns` by `rows` character cells at two pixels per cell
+/// vertically, keeping its aspect ratio, and render it with [`render`],
+/// returning the text with its width and height in cells
+pub fn render_fit(
+    image: &DynamicImage,
+    (columns, rows): (u16, u16),
+    palette: Palette,
+    dither: Dither,
+) -> (String, (u32, u32)) {
+    let (width, height) = (image.width().max(1), image.height().max(1));
+    let scale = (columns as f64 / width as f64)
+        .min(2.0 * rows.max(1) as f64 / height as f64)
+        .min(1.0);
+    let size = |length: u32| ((length as f64 * scale).floor() as u32).max(1);
+    let (fitted_width, fitted_height) = (size(width), size(height));
+    let fitted = imageops::resize(
+        &image.to_rgba8(),
+        fitted_width,
+        fitted_height,
+        FilterType::Triangle,
+    );
+    let text = render(&fitted, palette, dither);
+    (text, (fitted_width, fitted_height.div_ceil(2)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn gradient(width: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, 2, |x, _| {
+            let value = (x * 255 / (width - 1)) as u8;
+            Rgba([value, value, value, 255])
+        })
+    }
+
+    #[test]
+    fn test_render_true_color() {
+        let image = RgbaImage::from_fn(2, 3, |x, y| Rgba([x as u8, y as u8, 7, 255]));
+        let text = render(&image, Palette::TrueColor, Dither::None);
+        assert_eq!(
+            text,
+            "\x1b[38;2;0;0;7m\x1b[48;2;0;1;7m▀\x1b[38;2;1;0;7m\x1b[48;2;1;1;7m▀\x1b[0m\n\
+             \x1b[38;2;0;2;7m\x1b[49m▀\x1b[38;2;1;2;7m▀\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_render_256_colors() -> Result<()> {
+        let image = gradient(4);
+        // Black, two grays and white, the same in both rows
+        let text = render(&image, Palette::Ansi256, Dither::None);
+        assert_eq!(
+            text,
+            "\x1b[38;5;16m\x1b[48;5;16m▀\x1b[38;5;240m\x1b[48;5;240m▀\
+             \x1b[38;5;248m\x1b[48;5;248m▀\x1b[38;5;231m\x1b[48;5;231m▀\x1b[0m\n"
+        );
+        assert_eq!(nearest_256([255.0, 0.0, 0.0]), (196, [255, 0, 0]));
+        // A flat gray between two palette grays is dithered into both, keeping its mean
+        let flat = RgbaImage::from_pixel(16, 16, Rgba([113, 113, 113, 255]));
+        for dither in ["ordered", "floyd-steinberg"] {
+            let colors = quantize(&flat, Palette::Ansi256, dither.parse()?);
+            let grays: Vec<f32> = colors
+                .iter()
+                .map(|color| match color {
+                    Some(Color::Indexed(index)) => 8.0 + 10.0 * (*index - 232) as f32,
+                    _ => f32::NAN,
+                })
+                .collect();
+            let mean = grays.iter().sum::<f32>() / grays.len() as f32;
+            assert!((mean - 113.0).abs() < 1.5, "{} {}", dither, mean);
+            assert!(
+                grays.contains(&108.0) && grays.contains(&118.0),
+                "{}",
+                dither
+            );
+        }
+        let undithered = quantize(&flat, Palette::Ansi256, Dither::None);
+        assert!(undithered.iter().all(|color| *color == undithered[0]));
+        assert!("random".parse::<Dither>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_fit() {
+        let image = DynamicImage::ImageRgba8(gradient(40));
+        let (text, size) = render_fit(&image, (20, 10), Palette::TrueColor, Dither::None);
+        assert_eq!(size, (20, 1));
+        assert_eq!(text.lines().count(), 1);
+        assert_eq!(text.matches(UPPER_HALF).count(), 20);
+        // Small images are not enlarged
+        let small = DynamicImage::ImageRgba8(gradient(4));
+        assert_eq!(
+            render_fit(&small, (20, 10), Palette::Ansi256, Dither::None).1,
+            (4, 1)
+        );
+        // Transparent pixels are left to the terminal
+        let mut image = gradient(2);
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+        let text = render(&image, Palette::TrueColor, Dither::None);
+        assert!(text.starts_with("\x1b[38;2;0;0;0m\x1b[49m▄"));
+    }
+}
@@ -0,0 +1,350 @@
+use crate::projection::ProjectionSpan;
+use crate::scalebar::{draw_text, text_width};
+use crate::{ngff, Scale, SliceView, Window};
+use anyhow::Result;
+use image::RgbImage;
+
+/// What a rendered slice shows, for the line printed under it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caption {
+    /// Path of the image as given, shortened to its last components if long
+    pub path: String,
+    /// Index of the resolution level, or its path if not in the multiscales metadata
+    pub level: String,
+    /// Name and selected index of each non-spatial axis
+    pub indices: Vec<(String, u64)>,
+    /// Label of the selected channel from the `omero` metadata
+    pub channel: Option<String>,
+    /// Start and end of the crop along Y and X
+    pub extent: [(u64, u64); 2],
+    /// Values mapped to black and white, `None` for label images
+    pub window: Option<Window>,
+    /// Spacing of the intensities in the window
+    pub scale: Scale,
+    /// The planes of a projected slice, whose axis is left out of the indices
+    pub projection: Option<ProjectionSpan>,
+    /// Clock time the slice was last read at, when watching it
+    pub updated: Option<String>,
+}
+
+/// The last three components of a long path, e.g. `…/A/1/0`
+fn shorten(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.chars().count() <= 40 {
+        return trimmed.to_string();
+    }
+    let components: Vec<&str> = trimmed.rsplitn(4, '/').collect();
+    match components.as_slice() {
+        [last, parent, grandparent, _] => format!("…/{}/{}/{}", grandparent, parent, last),
+        _ => trimmed.to_string(),
+    }
+}
+
+impl Caption {
+    /// The caption of a slice read from `image_path`, as read before aspect correction
+    pub fn new(image_path: &str, view: &SliceView, window: Option<Window>, scale: Scale) -> Self {
+        let ndims = view.shape.len();
+        let names: Vec<String> = match &view.axes {
+            Some(axes) if axes.len() == ndims => axes.clone(),
+            _ => (0..ndims).map(|i| format!("dim{}", i)).collect(),
+        };
+        let level = ngff::dataset_paths(&view.attributes)
+            .and_then(|paths| paths.iter().position(|path| path == view.dataset_path()))
+            .map_or_else(
+                || view.dataset_path().to_string(),
+                |level| level.to_string(),
+            );
+        Self {
+            path: shorten(image_path),
+            level,
+            indices: names[..ndims - 2]
+                .iter()
+                .cloned()
+                .zip(view.start.iter().copied())
+                .collect(),
+            channel: view.channel_name(),
+            extent: view.extent(),
+            window,
+            scale,
+            projection: None,
+            updated: None,
+        }
+    }
+
+    /// Value of a placeholder, formatted with `precision` decimals if given for numbers
+    fn value(&self, name: &str, precision: Option<usize>) -> Option<String> {
+        let number = |value: f32| match precision {
+            Some(precision) => format!("{:.*}", precision, value),
+            None => value.to_string(),
+        };
+        let [(y0, y1), (x0, x1)] = self.extent;
+        let value = match name {
+            "path" => self.path.clone(),
+            "level" => self.level.clone(),
+            "channel" => self.channel.clone().unwrap_or_default(),
+            "extent" => format!("y={}..{} x={}..{}", y0, y1, x0, x1),
+            "min" => self
+                .window
+                .map(|window| number(window.min))
+                .unwrap_or_default(),
+            "max" => self
+                .window
+                .map(|window| number(window.max))
+                .unwrap_or_default(),
+            "source" => self
+                .window
+                .map(|window| window.source.to_string())
+                .unwrap_or_default(),
+            "scale" => match self.scale {
+                Scale::Linear => "linear".to_string(),
+                Scale::Log => "log".to_string(),
+            },
+            "projection" => self
+                .projection
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            "updated" => self.updated.clone().unwrap_or_default(),
+            _ => self
+                .indices
+                .iter()
+                .find(|(axis, _)| axis == name)?
+                .1
+                .to_string(),
+        };
+        Some(value)
+    }
+
+    /// Fill in a template such as `{path} {level} t={t} z={z} [{min:.1}-{max:.1}]`,
+    /// with `{{` and `}}` for literal braces
+    pub fn format(&self, template: &str) -> Result<String> {
+        let mut caption = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            caption.push_str(&rest[..start]);
+            let tail = &rest[start..];
+            if tail.starts_with("{{") || tail.starts_with("}}") {
+                caption.push_str(&tail[..1]);
+                rest = &tail[2..];
+                continue;
+            }
+            let Some(end) = tail.find('}').filter(|_| tail.starts_with('{')) else {
+                anyhow::bail!("Unmatched brace in caption template '{}'", template);
+            };
+            let placeholder = &tail[1..end];
+            let (name, precision) = match placeholder.split_once(":.") {
+                Some((name, precision)) => {
+                    let precision = precision.parse().map_err(|_| {
+                        anyhow::anyhow!("Invalid precision in placeholder '{{{}}}'", placeholder)
+                    })?;
+                    (name, Some(precision))
+                }
+                None => (placeholder, None),
+            };
+            let Some(value) = self.value(name, precision) else {
+                let axes: Vec<&str> = self.indices.iter().map(|(axis, _)| axis.as_str()).collect();
+                anyhow::bail!(
+                    "Unknown placeholder '{{{}}}' in caption template, use one of path, level, \
+                     channel, extent, min, max, source, scale, projection, updated \
+                     or a non-spatial axis ({})",
+                    name,
+                    axes.join(", ")
+                );
+            };
+            caption.push_str(&value);
+            rest = &tail[end + 1..];
+        }
+        caption.push_str(rest);
+        Ok(caption)
+    }
+}
+
+impl std::fmt::Display for Caption {
+    /// The path, level, indices, crop and window on one line,
+    /// e.g. `image.zarr  level 0  t=12 c=1 (GFP)  y=0..256 x=0..256  [1103-1286 quantiles 0.001-0.999]`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}  level {}", self.path, self.level)?;
+        let projected = self.projection.as_ref().map(|span| span.dimension);
+        let indices: Vec<String> = self
+            .indices
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != projected)
+            .map(|(_, (axis, index))| format!("{}={}", axis, index))
+            .collect();
+        if !indices.is_empty() {
+            write!(f, "  {}", indices.join(" "))?;
+            if let Some(channel) = &self.channel {
+                write!(f, " ({})", channel)?;
+            }
+        }
+        if let Some(span) = &self.projection {
+            write!(f, "  {}", span)?;
+        }
+        let [(y0, y1), (x0, x1)] = self.extent;
+        write!(f, "  y={}..{} x={}..{}", y0, y1, x0, x1)?;
+        if let Some(window) = self.window {
+            write!(f, "  [{}-{} {}", window.min, window.max, window.source)?;
+            match self.scale {
+                Scale::Linear => write!(f, "]")?,
+                Scale::Log => write!(f, " log]")?,
+            }
+        }
+        if let Some(updated) = &self.updated {
+            write!(f, "  updated {}", updated)?;
+        }
+        Ok(())
+    }
+}
+
+/// The local time of day of `time`, e.g. `14:05:09`, or UTC where it is not known
+pub fn clock_time(time: std::time::SystemTime) -> String {
+    let seconds = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    #[cfg(unix)]
+    {
+        let time = seconds as libc::time_t;
+        // Zeroed is a valid tm, filled in by localtime_r, which is thread-safe
+        let mut local: libc::tm = unsafe { std::mem::zeroed() };
+        if !unsafe { libc::localtime_r(&time, &mut local) }.is_null() {
+            return format!(
+                "{:02}:{:02}:{:02}",
+                local.tm_hour, local.tm_min, local.tm_sec
+            );
+        }
+    }
+    let day = seconds % 86400;
+    format!("{:02}:{:02}:{:02}", day / 3600, day / 60 % 60, day % 60)
+}
+
+/// Append a black strip with the caption in capitals under the image, for images written
+/// to files, cut short to fit the width of the image
+pub fn append_caption(image: &RgbImage, caption: &str) -> RgbImage {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    // Sized relative to the image like the colorbar labels
+    let scale = (width.max(height) / 120).max(1);
+    let mut text = caption.to_uppercase();
+    while text_width(&text) * scale + 2 * scale > width && text.pop().is_some() {}
+    let mut captioned = RgbImage::new(width as u32, (height + 7 * scale) as u32);
+    image::imageops::replace(&mut captioned, image, 0, 0);
+    draw_text(&mut captioned, &text, scale, height + scale, scale);
+    captioned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+
+    fn caption() -> Caption {
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [{"name": "t"}, {"name": "c", "type": "channel"}, {"name": "y"}, {"name": "x"}],
+                "datasets": [{"path": "s0"}, {"path": "s1"}]
+            }],
+            "omero": {"channels": [{"label": "BF"}, {"label": "GFP"}]}
+        });
+        let view = SliceView {
+            data: Array2::zeros((64, 32)),
+            path: "/s1".to_string(),
+            axes: Some(["t", "c", "y", "x"].map(String::from).to_vec()),
+            start: vec![12, 1, 16, 0],
+            shape: vec![20, 2, 128, 128],
+            data_type: "uint16".to_string(),
+            attributes: attributes.as_object().cloned().unwrap_or_default(),
+            step: 1,
+            exact: None,
+        };
+        let window = Window {
+            min: 100.25,
+            max: 2000.0,
+            source: crate::WindowSource::Quantiles {
+                low: 0.001,
+                high: 0.999,
+            },
+        };
+        Caption::new("image.zarr", &view, Some(window), Scale::Linear)
+    }
+
+    #[test]
+    fn test_caption() {
+        let caption = caption();
+        assert_eq!(caption.level, "1");
+        assert_eq!(
+            caption.to_string(),
+            "image.zarr  level 1  t=12 c=1 (GFP)  y=16..80 x=0..32  [100.25-2000 quantiles 0.001-0.999]"
+        );
+        assert_eq!(
+            shorten("s3://bucket/some/very/long/prefix/to/experiment/plate.zarr/A/1/0"),
+            "…/A/1/0"
+        );
+    }
+
+    #[test]
+    fn test_caption_template() -> Result<()> {
+        let caption = caption();
+        assert_eq!(
+            caption.format("{path} {level} t={t} {channel} [{min:.1}-{max:.1}] {{x}}")?,
+            "image.zarr 1 t=12 GFP [100.2-2000.0] {x}"
+        );
+        assert_eq!(caption.format("{extent}")?, "y=16..80 x=0..32");
+        let log = Caption {
+            scale: Scale::Log,
+            ..caption.clone()
+        };
+        assert!(log
+            .to_string()
+            .ends_with("[100.25-2000 quantiles 0.001-0.999 log]"));
+        let projected = Caption {
+            projection: Some(ProjectionSpan {
+                projection: crate::projection::Projection::Max,
+                dimension: 0,
+                axis: "t".to_string(),
+                start: 0,
+                end: 20,
+                colormap: None,
+            }),
+            ..caption.clone()
+        };
+        assert_eq!(
+            projected.to_string(),
+            "image.zarr  level 1  c=1 (GFP)  max projection t=0..20  y=16..80 x=0..32  \
+             [100.25-2000 quantiles 0.001-0.999]"
+        );
+        assert_eq!(projected.format("{projection}")?, "max projection t=0..20");
+        let watched = Caption {
+            updated: Some("14:05:09".to_string()),
+            ..caption.clone()
+        };
+        assert!(watched
+            .to_string()
+            .ends_with("quantiles 0.001-0.999]  updated 14:05:09"));
+        assert_eq!(watched.format("{updated}")?, "14:05:09");
+        assert_eq!(caption.format("{source}")?, "quantiles 0.001-0.999");
+        assert_eq!(log.format("{scale}")?, "log");
+        let err = caption.format("z={z}").expect_err("no z axis");
+        assert!(err.to_string().contains("a non-spatial axis (t, c)"));
+        assert!(caption.format("{path").is_err());
+        assert!(caption.format("{min:.x}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_clock_time() {
+        let time = clock_time(std::time::SystemTime::now());
+        assert_eq!(time.len(), 8);
+        assert_eq!(time.matches(':').count(), 2);
+    }
+
+    #[test]
+    fn test_append_caption() {
+        let image = RgbImage::from_pixel(20, 10, image::Rgb([255, 0, 0]));
+        let captioned = append_caption(&image, "t=1 and more than fits");
+        assert_eq!(captioned.dimensions(), (20, 17));
+        assert_eq!(captioned.get_pixel(0, 9).0, [255, 0, 0]);
+        // The T at the start and nothing past the edge
+        assert_eq!(captioned.get_pixel(1, 11).0, [255, 255, 255]);
+        assert_eq!(captioned.get_pixel(19, 11).0, [0, 0, 0]);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::info::{join_dims, ArrayInfo};
+use crate::scalebar::fill;
+use image::{Rgb, RgbImage};
+use std::fmt;
+
+/// Color of the chunk boundaries, or of the inner chunks of a sharded array
+pub const CHUNK_COLOR: [u8; 3] = [0, 255, 255];
+/// Color of the shard boundaries of a sharded array
+pub const SHARD_COLOR: [u8; 3] = [255, 255, 0];
+
+/// The chunks, and shards if sharded, of the array a crop is read from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkGrid {
+    /// Start and end of the crop along Y and X
+    pub extent: [(u64, u64); 2],
+    /// Shape of the chunks, the inner chunks if sharded
+    pub chunk_shape: Vec<u64>,
+    /// Shape of the shards if sharded
+    pub shard_shape: Option<Vec<u64>>,
+}
+
+/// Positions of the multiples of `size` strictly between `start` and `end`
+fn boundaries((start, end): (u64, u64), size: u64) -> impl Iterator<Item = u64> {
+    (start / size + 1..end.div_ceil(size)).map(move |index| index * size)
+}
+
+/// The sizes along Y and X of a chunk shape
+fn spatial(shape: &[u64]) -> [u64; 2] {
+    let ndims = shape.len();
+    [shape[ndims - 2], shape[ndims - 1]].map(|size| size.max(1))
+}
+
+impl ChunkGrid {
+    /// The chunk grid of the array described by `info` over the crop `extent`
+    pub fn new(info: &ArrayInfo, extent: [(u64, u64); 2]) -> Self {
+        match &info.inner_chunk_shape {
+            Some(inner) => Self {
+                extent,
+                chunk_shape: inner.clone(),
+                shard_shape: Some(info.chunk_shape.clone()),
+            },
+            None => Self {
+                extent,
+                chunk_shape: info.chunk_shape.clone(),
+                shard_shape: None,
+            },
+        }
+    }
+
+    /// Number of chunks of `shape` intersected by the crop
+    fn count(&self, shape: &[u64]) -> u64 {
+        self.extent
+            .iter()
+            .zip(spatial(shape))
+            .map(|(&(start, end), size)| end.div_ceil(size).saturating_sub(start / size))
+            .product()
+    }
+
+    /// Draw 1-pixel lines where the chunk and shard boundaries inside the crop fall
+    /// in the image of it, which may be resampled from the crop
+    pub fn draw(&self, image: &mut RgbImage) {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let [rows, columns] = self.extent;
+        // Shards over chunks, where their boundaries coincide
+        let grids = [
+            Some((&self.chunk_shape, CHUNK_COLOR)),
+            self.shard_shape.as_ref().map(|shape| (shape, SHARD_COLOR)),
+        ];
+        for (shape, color) in grids.into_iter().flatten() {
+            let [chunk_rows, chunk_columns] = spatial(shape);
+            let position = |boundary: u64, (start, end): (u64, u64), pixels: usize| {
+                ((boundary - start) as f64 * pixels as f64 / (end - start) as f64).round() as usize
+            };
+            for boundary in boundaries(rows, chunk_rows) {
+                fill(
+                    image,
+                    0,
+                    position(boundary, rows, height),
+                    width,
+                    1,
+                    Rgb(color),
+                );
+            }
+            for boundary in boundaries(columns, chunk_columns) {
+                let column = position(boundary, columns, width);
+                fill(image, column, 0, 1, height, Rgb(color));
+            }
+        }
+    }
+}
+
+impl fmt::Display for ChunkGrid {
+    /// The number of chunks in the crop, e.g. `Chunks: 4 of 1x1x32x32 in the crop`,
+    /// followed by the number of shards if sharded
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Chunks: {} of {} in the crop",
+            self.count(&self.chunk_shape),
+            join_dims(&self.chunk_shape)
+        )?;
+        if let Some(shard_shape) = &self.shard_shape {
+            write!(
+                f,
+                ", in {} shards of {}",
+                self.count(shard_shape),
+                join_dims(shard_shape)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_grid() {
+        let grid = ChunkGrid {
+            extent: [(16, 80), (0, 64)],
+            chunk_shape: vec![1, 32, 32],
+            shard_shape: Some(vec![1, 64, 64]),
+        };
+        assert_eq!(boundaries((16, 80), 32).collect::<Vec<_>>(), vec![32, 64]);
+        assert_eq!(boundaries((0, 64), 32).collect::<Vec<_>>(), vec![32]);
+        assert_eq!(boundaries((0, 64), 64).count(), 0);
+        assert_eq!(
+            grid.to_string(),
+            "Chunks: 6 of 1x32x32 in the crop, in 2 shards of 1x64x64"
+        );
+
+        // Shown at half the size of the crop
+        let mut image = RgbImage::new(32, 32);
+        grid.draw(&mut image);
+        // Chunk boundaries at Y 32 and X 32
+        assert_eq!(image.get_pixel(0, 8).0, CHUNK_COLOR);
+        assert_eq!(image.get_pixel(16, 0).0, CHUNK_COLOR);
+        // The shard boundary at Y 64 replaces the chunk boundary there
+        assert_eq!(image.get_pixel(0, 24).0, SHARD_COLOR);
+        assert_eq!(image.get_pixel(3, 3).0, [0, 0, 0]);
+    }
+}
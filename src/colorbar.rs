@@ -0,0 +1,197 @@
+use crate::scalebar::{draw_text, fill, text_width};
+use crate::Scale;
+use anyhow::Result;
+use image::{Rgb, RgbImage};
+
+/// Where to draw a colorbar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Colorbar {
+    Off,
+    /// Beside the image if there is room for it in the terminal, below it otherwise
+    Auto,
+    Vertical,
+    Horizontal,
+}
+
+impl std::str::FromStr for Colorbar {
+    type Err = anyhow::Error;
+
+    /// Parse `off`, `auto`, `vertical` or `horizontal`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "off" => Ok(Self::Off),
+            "auto" => Ok(Self::Auto),
+            "vertical" => Ok(Self::Vertical),
+            "horizontal" => Ok(Self::Horizontal),
+            _ => anyhow::bail!(
+                "Unknown colorbar '{}', use off, auto, vertical or horizontal",
+                s
+            ),
+        }
+    }
+}
+
+/// Label a value in at most 4 significant digits, in scientific notation if very large or small
+pub fn format_value(value: f32) -> String {
+    let magnitude = value.abs();
+    if value == 0.0 {
+        "0".to_string()
+    } else if (1e-2..1e5).contains(&magnitude) {
+        let decimals = (3 - magnitude.log10().floor() as i32).max(0) as usize;
+        let value = format!("{:.*}", decimals, value);
+        if value.contains('.') {
+            value
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        } else {
+            value
+        }
+    } else {
+        format!("{:.2e}", value)
+    }
+}
+
+/// Append a strip of the lookup table to the image, from `low` to `high`,
+/// labelled with both values at its ends and `log` in the middle for a logarithmic scale
+pub fn append_colorbar(
+    image: &RgbImage,
+    lut: &[[u8; 3]],
+    (low, high): (f32, f32),
+    scale: Scale,
+    vertical: bool,
+) -> RgbImage {
+    let middle = match scale {
+        Scale::Linear => "",
+        Scale::Log => "log",
+    };
+    let (low, high) = (format_value(low), format_value(high));
+    append_labelled_colorbar(image, lut, [&low, middle, &high], vertical)
+}
+
+/// Append a strip of the lookup table to the image, with the labels of its low end,
+/// middle and high end
+pub fn append_labelled_colorbar(
+    image: &RgbImage,
+    lut: &[[u8; 3]],
+    [low, middle, high]: [&str; 3],
+    vertical: bool,
+) -> RgbImage {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    // Sized relative to the image, which is shrunk to fit the terminal
+    let scale = (width.max(height) / 120).max(1);
+    let margin = 2 * scale;
+    let text_height = 5 * scale;
+    let color = |position: usize, length: usize| {
+        let index = position * (lut.len() - 1) / (length - 1).max(1);
+        Rgb(lut[index])
+    };
+    if vertical {
+        let thickness = (height / 16).max(4);
+        let label_width = [low, high, middle]
+            .map(text_width)
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+            * scale;
+        let mut bar = RgbImage::new(
+            (width + 2 * margin + thickness + margin + label_width + margin) as u32,
+            height as u32,
+        );
+        image::imageops::replace(&mut bar, image, 0, 0);
+        let left = width + 2 * margin;
+        for row in 0..height {
+            // The high end at the top
+            fill(
+                &mut bar,
+                left,
+                row,
+                thickness,
+                1,
+                color(height - 1 - row, height),
+            );
+        }
+        let text_left = left + thickness + margin;
+        draw_text(&mut bar, high, text_left, 0, scale);
+        draw_text(
+            &mut bar,
+            low,
+            text_left,
+            height.saturating_sub(text_height),
+            scale,
+        );
+        let middle_top = height.saturating_sub(text_height) / 2;
+        draw_text(&mut bar, middle, text_left, middle_top, scale);
+        bar
+    } else {
+        let thickness = (width / 16).max(4);
+        let mut bar = RgbImage::new(
+            width as u32,
+            (height + 2 * margin + thickness + margin + text_height) as u32,
+        );
+        image::imageops::replace(&mut bar, image, 0, 0);
+        let top = height + 2 * margin;
+        for column in 0..width {
+            fill(&mut bar, column, top, 1, thickness, color(column, width));
+        }
+        let text_top = top + thickness + margin;
+        draw_text(&mut bar, low, 0, text_top, scale);
+        let high_left = width.saturating_sub(text_width(high) * scale);
+        draw_text(&mut bar, high, high_left, text_top, scale);
+        let middle_left = width.saturating_sub(text_width(middle) * scale) / 2;
+        draw_text(&mut bar, middle, middle_left, text_top, scale);
+        bar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_value(0.0), "0");
+        assert_eq!(format_value(1103.0), "1103");
+        assert_eq!(format_value(12.3456), "12.35");
+        assert_eq!(format_value(-0.5), "-0.5");
+        assert_eq!(format_value(65535.0), "65535");
+        assert_eq!(format_value(123456.0), "1.23e5");
+        assert_eq!(format_value(1e-4), "1.00e-4");
+    }
+
+    #[test]
+    fn test_append_colorbar() {
+        let lut: Vec<[u8; 3]> = (0..=255).map(|value| [value, 0, 255 - value]).collect();
+        let image = RgbImage::from_pixel(32, 64, Rgb([9, 9, 9]));
+
+        let vertical = append_colorbar(&image, &lut, (0.0, 100.0), Scale::Linear, true);
+        assert_eq!(vertical.height(), 64);
+        assert!(vertical.width() > 32);
+        assert_eq!(vertical.get_pixel(0, 0).0, [9, 9, 9]);
+        // The strip starts after a gap, with the high end at the top
+        let left = 32 + 4;
+        assert_eq!(vertical.get_pixel(left, 0).0, [255, 0, 0]);
+        assert_eq!(vertical.get_pixel(left, 63).0, [0, 0, 255]);
+
+        let horizontal = append_colorbar(&image, &lut, (0.0, 100.0), Scale::Linear, false);
+        assert_eq!(horizontal.width(), 32);
+        let top = 64 + 4;
+        assert_eq!(horizontal.get_pixel(0, top).0, [0, 0, 255]);
+        assert_eq!(horizontal.get_pixel(31, top).0, [255, 0, 0]);
+        // Labels are drawn below the strip
+        let below = (top + 4 + 2)..horizontal.height();
+        assert!(below
+            .flat_map(|row| (0..32).map(move |column| (column, row)))
+            .any(|(column, row)| horizontal.get_pixel(column, row).0 == [255, 255, 255]));
+
+        // The log label is drawn between the values, which are too short to reach the middle
+        let white = |image: &RgbImage, columns: std::ops::Range<u32>| {
+            columns.into_iter().any(|column| {
+                (0..image.height()).any(|row| image.get_pixel(column, row).0 == [255, 255, 255])
+            })
+        };
+        let log = append_colorbar(&image, &lut, (0.0, 1.0), Scale::Log, false);
+        assert!(white(&log, 14..18));
+        assert!(!white(&horizontal, 14..18));
+    }
+}
@@ -0,0 +1,289 @@
+use image::RgbImage;
+use ndarray::Array2;
+use std::fmt;
+
+/// A colormap for single-channel display
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Colormap {
+    #[value(alias = "grey")]
+    Gray,
+    Viridis,
+    Magma,
+    Inferno,
+    Plasma,
+    Turbo,
+    /// The hue wheel, cyclic
+    Hsv,
+    /// Blue through gray to red, diverging from the middle, e.g. for signed differences
+    Coolwarm,
+}
+
+/// Polynomial fits of the matplotlib colormaps, one coefficient per power per component
+/// (from https://www.shadertoy.com/view/WlfXRN, CC0)
+#[allow(clippy::excessive_precision)]
+const VIRIDIS: [[f64; 3]; 7] = [
+    [0.2777273272234177, 0.005407344544966578, 0.3340998053353061],
+    [0.1050930431085774, 1.404613529898575, 1.384590162594685],
+    [-0.3308618287255563, 0.214847559468213, 0.09509516302823659],
+    [-4.634230498983486, -5.799100973351585, -19.33244095627987],
+    [6.228269936347081, 14.17993336680509, 56.69055260068105],
+    [4.776384997670288, -13.74514537774601, -65.35303263337234],
+    [-5.435455855934631, 4.645852612178535, 26.3124352495832],
+];
+
+#[allow(clippy::excessive_precision)]
+const MAGMA: [[f64; 3]; 7] = [
+    [
+        -0.002136485053939582,
+        -0.000749655052795221,
+        -0.005386127855323933,
+    ],
+    [0.2516605407371642, 0.6775232436837668, 2.494026599312351],
+    [8.353717279216625, -3.577719514958484, 0.3144679030132573],
+    [-27.66873308576866, 14.26473078096533, -13.64921318813922],
+    [52.17613981234068, -27.94360607168351, 12.94416944238394],
+    [-50.76852536473588, 29.04658282127291, 4.23415299384598],
+    [18.65570506591883, -11.48977351997711, -5.601961508734096],
+];
+
+#[allow(clippy::excessive_precision)]
+const INFERNO: [[f64; 3]; 7] = [
+    [
+        0.0002189403691192265,
+        0.001651004631001012,
+        -0.01948089843709184,
+    ],
+    [0.1065134194856116, 0.5639564367884091, 3.932712388889277],
+    [11.60249308247187, -3.972853965665698, -15.9423941062914],
+    [-41.70399613139459, 17.43639888205313, 44.35414519872813],
+    [77.162935699427, -33.40235894210092, -81.80730925738993],
+    [-71.31942824499214, 32.62606426397723, 73.20951985803202],
+    [25.13112622477341, -12.24266895238567, -23.07032500287172],
+];
+
+#[allow(clippy::excessive_precision)]
+const PLASMA: [[f64; 3]; 7] = [
+    [0.05873234392399702, 0.02333670892565664, 0.5433401826748754],
+    [2.176514634195958, 0.2383834171260182, 0.7539604599784036],
+    [-2.689460476458034, -7.455851135738909, 3.110799939717086],
+    [6.130348345893603, 42.3461881477227, -28.51885465332158],
+    [-11.10743619062271, -82.66631109428045, 60.13984767418263],
+    [10.02306557647065, 71.41361770095349, -54.07218655560067],
+    [-3.658713842777788, -22.93153465461149, 18.19190778539828],
+];
+
+/// Polynomial fit of turbo (from https://gist.github.com/mikhailov-work/0d177465a8151eb6ede1768d51d476c7)
+const TURBO: [[f64; 3]; 6] = [
+    [0.13572138, 0.09140261, 0.10667330],
+    [4.61539260, 2.19418839, 12.64194608],
+    [-42.66032258, 4.84296658, -60.58204836],
+    [132.13108234, -14.18503333, 110.36276771],
+    [-152.94239396, 4.27729857, -89.90310912],
+    [59.28637943, 2.82956604, 27.34824973],
+];
+
+/// Moreland's coolwarm at even steps from 0 to 1, as in matplotlib
+const COOLWARM: [[u8; 3]; 9] = [
+    [59, 76, 192],
+    [98, 130, 234],
+    [141, 176, 254],
+    [184, 208, 249],
+    [221, 221, 221],
+    [245, 196, 173],
+    [244, 154, 123],
+    [222, 96, 77],
+    [180, 4, 38],
+];
+
+/// Linear interpolation at `x` in `0..=1` between colors at even steps
+fn interpolate(stops: &[[u8; 3]], x: f64) -> [u8; 3] {
+    let position = x.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let below = (position.floor() as usize).min(stops.len() - 2);
+    let fraction = position - below as f64;
+    std::array::from_fn(|c| {
+        let (a, b) = (stops[below][c] as f64, stops[below + 1][c] as f64);
+        (a + (b - a) * fraction).round() as u8
+    })
+}
+
+/// The fully saturated color of `hue` in `0..1` of the way around the hue wheel
+fn hue(hue: f64) -> [u8; 3] {
+    std::array::from_fn(|c| {
+        // Red, green and blue peak a third of the wheel apart
+        let distance = ((hue * 6.0 - 2.0 * c as f64).rem_euclid(6.0) - 3.0).abs();
+        ((distance - 1.0).clamp(0.0, 1.0) * 255.0).round() as u8
+    })
+}
+
+/// Evaluate a polynomial fit at `x` in `0..=1`, as 8-bit RGB
+fn evaluate(coefficients: &[[f64; 3]], x: f64) -> [u8; 3] {
+    std::array::from_fn(|c| {
+        let value = coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |sum, coefficient| sum * x + coefficient[c]);
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    })
+}
+
+impl Colormap {
+    pub const NAMES: [&'static str; 8] = [
+        "gray", "viridis", "magma", "inferno", "plasma", "turbo", "hsv", "coolwarm",
+    ];
+
+    /// The 256-entry RGB lookup table, indexed by normalized 8-bit values
+    pub fn lut(&self) -> Vec<[u8; 3]> {
+        let coefficients: &[[f64; 3]] = match self {
+            Colormap::Gray => return (0..=255).map(|value| [value; 3]).collect(),
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Inferno => &INFERNO,
+            Colormap::Plasma => &PLASMA,
+            Colormap::Turbo => &TURBO,
+            // Short of coming back around to red
+            Colormap::Hsv => return (0..=255).map(|value| hue(value as f64 / 256.0)).collect(),
+            Colormap::Coolwarm => {
+                return (0..=255)
+                    .map(|value| interpolate(&COOLWARM, value as f64 / 255.0))
+                    .collect()
+            }
+        };
+        (0..=255)
+            .map(|value| evaluate(coefficients, value as f64 / 255.0))
+            .collect()
+    }
+
+    /// Whether the colors at the two ends meet, e.g. for hues of timepoints that repeat
+    pub fn is_cyclic(&self) -> bool {
+        *self == Colormap::Hsv
+    }
+
+    /// Color normalized 8-bit values through the lookup table
+    pub fn apply(&self, data: &Array2<u8>) -> RgbImage {
+        let lut = self.lut();
+        let (rows, columns) = data.dim();
+        RgbImage::from_fn(columns as u32, rows as u32, |x, y| {
+            image::Rgb(lut[data[[y as usize, x as usize]] as usize])
+        })
+    }
+}
+
+impl std::str::FromStr for Colormap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "gray" | "grey" => Colormap::Gray,
+            "viridis" => Colormap::Viridis,
+            "magma" => Colormap::Magma,
+            "inferno" => Colormap::Inferno,
+            "plasma" => Colormap::Plasma,
+            "turbo" => Colormap::Turbo,
+            "hsv" => Colormap::Hsv,
+            "coolwarm" => Colormap::Coolwarm,
+            _ => anyhow::bail!(
+                "Unknown colormap '{}', available colormaps are: {}",
+                s,
+                Self::NAMES.join(", ")
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Colormap::Gray => "gray",
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Inferno => "inferno",
+            Colormap::Plasma => "plasma",
+            Colormap::Turbo => "turbo",
+            Colormap::Hsv => "hsv",
+            Colormap::Coolwarm => "coolwarm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use ndarray::array;
+
+    fn luminance(color: [u8; 3]) -> f64 {
+        0.2126 * color[0] as f64 + 0.7152 * color[1] as f64 + 0.0722 * color[2] as f64
+    }
+
+    #[test]
+    fn test_lut_endpoints() -> Result<()> {
+        // Endpoints of the matplotlib colormaps, within rounding of the fits
+        let expected: [(&str, [u8; 3], [u8; 3]); 5] = [
+            ("gray", [0, 0, 0], [255, 255, 255]),
+            ("viridis", [68, 1, 84], [253, 231, 37]),
+            ("magma", [0, 0, 4], [252, 253, 191]),
+            ("inferno", [0, 0, 4], [252, 255, 164]),
+            ("plasma", [13, 8, 135], [240, 249, 33]),
+        ];
+        for (name, first, last) in expected {
+            let lut = name.parse::<Colormap>()?.lut();
+            assert_eq!(lut.len(), 256);
+            for (actual, expected) in [(lut[0], first), (lut[255], last)] {
+                let close = actual
+                    .iter()
+                    .zip(expected)
+                    .all(|(&a, b)| (a as i32 - b as i32).abs() <= 8);
+                assert!(close, "{}: {:?} != {:?}", name, actual, expected);
+            }
+        }
+        // The turbo fit runs from near black to dark red
+        let turbo = Colormap::Turbo.lut();
+        assert!(luminance(turbo[0]) < 32.0);
+        assert!(turbo[255][0] > 4 * turbo[255][1].max(turbo[255][2]));
+        // Around the hue wheel from red through green and blue
+        let hsv = Colormap::Hsv.lut();
+        assert_eq!(hsv[0], [255, 0, 0]);
+        assert_eq!(hsv[85], [2, 255, 0]);
+        assert_eq!(hsv[171], [2, 0, 255]);
+        assert_eq!(hsv[255], [255, 0, 6]);
+        // From blue through the gray middle that zero maps to, to red
+        let coolwarm = Colormap::Coolwarm.lut();
+        assert_eq!(coolwarm[0], [59, 76, 192]);
+        assert_eq!(coolwarm[255], [180, 4, 38]);
+        assert!(coolwarm[127..=128].iter().all(|color| color[1] >= 220));
+        Ok(())
+    }
+
+    #[test]
+    fn test_monotonic_luminance() -> Result<()> {
+        // Turbo is a rainbow map, brightest in the middle
+        for name in ["gray", "viridis", "magma", "inferno", "plasma"] {
+            let lut = name.parse::<Colormap>()?.lut();
+            for pair in lut.windows(2) {
+                // Allow one 8-bit step of a component from rounding and clamping the fits
+                assert!(
+                    luminance(pair[1]) >= luminance(pair[0]) - 1.0,
+                    "{}: {:?} then {:?}",
+                    name,
+                    pair[0],
+                    pair[1]
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply() -> Result<()> {
+        let image = Colormap::Gray.apply(&array![[0u8, 128, 255]]);
+        assert_eq!(image.as_raw(), &[0, 0, 0, 128, 128, 128, 255, 255, 255]);
+        let error = "jet".parse::<Colormap>().expect_err("unknown colormap");
+        assert_eq!(
+            error.to_string(),
+            "Unknown colormap 'jet', available colormaps are: \
+             gray, viridis, magma, inferno, plasma, turbo, hsv, coolwarm"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,360 @@
+use crate::{ngff, omero, open_array, read_array_slice, resolve_array_path};
+use crate::{ReadOptions, SliceIndex, SliceValue, SliceView};
+use anyhow::Result;
+use image::RgbImage;
+use ndarray::prelude::*;
+use std::sync::Arc;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// Colors of channels without one given or in the `omero` metadata, in order
+const DEFAULT_COLORS: [[u8; 3]; 6] = [
+    [255, 0, 255],
+    [0, 255, 0],
+    [0, 255, 255],
+    [255, 255, 0],
+    [255, 0, 0],
+    [0, 0, 255],
+];
+
+/// Parse a color name (e.g. `magenta`) or a hex RGB color (e.g. `FF00FF` or `#ff00ff`)
+pub fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let named = match color.trim().to_lowercase().as_str() {
+        "red" => [255, 0, 0],
+        "green" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "cyan" => [0, 255, 255],
+        "magenta" => [255, 0, 255],
+        "yellow" => [255, 255, 0],
+        "white" | "gray" | "grey" => [255, 255, 255],
+        "black" => [0, 0, 0],
+        _ => return omero::parse_color(color.trim()),
+    };
+    Some(named)
+}
+
+/// A channel of a composite, by index along the channel axis,
+/// with its color or the `omero` color where `None`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelSpec {
+    pub index: u64,
+    pub color: Option<[u8; 3]>,
+}
+
+impl std::str::FromStr for ChannelSpec {
+    type Err = anyhow::Error;
+
+    /// Parse `1` or `1:green`
+    fn from_str(s: &str) -> Result<Self> {
+        let (index, color) = match s.split_once(':') {
+            Some((index, color)) => (index, Some(color)),
+            None => (s, None),
+        };
+        let index = index
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid channel index in '{}'", s))?;
+        let color = color
+            .map(|color| {
+                parse_color(color).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown color '{}', use a name such as magenta or a hex code such as FF00FF",
+                        color
+                    )
+                })
+            })
+            .transpose()?;
+        Ok(Self { index, color })
+    }
+}
+
+/// Read one slice per channel of a composite, with the color of each channel:
+/// all channels if `channels` is `None`, colored from the `omero` metadata
+/// (unless `ignore_omero`) or a default palette
+pub fn read_channels<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+    channels: Option<&[ChannelSpec]>,
+    ignore_omero: bool,
+) -> Result<Vec<(SliceView, [u8; 3])>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let attributes = ngff::group_attributes(&store, ngff::parent_path(&path))?.unwrap_or_default();
+    let shape = open_array(store.clone(), &path)?.shape().to_vec();
+    let ndims = shape.len();
+    let axes = ngff::axis_names(&attributes).filter(|axes| axes.len() == ndims);
+    // The third-from-last dimension is the channel axis of czyx and tczyx layouts
+    let Some(dimension) = axes
+        .as_deref()
+        .and_then(|axes| ngff::channel_axis(&attributes, axes))
+        .or_else(|| ndims.checked_sub(3))
+    else {
+        anyhow::bail!("A composite needs a channel axis, the array has only 2 dimensions");
+    };
+    let name = axes.as_ref().map(|axes| axes[dimension].as_str());
+    check_free_axis(options, dimension, name, "--channels", "channel axis")?;
+
+    let size = shape[dimension];
+    let specs = match channels {
+        Some(channels) => channels.to_vec(),
+        None => (0..size)
+            .map(|index| ChannelSpec { index, color: None })
+            .collect(),
+    };
+    if let Some(spec) = specs.iter().find(|spec| spec.index >= size) {
+        anyhow::bail!(
+            "Channel {} is out of range, the channel axis has size {}",
+            spec.index,
+            size
+        );
+    }
+    let omero_channels = if ignore_omero {
+        vec![]
+    } else {
+        omero::omero_channels(&attributes)
+    };
+    let indices: Vec<u64> = specs.iter().map(|spec| spec.index).collect();
+    let views = read_along_axis(store, &path, &shape, options, dimension, name, &indices)?;
+    let read = views
+        .into_iter()
+        .zip(&specs)
+        .enumerate()
+        .map(|(i, (view, spec))| {
+            let color = spec
+                .color
+                .or_else(|| omero_channels.get(spec.index as usize)?.color)
+                .unwrap_or(DEFAULT_COLORS[i % DEFAULT_COLORS.len()]);
+            (view, color)
+        })
+        .collect();
+    Ok(read)
+}
+
+/// The non-spatial dimension of an array of `shape` and OME-NGFF `axes` that `axis` names,
+/// by axis name or by dimension, with its name if known, for `flag` in messages
+pub(crate) fn resolve_axis<'a>(
+    shape: &[u64],
+    axes: Option<&'a [String]>,
+    axis: &str,
+    flag: &str,
+    kind: &str,
+) -> Result<(usize, Option<&'a str>)> {
+    let ndims = shape.len();
+    let named = axes.and_then(|axes| axes.iter().position(|name| name.eq_ignore_ascii_case(axis)));
+    let Some(dimension) = named.or_else(|| axis.parse().ok().filter(|&i| i < ndims)) else {
+        match axes {
+            Some(axes) => anyhow::bail!(
+                "Unknown axis '{}' for {}, the axes are {}",
+                axis,
+                flag,
+                axes.join(", ")
+            ),
+            None => anyhow::bail!(
+                "Unknown axis '{}' for {}, give a dimension from 0 to {} \
+                 as the array has no axis names",
+                axis,
+                flag,
+                ndims - 1
+            ),
+        }
+    };
+    let name = axes.map(|axes| axes[dimension].as_str());
+    if dimension + 2 >= ndims {
+        anyhow::bail!(
+            "Cannot make a {} along the spatial axis '{}'",
+            kind,
+            name.unwrap_or(axis)
+        );
+    }
+    Ok((dimension, name))
+}
+
+/// Fail if the slice indices of `options` select an index along `dimension` (named `name`),
+/// which `flag` reads all of, calling it `kind` in the message
+pub(crate) fn check_free_axis(
+    options: &ReadOptions,
+    dimension: usize,
+    name: Option<&str>,
+    flag: &str,
+    kind: &str,
+) -> Result<()> {
+    let slices = options.slice_indices.as_deref().unwrap_or_default();
+    let positional = slices
+        .iter()
+        .filter(|slice| matches!(slice, SliceIndex::Position(_)))
+        .count();
+    let named = slices.iter().any(|slice| match slice {
+        SliceIndex::Axis(axis, _) | SliceIndex::Physical(axis, ..) | SliceIndex::All(axis) => {
+            name.is_some_and(|name| axis.eq_ignore_ascii_case(name))
+        }
+        SliceIndex::Position(_) => false,
+    });
+    if positional > dimension || named {
+        anyhow::bail!(
+            "Cannot combine {} with a slice index for the {} '{}'",
+            flag,
+            kind,
+            name.unwrap_or("c")
+        );
+    }
+    Ok(())
+}
+
+/// Read the slice at each of `indices` along `dimension` (named `name` if known) of the array
+/// at `path`, with the other slice indices of `options`
+pub(crate) fn read_along_axis<TStore>(
+    store: Arc<TStore>,
+    path: &str,
+    shape: &[u64],
+    options: &ReadOptions,
+    dimension: usize,
+    name: Option<&str>,
+    indices: &[u64],
+) -> Result<Vec<SliceView>>
+where
+    TStore: ReadableStorageTraits + ?Sized + 'static,
+{
+    let slices = options.slice_indices.clone().unwrap_or_default();
+    let positional = slices
+        .iter()
+        .filter(|slice| matches!(slice, SliceIndex::Position(_)))
+        .count();
+    let mut read = vec![];
+    for &index in indices {
+        let mut slices = slices.clone();
+        match name {
+            Some(name) if positional == 0 => {
+                slices.push(SliceIndex::Axis(name.to_string(), index.into()))
+            }
+            // Positional indices up to the axis, at the middle as by default
+            _ => {
+                slices
+                    .extend((positional..dimension).map(|i| {
+                        SliceIndex::Position(SliceValue::Middle.resolve(shape[i]).into())
+                    }));
+                slices.push(SliceIndex::Position(index.into()));
+            }
+        }
+        let slice_options = ReadOptions {
+            slice_indices: Some(slices),
+            consolidated: false,
+            ..options.clone()
+        };
+        read.push(read_array_slice(store.clone(), path, &slice_options)?);
+    }
+    Ok(read)
+}
+
+/// Tint each normalized channel with its color and add them up, saturating at white
+pub fn blend(channels: &[(Array2<u8>, [u8; 3])]) -> Result<RgbImage> {
+    let Some((first, _)) = channels.first() else {
+        anyhow::bail!("A composite needs at least one channel");
+    };
+    let (rows, columns) = first.dim();
+    let mut sum = Array3::<u16>::zeros((rows, columns, 3));
+    for (data, color) in channels {
+        if data.dim() != (rows, columns) {
+            anyhow::bail!("Channels of a composite must have the same shape");
+        }
+        for ((row, column), &value) in data.indexed_iter() {
+            for (c, &component) in color.iter().enumerate() {
+                sum[[row, column, c]] += value as u16 * component as u16 / 255;
+            }
+        }
+    }
+    let data = sum.iter().map(|&value| value.min(255) as u8).collect();
+    RgbImage::from_raw(columns as u32, rows as u32, data)
+        .ok_or_else(|| anyhow::anyhow!("Failed to create image from raw data"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_store;
+
+    #[test]
+    fn test_parse_channel_specs() -> Result<()> {
+        assert_eq!(
+            "0:magenta".parse::<ChannelSpec>()?,
+            ChannelSpec {
+                index: 0,
+                color: Some([255, 0, 255])
+            }
+        );
+        assert_eq!(
+            "2:#00ff80".parse::<ChannelSpec>()?.color,
+            Some([0, 255, 128])
+        );
+        assert_eq!("1".parse::<ChannelSpec>()?.color, None);
+        assert!("1:chartreuse".parse::<ChannelSpec>().is_err());
+        assert!("c:red".parse::<ChannelSpec>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_channels() -> Result<()> {
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [{"name": "t"}, {"name": "c", "type": "channel"}, {"name": "y"}, {"name": "x"}],
+                "datasets": [{"path": "0"}]
+            }],
+            "omero": {"channels": [{"color": "FF0000"}, {"color": "00FF00"}, {}]}
+        });
+        let store = image_store(attributes, &[2, 3, 4, 4], &[1, 1, 4, 4])?;
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("t".to_string(), 0.into())]),
+            ..Default::default()
+        };
+        let channels = read_channels(store.clone(), None, &options, None, false)?;
+        let colors: Vec<_> = channels.iter().map(|(_, color)| *color).collect();
+        assert_eq!(colors, vec![[255, 0, 0], [0, 255, 0], [0, 255, 255]]);
+        assert_eq!(channels[2].0.data[[0, 0]], 2.0);
+
+        let specs = ["2:blue".parse()?, "0".parse()?];
+        let positional = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Position(1.into())]),
+            ..Default::default()
+        };
+        let channels = read_channels(store.clone(), None, &positional, Some(&specs), true)?;
+        assert_eq!(channels[0].0.data[[0, 0]], 12.0);
+        assert_eq!(channels[0].1, [0, 0, 255]);
+        // The second channel takes the second default color without omero colors
+        assert_eq!(channels[1].1, DEFAULT_COLORS[1]);
+
+        let conflicting = ReadOptions {
+            slice_indices: Some(vec![
+                SliceIndex::Position(0.into()),
+                SliceIndex::Position(1.into()),
+            ]),
+            ..Default::default()
+        };
+        let err = read_channels(store.clone(), None, &conflicting, None, false)
+            .expect_err("positional channel index");
+        assert_eq!(
+            err.to_string(),
+            "Cannot combine --channels with a slice index for the channel axis 'c'"
+        );
+        let out_of_range = ["3".parse()?];
+        assert!(read_channels(store, None, &options, Some(&out_of_range), false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blend() -> Result<()> {
+        let red = (array![[255u8, 0]], [255, 0, 0]);
+        let cyan = (array![[128u8, 255]], [0, 255, 255]);
+        let image = blend(&[red, cyan.clone()])?;
+        assert_eq!(image.get_pixel(0, 0).0, [255, 128, 128]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 255, 255]);
+        let white = (array![[200u8, 200]], [255, 255, 255]);
+        assert_eq!(blend(&[cyan, white])?.get_pixel(1, 0).0, [200, 255, 255]);
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+use crate::warn;
+use anyhow::{Context, Result};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the environment variables setting defaults of flags, e.g. `PEEKZARR_CROP_SIZE`
+pub const ENV_PREFIX: &str = "PEEKZARR_";
+
+/// Where the value of a setting came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    CommandLine,
+    /// The environment variable of this name
+    Env(String),
+    /// The config file at this path
+    Config(PathBuf),
+    Default,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CommandLine => write!(f, "command line"),
+            Self::Env(name) => write!(f, "environment variable {}", name),
+            Self::Config(path) => write!(f, "config file {}", path.display()),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A flag not given on the command line, set from the environment or the config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting {
+    /// Id of the argument
+    pub id: String,
+    /// The flags it stands for, e.g. `--cmap=magma`
+    pub args: Vec<OsString>,
+    pub origin: Origin,
+}
+
+/// `$XDG_CONFIG_HOME/peekzarr/config.toml`, in `~/.config` if the variable is not set
+pub fn config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("peekzarr").join("config.toml"))
+}
+
+/// The table of the config file at `path`, none if there is no such file
+pub fn read_config(path: &Path) -> Result<Option<toml::Table>> {
+    let text = match std::fs::read_to_string(path) {
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        text => text.with_context(|| format!("Failed to read {}", path.display()))?,
+    };
+    let table = text
+        .parse::<toml::Table>()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(table))
+}
+
+/// The `PEEKZARR_*` variables of the environment
+pub fn env_vars() -> Vec<(String, String)> {
+    std::env::vars()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect()
+}
+
+/// Name of the environment variable of the flag `long`, e.g. `PEEKZARR_CROP_SIZE`
+pub fn env_name(long: &str) -> String {
+    format!("{}{}", ENV_PREFIX, long.to_uppercase().replace('-', "_"))
+}
+
+/// Whether `arg` can be set outside the command line, i.e. is a visible flag
+/// other than help, version and those in `exclude`
+fn settable(arg: &Arg, exclude: &[&str]) -> bool {
+    arg.get_long().is_some()
+        && !arg.is_hide_set()
+        && !exclude.contains(&arg.get_id().as_str())
+        && matches!(
+            arg.get_action(),
+            ArgAction::Set | ArgAction::Append | ArgAction::SetTrue
+        )
+}
+
+/// A scalar of the config file as given on the command line
+fn config_scalar(value: &toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(text) => text.clone(),
+        toml::Value::Integer(number) => number.to_string(),
+        toml::Value::Float(number) => number.to_string(),
+        toml::Value::Boolean(flag) => flag.to_string(),
+        _ => anyhow::bail!("Expected a string, number, boolean or array"),
+    })
+}
+
+/// The values of a key of the config file, several for an array
+fn config_values(value: &toml::Value) -> Result<Vec<String>> {
+    match value {
+        toml::Value::Array(values) => values.iter().map(config_scalar).collect(),
+        value => Ok(vec![config_scalar(value)?]),
+    }
+}
+
+/// Whether a boolean flag is set by `value`, as `true`, `1`, `false`, `0` or empty
+fn parse_flag(value: &str) -> Result<bool> {
+    match value.trim() {
+        "true" | "1" => Ok(true),
+        "false" | "0" | "" => Ok(false),
+        _ => anyhow::bail!("Expected true or false, got '{}'", value),
+    }
+}
+
+/// The flags setting `arg` to `values`
+fn flag_args(arg: &Arg, long: &str, values: &[String]) -> Result<Vec<OsString>> {
+    if matches!(arg.get_action(), ArgAction::SetTrue) {
+        let [value] = values else {
+            anyhow::bail!("Expected true or false, got {} values", values.len());
+        };
+        return Ok(match parse_flag(value)? {
+            true => vec![format!("--{}", long).into()],
+            false => vec![],
+        });
+    }
+    let values = match (arg.get_action(), arg.get_value_delimiter(), values) {
+        (ArgAction::Append, _, values) => values.to_vec(),
+        (_, _, [value]) => vec![value.clone()],
+        (_, Some(delimiter), values) => vec![values.join(&delimiter.to_string())],
+        (_, None, values) => anyhow::bail!("Expected one value, got {}", values.len()),
+    };
+    // An optional value left empty is the bare flag, e.g. --channels
+    let optional = arg
+        .get_num_args()
+        .is_some_and(|range| range.min_values() == 0);
+    Ok(values
+        .iter()
+        .map(|value| match value.is_empty() && optional {
+            true => format!("--{}", long).into(),
+            false => format!("--{}={}", long, value).into(),
+        })
+        .collect())
+}
+
+/// Fail if the flags `args` do not parse, e.g. with a value out of its range,
+/// leaving missing required arguments to the command line they are added to
+fn check_args(command: &Command, args: &[OsString]) -> Result<()> {
+    let mut argv = vec![OsString::from(command.get_name())];
+    argv.extend(args.iter().cloned());
+    match command.clone().try_get_matches_from(argv) {
+        Err(error) if error.kind() != ErrorKind::MissingRequiredArgument => {
+            let message = error.to_string();
+            let message = message.split("\n\nFor more information").next();
+            let message = message.unwrap_or_default().trim_start_matches("error: ");
+            anyhow::bail!("{}", message.trim_end())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether the arguments `a` and `b` of `command` cannot be given together
+fn conflicting(command: &Command, a: &Arg, b: &Arg) -> bool {
+    let conflicts = |arg: &Arg, other: &Arg| {
+        command
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .any(|conflict| conflict.get_id() == other.get_id())
+    };
+    conflicts(a, b) || conflicts(b, a)
+}
+
+/// Settings of the flags of `command` not given on the command line `matches`,
+/// from the `env` variables before the `config` file (with its path), warning about
+/// keys matching no flag and leaving out those conflicting with the command line
+pub fn settings(
+    command: &Command,
+    matches: &ArgMatches,
+    exclude: &[&str],
+    env: &[(String, String)],
+    config: Option<(&Path, &toml::Table)>,
+) -> Result<Vec<Setting>> {
+    let args: Vec<&Arg> = command
+        .get_arguments()
+        .filter(|arg| settable(arg, exclude))
+        .collect();
+    let given: Vec<&Arg> = command
+        .get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect();
+    let long = |arg: &Arg| arg.get_long().unwrap_or_default().to_string();
+    for (name, _) in env {
+        if !args.iter().any(|&arg| env_name(&long(arg)) == *name) {
+            warn!("Ignoring the unknown environment variable {}", name);
+        }
+    }
+    if let Some((path, table)) = config {
+        for key in table.keys() {
+            if !args.iter().any(|&arg| long(arg) == key.replace('_', "-")) {
+                warn!("Ignoring the unknown key '{}' in {}", key, path.display());
+            }
+        }
+    }
+    let mut settings = Vec::new();
+    for &arg in &args {
+        let long = long(arg);
+        if given
+            .iter()
+            .any(|&other| other.get_id() == arg.get_id() || conflicting(command, arg, other))
+        {
+            continue;
+        }
+        let name = env_name(&long);
+        let from_env = env
+            .iter()
+            .find(|(var, _)| *var == name)
+            .map(|(_, value)| (vec![value.clone()], Origin::Env(name.clone())));
+        let from_config = || -> Result<_> {
+            let Some((path, table)) = config else {
+                return Ok(None);
+            };
+            let value = table
+                .iter()
+                .find(|(key, _)| key.replace('_', "-") == long)
+                .map(|(_, value)| value);
+            let Some(value) = value else {
+                return Ok(None);
+            };
+            let origin = Origin::Config(path.to_path_buf());
+            let values = config_values(value)
+                .with_context(|| format!("Invalid setting '{}' in {}", long, origin))?;
+            Ok(Some((values, origin)))
+        };
+        let Some((values, origin)) = from_env.map_or_else(from_config, |found| Ok(Some(found)))?
+        else {
+            continue;
+        };
+        let args = flag_args(arg, &long, &values)
+            .and_then(|args| check_args(command, &args).map(|_| args))
+            .with_context(|| format!("Invalid setting '{}' in {}", long, origin))?;
+        settings.push(Setting {
+            id: arg.get_id().to_string(),
+            args,
+            origin,
+        });
+    }
+    Ok(settings)
+}
+
+/// `args` with the flags of `settings` added before any `--`
+pub fn with_settings(args: &[OsString], settings: &[Setting]) -> Vec<OsString> {
+    let end = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let mut merged = args[..end].to_vec();
+    merged.extend(
+        settings
+            .iter()
+            .flat_map(|setting| setting.args.iter().cloned()),
+    );
+    merged.extend(args[end..].iter().cloned());
+    merged
+}
+
+/// The effective value of each flag of `command` in `matches`, parsed with `settings`
+/// added, and where it came from, leaving out unset flags and those in `exclude`
+pub fn format_settings(
+    command: &Command,
+    matches: &ArgMatches,
+    exclude: &[&str],
+    settings: &[Setting],
+) -> String {
+    let rows: Vec<(String, String)> = command
+        .get_arguments()
+        .filter(|arg| settable(arg, exclude))
+        .filter_map(|arg| {
+            let id = arg.get_id().as_str();
+            let values: Vec<String> = matches
+                .get_raw(id)?
+                .map(|value| value.to_string_lossy().into_owned())
+                .collect();
+            let origin = match settings.iter().find(|setting| setting.id == id) {
+                Some(setting) => setting.origin.clone(),
+                None if matches.value_source(id) == Some(ValueSource::DefaultValue) => {
+                    Origin::Default
+                }
+                None => Origin::CommandLine,
+            };
+            if matches!(arg.get_action(), ArgAction::SetTrue) && values == ["false"] {
+                return None;
+            }
+            let key = format!("{} = {}", arg.get_long()?, values.join(","));
+            Some((key, origin.to_string()))
+        })
+        .collect();
+    let width = rows.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    rows.iter()
+        .map(|(key, origin)| format!("{:<width$}  # {}", key, origin, width = width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::value_parser;
+
+    fn command() -> Command {
+        Command::new("peekzarr")
+            .arg(Arg::new("path").required(true))
+            .arg(
+                Arg::new("crop_size")
+                    .long("crop-size")
+                    .value_parser(value_parser!(u64).range(1..)),
+            )
+            .arg(Arg::new("cmap").long("cmap").conflicts_with("channels"))
+            .arg(
+                Arg::new("channels")
+                    .long("channels")
+                    .num_args(0..=1)
+                    .value_delimiter(','),
+            )
+            .arg(Arg::new("stats").long("stats").action(ArgAction::SetTrue))
+            .arg(Arg::new("low").long("low").default_value("0.001"))
+    }
+
+    #[test]
+    fn test_settings() -> Result<()> {
+        let command = command();
+        let args: Vec<OsString> = ["peekzarr", "image.zarr", "--crop-size", "64"]
+            .map(OsString::from)
+            .to_vec();
+        let matches = command.clone().try_get_matches_from(&args)?;
+        let table: toml::Table =
+            "cmap = 'magma'\ncrop_size = 1024\nstats = true\nzoom = 2".parse()?;
+        let path = Path::new("config.toml");
+        let env = [("PEEKZARR_CMAP".to_string(), "viridis".to_string())];
+        let found = settings(&command, &matches, &[], &env, Some((path, &table)))?;
+        // The command line wins over both, the environment over the config file
+        let flags: Vec<_> = found.iter().flat_map(|s| s.args.clone()).collect();
+        assert_eq!(flags, ["--cmap=viridis", "--stats"]);
+        assert_eq!(found[0].origin, Origin::Env("PEEKZARR_CMAP".to_string()));
+        assert_eq!(found[1].origin, Origin::Config(path.to_path_buf()));
+
+        let merged = command
+            .clone()
+            .try_get_matches_from(with_settings(&args, &found))?;
+        let report = format_settings(&command, &merged, &[], &found);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "crop-size = 64  # command line",
+                "cmap = viridis  # environment variable PEEKZARR_CMAP",
+                "stats = true    # config file config.toml",
+                "low = 0.001     # default",
+            ]
+        );
+
+        // Settings conflicting with the command line are left out
+        let args = ["peekzarr", "image.zarr", "--channels"];
+        let matches = command.clone().try_get_matches_from(args)?;
+        assert!(settings(&command, &matches, &[], &env, None)?.is_empty());
+
+        let table: toml::Table = "crop-size = 0".parse()?;
+        let error =
+            settings(&command, &matches, &[], &[], Some((path, &table))).expect_err("out of range");
+        assert_eq!(
+            error.to_string(),
+            "Invalid setting 'crop-size' in config file config.toml"
+        );
+        let table: toml::Table = "channels = ['0', '2']".parse()?;
+        let matches = command.clone().try_get_matches_from(["peekzarr", "x"])?;
+        let found = settings(&command, &matches, &[], &[], Some((path, &table)))?;
+        assert_eq!(found[0].args, ["--channels=0,2"]);
+        Ok(())
+    }
+}
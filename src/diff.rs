@@ -0,0 +1,158 @@
+use crate::{image_quantile, SliceView};
+use anyhow::Result;
+use ndarray::{Array2, Zip};
+use std::fmt;
+
+/// The signed difference `b - a` of two slices read over the same subset,
+/// failing if the arrays or the planes read differ in shape
+pub fn difference(a: &SliceView, b: &SliceView) -> Result<Array2<f32>> {
+    if a.shape != b.shape {
+        anyhow::bail!(
+            "Cannot take the difference of arrays of different shapes, {:?} ({}) and {:?} ({})",
+            a.shape,
+            a.path,
+            b.shape,
+            b.path
+        );
+    }
+    if a.data.dim() != b.data.dim() || a.extent() != b.extent() {
+        let [a_extent, b_extent] = [a, b].map(|view| {
+            let [(y0, y1), (x0, x1)] = view.extent();
+            let (rows, columns) = view.data.dim();
+            format!("{}x{} at y={}..{} x={}..{}", columns, rows, y0, y1, x0, x1)
+        });
+        anyhow::bail!(
+            "Cannot take the difference of slices read differently, {} and {}",
+            a_extent,
+            b_extent
+        );
+    }
+    Ok(Zip::from(&a.data).and(&b.data).map_collect(|&a, &b| b - a))
+}
+
+/// Summary of a difference image, over the pixels where both slices have values
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffStats {
+    pub mean_abs: f32,
+    pub max_abs: f32,
+    /// Fraction of the pixels whose absolute difference is above `threshold`
+    pub changed: f64,
+    pub threshold: f32,
+    /// Half-width of the symmetric window around zero, the `quantile` of |diff|
+    pub clip: f32,
+    pub quantile: f64,
+}
+
+impl DiffStats {
+    /// The metrics of `diff`, with the window at `quantile` of its absolute values;
+    /// NaN for a difference without values, and the window never empty
+    pub fn new(diff: &Array2<f32>, threshold: f32, quantile: f64) -> Result<Self> {
+        let abs = diff.mapv(f32::abs);
+        let values: Vec<f32> = abs.iter().copied().filter(|v| !v.is_nan()).collect();
+        let count = values.len();
+        let mean_abs = match count {
+            0 => f32::NAN,
+            _ => (values.iter().map(|&v| v as f64).sum::<f64>() / count as f64) as f32,
+        };
+        let max_abs = values.iter().copied().fold(f32::NAN, f32::max);
+        let changed = match count {
+            0 => f64::NAN,
+            _ => values.iter().filter(|&&v| v > threshold).count() as f64 / count as f64,
+        };
+        let clip = image_quantile(&abs, quantile)?;
+        // At the largest change if most pixels are unchanged, and +-1 for identical slices,
+        // rather than a window of zero width
+        let clip = [clip, max_abs, 1.0]
+            .into_iter()
+            .find(|&clip| clip > 0.0)
+            .unwrap_or(1.0);
+        Ok(Self {
+            mean_abs,
+            max_abs,
+            changed,
+            threshold,
+            clip,
+            quantile,
+        })
+    }
+
+    /// The window of the diverging colormap, centered at zero
+    pub fn window(&self) -> (f32, f32) {
+        (-self.clip, self.clip)
+    }
+}
+
+impl fmt::Display for DiffStats {
+    /// E.g. `mean |diff| 3.25, max |diff| 40, 12.50% of pixels changed by more than 0`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mean |diff| {}, max |diff| {}, {:.2}% of pixels changed by more than {}",
+            self.mean_abs,
+            self.max_abs,
+            self.changed * 100.0,
+            self.threshold
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    fn view(data: Array2<f32>, shape: Vec<u64>) -> SliceView {
+        SliceView {
+            data,
+            path: "0".to_string(),
+            axes: None,
+            start: vec![0, 0, 0],
+            shape,
+            data_type: "float32".to_string(),
+            attributes: Default::default(),
+            step: 1,
+            exact: None,
+        }
+    }
+
+    #[test]
+    fn test_difference() -> Result<()> {
+        let a = view(array![[1.0, 2.0], [3.0, 4.0]], vec![2, 2, 2]);
+        let b = view(array![[1.0, 5.0], [0.0, f32::NAN]], vec![2, 2, 2]);
+        let diff = difference(&a, &b)?;
+        assert_eq!(diff.row(0).to_vec(), vec![0.0, 3.0]);
+        assert_eq!(diff[[1, 0]], -3.0);
+        assert!(diff[[1, 1]].is_nan());
+        // Both shapes are named
+        let other = view(array![[1.0, 2.0], [3.0, 4.0]], vec![5, 2, 2]);
+        let error = difference(&a, &other).unwrap_err().to_string();
+        assert!(
+            error.contains("[2, 2, 2]") && error.contains("[5, 2, 2]"),
+            "{}",
+            error
+        );
+        let smaller = view(array![[1.0, 2.0]], vec![2, 2, 2]);
+        let error = difference(&a, &smaller).unwrap_err().to_string();
+        assert!(error.contains("2x2 at y=0..2") && error.contains("2x1 at y=0..1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_stats() -> Result<()> {
+        let diff = array![[0.0, 3.0], [-3.0, f32::NAN], [-6.0, 0.5]];
+        let stats = DiffStats::new(&diff, 1.0, 0.5)?;
+        assert_eq!(stats.mean_abs, 2.5);
+        assert_eq!(stats.max_abs, 6.0);
+        assert_eq!(stats.changed, 0.6);
+        assert_eq!(stats.clip, 3.0);
+        assert_eq!(stats.window(), (-3.0, 3.0));
+        assert_eq!(
+            stats.to_string(),
+            "mean |diff| 2.5, max |diff| 6, 60.00% of pixels changed by more than 1"
+        );
+        // No change still has a window
+        let stats = DiffStats::new(&Array2::zeros((2, 2)), 0.0, 0.999)?;
+        assert_eq!((stats.max_abs, stats.changed, stats.clip), (0.0, 0.0, 1.0));
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+/// Data types of arrays that can be read, as named in the metadata
+pub const SUPPORTED_DATA_TYPES: [&str; 11] = [
+    "int8", "int16", "int32", "int64", "uint8", "uint16", "uint32", "uint64", "float16", "float32",
+    "float64",
+];
+
+/// Failures that callers may want to tell apart, e.g. for the exit code of the command;
+/// they are returned within `anyhow::Error`, under any context added on the way up
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Nothing at the path or URL of the store
+    #[error("No store at {location}, the path does not exist")]
+    StoreNotFound { location: String },
+    /// No array metadata at `path`, with the names found in its group
+    #[error("No array at '{path}': neither Zarr v3 (zarr.json) nor v2 (.zarray) metadata found{}", candidates_note(.candidates))]
+    ArrayNotFound {
+        path: String,
+        candidates: Vec<String>,
+    },
+    #[error("Unsupported data type {data_type}, supported are {}", SUPPORTED_DATA_TYPES.join(", "))]
+    UnsupportedDataType { data_type: String },
+    /// Slice indices that do not fit the array, e.g. out of bounds or naming no axis
+    #[error("{0}")]
+    InvalidSlice(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+fn candidates_note(candidates: &[String]) -> String {
+    match candidates.is_empty() {
+        true => String::new(),
+        false => format!(", the group has: {}", candidates.join(", ")),
+    }
+}
+
+/// The first [`Error`] in the chain of `error`
+pub fn find(error: &anyhow::Error) -> Option<&Error> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<Error>())
+}
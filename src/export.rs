@@ -0,0 +1,330 @@
+use crate::{info, SliceView};
+use anyhow::{Context, Result};
+use ndarray::Array2;
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::Path;
+use tiff::encoder::{colortype, Rational, TiffEncoder, TiffValue};
+use tiff::tags::ResolutionUnit;
+
+/// Format of a file the decoded values are exported to, from its extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawFormat {
+    /// 16-bit unsigned or 32-bit float grayscale TIFF
+    Tiff,
+    /// NumPy array file
+    Npy,
+}
+
+impl RawFormat {
+    /// The format of `path` from its extension, `.tif`, `.tiff` or `.npy`
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("tif" | "tiff") => Ok(Self::Tiff),
+            Some("npy") => Ok(Self::Npy),
+            _ => anyhow::bail!(
+                "Unknown raw export format of {}, use a .tif or .npy file",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// The format to export to `path` in, failing before anything is read if it is unknown
+/// or if the file exists and is not to be overwritten
+pub fn check_raw_output(path: &Path, force: bool) -> Result<RawFormat> {
+    let format = RawFormat::from_path(path)?;
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        );
+    }
+    Ok(format)
+}
+
+/// Element type the values are stored as, the data type of the array when every value
+/// fits it exactly, otherwise 32-bit floats
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Element {
+    UInt8,
+    Int8,
+    UInt16,
+    Int16,
+    Float32,
+}
+
+impl Element {
+    fn of(data: &Array2<f32>, data_type: &str) -> Self {
+        let (element, min, max) = match data_type {
+            "uint8" => (Self::UInt8, u8::MIN as f32, u8::MAX as f32),
+            "int8" => (Self::Int8, i8::MIN as f32, i8::MAX as f32),
+            "uint16" => (Self::UInt16, u16::MIN as f32, u16::MAX as f32),
+            "int16" => (Self::Int16, i16::MIN as f32, i16::MAX as f32),
+            _ => return Self::Float32,
+        };
+        // Projections (e.g. means) and masked NaN pixels are not whole numbers
+        let exact = data
+            .iter()
+            .all(|&value| value.fract() == 0.0 && (min..=max).contains(&value));
+        match exact {
+            true => element,
+            false => Self::Float32,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::UInt8 => "uint8",
+            Self::Int8 => "int8",
+            Self::UInt16 => "uint16",
+            Self::Int16 => "int16",
+            Self::Float32 => "float32",
+        }
+    }
+
+    /// The NumPy type string, little endian
+    fn descr(self) -> &'static str {
+        match self {
+            Self::UInt8 => "|u1",
+            Self::Int8 => "|i1",
+            Self::UInt16 => "<u2",
+            Self::Int16 => "<i2",
+            Self::Float32 => "<f4",
+        }
+    }
+
+    fn bytes(self, value: f32) -> Vec<u8> {
+        match self {
+            Self::UInt8 => vec![value as u8],
+            Self::Int8 => (value as i8).to_le_bytes().to_vec(),
+            Self::UInt16 => (value as u16).to_le_bytes().to_vec(),
+            Self::Int16 => (value as i16).to_le_bytes().to_vec(),
+            Self::Float32 => value.to_le_bytes().to_vec(),
+        }
+    }
+}
+
+/// A NumPy `.npy` file (format version 1.0) with a C-order 2-D array of `element` values
+fn npy_bytes(data: &Array2<f32>, element: Element) -> Vec<u8> {
+    let (rows, columns) = data.dim();
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}",
+        element.descr(),
+        rows,
+        columns
+    );
+    // The magic, version and header length take 10 bytes, the data starts aligned to 64
+    let length = (10 + header.len() + 1).div_ceil(64) * 64 - 10;
+    header.push_str(&" ".repeat(length - header.len() - 1));
+    header.push('\n');
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for &value in data.iter() {
+        bytes.extend(element.bytes(value));
+    }
+    bytes
+}
+
+/// Pixels per centimetre of pixels `size` metres wide, as a TIFF resolution
+fn resolution(size: f64) -> Rational {
+    let per_centimetre = 0.01 / size;
+    let denominator = (u32::MAX as f64 / per_centimetre).clamp(1.0, 1e6).floor();
+    Rational {
+        n: (per_centimetre * denominator).round().min(u32::MAX as f64) as u32,
+        d: denominator as u32,
+    }
+}
+
+fn write_tiff_image<W, C>(
+    encoder: &mut TiffEncoder<W>,
+    (width, height): (u32, u32),
+    sizes: Option<(f64, f64)>,
+    data: &[C::Inner],
+) -> Result<()>
+where
+    W: Write + Seek,
+    C: colortype::ColorType,
+    [C::Inner]: TiffValue,
+{
+    let mut image = encoder.new_image::<C>(width, height)?;
+    if let Some((y, x)) = sizes {
+        image.resolution_unit(ResolutionUnit::Centimeter);
+        image.x_resolution(resolution(x));
+        image.y_resolution(resolution(y));
+    }
+    Ok(image.write_data(data)?)
+}
+
+fn write_tiff(file: File, view: &SliceView, element: Element) -> Result<()> {
+    let mut encoder = TiffEncoder::new(BufWriter::new(file))?;
+    let (rows, columns) = view.data.dim();
+    let size = (columns as u32, rows as u32);
+    // Along Y from the aspect of the pixels, square without one
+    let sizes = view
+        .pixel_size()
+        .map(|x| (x * view.pixel_aspect().unwrap_or(1.0), x));
+    let values = view.data.iter();
+    match element {
+        Element::UInt16 => {
+            let data: Vec<u16> = values.map(|&value| value as u16).collect();
+            write_tiff_image::<_, colortype::Gray16>(&mut encoder, size, sizes, &data)
+        }
+        _ => {
+            let data: Vec<f32> = values.copied().collect();
+            write_tiff_image::<_, colortype::Gray32Float>(&mut encoder, size, sizes, &data)
+        }
+    }
+}
+
+/// Write the values of the slice as they were decoded, before any normalization,
+/// to `path` in the format of its extension, creating the directories above it;
+/// `.npy` keeps 8 and 16-bit integer data types and TIFF unsigned ones (as 16 bits),
+/// other values are written as 32-bit floats, and TIFF records the pixel size if known
+pub fn write_raw(view: &SliceView, path: &Path, force: bool) -> Result<()> {
+    let format = check_raw_output(path, force)?;
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let element = match (format, Element::of(&view.data, &view.data_type)) {
+        (RawFormat::Tiff, Element::UInt8 | Element::UInt16) => Element::UInt16,
+        (RawFormat::Tiff, _) => Element::Float32,
+        (RawFormat::Npy, element) => element,
+    };
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let written = match format {
+        RawFormat::Npy => BufWriter::new(file)
+            .write_all(&npy_bytes(&view.data, element))
+            .map_err(anyhow::Error::from),
+        RawFormat::Tiff => write_tiff(file, view, element),
+    };
+    written.with_context(|| format!("Failed to write {}", path.display()))?;
+    let (rows, columns) = view.data.dim();
+    info!(
+        "Exported {}x{} {} values to {}",
+        rows,
+        columns,
+        element.name(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Fail before anything is read if the CSV file `path` exists and is not to be overwritten
+pub fn check_csv_output(path: &Path, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Write the CSV `text` to `path`, creating the directories above it
+pub fn write_csv(path: &Path, text: &str, force: bool) -> Result<()> {
+    check_csv_output(path, force)?;
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::decoder::ifd::Value;
+    use tiff::decoder::{Decoder, DecodingResult};
+    use tiff::tags::Tag;
+
+    fn view(data: Array2<f32>, data_type: &str) -> SliceView {
+        let attributes = serde_json::json!({"multiscales": [{
+            "axes": [{"name": "y", "unit": "micrometer"}, {"name": "x", "unit": "micrometer"}],
+            "datasets": [{"path": "0", "coordinateTransformations": [
+                {"type": "scale", "scale": [1.0, 0.5]}]}]}]});
+        SliceView {
+            data,
+            path: "/0".to_string(),
+            axes: Some(vec!["y".to_string(), "x".to_string()]),
+            start: vec![0, 0],
+            shape: vec![2, 3],
+            data_type: data_type.to_string(),
+            attributes: attributes.as_object().cloned().unwrap_or_default(),
+            step: 1,
+            exact: None,
+        }
+    }
+
+    #[test]
+    fn test_npy_header() {
+        let data = ndarray::array![[1.0f32, 2.0, 3.0], [4.0, 5.0, 65535.0]];
+        let bytes = npy_bytes(&data, Element::of(&data, "uint16"));
+        let header = "{'descr': '<u2', 'fortran_order': False, 'shape': (2, 3), }";
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        assert_eq!(u16::from_le_bytes([bytes[8], bytes[9]]), 118);
+        assert_eq!(&bytes[10..10 + header.len()], header.as_bytes());
+        assert_eq!(bytes[127], b'\n');
+        assert_eq!(bytes.len(), 128 + 6 * 2);
+        assert_eq!(&bytes[138..], &[0xff, 0xff]);
+        // Values that do not fit the data type are written as floats
+        let mean = ndarray::array![[0.5f32, f32::NAN]];
+        assert_eq!(Element::of(&mean, "uint8"), Element::Float32);
+        assert_eq!(Element::of(&(-&data), "int16"), Element::Float32);
+        assert_eq!(Element::of(&(-&data / 1e3), "int8"), Element::Float32);
+        assert_eq!(Element::of(&data, "uint32"), Element::Float32);
+        assert_eq!(npy_bytes(&mean, Element::Float32).len(), 128 + 8);
+    }
+
+    #[test]
+    fn test_write_raw() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("peekzarr-export-{}", std::process::id()));
+        let data = ndarray::array![[0.0f32, 1.0, 2.0], [300.0, 400.0, 500.0]];
+        let path = directory.join("nested/raw.tif");
+        write_raw(&view(data.clone(), "uint16"), &path, false)?;
+        let mut decoder = Decoder::new(File::open(&path)?)?;
+        assert_eq!(decoder.dimensions()?, (3, 2));
+        match decoder.read_image()? {
+            DecodingResult::U16(values) => assert_eq!(values, [0, 1, 2, 300, 400, 500]),
+            _ => anyhow::bail!("Expected 16-bit values"),
+        }
+        // 0.5 µm along X and 1 µm along Y, in pixels per centimetre
+        let mut per_centimetre = |tag: Tag| match decoder.get_tag(tag) {
+            Ok(Value::Rational(n, d)) => n as f64 / d as f64,
+            _ => f64::NAN,
+        };
+        let (x, y) = (
+            per_centimetre(Tag::XResolution),
+            per_centimetre(Tag::YResolution),
+        );
+        assert_eq!((x.round(), y.round()), (20000.0, 10000.0));
+        assert!(write_raw(&view(data.clone(), "uint16"), &path, false).is_err());
+        write_raw(&view(data / 4.0, "float64"), &path, true)?;
+        let mut decoder = Decoder::new(File::open(&path)?)?;
+        match decoder.read_image()? {
+            DecodingResult::F32(values) => assert_eq!(values[5], 125.0),
+            _ => anyhow::bail!("Expected float values"),
+        }
+        let path = directory.join("raw.npy");
+        write_raw(&view(ndarray::array![[1.0, -2.0]], "int8"), &path, false)?;
+        let bytes = std::fs::read(&path)?;
+        assert_eq!(&bytes[bytes.len() - 2..], &[1, 0xfe]);
+        assert!(RawFormat::from_path(Path::new("raw.png")).is_err());
+        std::fs::remove_dir_all(directory)?;
+        Ok(())
+    }
+}
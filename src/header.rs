@@ -0,0 +1,141 @@
+use crate::ngff::{self, Quantity};
+use crate::{omero, SliceView};
+
+/// Symbol of a unit, e.g. `µm` for `micrometer`, or the unit itself if unknown
+pub(crate) fn unit_symbol(unit: &str) -> String {
+    let symbols: &[(f64, &str)] = match ngff::parse_unit(unit) {
+        Some((Quantity::Length, _)) => &[
+            (1e-12, "pm"),
+            (1e-10, "Å"),
+            (1e-9, "nm"),
+            (1e-6, "µm"),
+            (1e-3, "mm"),
+            (1e-2, "cm"),
+            (1.0, "m"),
+        ],
+        Some((Quantity::Time, _)) => &[
+            (1e-12, "ps"),
+            (1e-9, "ns"),
+            (1e-6, "µs"),
+            (1e-3, "ms"),
+            (1.0, "s"),
+            (60.0, "min"),
+            (3600.0, "h"),
+            (86400.0, "d"),
+        ],
+        None => return unit.to_string(),
+    };
+    let size = ngff::parse_unit(unit).map_or(1.0, |(_, size)| size);
+    symbols
+        .iter()
+        .find(|(symbol_size, _)| (symbol_size / size - 1.0).abs() < 1e-9)
+        .map_or_else(|| unit.to_string(), |(_, symbol)| symbol.to_string())
+}
+
+/// One entry per axis with its name, size and pixel spacing,
+/// marking the index shown along non-spatial axes and the channel names,
+/// e.g. `t: 0..240 @12 (Δ30 s)  c: 3 [BF, *GFP*, mCherry]  y: 2048  x: 2048`
+pub fn format_header(view: &SliceView) -> String {
+    let ndims = view.shape.len();
+    let names: Vec<String> = match &view.axes {
+        Some(axes) if axes.len() == ndims => axes.clone(),
+        _ => (0..ndims).map(|i| format!("dim{}", i)).collect(),
+    };
+    let units = ngff::axis_units(&view.attributes).filter(|units| units.len() == ndims);
+    let scale = ngff::physical_transform(&view.attributes, view.dataset_path(), ndims)
+        .map(|(scale, _)| scale);
+    let channel_axis = view
+        .axes
+        .as_deref()
+        .and_then(|axes| ngff::channel_axis(&view.attributes, axes));
+    let labels = omero::channel_labels(&view.attributes);
+    (0..ndims)
+        .map(|i| {
+            let size = view.shape[i];
+            let mut entry = if i + 2 >= ndims {
+                format!("{}: {}", names[i], size)
+            } else if channel_axis == Some(i)
+                && labels.len() as u64 == size
+                && labels.iter().any(Option::is_some)
+            {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .enumerate()
+                    .map(|(index, label)| {
+                        let label = label.clone().unwrap_or_else(|| index.to_string());
+                        if index as u64 == view.start[i] {
+                            format!("*{}*", label)
+                        } else {
+                            label
+                        }
+                    })
+                    .collect();
+                format!("{}: {} [{}]", names[i], size, labels.join(", "))
+            } else {
+                format!("{}: 0..{} @{}", names[i], size, view.start[i])
+            };
+            let unit = units.as_ref().and_then(|units| units[i].as_deref());
+            match (scale.as_ref().map(|scale| scale[i]), unit) {
+                (Some(step), Some(unit)) => {
+                    entry.push_str(&format!(" (Δ{} {})", step, unit_symbol(unit)))
+                }
+                (Some(step), None) if step != 1.0 && channel_axis != Some(i) => {
+                    entry.push_str(&format!(" (Δ{})", step))
+                }
+                _ => {}
+            }
+            entry
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+
+    #[test]
+    fn test_format_header() {
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [
+                    {"name": "t", "type": "time", "unit": "second"},
+                    {"name": "c", "type": "channel"},
+                    {"name": "z", "type": "space", "unit": "micrometer"},
+                    {"name": "y", "type": "space", "unit": "micrometer"},
+                    {"name": "x", "type": "space", "unit": "micrometer"}
+                ],
+                "datasets": [{"path": "0", "coordinateTransformations": [
+                    {"type": "scale", "scale": [30.0, 1.0, 0.25, 0.5, 0.5]}]}]
+            }],
+            "omero": {"channels": [{"label": "BF"}, {"label": "GFP"}, {"label": "mCherry"}]}
+        });
+        let view = SliceView {
+            data: Array2::zeros((4, 4)),
+            path: "/0".to_string(),
+            axes: Some(["t", "c", "z", "y", "x"].map(String::from).to_vec()),
+            start: vec![12, 1, 40, 0, 0],
+            shape: vec![240, 3, 80, 2048, 2048],
+            data_type: "uint16".to_string(),
+            attributes: attributes.as_object().cloned().unwrap_or_default(),
+            step: 1,
+            exact: None,
+        };
+        assert_eq!(
+            format_header(&view),
+            "t: 0..240 @12 (Δ30 s)  c: 3 [BF, *GFP*, mCherry]  z: 0..80 @40 (Δ0.25 µm)  \
+             y: 2048 (Δ0.5 µm)  x: 2048 (Δ0.5 µm)"
+        );
+
+        let bare = SliceView {
+            axes: None,
+            start: vec![1, 0, 0],
+            shape: vec![3, 64, 64],
+            attributes: Default::default(),
+            step: 1,
+            ..view
+        };
+        assert_eq!(format_header(&bare), "dim0: 0..3 @1  dim1: 64  dim2: 64");
+    }
+}
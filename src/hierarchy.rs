@@ -0,0 +1,600 @@
+use crate::storagestats::{format_bytes, StorageStats};
+use crate::{join_path, ngff, open_array};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits, StorageError, StorePrefix};
+
+/// What kind of zarr node lives at a path
+#[derive(Debug, PartialEq)]
+pub enum NodeKind {
+    Array {
+        shape: Vec<u64>,
+        data_type: String,
+        chunk_shape: Vec<u64>,
+        /// Axis names from the OME-NGFF metadata of the parent group
+        axes: Option<Vec<String>>,
+    },
+    Group,
+}
+
+/// A node found while walking a hierarchy
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    pub path: String,
+    pub kind: NodeKind,
+    /// Index in the parent's `multiscales.datasets` if it is a resolution level
+    pub level: Option<usize>,
+}
+
+fn join_dims(dims: &[u64]) -> String {
+    dims.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join("x")
+}
+
+impl Node {
+    /// JSON object with the path, kind, metadata fields and level
+    pub fn to_json(&self) -> Value {
+        let mut value = match &self.kind {
+            NodeKind::Array {
+                shape,
+                data_type,
+                chunk_shape,
+                axes,
+            } => serde_json::json!({
+                "path": self.path,
+                "kind": "array",
+                "shape": shape,
+                "data_type": data_type,
+                "chunk_shape": chunk_shape,
+                "axes": axes,
+            }),
+            NodeKind::Group => serde_json::json!({"path": self.path, "kind": "group"}),
+        };
+        value["level"] = serde_json::json!(self.level);
+        value
+    }
+}
+
+impl fmt::Display for Node {
+    /// One tab-separated line per node: the path, `array` or `group`,
+    /// then `key=value` fields such as `shape=2x3x64x64` and `level=0`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            NodeKind::Array {
+                shape,
+                data_type,
+                chunk_shape,
+                ..
+            } => write!(
+                f,
+                "{}\tarray\tshape={}\tdtype={}\tchunks={}",
+                self.path,
+                join_dims(shape),
+                data_type,
+                join_dims(chunk_shape)
+            )?,
+            NodeKind::Group => write!(f, "{}\tgroup", self.path)?,
+        }
+        if let Some(level) = self.level {
+            write!(f, "\tlevel={}", level)?;
+        }
+        Ok(())
+    }
+}
+
+/// Child paths referenced by OME-NGFF metadata of a group:
+/// resolution levels, labels, plate wells, well fields and bioformats2raw series
+fn referenced_children(attributes: &serde_json::Map<String, Value>) -> Vec<String> {
+    let attributes = ngff::ome_attributes(attributes);
+    let paths = |key: &str, field: &str| -> Vec<String> {
+        attributes
+            .get(key)
+            .and_then(|value| value.get(field))
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.get("path").and_then(Value::as_str))
+            .map(str::to_string)
+            .collect()
+    };
+    let mut children = ngff::dataset_paths(attributes).unwrap_or_default();
+    children.extend(paths("plate", "wells"));
+    children.extend(paths("well", "images"));
+    children.extend(ngff::label_names(attributes));
+    if ngff::bioformats2raw_layout(attributes).is_some() {
+        children.push("OME".to_string());
+    }
+    children
+}
+
+/// Names of the direct children of `group`, from store listing where supported
+/// and from OME-NGFF metadata otherwise (e.g. over plain HTTP)
+pub fn child_names<TStore>(store: &Arc<TStore>, group: &str) -> Result<Vec<String>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let mut names = BTreeSet::new();
+    let prefix = match group.trim_matches('/') {
+        "" => StorePrefix::root(),
+        path => StorePrefix::new(format!("{}/", path))?,
+    };
+    match store.list_dir(&prefix) {
+        Ok(listing) => names.extend(listing.prefixes().iter().map(|child| {
+            child.as_str()[prefix.as_str().len()..]
+                .trim_end_matches('/')
+                .to_string()
+        })),
+        Err(StorageError::Unsupported(_)) => {}
+        Err(err) => return Err(err.into()),
+    }
+    let attributes = ngff::group_attributes(store, group)?;
+    if let Some(attributes) = &attributes {
+        names.extend(referenced_children(attributes));
+    }
+    // Label images are not referenced from the image group metadata
+    if attributes.is_some() && ngff::group_attributes(store, &join_path(group, "labels"))?.is_some()
+    {
+        names.insert("labels".to_string());
+    }
+    Ok(names.into_iter().collect())
+}
+
+/// Paths of the multiscale image groups below `group` up to `depth` levels,
+/// not descending into the images found
+pub fn find_images<TStore>(store: &Arc<TStore>, group: &str, depth: usize) -> Result<Vec<String>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let mut images = vec![];
+    if depth == 0 {
+        return Ok(images);
+    }
+    for name in child_names(store, group)? {
+        let path = join_path(group, &name);
+        let attributes = ngff::group_attributes(store, &path)?;
+        if attributes.as_ref().and_then(ngff::dataset_paths).is_some() {
+            images.push(path);
+        } else if open_array(store.clone(), &path).is_err() {
+            // Plain directories without group metadata may still contain images
+            images.extend(find_images(store, &path, depth - 1)?);
+        }
+    }
+    Ok(images)
+}
+
+/// Walk the hierarchy below `group` up to `depth` levels, listing arrays and groups
+pub fn list_nodes<TStore>(store: &Arc<TStore>, group: &str, depth: usize) -> Result<Vec<Node>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let mut nodes = vec![];
+    if depth == 0 {
+        return Ok(nodes);
+    }
+    let levels = ngff::group_attributes(store, group)?
+        .as_ref()
+        .and_then(ngff::dataset_paths)
+        .unwrap_or_default();
+    for name in child_names(store, group)? {
+        let path = join_path(group, &name);
+        let level = levels.iter().position(|level| *level == name);
+        if let Ok(array) = open_array(store.clone(), &path) {
+            let chunk_shape = array
+                .chunk_shape(&vec![0; array.dimensionality()])?
+                .iter()
+                .map(|size| size.get())
+                .collect();
+            let axes = ngff::array_axis_names(store, &path)?
+                .filter(|axes| axes.len() == array.dimensionality());
+            nodes.push(Node {
+                path,
+                kind: NodeKind::Array {
+                    shape: array.shape().to_vec(),
+                    data_type: array.data_type().to_string(),
+                    chunk_shape,
+                    axes,
+                },
+                level,
+            });
+        } else if ngff::group_attributes(store, &path)?.is_some() {
+            let children = list_nodes(store, &path, depth - 1)?;
+            nodes.push(Node {
+                path,
+                kind: NodeKind::Group,
+                level,
+            });
+            nodes.extend(children);
+        }
+    }
+    Ok(nodes)
+}
+
+/// An array of a resolution level, as shown in the pyramid tree
+#[derive(Debug, PartialEq)]
+pub struct LevelArray {
+    pub shape: Vec<u64>,
+    pub data_type: String,
+    /// Shape of the chunks (shards, if sharded) in the chunk grid
+    pub chunk_shape: Vec<u64>,
+    pub chunks: u64,
+    /// Factor each dimension is downscaled by from the first level,
+    /// `None` if the number of dimensions differs
+    pub downscale: Option<Vec<f64>>,
+    /// Stored size from listing the chunk objects, if asked for
+    pub storage: Option<StorageStats>,
+}
+
+/// A resolution level of a multiscale image, `None` if its array is missing
+#[derive(Debug, PartialEq)]
+pub struct PyramidLevel {
+    pub path: String,
+    pub array: Option<LevelArray>,
+}
+
+/// A multiscale image with its resolution levels and label images
+#[derive(Debug, PartialEq)]
+pub struct Pyramid {
+    pub path: String,
+    pub axes: Option<Vec<String>>,
+    /// Whether it is a label image, with `image-label` metadata
+    pub label: bool,
+    pub levels: Vec<PyramidLevel>,
+    pub labels: Vec<Pyramid>,
+}
+
+/// A downscale factor without trailing zeros, e.g. `2` or `1.5`
+fn format_factor(factor: f64) -> String {
+    let rounded = (factor * 100.0).round() / 100.0;
+    rounded.to_string()
+}
+
+impl PyramidLevel {
+    /// The name of the level followed by its metadata, e.g. `1  shape=32x32 ...  downscale=2x2`
+    fn line(&self) -> String {
+        let name = self.path.rsplit('/').next().unwrap_or_default();
+        let Some(array) = &self.array else {
+            return format!("{}  missing", name);
+        };
+        let mut fields = vec![
+            name.to_string(),
+            format!("shape={}", join_dims(&array.shape)),
+            format!("dtype={}", array.data_type),
+            format!(
+                "chunks={} ({})",
+                join_dims(&array.chunk_shape),
+                array.chunks
+            ),
+        ];
+        fields.push(match &array.downscale {
+            Some(factors) => {
+                let factors: Vec<String> = factors.iter().copied().map(format_factor).collect();
+                format!("downscale={}", factors.join("x"))
+            }
+            None => "downscale=?".to_string(),
+        });
+        if let Some(storage) = &array.storage {
+            fields.push(format!("stored={}", format_bytes(storage.stored_bytes)));
+            if storage.missing_chunks > 0 {
+                fields.push(format!("missing={}", storage.missing_chunks));
+            }
+        }
+        fields.join("  ")
+    }
+
+    /// JSON object with the path and the array metadata, `missing` if there is no array
+    pub fn to_json(&self) -> Value {
+        match &self.array {
+            Some(array) => serde_json::json!({
+                "path": self.path,
+                "shape": array.shape,
+                "data_type": array.data_type,
+                "chunk_shape": array.chunk_shape,
+                "chunks": array.chunks,
+                "downscale": array.downscale,
+                "storage": array.storage.as_ref().map(StorageStats::to_json),
+            }),
+            None => serde_json::json!({"path": self.path, "missing": true}),
+        }
+    }
+}
+
+impl Pyramid {
+    /// JSON object with the levels and the label images
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "path": self.path,
+            "axes": self.axes,
+            "label": self.label,
+            "levels": self.levels.iter().map(PyramidLevel::to_json).collect::<Vec<_>>(),
+            "labels": self.labels.iter().map(Pyramid::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    fn title(&self, name: &str) -> String {
+        let kind = match self.label {
+            true => "labels",
+            false => "image",
+        };
+        let levels = match self.levels.len() {
+            1 => "1 level".to_string(),
+            count => format!("{} levels", count),
+        };
+        match &self.axes {
+            Some(axes) => format!(
+                "{} (multiscale {}, {}: {})",
+                name,
+                kind,
+                levels,
+                axes.join(", ")
+            ),
+            None => format!("{} (multiscale {}, {})", name, kind, levels),
+        }
+    }
+
+    fn write_children(&self, lines: &mut Vec<String>, indent: &str) {
+        let last = self.levels.len() + (!self.labels.is_empty()) as usize;
+        let branch = |i: usize| match i + 1 == last {
+            true => ("└── ", "    "),
+            false => ("├── ", "│   "),
+        };
+        for (i, level) in self.levels.iter().enumerate() {
+            lines.push(format!("{}{}{}", indent, branch(i).0, level.line()));
+        }
+        if self.labels.is_empty() {
+            return;
+        }
+        let (head, rest) = branch(last - 1);
+        lines.push(format!("{}{}labels", indent, head));
+        let indent = format!("{}{}", indent, rest);
+        for (i, label) in self.labels.iter().enumerate() {
+            let (head, rest) = match i + 1 == self.labels.len() {
+                true => ("└── ", "    "),
+                false => ("├── ", "│   "),
+            };
+            let name = label.path.rsplit('/').next().unwrap_or_default();
+            lines.push(format!("{}{}{}", indent, head, label.title(name)));
+            label.write_children(lines, &format!("{}{}", indent, rest));
+        }
+    }
+}
+
+impl fmt::Display for Pyramid {
+    /// The image group, then one line per level and label image drawn as a tree
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = vec![self.title(&self.path)];
+        self.write_children(&mut lines, "");
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// The resolution levels and label images of the multiscale image at `group`,
+/// with the stored size of each level from listing its chunks if `sizes`
+pub fn pyramid_tree<TStore>(store: &Arc<TStore>, group: &str, sizes: bool) -> Result<Pyramid>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let attributes = ngff::group_attributes(store, group)?;
+    let multiscale = attributes
+        .as_ref()
+        .and_then(ngff::multiscale)
+        .filter(|multiscale| !multiscale.datasets.is_empty())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No multiscales metadata in {}, list its arrays with --list",
+                group
+            )
+        })?;
+    let axes = multiscale
+        .axes
+        .map(|axes| axes.into_iter().map(|axis| axis.name).collect());
+    let mut levels = vec![];
+    let mut first: Option<Vec<u64>> = None;
+    for dataset in &multiscale.datasets {
+        let path = join_path(group, &dataset.path);
+        let Ok(array) = open_array(store.clone(), &path) else {
+            levels.push(PyramidLevel { path, array: None });
+            continue;
+        };
+        let shape = array.shape().to_vec();
+        let finest = first.get_or_insert_with(|| shape.clone());
+        let downscale = (finest.len() == shape.len()).then(|| {
+            finest
+                .iter()
+                .zip(&shape)
+                .map(|(&finest, &size)| finest as f64 / size.max(1) as f64)
+                .collect()
+        });
+        let chunk_shape = array
+            .chunk_shape(&vec![0; array.dimensionality()])?
+            .iter()
+            .map(|size| size.get())
+            .collect();
+        let storage = sizes
+            .then(|| crate::storagestats::storage_stats(store, &path))
+            .transpose()?;
+        levels.push(PyramidLevel {
+            array: Some(LevelArray {
+                shape,
+                data_type: array.data_type().to_string(),
+                chunk_shape,
+                chunks: array.chunk_grid_shape().iter().product(),
+                downscale,
+                storage,
+            }),
+            path,
+        });
+    }
+    let labels_group = join_path(group, "labels");
+    let labels = match ngff::group_attributes(store, &labels_group)? {
+        Some(attributes) => ngff::label_names(&attributes)
+            .iter()
+            .map(|name| pyramid_tree(store, &join_path(&labels_group, name), sizes))
+            .collect::<Result<_>>()?,
+        None => vec![],
+    };
+    Ok(Pyramid {
+        path: group.to_string(),
+        axes,
+        label: attributes.as_ref().is_some_and(ngff::is_label_image),
+        levels,
+        labels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_group;
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    fn array(store: &Arc<MemoryStore>, path: &str, shape: Vec<u64>) -> Result<()> {
+        ArrayBuilder::new(
+            shape,
+            vec![1, 32, 32],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .build(store.clone(), path)?
+        .store_metadata()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_nodes() -> Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        store_group(
+            &store,
+            "/",
+            serde_json::json!({"multiscales": [{"datasets": [{"path": "s0"}, {"path": "s1"}]}]}),
+        )?;
+        array(&store, "/s0", vec![2, 64, 64])?;
+        array(&store, "/s1", vec![2, 32, 32])?;
+        store_group(&store, "/labels", serde_json::json!({"labels": ["cells"]}))?;
+        store_group(
+            &store,
+            "/labels/cells",
+            serde_json::json!({"multiscales": [{"datasets": [{"path": "0"}]}]}),
+        )?;
+        array(&store, "/labels/cells/0", vec![2, 64, 64])?;
+
+        let lines: Vec<String> = list_nodes(&store, "/", 3)?
+            .iter()
+            .map(Node::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "/labels\tgroup",
+                "/labels/cells\tgroup",
+                "/labels/cells/0\tarray\tshape=2x64x64\tdtype=uint16\tchunks=1x32x32\tlevel=0",
+                "/s0\tarray\tshape=2x64x64\tdtype=uint16\tchunks=1x32x32\tlevel=0",
+                "/s1\tarray\tshape=2x32x32\tdtype=uint16\tchunks=1x32x32\tlevel=1",
+            ]
+        );
+
+        let json = list_nodes(&store, "/", 3)?[3].to_json();
+        assert_eq!(json["kind"], "array");
+        assert_eq!(json["shape"], serde_json::json!([2, 64, 64]));
+        assert_eq!(json["axes"], Value::Null);
+        assert_eq!(json["level"], 0);
+
+        let shallow = list_nodes(&store, "/", 1)?;
+        assert_eq!(shallow.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pyramid_tree() -> Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        let datasets = serde_json::json!([{"path": "0"}, {"path": "1"}, {"path": "2"}]);
+        store_group(
+            &store,
+            "/",
+            serde_json::json!({"multiscales": [{"axes": ["t", "y", "x"], "datasets": datasets}]}),
+        )?;
+        array(&store, "/0", vec![2, 64, 64])?;
+        array(&store, "/1", vec![2, 32, 32])?;
+        store_group(&store, "/labels", serde_json::json!({"labels": ["cells"]}))?;
+        store_group(
+            &store,
+            "/labels/cells",
+            serde_json::json!({"image-label": {},
+                "multiscales": [{"datasets": [{"path": "0"}]}]}),
+        )?;
+        array(&store, "/labels/cells/0", vec![2, 64, 64])?;
+
+        let tree = pyramid_tree(&store, "/", false)?;
+        assert_eq!(
+            tree.levels[1].array.as_ref().map(|array| array.chunks),
+            Some(2)
+        );
+        assert_eq!(
+            tree.to_string(),
+            "/ (multiscale image, 3 levels: t, y, x)\n\
+             ├── 0  shape=2x64x64  dtype=uint16  chunks=1x32x32 (8)  downscale=1x1x1\n\
+             ├── 1  shape=2x32x32  dtype=uint16  chunks=1x32x32 (2)  downscale=1x2x2\n\
+             ├── 2  missing\n\
+             └── labels\n    \
+                 └── cells (multiscale labels, 1 level)\n        \
+                     └── 0  shape=2x64x64  dtype=uint16  chunks=1x32x32 (8)  downscale=1x1x1"
+        );
+        assert_eq!(tree.to_json()["levels"][2]["missing"], true);
+        assert_eq!(tree.to_json()["labels"][0]["label"], true);
+        // Nothing is stored in these arrays
+        let sized = pyramid_tree(&store, "/", true)?;
+        assert!(sized
+            .to_string()
+            .contains("downscale=1x2x2  stored=0 B  missing=2\n"));
+        assert!(pyramid_tree(&store, "/labels", false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_images() -> Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        let multiscales = serde_json::json!({"multiscales": [{"datasets": [{"path": "0"}]}]});
+        store_group(&store, "/", serde_json::json!({}))?;
+        store_group(&store, "/a.zarr", multiscales.clone())?;
+        array(&store, "/a.zarr/0", vec![1, 32, 32])?;
+        store_group(
+            &store,
+            "/a.zarr/labels",
+            serde_json::json!({"labels": ["cells"]}),
+        )?;
+        store_group(&store, "/a.zarr/labels/cells", multiscales.clone())?;
+        store_group(&store, "/run", serde_json::json!({}))?;
+        store_group(&store, "/run/b.zarr", multiscales.clone())?;
+        store_group(&store, "/run/nested", serde_json::json!({}))?;
+        store_group(&store, "/run/nested/c.zarr", multiscales.clone())?;
+        // A directory without group metadata
+        store_group(&store, "/plain/d.zarr", multiscales)?;
+
+        assert_eq!(
+            find_images(&store, "/", 2)?,
+            vec!["/a.zarr", "/plain/d.zarr", "/run/b.zarr"]
+        );
+        assert_eq!(find_images(&store, "/run", 1)?, vec!["/run/b.zarr"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_child_names_without_listing() -> Result<()> {
+        let memory = Arc::new(MemoryStore::new());
+        store_group(
+            &memory,
+            "/",
+            serde_json::json!({"multiscales": [{"datasets": [{"path": "0"}]}]}),
+        )?;
+        array(&memory, "/0", vec![1, 32, 32])?;
+        store_group(&memory, "/labels", serde_json::json!({"labels": []}))?;
+        let store = Arc::new(crate::store::Unlisted::new(memory));
+        assert_eq!(child_names(&store, "/")?, vec!["0", "labels"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::SliceStats;
+use ndarray::Array2;
+
+/// Block characters from an empty bin to the fullest one
+pub(crate) const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Number of bins of the histogram under the image and of `--export-hist`
+pub const BINS: usize = 64;
+
+/// Counts of values in equal-width bins from `start`
+#[derive(Debug, PartialEq)]
+pub struct Histogram {
+    pub start: f64,
+    pub bin_width: f64,
+    pub counts: Vec<u64>,
+}
+
+impl Histogram {
+    /// Histogram of the finite values in up to `bins` bins over their range,
+    /// with bins of whole numbers of integers if `integer`, `None` without finite values
+    pub fn new(data: &Array2<f32>, bins: usize, integer: bool) -> Option<Self> {
+        let values = || data.iter().copied().filter(|value| value.is_finite());
+        let min = values().fold(f32::INFINITY, f32::min) as f64;
+        let max = values().fold(f32::NEG_INFINITY, f32::max) as f64;
+        if min > max {
+            return None;
+        }
+        let (bin_width, bins) = if integer {
+            // Bins of whole integers centered on them, one per value over a small range
+            let span = max - min + 1.0;
+            let width = (span / bins as f64).ceil();
+            (width, (span / width).ceil() as usize)
+        } else if max > min {
+            ((max - min) / bins as f64, bins)
+        } else {
+            (1.0, 1)
+        };
+        let start = if integer { min - 0.5 } else { min };
+        let mut counts = vec![0; bins];
+        for value in values() {
+            let bin = ((value as f64 - start) / bin_width) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+        Some(Self {
+            start,
+            bin_width,
+            counts,
+        })
+    }
+
+    /// Index of the bin of `value`, clamped to the histogram
+    fn bin(&self, value: f64) -> usize {
+        let bin = ((value - self.start) / self.bin_width).max(0.0) as usize;
+        bin.min(self.counts.len() - 1)
+    }
+
+    /// The bins as a line of block characters, scaled to the fullest bin,
+    /// with a line below marking the bins of the cut points with `^`
+    pub fn format(&self, cuts: (f32, f32)) -> String {
+        let peak = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        let bars: String = self
+            .counts
+            .iter()
+            .map(|&count| {
+                // Any nonzero count shows at least the lowest block
+                let level = (count as f64 / peak as f64 * 8.0).ceil() as usize;
+                BLOCKS[level]
+            })
+            .collect();
+        let marks = [self.bin(cuts.0 as f64), self.bin(cuts.1 as f64)];
+        let markers: String = (0..self.counts.len())
+            .map(|bin| if marks.contains(&bin) { '^' } else { ' ' })
+            .collect();
+        format!("{}\n{}", bars, markers.trim_end())
+    }
+}
+
+/// Histograms as CSV with a row per bin: the channel index (`0` without a channel axis),
+/// the start and end of the bin and the number of finite values in it
+pub fn format_csv(histograms: &[(usize, Histogram)]) -> String {
+    let mut lines = vec!["channel,bin_start,bin_end,count".to_string()];
+    for (channel, histogram) in histograms {
+        for (bin, count) in histogram.counts.iter().enumerate() {
+            let start = histogram.start + bin as f64 * histogram.bin_width;
+            lines.push(format!(
+                "{},{},{},{}",
+                channel,
+                start,
+                start + histogram.bin_width,
+                count
+            ));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// The histogram of the slice with the normalization cut points marked,
+/// followed by its extrema and quantiles
+pub fn format_histogram(
+    data: &Array2<f32>,
+    integer: bool,
+    stats: &SliceStats,
+    cuts: (f32, f32),
+) -> String {
+    let mut lines = vec![];
+    if let Some(histogram) = Histogram::new(data, BINS, integer) {
+        lines.push(histogram.format(cuts));
+    }
+    let mut summary = vec![format!("min {}", stats.min), format!("max {}", stats.max)];
+    summary.extend(
+        stats
+            .quantiles
+            .iter()
+            .map(|(quantile, value)| format!("q{} {}", quantile, value)),
+    );
+    if stats.nan_fraction > 0.0 {
+        summary.push(format!("nan {:.1}%", stats.nan_fraction * 100.0));
+    }
+    lines.push(summary.join("  "));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use ndarray::array;
+
+    #[test]
+    fn test_integer_bins() {
+        // One bin per integer over a small range
+        let histogram = Histogram::new(&array![[3.0, 4.0, 4.0, 7.0]], 64, true);
+        assert_eq!(
+            histogram,
+            Some(Histogram {
+                start: 2.5,
+                bin_width: 1.0,
+                counts: vec![1, 2, 0, 0, 1],
+            })
+        );
+        // Whole integers per bin over a larger range
+        let wide = Histogram::new(&array![[0.0, 999.0]], 64, true).expect("finite values");
+        assert_eq!(wide.bin_width, 16.0);
+        assert_eq!(wide.counts.len(), 63);
+        assert_eq!(wide.counts[62], 1);
+    }
+
+    #[test]
+    fn test_float_bins() {
+        let histogram =
+            Histogram::new(&array![[0.0, 0.5, 1.0, f32::NAN]], 4, false).expect("finite values");
+        assert_eq!(histogram.bin_width, 0.25);
+        // The maximum falls in the last bin
+        assert_eq!(histogram.counts, vec![1, 0, 1, 1]);
+        assert_eq!(Histogram::new(&array![[f32::NAN]], 4, false), None);
+        let flat = Histogram::new(&array![[2.0, 2.0]], 4, false).expect("finite values");
+        assert_eq!(flat.counts, vec![2]);
+    }
+
+    #[test]
+    fn test_format_histogram() -> Result<()> {
+        let data = array![[0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 3.0]];
+        let histogram = Histogram::new(&data, 64, true).expect("finite values");
+        assert_eq!(histogram.format((1.0, 3.0)), "▂█ ▂\n ^ ^");
+        let stats = crate::slice_stats(&data, &[0.5])?;
+        assert_eq!(
+            format_histogram(&data, true, &stats, (1.0, 3.0)),
+            "▂█ ▂\n ^ ^\nmin 0  max 3  q0.5 1"
+        );
+        assert_eq!(
+            format_csv(&[(1, histogram)]),
+            "channel,bin_start,bin_end,count\n1,-0.5,0.5,1\n1,0.5,1.5,7\n1,1.5,2.5,0\n1,2.5,3.5,1\n"
+        );
+        let masked = array![[0.0, f32::NAN, 2.0, 2.0]];
+        let stats = crate::slice_stats(&masked, &[])?;
+        assert!(format_histogram(&masked, false, &stats, (0.0, 2.0)).ends_with("  nan 25.0%"));
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{fill_value_json, ngff, open_array, resolve_array_path, ReadOptions};
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::sync::Arc;
+use zarrs::array::ArrayMetadataOptions;
+use zarrs::config::MetadataConvertVersion;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// Metadata of an array, gathered without reading any chunks
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayInfo {
+    pub path: String,
+    /// OME-NGFF version of the image group containing the array
+    pub ngff_version: Option<String>,
+    pub shape: Vec<u64>,
+    /// Axis names from OME-NGFF metadata or zarr v3 dimension names
+    pub axes: Option<Vec<String>>,
+    pub data_type: String,
+    /// Shape of the chunks (shards, if sharded) in the chunk grid
+    pub chunk_shape: Vec<u64>,
+    /// Shape of the inner chunks if the array is sharded
+    pub inner_chunk_shape: Option<Vec<u64>>,
+    /// Codec chain as zarr v3 codecs, converted from v2 compressor and filters
+    pub codecs: String,
+    pub fill_value: String,
+    /// Number of chunks (shards, if sharded) along each dimension
+    pub chunk_grid_shape: Vec<u64>,
+}
+
+pub(crate) fn join_dims(dims: &[u64]) -> String {
+    dims.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join("x")
+}
+
+/// Describe a codec chain, e.g. `bytes(endian="little") -> zstd(level=0, checksum=false)`
+fn describe_codecs(codecs: &[Value]) -> String {
+    codecs
+        .iter()
+        .map(|codec| {
+            let name = codec.get("name").and_then(Value::as_str).unwrap_or("?");
+            match codec.get("configuration").and_then(Value::as_object) {
+                Some(configuration) if !configuration.is_empty() => {
+                    format!("{}({})", name, describe_configuration(configuration))
+                }
+                _ => name.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+fn describe_configuration(configuration: &Map<String, Value>) -> String {
+    configuration
+        .iter()
+        .map(|(key, value)| match value.as_array() {
+            // Nested codec chains of sharding
+            Some(codecs) if key.ends_with("codecs") => {
+                format!("{}=[{}]", key, describe_codecs(codecs))
+            }
+            _ => format!("{}={}", key, value),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl ArrayInfo {
+    /// JSON object with the metadata fields, the codecs as a description string
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "path": self.path,
+            "ngff_version": self.ngff_version,
+            "shape": self.shape,
+            "axes": self.axes,
+            "data_type": self.data_type,
+            "chunk_shape": self.chunk_shape,
+            "inner_chunk_shape": self.inner_chunk_shape,
+            "codecs": self.codecs,
+            "fill_value": serde_json::from_str::<Value>(&self.fill_value).unwrap_or(Value::Null),
+            "chunk_grid_shape": self.chunk_grid_shape,
+        })
+    }
+}
+
+impl fmt::Display for ArrayInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Array: {}", self.path)?;
+        if let Some(version) = &self.ngff_version {
+            writeln!(f, "OME-NGFF version: {}", version)?;
+        }
+        match &self.axes {
+            Some(axes) => writeln!(f, "Shape: {} ({})", join_dims(&self.shape), axes.join(", "))?,
+            None => writeln!(f, "Shape: {}", join_dims(&self.shape))?,
+        }
+        writeln!(f, "Data type: {}", self.data_type)?;
+        writeln!(f, "Chunk shape: {}", join_dims(&self.chunk_shape))?;
+        match &self.inner_chunk_shape {
+            Some(inner) => writeln!(f, "Sharding: inner chunks {}", join_dims(inner))?,
+            None => writeln!(f, "Sharding: none")?,
+        }
+        writeln!(f, "Codecs: {}", self.codecs)?;
+        writeln!(f, "Fill value: {}", self.fill_value)?;
+        write!(
+            f,
+            "Chunks: {} ({})",
+            self.chunk_grid_shape.iter().product::<u64>(),
+            join_dims(&self.chunk_grid_shape)
+        )
+    }
+}
+
+/// Read the metadata of the array that [`crate::read_slice`] would read
+pub fn array_info<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+) -> Result<ArrayInfo>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let multiscale = ngff::group_attributes(&store, ngff::parent_path(&path))?
+        .as_ref()
+        .and_then(ngff::multiscale);
+    let ngff_version = multiscale
+        .as_ref()
+        .and_then(|multiscale| multiscale.version.clone());
+    let axes = multiscale
+        .and_then(|multiscale| multiscale.axes)
+        .map(|axes| axes.into_iter().map(|axis| axis.name).collect::<Vec<_>>());
+    let array = open_array(store, &path)?;
+    let shape = array.shape().to_vec();
+    let axes = axes
+        .or_else(|| array.dimension_names().clone()?.into_iter().collect())
+        .filter(|axes| axes.len() == shape.len());
+    let chunk_shape = array
+        .chunk_shape(&vec![0; array.dimensionality()])?
+        .iter()
+        .map(|size| size.get())
+        .collect();
+    // Describe v2 compressors and filters in terms of the equivalent v3 codecs
+    let metadata = array.metadata_opt(
+        &ArrayMetadataOptions::default()
+            .with_metadata_convert_version(MetadataConvertVersion::V3)
+            .with_include_zarrs_metadata(false),
+    );
+    let metadata = serde_json::to_value(metadata)?;
+    let codecs = metadata
+        .get("codecs")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let inner_chunk_shape = codecs
+        .iter()
+        .find(|codec| codec.get("name").and_then(Value::as_str) == Some("sharding_indexed"))
+        .and_then(|codec| codec.get("configuration")?.get("chunk_shape"))
+        .and_then(|shape| serde_json::from_value(shape.clone()).ok());
+    Ok(ArrayInfo {
+        path,
+        ngff_version,
+        shape,
+        axes,
+        data_type: array.data_type().to_string(),
+        chunk_shape,
+        inner_chunk_shape,
+        codecs: describe_codecs(&codecs),
+        fill_value: fill_value_json(&array),
+        chunk_grid_shape: array.chunk_grid_shape().to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_group;
+    use zarrs::array::codec::ShardingCodecBuilder;
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    #[test]
+    fn test_array_info() -> Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        store_group(
+            &store,
+            "/",
+            serde_json::json!({"multiscales": [{
+                "version": "0.4",
+                "axes": [{"name": "c"}, {"name": "y"}, {"name": "x"}],
+                "datasets": [{"path": "0"}]
+            }]}),
+        )?;
+        ArrayBuilder::new(
+            vec![2, 100, 64],
+            vec![1, 64, 64],
+            DataType::UInt16,
+            FillValue::from(7u16),
+        )
+        .array_to_bytes_codec(Arc::new(
+            ShardingCodecBuilder::new(vec![1, 16, 16].try_into()?).build(),
+        ))
+        .build(store.clone(), "/0")?
+        .store_metadata()?;
+
+        let info = array_info(store, None, &ReadOptions::default())?;
+        assert_eq!(info.axes, Some(vec!["c".into(), "y".into(), "x".into()]));
+        assert_eq!(info.inner_chunk_shape, Some(vec![1, 16, 16]));
+        assert_eq!(info.chunk_grid_shape, vec![2, 2, 1]);
+        assert!(info.codecs.starts_with("sharding_indexed("));
+        assert!(info.codecs.contains("codecs=[bytes"));
+        let text = info.to_string();
+        assert!(text.contains("OME-NGFF version: 0.4\nShape: 2x100x64 (c, y, x)"));
+        assert!(text.contains("Chunk shape: 1x64x64"));
+        assert!(text.contains("Fill value: 7"));
+        assert!(text.ends_with("Chunks: 4 (2x2x1)"));
+        let json = info.to_json();
+        assert_eq!(json["axes"], serde_json::json!(["c", "y", "x"]));
+        assert_eq!(json["fill_value"], 7);
+        Ok(())
+    }
+}
@@ -0,0 +1,518 @@
+use crate::roi::{Roi, RoiRange};
+use crate::{ngff, SliceIndex, SliceValue, SliceView, Window};
+use anyhow::Result;
+
+/// A key pressed in the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Char(char),
+}
+
+/// The keys in bytes read from the terminal, leaving out the escape sequences of other keys
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let mut rest = bytes;
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != 0x1b {
+            keys.push(Key::Char(byte as char));
+            continue;
+        }
+        // CSI or SS3 sequences, e.g. ESC [ A or ESC O A for the up arrow
+        let Some((b'[' | b'O', tail)) = rest.split_first() else {
+            continue;
+        };
+        let end = tail
+            .iter()
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .unwrap_or(tail.len().saturating_sub(1));
+        let key = match tail.get(end) {
+            Some(b'A') => Some(Key::Up),
+            Some(b'B') => Some(Key::Down),
+            Some(b'C') => Some(Key::Right),
+            Some(b'D') => Some(Key::Left),
+            _ => None,
+        };
+        keys.extend(key);
+        rest = tail.get(end + 1..).unwrap_or_default();
+    }
+    keys
+}
+
+/// What a key does in interactive mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Step along Z by this many indices
+    Z(i64),
+    /// Step along T by this many indices
+    T(i64),
+    /// Show the next channel
+    Channel,
+    /// Halve the size of the region shown
+    ZoomIn,
+    /// Double the size of the region shown
+    ZoomOut,
+    /// Move the region by a quarter of its size along Y and X
+    Pan(i64, i64),
+    /// Take the contrast window of the slice shown, from its quantiles or omero window
+    AutoContrast,
+    /// Move the value shown black by this many steps
+    Low(i64),
+    /// Move the value shown white by this many steps
+    High(i64),
+    /// Raise or lower the gamma by this many steps
+    Gamma(i64),
+    /// Swap black and white
+    Invert,
+    Quit,
+}
+
+impl Action {
+    /// The action of a key: arrows step through Z, `,` and `.` through T, `c` cycles
+    /// the channels, `+` and `-` zoom, `hjkl` pan, `a` or `r` recomputes the contrast,
+    /// `[` and `]` move the low clip, `{` and `}` the high clip, `g` and `G` the gamma,
+    /// `i` inverts and `q` quits
+    pub fn from_key(key: Key) -> Option<Self> {
+        Some(match key {
+            Key::Up | Key::Right => Self::Z(1),
+            Key::Down | Key::Left => Self::Z(-1),
+            Key::Char('.') => Self::T(1),
+            Key::Char(',') => Self::T(-1),
+            Key::Char('c') => Self::Channel,
+            Key::Char('+' | '=') => Self::ZoomIn,
+            Key::Char('-' | '_') => Self::ZoomOut,
+            Key::Char('h') => Self::Pan(0, -1),
+            Key::Char('j') => Self::Pan(1, 0),
+            Key::Char('k') => Self::Pan(-1, 0),
+            Key::Char('l') => Self::Pan(0, 1),
+            Key::Char('a' | 'r') => Self::AutoContrast,
+            Key::Char('[') => Self::Low(-1),
+            Key::Char(']') => Self::Low(1),
+            Key::Char('{') => Self::High(-1),
+            Key::Char('}') => Self::High(1),
+            Key::Char('g') => Self::Gamma(-1),
+            Key::Char('G') => Self::Gamma(1),
+            Key::Char('i') => Self::Invert,
+            // Ctrl-C and Ctrl-D, which do not signal in raw mode
+            Key::Char('q' | '\x03' | '\x04') => Self::Quit,
+            _ => return None,
+        })
+    }
+
+    /// Whether the action moves through the image, so that another slice is read
+    pub fn navigates(self) -> bool {
+        matches!(
+            self,
+            Self::Z(_) | Self::T(_) | Self::Channel | Self::ZoomIn | Self::ZoomOut | Self::Pan(..)
+        )
+    }
+}
+
+/// Steps of the clips across the automatic window
+const CLIP_STEPS: f32 = 50.0;
+
+/// Factor of one step of the gamma
+const GAMMA_STEP: f32 = 1.1;
+
+/// The display contrast adjusted with the keys, rendered from the slice already read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contrast {
+    pub min: f32,
+    pub max: f32,
+    pub gamma: f32,
+    pub invert: bool,
+    /// Step of the clips, a fraction of the automatic window
+    step: f32,
+}
+
+impl Contrast {
+    /// The contrast at the automatic `window`
+    pub fn new(window: Window, gamma: f32, invert: bool) -> Self {
+        let step = match window.max > window.min {
+            true => (window.max - window.min) / CLIP_STEPS,
+            false => 1.0,
+        };
+        Self {
+            min: window.min,
+            max: window.max,
+            gamma,
+            invert,
+            step,
+        }
+    }
+
+    /// Apply a contrast action, keeping the low clip below the high one,
+    /// returning whether anything changed
+    pub fn apply(&mut self, action: Action) -> bool {
+        let before = *self;
+        match action {
+            Action::Low(steps) => {
+                self.min = (self.min + steps as f32 * self.step).min(self.max - self.step);
+            }
+            Action::High(steps) => {
+                self.max = (self.max + steps as f32 * self.step).max(self.min + self.step);
+            }
+            Action::Gamma(steps) => {
+                let gamma = self.gamma * GAMMA_STEP.powi(steps as i32);
+                // Rounded so that stepping back returns to the same value
+                self.gamma = ((gamma * 100.0).round() / 100.0).clamp(0.1, 10.0);
+            }
+            Action::Invert => self.invert = !self.invert,
+            _ => {}
+        }
+        *self != before
+    }
+
+    /// The flags showing the slice at this contrast, e.g. `--min 100 --max 2000 --gamma 1.2`
+    pub fn flags(&self) -> String {
+        let mut flags = format!("--min {} --max {}", self.min, self.max);
+        if self.gamma != 1.0 {
+            flags.push_str(&format!(" --gamma {}", self.gamma));
+        }
+        if self.invert {
+            flags.push_str(" --invert");
+        }
+        flags
+    }
+}
+
+impl std::fmt::Display for Contrast {
+    /// The window and gamma, e.g. `[100-2000 γ1.2 inverted]`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}-{}", self.min, self.max)?;
+        if self.gamma != 1.0 {
+            write!(f, " γ{}", self.gamma)?;
+        }
+        if self.invert {
+            write!(f, " inverted")?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Smallest side of the region shown when zooming in, in pixels
+const MIN_REGION: u64 = 16;
+
+/// Where the view is in the image: the index along each non-spatial dimension
+/// and the region of the plane shown
+#[derive(Debug, Clone, PartialEq)]
+pub struct Navigation {
+    /// Names of the dimensions, `dim0`, `dim1`... if the array has none
+    pub names: Vec<String>,
+    /// Whether the names are the axes of the array, to slice by name rather than position
+    named: bool,
+    /// Index along each non-spatial dimension
+    pub indices: Vec<u64>,
+    /// Length of each non-spatial dimension
+    sizes: Vec<u64>,
+    /// Dimensions stepped through with Z, T and the channel key
+    z: Option<usize>,
+    t: Option<usize>,
+    c: Option<usize>,
+    /// Size of the plane the region is in, along Y and X
+    pub plane: [u64; 2],
+    /// Start and end of the region shown along Y and X
+    pub region: [(u64, u64); 2],
+}
+
+impl Navigation {
+    /// The position of `view` in a plane of `plane` pixels, showing `region` of it
+    pub fn new(view: &SliceView, plane: [u64; 2], region: [(u64, u64); 2]) -> Self {
+        let ndims = view.shape.len();
+        let axes = view.axes.as_ref().filter(|axes| axes.len() == ndims);
+        let names: Vec<String> = match axes {
+            Some(axes) => axes.clone(),
+            None => (0..ndims).map(|i| format!("dim{}", i)).collect(),
+        };
+        let spatial = ndims.saturating_sub(2);
+        let position =
+            |name: &str| axes.and_then(|axes| axes[..spatial].iter().position(|axis| axis == name));
+        let c = axes
+            .and_then(|axes| ngff::channel_axis(&view.attributes, axes))
+            .filter(|&axis| axis < spatial);
+        // The last non-spatial dimension of an array without axes, e.g. Z of a stack
+        let z = match axes {
+            Some(_) => position("z"),
+            None => spatial.checked_sub(1),
+        };
+        Self {
+            names,
+            named: axes.is_some(),
+            indices: view.start[..spatial].to_vec(),
+            sizes: view.shape[..spatial].to_vec(),
+            z,
+            t: position("t"),
+            c,
+            plane,
+            region,
+        }
+    }
+
+    /// Apply a navigation action, clamping indices and the region to the image,
+    /// returning whether anything changed
+    pub fn apply(&mut self, action: Action) -> bool {
+        let before = self.clone();
+        let step = |indices: &mut [u64], dimension: Option<usize>, delta: i64, sizes: &[u64]| {
+            if let Some(dimension) = dimension {
+                let last = sizes[dimension].saturating_sub(1) as i64;
+                indices[dimension] = (indices[dimension] as i64 + delta).clamp(0, last) as u64;
+            }
+        };
+        match action {
+            Action::Z(delta) => step(&mut self.indices, self.z, delta, &self.sizes),
+            Action::T(delta) => step(&mut self.indices, self.t, delta, &self.sizes),
+            Action::Channel => {
+                if let Some(c) = self.c {
+                    self.indices[c] = (self.indices[c] + 1) % self.sizes[c].max(1);
+                }
+            }
+            Action::ZoomIn => self.resize(0.5),
+            Action::ZoomOut => self.resize(2.0),
+            Action::Pan(dy, dx) => {
+                for (axis, delta) in [dy, dx].into_iter().enumerate() {
+                    let (start, end) = self.region[axis];
+                    let shift = delta * ((end - start) / 4).max(1) as i64;
+                    self.region[axis] = place(start as i64 + shift, end - start, self.plane[axis]);
+                }
+            }
+            _ => {}
+        }
+        *self != before
+    }
+
+    /// Scale the region around its center by `factor`, within the plane
+    /// and no smaller than a few pixels
+    fn resize(&mut self, factor: f64) {
+        for axis in 0..2 {
+            let (start, end) = self.region[axis];
+            let size = self.plane[axis];
+            let length =
+                (((end - start) as f64 * factor).round() as u64).clamp(MIN_REGION.min(size), size);
+            let center = (start + end) as i64 / 2;
+            self.region[axis] = place(center - length as i64 / 2, length, size);
+        }
+    }
+
+    /// The indices to read, by axis name when the array has axes
+    pub fn slice_indices(&self) -> Vec<SliceIndex> {
+        self.indices
+            .iter()
+            .zip(&self.names)
+            .map(|(&index, name)| match self.named {
+                true => SliceIndex::Axis(name.clone(), SliceValue::Index(index)),
+                false => SliceIndex::Position(SliceValue::Index(index)),
+            })
+            .collect()
+    }
+
+    /// The region to read, or `None` for the whole plane
+    pub fn roi(&self) -> Option<Roi> {
+        let whole = self.region == [(0, self.plane[0]), (0, self.plane[1])];
+        let range = |(start, end): (u64, u64)| RoiRange {
+            start: Some(start as i64),
+            end: Some(end as i64),
+        };
+        (!whole).then(|| Roi {
+            y: range(self.region[0]),
+            x: range(self.region[1]),
+        })
+    }
+
+    /// The indices and region on one line, e.g. `t=3/10 c=1/2 z=12/40  y=0..512 x=0..512`
+    pub fn status(&self) -> String {
+        let indices: Vec<String> = (self.indices.iter().zip(&self.sizes).zip(&self.names))
+            .map(|((index, size), name)| format!("{}={}/{}", name, index, size))
+            .collect();
+        let [(y0, y1), (x0, x1)] = self.region;
+        let region = format!("y={}..{} x={}..{}", y0, y1, x0, x1);
+        match indices.is_empty() {
+            true => region,
+            false => format!("{}  {}", indices.join(" "), region),
+        }
+    }
+}
+
+/// The range of `length` pixels starting at `start`, moved within `size` pixels
+fn place(start: i64, length: u64, size: u64) -> (u64, u64) {
+    let start = start.clamp(0, size.saturating_sub(length) as i64) as u64;
+    (start, start + length)
+}
+
+/// The terminal in non-canonical mode, reading keys as they are pressed without echoing them,
+/// restored to its previous mode when dropped
+#[cfg(unix)]
+pub struct KeyReader {
+    saved: libc::termios,
+}
+
+#[cfg(unix)]
+impl KeyReader {
+    /// Read keys from stdin, which must be a terminal
+    pub fn new() -> Result<Self> {
+        // Zeroed is a valid termios, filled in by tcgetattr
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+            anyhow::bail!(
+                "Cannot read keys from stdin: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        let mut raw = saved;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        // Reads return after a tenth of a second without keys
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 1;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            anyhow::bail!(
+                "Cannot read keys from stdin: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(Self { saved })
+    }
+
+    /// The keys pressed in the next tenth of a second, none if there were none
+    pub fn read_keys(&self) -> Result<Vec<Key>> {
+        let mut buffer = [0u8; 64];
+        let read = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+            )
+        };
+        if read < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(vec![]);
+            }
+            return Err(error.into());
+        }
+        Ok(parse_keys(&buffer[..read as usize]))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for KeyReader {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+
+    fn view(shape: Vec<u64>, axes: Option<Vec<&str>>) -> SliceView {
+        let ndims = shape.len();
+        let mut start = vec![1; ndims - 2];
+        start.extend([0, 0]);
+        SliceView {
+            data: Array2::zeros((4, 4)),
+            path: "/0".to_string(),
+            axes: axes.map(|axes| axes.into_iter().map(String::from).collect()),
+            start,
+            shape,
+            data_type: "uint16".to_string(),
+            attributes: serde_json::Map::new(),
+            step: 1,
+            exact: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"\x1b[A\x1bOBq\x1b[1;5C.\x1b[3~c"),
+            [
+                Key::Up,
+                Key::Down,
+                Key::Char('q'),
+                Key::Right,
+                Key::Char('.'),
+                Key::Char('c')
+            ]
+        );
+        assert_eq!(Action::from_key(Key::Char('\x03')), Some(Action::Quit));
+        assert_eq!(Action::from_key(Key::Char('x')), None);
+    }
+
+    #[test]
+    fn test_navigation_clamps() {
+        let view = view(vec![3, 2, 5, 100, 200], Some(vec!["t", "c", "z", "y", "x"]));
+        let mut navigation = Navigation::new(&view, [100, 200], [(0, 100), (0, 200)]);
+        assert!(navigation.apply(Action::Z(-1)));
+        assert!(!navigation.apply(Action::Z(-1)));
+        assert!(navigation.apply(Action::T(5)));
+        assert_eq!(navigation.indices, [2, 1, 0]);
+        navigation.apply(Action::Channel);
+        assert_eq!(navigation.indices[1], 0);
+        assert_eq!(navigation.roi(), None);
+        assert!(!navigation.apply(Action::ZoomOut));
+        assert!(!navigation.apply(Action::Pan(1, 1)));
+        navigation.apply(Action::ZoomIn);
+        assert_eq!(navigation.region, [(25, 75), (50, 150)]);
+        navigation.apply(Action::Pan(-1, 1));
+        assert_eq!(navigation.region, [(13, 63), (75, 175)]);
+        for _ in 0..10 {
+            navigation.apply(Action::Pan(-1, 1));
+        }
+        assert_eq!(navigation.region, [(0, 50), (100, 200)]);
+        assert_eq!(navigation.roi(), Some("0:50,100:200".parse().unwrap()));
+        for _ in 0..10 {
+            navigation.apply(Action::ZoomIn);
+        }
+        assert_eq!(navigation.region[0].1 - navigation.region[0].0, MIN_REGION);
+        assert_eq!(
+            navigation.status(),
+            "t=2/3 c=0/2 z=0/5  y=17..33 x=142..158"
+        );
+        assert_eq!(navigation.slice_indices()[2].to_string(), "z=0");
+    }
+
+    #[test]
+    fn test_contrast() {
+        let window = Window {
+            min: 100.0,
+            max: 600.0,
+            source: crate::WindowSource::Quantiles {
+                low: 0.001,
+                high: 0.999,
+            },
+        };
+        let mut contrast = Contrast::new(window, 1.0, false);
+        assert!(contrast.apply(Action::Low(1)));
+        assert!(contrast.apply(Action::High(-2)));
+        assert_eq!((contrast.min, contrast.max), (110.0, 580.0));
+        for _ in 0..100 {
+            contrast.apply(Action::Low(1));
+        }
+        assert_eq!(contrast.min, 570.0);
+        contrast.apply(Action::Gamma(1));
+        contrast.apply(Action::Gamma(1));
+        contrast.apply(Action::Gamma(-1));
+        assert_eq!(contrast.gamma, 1.1);
+        assert!(!contrast.apply(Action::Z(1)));
+        contrast.apply(Action::Invert);
+        assert_eq!(contrast.flags(), "--min 570 --max 580 --gamma 1.1 --invert");
+        assert_eq!(contrast.to_string(), "[570-580 γ1.1 inverted]");
+        assert!(Action::Pan(1, 0).navigates() && !Action::Invert.navigates());
+    }
+
+    #[test]
+    fn test_navigation_without_axes() {
+        let view = view(vec![5, 10, 10], None);
+        let mut navigation = Navigation::new(&view, [10, 10], [(0, 10), (0, 10)]);
+        assert!(navigation.apply(Action::Z(1)));
+        assert!(!navigation.apply(Action::T(1)));
+        assert!(!navigation.apply(Action::Channel));
+        assert_eq!(navigation.slice_indices()[0].to_string(), "2");
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{decode_subset, join_path, list_labels, ngff, open_array, resample};
+use crate::{Filter, ReadOptions, Sample, SliceView};
+use anyhow::Result;
+use image::RgbImage;
+use ndarray::prelude::*;
+use std::sync::Arc;
+use zarrs::array_subset::ArraySubset;
+use zarrs::storage::ReadableStorageTraits;
+
+/// The splitmix64 finalizer, scattering consecutive integers over all bits
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Color of a label id from a hash of the id and `seed`, black for the background id 0,
+/// so an id has the same color whatever other ids are shown
+pub fn label_color(id: u64, seed: u64) -> [u8; 3] {
+    if id == 0 {
+        return [0, 0, 0];
+    }
+    let hash = mix(id ^ mix(seed));
+    // Hue from the high bits, saturation and brightness varied a little from the low bits
+    let hue = (hash >> 11) as f64 / (1u64 << 53) as f64 * 6.0;
+    let saturation = 0.55 + 0.4 * (hash & 0xff) as f64 / 255.0;
+    let value = 0.8 + 0.2 * ((hash >> 8) & 0xff) as f64 / 255.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let offset = value - chroma;
+    [r, g, b].map(|channel| ((channel + offset) * 255.0).round() as u8)
+}
+
+/// Interleaved RGB pixels coloring each label id of a mask
+pub fn colorize_labels<T: Sample>(labels: &Array2<T>, seed: u64) -> Vec<u8> {
+    labels
+        .iter()
+        .map(|&id| id.to_f64())
+        .flat_map(|id| label_color(if id > 0.0 { id as u64 } else { 0 }, seed))
+        .collect()
+}
+
+/// Start and shape in a label array of `label_shape` covering the same region as `view`,
+/// matching non-spatial dimensions by axis name or aligned from the last dimension
+fn label_subset(
+    view: &SliceView,
+    label_shape: &[u64],
+    label_axes: Option<&[String]>,
+) -> (Vec<u64>, Vec<u64>) {
+    let (ndims, label_ndims) = (view.shape.len(), label_shape.len());
+    let mut start = vec![];
+    let mut shape = vec![];
+    for (i, &size) in label_shape.iter().enumerate().take(label_ndims - 2) {
+        let by_name = label_axes
+            .zip(view.axes.as_deref())
+            .and_then(|(label_axes, axes)| axes.iter().position(|axis| *axis == label_axes[i]));
+        let dimension = by_name.or_else(|| (i + ndims).checked_sub(label_ndims));
+        let index = dimension.map_or(0, |dimension| view.start[dimension]);
+        start.push(index.min(size - 1));
+        shape.push(1);
+    }
+    for (i, (first, last)) in view.extent().into_iter().enumerate() {
+        let length = last - first;
+        let (image_size, label_size) =
+            (view.shape[ndims - 2 + i], label_shape[label_ndims - 2 + i]);
+        let scale = label_size as f64 / image_size as f64;
+        let first = ((view.start[ndims - 2 + i] as f64 * scale).floor() as u64).min(label_size - 1);
+        let last = (((view.start[ndims - 2 + i] + length) as f64 * scale).ceil() as u64)
+            .clamp(first + 1, label_size);
+        start.push(first);
+        shape.push(last - first);
+    }
+    (start, shape)
+}
+
+/// Read the label image `label` of the image that `view` was read from,
+/// over the same region as the view and resampled to its shape
+pub fn read_overlay_label<TStore: ReadableStorageTraits + ?Sized + 'static>(
+    store: Arc<TStore>,
+    view: &SliceView,
+    label: &str,
+    options: &ReadOptions,
+) -> Result<Array2<f32>> {
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let image_group = ngff::parent_path(&view.path);
+    let labels = list_labels(&store, image_group)?;
+    if !labels.iter().any(|name| name == label) {
+        anyhow::bail!(
+            "Label image '{}' not found, available labels: {}",
+            label,
+            labels.join(", ")
+        );
+    }
+    let group = join_path(image_group, &format!("labels/{}", label));
+    let attributes = ngff::group_attributes(&store, &group)?.unwrap_or_default();
+    let levels = ngff::dataset_paths(&attributes).unwrap_or_else(|| vec!["0".to_string()]);
+    // Prefer the level matching the image's YX shape, which needs no resampling
+    let spatial = &view.shape[view.shape.len() - 2..];
+    let mut arrays = levels
+        .iter()
+        .map(|level| open_array(store.clone(), &join_path(&group, level)))
+        .collect::<Result<Vec<_>>>()?;
+    let index = arrays
+        .iter()
+        .position(|array| {
+            array.shape().len() >= 2 && array.shape()[array.shape().len() - 2..] == *spatial
+        })
+        .unwrap_or(0);
+    let array = arrays.swap_remove(index);
+    if array.shape().len() < 2 {
+        anyhow::bail!("Label image '{}' must have at least 2 dimensions", label);
+    }
+    let axes = ngff::axis_names(&attributes).filter(|axes| axes.len() == array.shape().len());
+    let (start, shape) = label_subset(view, array.shape(), axes.as_deref());
+    let subset = ArraySubset::new_with_start_shape(start, shape)?;
+    let decoded = decode_subset(
+        &array,
+        &subset,
+        &zarrs::array::codec::CodecOptions::default(),
+        options.partial_decode,
+    )?;
+    let (rows, columns) = view.data.dim();
+    Ok(resample(&decoded, rows, columns, Filter::Nearest))
+}
+
+/// Blend the colors of nonzero label ids onto the image with opacity `alpha`
+pub fn overlay_labels(image: &mut RgbImage, labels: &Array2<f32>, alpha: f32, seed: u64) {
+    for ((row, column), &id) in labels.indexed_iter() {
+        if id <= 0.0 || column as u32 >= image.width() || row as u32 >= image.height() {
+            continue;
+        }
+        let color = label_color(id as u64, seed);
+        let pixel = image.get_pixel_mut(column as u32, row as u32);
+        for (channel, label) in pixel.0.iter_mut().zip(color) {
+            *channel = (*channel as f32 * (1.0 - alpha) + label as f32 * alpha).round() as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_colors() {
+        assert_eq!(label_color(0, 0), [0, 0, 0]);
+        assert_eq!(label_color(0, 5), [0, 0, 0]);
+        assert_eq!(label_color(7, 0), label_color(7, 0));
+        let colors: std::collections::HashSet<_> = (1..=64).map(|id| label_color(id, 0)).collect();
+        assert_eq!(colors.len(), 64);
+        let pixels = colorize_labels(&array![[0.0, 3.0]], 0);
+        assert_eq!(pixels.len(), 6);
+        assert_eq!(&pixels[..3], &[0, 0, 0]);
+        assert_eq!(&pixels[3..], &label_color(3, 0));
+        // Another seed reshuffles the colors
+        assert_ne!(label_color(3, 1), label_color(3, 0));
+    }
+
+    #[test]
+    fn test_label_colors_independent_of_other_ids() {
+        let crop = colorize_labels(&array![[17.0, 0.0], [2.0, 17.0]], 3);
+        let other = colorize_labels(&array![[40000.0, 17.0]], 3);
+        assert_eq!(&crop[..3], &other[3..]);
+        assert_eq!(&crop[9..], &label_color(17, 3));
+        // Ids of 64-bit labels beyond 2^24 keep their own colors
+        let wide = (1u64 << 40) + 1;
+        let exact = colorize_labels(&array![[wide as f64, wide as f64 + 1.0]], 3);
+        assert_eq!(&exact[..3], &label_color(wide, 3));
+        assert_ne!(&exact[..3], &exact[3..]);
+    }
+
+    #[test]
+    fn test_overlay_label_at_lower_resolution() -> Result<()> {
+        use crate::store_group;
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let multiscales = serde_json::json!([{
+            "axes": [{"name": "c"}, {"name": "y"}, {"name": "x"}],
+            "datasets": [{"path": "0"}]
+        }]);
+        store_group(&store, "/", serde_json::json!({"multiscales": multiscales}))?;
+        store_group(&store, "/labels", serde_json::json!({"labels": ["nuclei"]}))?;
+        store_group(
+            &store,
+            "/labels/nuclei",
+            serde_json::json!({"multiscales": multiscales, "image-label": {}}),
+        )?;
+        let image = ArrayBuilder::new(
+            vec![2, 8, 8],
+            vec![1, 8, 8],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/0")?;
+        image.store_metadata()?;
+        // A single-channel mask at half the resolution, with ids increasing along X
+        let labels = ArrayBuilder::new(
+            vec![1, 4, 4],
+            vec![1, 4, 4],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/labels/nuclei/0")?;
+        labels.store_metadata()?;
+        let data = ArrayD::from_shape_fn(IxDyn(&[1, 4, 4]), |idx| idx[2] as u8 + 1);
+        labels.store_array_subset_ndarray(&[0, 0, 0], data)?;
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![crate::SliceIndex::Axis("c".to_string(), 1.into())]),
+            crop_size: 4,
+            fit: crate::Fit::Crop,
+            ..Default::default()
+        };
+        let view = crate::read_slice_view(store.clone(), None, &options)?;
+        assert_eq!(view.start, vec![1, 0, 0]);
+        let overlay = read_overlay_label(store.clone(), &view, "nuclei", &options)?;
+        assert_eq!(overlay.dim(), (4, 4));
+        assert_eq!(overlay.row(0).to_vec(), vec![1.0, 1.0, 2.0, 2.0]);
+
+        // An offset crop maps to the same region of the mask, rounding outwards
+        let offset = SliceView {
+            data: Array2::zeros((3, 3)),
+            start: vec![1, 3, 3],
+            ..view.clone()
+        };
+        assert_eq!(
+            label_subset(&offset, &[1, 4, 4], None),
+            (vec![0, 1, 1], vec![1, 2, 2])
+        );
+
+        let err = read_overlay_label(store, &view, "cells", &options).expect_err("unknown label");
+        assert!(err.to_string().contains("available labels: nuclei"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_overlay_labels() {
+        let mut image = RgbImage::from_pixel(2, 1, image::Rgb([100, 100, 100]));
+        overlay_labels(&mut image, &array![[0.0, 5.0]], 0.5, 0);
+        assert_eq!(image.get_pixel(0, 0).0, [100, 100, 100]);
+        let color = label_color(5, 0);
+        let expected = color.map(|c| ((100.0 + c as f32) / 2.0).round() as u8);
+        assert_eq!(image.get_pixel(1, 0).0, expected);
+    }
+}
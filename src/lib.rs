@@ -0,0 +1,3650 @@
+//! Read and normalize 2D slices of OME-Zarr images from any zarr store.
+
+pub mod animation;
+pub mod blocks;
+pub mod caption;
+pub mod chunkgrid;
+pub mod colorbar;
+pub mod colormap;
+pub mod composite;
+pub mod config;
+pub mod diff;
+pub mod error;
+pub mod export;
+pub mod header;
+pub mod hierarchy;
+pub mod histogram;
+pub mod info;
+pub mod interactive;
+pub mod labels;
+pub mod montage;
+pub mod movie;
+pub mod ngff;
+pub mod normalizer;
+pub mod omero;
+pub mod orientation;
+pub mod ortho;
+pub mod plate;
+pub mod probe;
+pub mod profile;
+pub mod projection;
+pub mod pyramid;
+pub mod roi;
+pub mod scalebar;
+pub mod sequence;
+pub mod snapshot;
+pub mod storagestats;
+pub mod store;
+pub mod terminal;
+pub mod thumbnail;
+pub mod timings;
+pub mod validate;
+pub mod verbosity;
+
+use anyhow::{Context, Ok, Result};
+use error::Error;
+use ndarray::prelude::*;
+use ndarray_stats::interpolate::Nearest;
+use ndarray_stats::QuantileExt;
+use noisy_float::types::n64;
+use std::fmt;
+use std::sync::Arc;
+use std::vec;
+use timings::Stage;
+
+/// Version of the documents printed with `--json`,
+/// bumped on changes that are not backwards compatible
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Options for reading a 2D slice with [`read_slice`]
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    /// Indices to slice non-XY dimensions, by position or axis name,
+    /// the middle of each dimension by default
+    pub slice_indices: Option<Vec<SliceIndex>>,
+    /// Maximum size to read in each of the Y and X dimensions
+    pub crop_size: u64,
+    /// Maximum size along Y instead of `crop_size`
+    pub max_height: Option<u64>,
+    /// Maximum size along X instead of `crop_size`
+    pub max_width: Option<u64>,
+    /// Region along Y and X to read instead of a crop from the origin,
+    /// downsampled if it is larger than the size limits
+    pub roi: Option<roi::Roi>,
+    /// Where a crop is placed with [`Fit::Crop`], before its `offset`
+    pub anchor: roi::Anchor,
+    /// Shift of the crop from its anchor in pixels along Y and X
+    pub offset: roi::Offset,
+    /// Whether planes larger than the crop size are downsampled or cropped to fit it
+    pub fit: Fit,
+    /// Series to open in a bioformats2raw layout
+    pub series: Option<usize>,
+    /// Well to open in an HCS plate, e.g. `B03`
+    pub well: Option<String>,
+    /// Field of view to open in the well, the first by default
+    pub field: Option<usize>,
+    /// Label image under the image's `labels` group to open instead of the image
+    pub label: Option<String>,
+    /// Index of the resolution level in `multiscales.datasets` to read,
+    /// instead of the level fitting the crop size
+    pub level: Option<usize>,
+    /// Read the finest level when no level or array is given, as for a region of interest,
+    /// e.g. to show its pixels as they are
+    pub finest: bool,
+    /// Look for a single multiscale image below the image path if it is not one itself
+    pub discover: bool,
+    /// Maximum number of chunks fetched and decoded concurrently
+    pub concurrency: usize,
+    /// Serve metadata from consolidated metadata when present
+    pub consolidated: bool,
+    /// Decode only the parts of chunks (e.g. inner chunks of shards) intersecting the slice,
+    /// reading them with byte range requests
+    pub partial_decode: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            slice_indices: None,
+            crop_size: 2560,
+            max_height: None,
+            max_width: None,
+            roi: None,
+            anchor: roi::Anchor::default(),
+            offset: roi::Offset::default(),
+            fit: Fit::default(),
+            series: None,
+            well: None,
+            field: None,
+            label: None,
+            level: None,
+            finest: false,
+            discover: true,
+            concurrency: default_concurrency(),
+            consolidated: true,
+            partial_decode: true,
+        }
+    }
+}
+
+/// How a plane larger than the crop size is brought within it
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Fit {
+    /// The whole plane, at a coarser resolution level if there is one,
+    /// or reading every few pixels otherwise
+    #[default]
+    Downsample,
+    /// A window of the crop size from the origin of the plane
+    Crop,
+}
+
+impl std::str::FromStr for Fit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "downsample" => Ok(Self::Downsample),
+            "crop" => Ok(Self::Crop),
+            _ => anyhow::bail!("Unknown fit '{}', use downsample or crop", s),
+        }
+    }
+}
+
+impl ReadOptions {
+    /// Maximum sizes along Y and X, from `max_height` and `max_width` or the crop size
+    pub fn size_limits(&self) -> [u64; 2] {
+        [
+            self.max_height.unwrap_or(self.crop_size),
+            self.max_width.unwrap_or(self.crop_size),
+        ]
+    }
+
+    /// Where the YX window of a slice is read from, the whole plane unless
+    /// there is a region of interest or it is cropped to fit
+    pub fn placement(&self) -> roi::Placement {
+        let limits = match self.fit {
+            Fit::Crop => self.size_limits(),
+            Fit::Downsample => [u64::MAX; 2],
+        };
+        roi::Placement {
+            limits,
+            roi: self.roi,
+            anchor: self.anchor,
+            offset: self.offset,
+        }
+    }
+}
+
+fn ensure_at_least_2d(array_shape: &[u64]) -> Result<()> {
+    if array_shape.len() < 2 {
+        anyhow::bail!("Array must have at least 2 dimensions");
+    }
+    Ok(())
+}
+
+/// Index along a dimension, a number or a keyword resolved against the length of the axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SliceValue {
+    Index(u64),
+    First,
+    Last,
+    /// Half the length, the default of unsliced dimensions
+    Middle,
+}
+
+impl SliceValue {
+    /// The index along an axis of `size`
+    pub fn resolve(self, size: u64) -> u64 {
+        match self {
+            Self::Index(index) => index,
+            Self::First => 0,
+            Self::Last => size.saturating_sub(1),
+            Self::Middle => size / 2,
+        }
+    }
+
+    /// Name of the keyword, `None` for a number
+    pub fn keyword(self) -> Option<&'static str> {
+        match self {
+            Self::Index(_) => None,
+            Self::First => Some("first"),
+            Self::Last => Some("last"),
+            Self::Middle => Some("middle"),
+        }
+    }
+}
+
+impl fmt::Display for SliceValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{}", index),
+            _ => write!(f, "{}", self.keyword().unwrap_or_default()),
+        }
+    }
+}
+
+impl From<u64> for SliceValue {
+    fn from(index: u64) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl std::str::FromStr for SliceValue {
+    type Err = anyhow::Error;
+
+    /// Parse `12`, `first`, `last` or `middle`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            "middle" => Ok(Self::Middle),
+            index => match index.parse::<u64>().ok() {
+                Some(index) => Ok(Self::Index(index)),
+                None => anyhow::bail!(
+                    "Invalid slice index '{}', use a number, first, last or middle",
+                    index
+                ),
+            },
+        }
+    }
+}
+
+/// Index along a non-spatial dimension, by position or by OME-NGFF axis name,
+/// or a physical coordinate with its unit along a named axis
+#[derive(Debug, Clone, PartialEq)]
+pub enum SliceIndex {
+    Position(SliceValue),
+    Axis(String, SliceValue),
+    Physical(String, f64, String),
+    /// Every index along a named axis, e.g. `c=all`, shown as a montage
+    /// rather than read as one slice
+    All(String),
+}
+
+impl fmt::Display for SliceIndex {
+    /// As parsed, e.g. `12`, `t=last` or `t=120s`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Position(value) => write!(f, "{}", value),
+            Self::Axis(axis, value) => write!(f, "{}={}", axis, value),
+            Self::Physical(axis, value, unit) => write!(f, "{}={}{}", axis, value, unit),
+            Self::All(axis) => write!(f, "{}=all", axis),
+        }
+    }
+}
+
+impl std::str::FromStr for SliceIndex {
+    type Err = anyhow::Error;
+
+    /// Parse `12`, `last`, `t=12`, `t=middle`, `t=120s` or `c=all`
+    fn from_str(s: &str) -> Result<Self> {
+        let (axis, index) = match s.split_once('=') {
+            Some((axis, index)) if !axis.trim().is_empty() => (axis.trim(), index.trim()),
+            Some(_) => anyhow::bail!("Missing axis name in slice '{}'", s),
+            None if s.trim() == "all" => {
+                anyhow::bail!("Name the axis to show every index of, e.g. c=all")
+            }
+            None => return Ok(Self::Position(s.parse()?)),
+        };
+        if index == "all" {
+            return Ok(Self::All(axis.to_string()));
+        }
+        let unit_start = index.find(|c: char| c.is_alphabetic() || "µμÅ".contains(c));
+        let (value, unit) = match unit_start {
+            // A keyword or a misspelled one, rather than a coordinate without a number
+            Some(0) | None => return Ok(Self::Axis(axis.to_string(), index.parse()?)),
+            Some(unit_start) => index.split_at(unit_start),
+        };
+        let value = value
+            .trim()
+            .parse::<f64>()
+            .with_context(|| format!("Invalid physical coordinate '{}'", index))?;
+        if ngff::parse_unit(unit).is_none() {
+            anyhow::bail!("Unknown unit '{}' in slice '{}'", unit, s);
+        }
+        Ok(Self::Physical(axis.to_string(), value, unit.to_string()))
+    }
+}
+
+/// Convert physical coordinates to the nearest index along their axis,
+/// using the coordinate transformations of the resolution level at `dataset_path`
+/// and checking units against the axis units
+pub fn resolve_physical_slices(
+    slices: &[SliceIndex],
+    attributes: &serde_json::Map<String, serde_json::Value>,
+    axes: Option<&[String]>,
+    dataset_path: &str,
+) -> Result<Vec<SliceIndex>> {
+    slices
+        .iter()
+        .map(|slice| {
+            let SliceIndex::Physical(name, value, unit) = slice else {
+                return Ok(slice.clone());
+            };
+            let Some(dimension) =
+                axes.and_then(|axes| axes.iter().position(|axis| axis.eq_ignore_ascii_case(name)))
+            else {
+                // Reported as an unknown axis when resolving the indices
+                return Ok(SliceIndex::Axis(name.clone(), SliceValue::Index(0)));
+            };
+            let axis_unit = ngff::axis_units(attributes)
+                .and_then(|units| units.get(dimension).cloned().flatten())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(Error::InvalidSlice(format!(
+                        "Cannot slice '{}' at {}{}: the axis has no unit in the metadata",
+                        name, value, unit
+                    )))
+                })?;
+            let (quantity, size) = ngff::parse_unit(unit).ok_or_else(|| {
+                anyhow::anyhow!(Error::InvalidSlice(format!("Unknown unit '{}'", unit)))
+            })?;
+            let (axis_quantity, axis_size) = ngff::parse_unit(&axis_unit).ok_or_else(|| {
+                anyhow::anyhow!(Error::InvalidSlice(format!(
+                    "Unknown unit '{}' of axis '{}'",
+                    axis_unit, name
+                )))
+            })?;
+            if quantity != axis_quantity {
+                anyhow::bail!(Error::InvalidSlice(format!(
+                    "Cannot slice '{}' at {}{}: the axis is in {} ({}), not a {}",
+                    name, value, unit, axis_unit, axis_quantity, quantity
+                )));
+            }
+            let ndims = axes.map_or(0, <[String]>::len);
+            let (scale, translation) = ngff::physical_transform(attributes, dataset_path, ndims)
+                .unwrap_or((vec![1.0; ndims], vec![0.0; ndims]));
+            let coordinate = value * size / axis_size;
+            let index = ((coordinate - translation[dimension]) / scale[dimension]).round();
+            if index < 0.0 {
+                anyhow::bail!(Error::InvalidSlice(format!(
+                    "Cannot slice '{}' at {}{}: before the first index at {} {}",
+                    name,
+                    value,
+                    unit,
+                    translation[dimension],
+                    header::unit_symbol(&axis_unit)
+                )));
+            }
+            Ok(SliceIndex::Axis(
+                axes.unwrap_or_default()[dimension].clone(),
+                SliceValue::Index(index as u64),
+            ))
+        })
+        .collect()
+}
+
+/// Resolve slice indices to an optional index for each non-spatial dimension,
+/// matching named indices against the array's axis names
+pub fn resolve_slice_indices(
+    slices: &[SliceIndex],
+    axes: Option<&[String]>,
+    ndims_to_be_sliced: usize,
+) -> Result<Vec<Option<SliceValue>>> {
+    let mut indices = vec![None; ndims_to_be_sliced];
+    let positional = slices
+        .iter()
+        .filter(|slice| matches!(slice, SliceIndex::Position(_)))
+        .count();
+    if positional > 0 && positional < slices.len() {
+        anyhow::bail!(Error::InvalidSlice(
+            "Cannot mix named (t=0) and positional slice indices".to_string()
+        ));
+    }
+    if positional > ndims_to_be_sliced {
+        let names = MessageAxes::new(axes, ndims_to_be_sliced + 2);
+        match &names {
+            Some(names) => anyhow::bail!(Error::InvalidSlice(format!(
+                "Too many slice indices: got {} for {} non-spatial axes ({}){}",
+                positional,
+                ndims_to_be_sliced,
+                names.names[..ndims_to_be_sliced].join(", "),
+                names.note()
+            ))),
+            None => anyhow::bail!(Error::InvalidSlice(format!(
+                "Too many slice indices: got {} for {} non-spatial dimensions",
+                positional, ndims_to_be_sliced
+            ))),
+        }
+    }
+    for (i, slice) in slices.iter().enumerate() {
+        match slice {
+            SliceIndex::Position(index) => indices[i] = Some(*index),
+            SliceIndex::Physical(name, ..) => anyhow::bail!(Error::InvalidSlice(format!(
+                "Cannot slice '{}' by physical coordinate without the image metadata",
+                name
+            ))),
+            SliceIndex::All(name) => anyhow::bail!(Error::InvalidSlice(format!(
+                "Cannot read every index along '{}' as one slice, \
+                 {}=all only shows them as a montage",
+                name, name
+            ))),
+            SliceIndex::Axis(name, index) => {
+                let Some(axes) = axes else {
+                    anyhow::bail!(Error::InvalidSlice(format!(
+                        "Cannot slice '{}' by name: the image has no OME-NGFF axes metadata",
+                        name
+                    )));
+                };
+                let dimension = axes
+                    .iter()
+                    .position(|axis| axis.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(Error::InvalidSlice(format!(
+                            "Unknown axis '{}', available axes: {}",
+                            name,
+                            axes.join(", ")
+                        )))
+                    })?;
+                if dimension >= ndims_to_be_sliced {
+                    anyhow::bail!(Error::InvalidSlice(format!(
+                        "Cannot slice spatial axis '{}'",
+                        axes[dimension]
+                    )));
+                }
+                if indices[dimension].replace(*index).is_some() {
+                    anyhow::bail!(Error::InvalidSlice(format!(
+                        "Axis '{}' is sliced more than once",
+                        axes[dimension]
+                    )));
+                }
+            }
+        }
+    }
+    Ok(indices)
+}
+
+/// Axis names for messages, from the metadata or else assumed from the dimensionality
+struct MessageAxes {
+    names: Vec<String>,
+    assumed: bool,
+}
+
+impl MessageAxes {
+    /// Names of the `ndims` axes, assuming the last of t, c, z, y, x without metadata,
+    /// `None` for more than 5 dimensions without metadata
+    fn new(axes: Option<&[String]>, ndims: usize) -> Option<Self> {
+        match axes.filter(|axes| axes.len() == ndims) {
+            Some(axes) => Some(Self {
+                names: axes.to_vec(),
+                assumed: false,
+            }),
+            None => (ndims <= 5).then(|| Self {
+                names: ["t", "c", "z", "y", "x"][5 - ndims..]
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect(),
+                assumed: true,
+            }),
+        }
+    }
+
+    /// Note to append to messages naming assumed axes
+    fn note(&self) -> String {
+        if self.assumed {
+            format!(
+                ", assuming axes {} without axes metadata",
+                self.names.concat()
+            )
+        } else {
+            String::new()
+        }
+    }
+}
+
+fn axis_label(axes: Option<&MessageAxes>, dimension: usize) -> String {
+    match axes {
+        Some(axes) if axes.assumed => format!("{} (assumed)", axes.names[dimension]),
+        Some(axes) => axes.names[dimension].clone(),
+        None => format!("dimension {}", dimension),
+    }
+}
+
+pub fn start_and_shape(
+    array_shape: &[u64],
+    slice_indices: Option<&[u64]>,
+    limits: [u64; 2],
+) -> Result<(Vec<u64>, Vec<u64>)> {
+    ensure_at_least_2d(array_shape)?;
+    let slices: Vec<SliceIndex> = slice_indices
+        .unwrap_or_default()
+        .iter()
+        .map(|&index| SliceIndex::Position(SliceValue::Index(index)))
+        .collect();
+    let indices = resolve_slice_indices(&slices, None, array_shape.len() - 2)?;
+    start_and_shape_for_axes(
+        array_shape,
+        &indices,
+        None,
+        None,
+        &roi::Placement::crop(limits),
+    )
+}
+
+fn slice_message(
+    axes: Option<&MessageAxes>,
+    channel: Option<(usize, &[Option<String>])>,
+    dimension: usize,
+    value: Option<SliceValue>,
+    index: u64,
+) -> String {
+    let label = channel
+        .filter(|(axis, _)| *axis == dimension)
+        .and_then(|(_, labels)| labels.get(index as usize)?.as_deref());
+    let keyword = value.and_then(SliceValue::keyword);
+    match (label, keyword) {
+        (Some(label), Some(keyword)) => format!(
+            "Slicing channel '{}' ({}={}, index {})",
+            label,
+            axis_label(axes, dimension),
+            keyword,
+            index
+        ),
+        (Some(label), None) => format!(
+            "Slicing channel '{}' ({}={})",
+            label,
+            axis_label(axes, dimension),
+            index
+        ),
+        (None, Some(keyword)) => format!(
+            "Slicing {} at {} (index {})",
+            axis_label(axes, dimension),
+            keyword,
+            index
+        ),
+        (None, None) => format!("Slicing {} at index {}", axis_label(axes, dimension), index),
+    }
+}
+
+/// Start and shape of the 2D subset, slicing each non-spatial dimension at `indices`
+/// (the midpoint where `None`) and placing the window along Y and X with `placement`,
+/// labelling messages with `axes` names when known
+/// and with the channel labels of the `channel` axis (position and labels)
+pub fn start_and_shape_for_axes(
+    array_shape: &[u64],
+    indices: &[Option<SliceValue>],
+    axes: Option<&[String]>,
+    channel: Option<(usize, &[Option<String>])>,
+    placement: &roi::Placement,
+) -> Result<(Vec<u64>, Vec<u64>)> {
+    let ndims = array_shape.len();
+    ensure_at_least_2d(array_shape)?;
+    let ndims_to_be_sliced = ndims - 2;
+    let axes = MessageAxes::new(axes, ndims);
+    let axes = axes.as_ref();
+    let mut start: Vec<u64> = vec![];
+    for (i, size) in array_shape.iter().enumerate().take(ndims_to_be_sliced) {
+        let value = indices.get(i).copied().flatten();
+        let index = match value.map(|value| value.resolve(*size)) {
+            Some(index) if index >= *size => match axes {
+                Some(axes) => anyhow::bail!(Error::InvalidSlice(format!(
+                    "Slice index {} is out of bounds for axis '{}' (size {}, valid 0..={}){}",
+                    index,
+                    axes.names[i],
+                    size,
+                    size.saturating_sub(1),
+                    axes.note()
+                ))),
+                None => anyhow::bail!(Error::InvalidSlice(format!(
+                    "Slice index {} is out of bounds for dimension {} (size {})",
+                    index, i, size
+                ))),
+            },
+            Some(index) => index,
+            None => SliceValue::Middle.resolve(*size),
+        };
+        info!("{}", slice_message(axes, channel, i, value, index));
+        start.push(index);
+    }
+    let mut shape = vec![1; ndims];
+    let [(y0, height), (x0, width)] = placement.resolve(
+        array_shape[ndims_to_be_sliced],
+        array_shape[ndims_to_be_sliced + 1],
+    )?;
+    start.extend([y0, x0]);
+    shape[ndims_to_be_sliced..].copy_from_slice(&[height, width]);
+    Ok((start, shape))
+}
+
+/// Read whole chunks intersecting `subset` and copy out the overlap,
+/// for stores where many small range requests are slower than full reads
+fn retrieve_whole_chunks<T, U, TStore>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    options: &zarrs::array::codec::CodecOptions,
+    convert: fn(T) -> U,
+) -> Result<ArrayD<U>>
+where
+    T: zarrs::array::ElementOwned,
+    U: Sample,
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    use rayon::prelude::*;
+    let to_slices = |subset: &zarrs::array_subset::ArraySubset| -> Vec<ndarray::SliceInfoElem> {
+        subset
+            .to_ranges()
+            .into_iter()
+            .map(|range| ndarray::Slice::from(range.start as usize..range.end as usize).into())
+            .collect()
+    };
+    let mut output = ArrayD::from_elem(subset.shape_usize(), U::ZERO);
+    let Some(chunks) = array.chunks_in_array_subset(subset)? else {
+        return Ok(output);
+    };
+    let parts = chunks
+        .indices()
+        .into_par_iter()
+        .map(|indices| {
+            let chunk_subset = array.chunk_subset(&indices)?;
+            let overlap = chunk_subset.overlap(subset)?;
+            let chunk = array.retrieve_chunk_ndarray_opt::<T>(&indices, options)?;
+            let in_chunk = to_slices(&overlap.relative_to(chunk_subset.start())?);
+            let part = chunk.slice(in_chunk.as_slice()).mapv(convert);
+            Ok((to_slices(&overlap.relative_to(subset.start())?), part))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (in_output, part) in parts {
+        output.slice_mut(in_output.as_slice()).assign(&part);
+    }
+    Ok(output)
+}
+
+fn retrieve<T, U, TStore>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    options: &zarrs::array::codec::CodecOptions,
+    partial_decode: bool,
+    convert: fn(T) -> U,
+) -> Result<ArrayD<U>>
+where
+    T: zarrs::array::ElementOwned,
+    U: Sample,
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    if partial_decode {
+        // Chunks partially covered by the subset (e.g. inner chunks of a shard)
+        // are decoded from byte ranges rather than whole
+        Ok(array
+            .retrieve_array_subset_ndarray_opt::<T>(subset, options)?
+            .mapv(convert))
+    } else {
+        retrieve_whole_chunks(array, subset, options, convert)
+    }
+}
+
+pub fn decode_subset<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    options: &zarrs::array::codec::CodecOptions,
+    partial_decode: bool,
+) -> Result<Array2<f32>> {
+    let ndims = subset.dimensionality();
+    decode_subset_plane(
+        array,
+        subset,
+        options,
+        partial_decode,
+        [ndims - 2, ndims - 1],
+    )
+}
+
+/// Like [`decode_subset`] for a subset one index wide along every dimension but `dims`,
+/// with the first of them along the rows, e.g. `[y, z]` for a YZ plane
+pub fn decode_subset_plane<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    options: &zarrs::array::codec::CodecOptions,
+    partial_decode: bool,
+    dims: [usize; 2],
+) -> Result<Array2<f32>> {
+    decode_subset_plane_as(array, subset, options, partial_decode, dims)
+}
+
+/// Like [`decode_subset_plane`] into `U`, f64 to keep 64-bit integers exact
+fn decode_subset_plane_as<U, TStore>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    options: &zarrs::array::codec::CodecOptions,
+    partial_decode: bool,
+    dims: [usize; 2],
+) -> Result<Array2<U>>
+where
+    U: Sample,
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    timings::time_bytes(
+        Stage::Decode,
+        || decode_plane(array, subset, options, partial_decode, dims),
+        |decoded| {
+            decoded.as_ref().map_or(0, |decoded| {
+                (decoded.len() * std::mem::size_of::<U>()) as u64
+            })
+        },
+    )
+}
+
+fn decode_plane<U, TStore>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    options: &zarrs::array::codec::CodecOptions,
+    partial_decode: bool,
+    dims: [usize; 2],
+) -> Result<Array2<U>>
+where
+    U: Sample,
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    use zarrs::array::DataType;
+    let dtype = array.data_type();
+    let decoded = match dtype {
+        DataType::Int8 => retrieve::<i8, U, _>(array, subset, options, partial_decode, |x| {
+            U::from_i64(x as i64)
+        })?,
+        DataType::Int16 => retrieve::<i16, U, _>(array, subset, options, partial_decode, |x| {
+            U::from_i64(x as i64)
+        })?,
+        DataType::Int32 => retrieve::<i32, U, _>(array, subset, options, partial_decode, |x| {
+            U::from_i64(x as i64)
+        })?,
+        DataType::Int64 => {
+            retrieve::<i64, U, _>(array, subset, options, partial_decode, |x| U::from_i64(x))?
+        }
+        DataType::UInt8 => retrieve::<u8, U, _>(array, subset, options, partial_decode, |x| {
+            U::from_i64(x as i64)
+        })?,
+        DataType::UInt16 => retrieve::<u16, U, _>(array, subset, options, partial_decode, |x| {
+            U::from_i64(x as i64)
+        })?,
+        DataType::UInt32 => retrieve::<u32, U, _>(array, subset, options, partial_decode, |x| {
+            U::from_i64(x as i64)
+        })?,
+        DataType::UInt64 => {
+            retrieve::<u64, U, _>(array, subset, options, partial_decode, |x| U::from_u64(x))?
+        }
+        // Every half-precision value, NaN and infinities included, is exact in f32
+        DataType::Float16 => {
+            retrieve::<half::f16, U, _>(array, subset, options, partial_decode, |x| {
+                U::from_f64(x.to_f64())
+            })?
+        }
+        DataType::Float32 => retrieve::<f32, U, _>(array, subset, options, partial_decode, |x| {
+            U::from_f64(x as f64)
+        })?,
+        DataType::Float64 => {
+            retrieve::<f64, U, _>(array, subset, options, partial_decode, |x| U::from_f64(x))?
+        }
+        _ => anyhow::bail!(Error::UnsupportedDataType {
+            data_type: dtype.to_string()
+        }),
+    };
+    let shape = decoded.shape();
+    let (rows, columns) = (shape[dims[0]], shape[dims[1]]);
+    // The other dimensions are one index wide, so the elements are in the order
+    // of the two dimensions in the array
+    if dims[0] < dims[1] {
+        Ok(decoded.to_shape((rows, columns))?.to_owned())
+    } else {
+        let transposed = decoded.to_shape((columns, rows))?;
+        Ok(transposed.t().as_standard_layout().into_owned())
+    }
+}
+
+pub fn default_concurrency() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Open an array stored as either Zarr v2 (`.zarray`) or v3 (`zarr.json`)
+pub fn open_array<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    store: Arc<TStore>,
+    array_name: &str,
+) -> Result<zarrs::array::Array<TStore>> {
+    use zarrs::config::MetadataRetrieveVersion;
+    use zarrs::node::{meta_key_v2_array, meta_key_v2_group, meta_key_v3, NodePath};
+    let path = NodePath::new(array_name)?;
+    let (version, key) = if store.size_key(&meta_key_v3(&path))?.is_some() {
+        (MetadataRetrieveVersion::V3, meta_key_v3(&path))
+    } else if store.size_key(&meta_key_v2_array(&path))?.is_some() {
+        (MetadataRetrieveVersion::V2, meta_key_v2_array(&path))
+    } else if store.size_key(&meta_key_v2_group(&path))?.is_some() {
+        anyhow::bail!("'{}' is a Zarr v2 group, not an array", array_name);
+    } else {
+        // The levels of the image the array would be in, as the store may not be listable here
+        let candidates = ngff::group_attributes(&store, ngff::parent_path(array_name))?
+            .as_ref()
+            .and_then(ngff::dataset_paths)
+            .unwrap_or_default();
+        anyhow::bail!(Error::ArrayNotFound {
+            path: array_name.to_string(),
+            candidates,
+        });
+    };
+    let array = zarrs::array::Array::open_opt(store, array_name, &version)
+        .with_context(|| format!("Failed to open array metadata {}", key))?;
+    Ok(array)
+}
+
+/// Resolve the path of the image group, navigating into a series for bioformats2raw layouts
+pub fn resolve_image_group<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+    series: Option<usize>,
+) -> Result<String> {
+    let layout = ngff::group_attributes(store, "/")?
+        .as_ref()
+        .and_then(ngff::bioformats2raw_layout);
+    if layout.is_none() {
+        if series.is_some() {
+            anyhow::bail!("Selecting a series requires a bioformats2raw layout at the image path");
+        }
+        return Ok("/".to_string());
+    }
+    let available = ngff::list_series(store)?;
+    let index = match (series, available.len()) {
+        (Some(index), n) if index < n => index,
+        (Some(index), n) => anyhow::bail!(
+            "Series {} is out of range, {} series available:\n{}",
+            index,
+            n,
+            ngff::format_series(&available)
+        ),
+        (None, 0) => anyhow::bail!("No series found in bioformats2raw layout"),
+        (None, 1) => 0,
+        (None, n) => anyhow::bail!(
+            "Found {} series in bioformats2raw layout, choose one with --series N:\n{}",
+            n,
+            ngff::format_series(&available)
+        ),
+    };
+    let selected = &available[index];
+    match &selected.name {
+        Some(name) => info!("Opening series {} ({})", index, name),
+        None => info!("Opening series {}", index),
+    }
+    Ok(format!("/{}", selected.path))
+}
+
+/// Resolve the path of a field of view in the well `well` of an HCS plate at the root
+pub fn resolve_well_image<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+    plate: &ngff::Plate,
+    well: Option<&str>,
+    field: Option<usize>,
+) -> Result<String> {
+    let Some(well) = well else {
+        anyhow::bail!(
+            "Found an HCS plate, choose a well with --well (x: acquired):\n{}",
+            plate.format_layout()
+        );
+    };
+    let Some(path) = plate.find_well(well) else {
+        anyhow::bail!(
+            "Well {} is not in the plate, available wells: {}",
+            well,
+            plate.well_names().join(", ")
+        );
+    };
+    let well_group = format!("/{}", path);
+    let images = ngff::group_attributes(store, &well_group)?
+        .as_ref()
+        .map(ngff::well_images)
+        .unwrap_or_default();
+    let index = field.unwrap_or(0);
+    let Some(image) = images.get(index) else {
+        anyhow::bail!(
+            "Field {} is out of range, well {} has {} fields",
+            index,
+            well,
+            images.len()
+        );
+    };
+    info!("Opening well {} field {}", path, index);
+    Ok(join_path(&well_group, image))
+}
+
+/// Join a path relative to a group path
+pub fn join_path(group: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        group.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+/// Pick the coarsest level of the multiscale image in `group` whose Y or X extent
+/// still covers the `limits` along it, or the finest level if none does,
+/// or with [`Fit::Downsample`] the finest level fitting within `limits`
+/// along both, or the coarsest if none does,
+/// falling back to the array `0` when there is no multiscales metadata
+pub fn select_level<TStore>(
+    store: &Arc<TStore>,
+    group: &str,
+    [height, width]: [u64; 2],
+    fit: Fit,
+) -> Result<String>
+where
+    TStore: zarrs::storage::ReadableStorageTraits
+        + zarrs::storage::ListableStorageTraits
+        + ?Sized
+        + 'static,
+{
+    let Some(datasets) = ngff::group_attributes(store, group)?
+        .as_ref()
+        .and_then(ngff::dataset_paths)
+        .filter(|datasets| !datasets.is_empty())
+    else {
+        let path = join_path(group, "0");
+        if open_array(store.clone(), &path).is_err() {
+            let children = hierarchy::child_names(store, group)?;
+            if children.is_empty() {
+                anyhow::bail!(
+                    "No multiscales metadata in {} and no array at {}, the group is empty",
+                    group,
+                    path
+                );
+            }
+            anyhow::bail!(
+                "No multiscales metadata in {} and no array at {}, choose one of its children with --array-name: {}",
+                group,
+                path,
+                children.join(", ")
+            );
+        }
+        return Ok(path);
+    };
+    let mut extents = vec![];
+    for path in &datasets {
+        let shape = open_array(store.clone(), &join_path(group, path))?
+            .shape()
+            .to_vec();
+        ensure_at_least_2d(&shape)?;
+        extents.push((shape[shape.len() - 2], shape[shape.len() - 1]));
+    }
+    let level = match fit {
+        Fit::Crop => extents
+            .iter()
+            .rposition(|&(y, x)| y >= height || x >= width)
+            .unwrap_or(0),
+        Fit::Downsample => extents
+            .iter()
+            .position(|&(y, x)| y <= height && x <= width)
+            .unwrap_or(extents.len() - 1),
+    };
+    let factor = extents[0].1 as f64 / extents[level].1.max(1) as f64;
+    info!(
+        "Using level {} ({}), downscaled {}x",
+        level,
+        datasets[level],
+        (factor * 100.0).round() / 100.0
+    );
+    Ok(join_path(group, &datasets[level]))
+}
+
+/// Path of the resolution level at index `level` of the multiscale image in `group`
+pub fn level_path<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+    group: &str,
+    level: usize,
+) -> Result<String> {
+    let attributes = ngff::group_attributes(store, group)?.unwrap_or_default();
+    let Some(datasets) = ngff::dataset_paths(&attributes).filter(|datasets| !datasets.is_empty())
+    else {
+        anyhow::bail!(
+            "Cannot select a level, there is no multiscales metadata in {}",
+            group
+        );
+    };
+    let Some(path) = datasets.get(level) else {
+        anyhow::bail!(
+            "Level {} is out of range, the image has {} levels",
+            level,
+            datasets.len()
+        );
+    };
+    let ndims = open_array(store.clone(), &join_path(group, path))?.dimensionality();
+    // Downscaling along X relative to the finest level, from the scale transforms
+    let scale_x = |path: &str| {
+        ngff::physical_transform(&attributes, path, ndims)
+            .and_then(|(scale, _)| scale.last().copied())
+    };
+    match scale_x(&datasets[0])
+        .zip(scale_x(path))
+        .map(|(finest, scale)| scale / finest)
+    {
+        Some(factor) => info!(
+            "Using level {} ({}), downscaled {}x",
+            level,
+            path,
+            (factor * 100.0).round() / 100.0
+        ),
+        None => info!("Using level {} ({})", level, path),
+    }
+    Ok(join_path(group, path))
+}
+
+/// Path of the array `array_name` in the image group selected by `options`,
+/// or of the resolution level `options.level`, or the one fitting the crop size
+pub fn resolve_array_path<TStore>(
+    store: &Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+) -> Result<String>
+where
+    TStore: zarrs::storage::ReadableStorageTraits
+        + zarrs::storage::ListableStorageTraits
+        + ?Sized
+        + 'static,
+{
+    let mut group = resolve_image_path(store, options)?;
+    if let Some(label) = &options.label {
+        let labels = list_labels(store, &group)?;
+        if labels.is_empty() {
+            anyhow::bail!("Label image '{}' not found, the image has no labels", label);
+        }
+        if !labels.contains(label) {
+            anyhow::bail!(
+                "Label image '{}' not found, available labels: {}",
+                label,
+                labels.join(", ")
+            );
+        }
+        group = join_path(&group, &format!("labels/{}", label));
+    }
+    match (array_name, options.level) {
+        (Some(array_name), _) => {
+            let path = join_path(&group, array_name);
+            if !has_array_metadata(store, &path)? {
+                let parent = ngff::parent_path(&path);
+                anyhow::bail!(Error::ArrayNotFound {
+                    candidates: hierarchy::child_names(store, parent)?,
+                    path,
+                });
+            }
+            Ok(path)
+        }
+        (None, Some(level)) => level_path(store, &group, level),
+        // The finest level, whose pixels the region is in
+        (None, None) if options.roi.is_some() || options.finest => {
+            select_level(store, &group, [u64::MAX; 2], Fit::Downsample)
+        }
+        (None, None) => select_level(store, &group, options.size_limits(), options.fit),
+    }
+}
+
+/// Whether there is Zarr v3 (`zarr.json`) or v2 (`.zarray`) metadata at `path`,
+/// the former possibly of a group
+fn has_array_metadata<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+    path: &str,
+) -> Result<bool> {
+    use zarrs::node::{meta_key_v2_array, meta_key_v3, NodePath};
+    let path = NodePath::new(path)?;
+    Ok(store.size_key(&meta_key_v3(&path))?.is_some()
+        || store.size_key(&meta_key_v2_array(&path))?.is_some())
+}
+
+/// Names of the label images of the image group at `group`
+pub fn list_labels<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+    group: &str,
+) -> Result<Vec<String>> {
+    Ok(ngff::group_attributes(store, &join_path(group, "labels"))?
+        .as_ref()
+        .map(ngff::label_names)
+        .unwrap_or_default())
+}
+
+/// Path of the only multiscale image within two levels below `group` if it is not an image,
+/// or `group` itself if there are none
+pub fn discover_image<TStore>(store: &Arc<TStore>, group: &str) -> Result<String>
+where
+    TStore: zarrs::storage::ReadableStorageTraits
+        + zarrs::storage::ListableStorageTraits
+        + ?Sized
+        + 'static,
+{
+    let is_image = ngff::group_attributes(store, group)?
+        .as_ref()
+        .and_then(ngff::dataset_paths)
+        .is_some();
+    if is_image {
+        return Ok(group.to_string());
+    }
+    let images = hierarchy::find_images(store, group, 2)?;
+    match images.as_slice() {
+        [] => Ok(group.to_string()),
+        [image] => {
+            info!("No image at {}, opening {} found below it", group, image);
+            Ok(image.clone())
+        }
+        images => anyhow::bail!(
+            "No image at {}, found {} images below it, open one of them directly:\n{}",
+            group,
+            images.len(),
+            images.join("\n")
+        ),
+    }
+}
+
+/// Path of the image group selected by `options`,
+/// navigating into an HCS plate well or a bioformats2raw series,
+/// or discovering an image below the root
+pub fn resolve_image_path<TStore>(store: &Arc<TStore>, options: &ReadOptions) -> Result<String>
+where
+    TStore: zarrs::storage::ReadableStorageTraits
+        + zarrs::storage::ListableStorageTraits
+        + ?Sized
+        + 'static,
+{
+    let plate = ngff::group_attributes(store, "/")?
+        .as_ref()
+        .and_then(ngff::plate);
+    match plate {
+        Some(plate) => resolve_well_image(store, &plate, options.well.as_deref(), options.field),
+        None if options.well.is_some() => {
+            anyhow::bail!("Selecting a well requires an HCS plate at the image path")
+        }
+        None if options.discover && options.series.is_none() => {
+            let group = resolve_image_group(store, None)?;
+            discover_image(store, &group)
+        }
+        None => resolve_image_group(store, options.series),
+    }
+}
+
+/// A decoded 2D slice with the metadata needed to render it
+#[derive(Debug, Clone)]
+pub struct SliceView {
+    pub data: Array2<f32>,
+    /// Path of the array the slice was read from
+    pub path: String,
+    /// OME-NGFF axis names of the array, if known
+    pub axes: Option<Vec<String>>,
+    /// Start of the slice in each dimension of the array,
+    /// i.e. the selected index of each non-spatial dimension
+    pub start: Vec<u64>,
+    /// Shape of the whole array
+    pub shape: Vec<u64>,
+    /// Data type of the array, e.g. `uint16`
+    pub data_type: String,
+    /// Attributes of the image group containing the array
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+    /// Step between the pixels read along Y and X, above 1 when the plane was
+    /// downsampled to fit the crop size
+    pub step: u64,
+    /// The values of a 64-bit integer array as f64, which `data` rounds beyond 2^24,
+    /// for the window, statistics and label ids
+    pub exact: Option<Array2<f64>>,
+}
+
+impl SliceView {
+    /// Replace the values with `data` resampled from them with `filter`, picking the same
+    /// pixels of the exact values with [`Filter::Nearest`] and dropping them otherwise,
+    /// unless the resampling kept the shape and so the values
+    pub fn set_resampled(&mut self, data: Array2<f32>, filter: Filter) {
+        let (rows, columns) = data.dim();
+        self.exact = match (self.exact.take(), filter) {
+            (Some(exact), _) if exact.dim() == (rows, columns) => Some(exact),
+            (Some(exact), Filter::Nearest) => Some(resample_nearest(&exact, rows, columns)),
+            (Some(exact), _) => {
+                if rounds_to_f32(&exact) {
+                    warn!(
+                        "Values of {} ({}) are rounded to f32 by the {} filter",
+                        self.path,
+                        self.data_type,
+                        format!("{:?}", filter).to_lowercase()
+                    );
+                }
+                None
+            }
+            (None, _) => None,
+        };
+        self.data = data;
+    }
+
+    /// Turn the values, and the exact ones along, to `orientation`
+    pub fn orient(&mut self, orientation: &orientation::Orientation) {
+        self.data = orientation.apply(&self.data);
+        self.exact = self.exact.as_ref().map(|exact| orientation.apply(exact));
+    }
+
+    /// Index of the selected channel, `0` for an image without a channel axis
+    pub fn channel_index(&self) -> usize {
+        self.axes
+            .as_deref()
+            .and_then(|axes| ngff::channel_axis(&self.attributes, axes))
+            .map_or(0, |axis| self.start[axis] as usize)
+    }
+
+    /// Start and end of the slice along Y and X, in the pixels of the array
+    pub fn extent(&self) -> [(u64, u64); 2] {
+        let ndims = self.start.len();
+        let (rows, columns) = self.data.dim();
+        let (y, x) = (self.start[ndims - 2], self.start[ndims - 1]);
+        let end = |start: u64, length: usize, size: u64| match self.step {
+            1 => start + length as u64,
+            step => (start + length as u64 * step).min(size),
+        };
+        [
+            (y, end(y, rows, self.shape[ndims - 2])),
+            (x, end(x, columns, self.shape[ndims - 1])),
+        ]
+    }
+
+    /// The subset of the array covered by the slice, at `start` along the other dimensions,
+    /// to read the same crop at another index with [`decode_view_plane`]
+    pub(crate) fn subset_at(&self, start: Vec<u64>) -> Result<zarrs::array_subset::ArraySubset> {
+        let ndims = start.len();
+        let [(y0, y1), (x0, x1)] = self.extent();
+        let mut shape = vec![1; ndims];
+        shape[ndims - 2..].copy_from_slice(&[y1 - y0, x1 - x0]);
+        Ok(zarrs::array_subset::ArraySubset::new_with_start_shape(
+            start, shape,
+        )?)
+    }
+
+    /// Path of the resolution level relative to the image group, e.g. `0`
+    pub fn dataset_path(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or_default()
+    }
+
+    /// Size of a pixel along X in metres,
+    /// from the coordinate transformations and unit of the last axis
+    pub fn pixel_size(&self) -> Option<f64> {
+        let ndims = self.start.len();
+        let unit = ngff::axis_units(&self.attributes)?
+            .get(ndims - 1)?
+            .clone()?;
+        let (ngff::Quantity::Length, size) = ngff::parse_unit(&unit)? else {
+            return None;
+        };
+        let (scale, _) = ngff::physical_transform(&self.attributes, self.dataset_path(), ndims)?;
+        Some(scale[ndims - 1] * size * self.step as f64).filter(|size| *size > 0.0)
+    }
+
+    /// Ratio of the physical height to width of a pixel,
+    /// from the coordinate transformations (and units, if any) of the last two axes
+    pub fn pixel_aspect(&self) -> Option<f64> {
+        self.spacing_ratio(self.start.len() - 2)
+    }
+
+    /// Ratio of the pixel spacing along `axis` to the spacing along X,
+    /// from the coordinate transformations (and units, if any) of the two axes
+    pub fn spacing_ratio(&self, axis: usize) -> Option<f64> {
+        let ndims = self.start.len();
+        let (scale, _) = ngff::physical_transform(&self.attributes, self.dataset_path(), ndims)?;
+        let units = ngff::axis_units(&self.attributes).unwrap_or_default();
+        let unit_size = |axis: usize| {
+            let unit = units.get(axis).cloned().flatten()?;
+            ngff::parse_unit(&unit).map(|(_, size)| size)
+        };
+        // Scales are compared as they are unless both axes have known units
+        let (height_unit, width_unit) = match (unit_size(axis), unit_size(ndims - 1)) {
+            (Some(height), Some(width)) => (height, width),
+            _ => (1.0, 1.0),
+        };
+        // Y is downsampled by the same step as X, other axes are not
+        let step = match axis >= ndims - 2 {
+            true => 1.0,
+            false => self.step as f64,
+        };
+        let height = scale[axis] * height_unit;
+        let width = scale[ndims - 1] * width_unit * step;
+        (height > 0.0 && width > 0.0).then(|| height / width)
+    }
+
+    /// Whether the array has an integer data type
+    pub fn is_integer(&self) -> bool {
+        self.data_type.starts_with("int") || self.data_type.starts_with("uint")
+    }
+
+    /// Whether the slice is of a label image (segmentation mask) rather than intensities
+    pub fn is_label(&self) -> bool {
+        ngff::is_label_image(&self.attributes)
+    }
+
+    /// Label of the selected channel from the `omero` metadata, for captions
+    pub fn channel_name(&self) -> Option<String> {
+        self.omero_channel()?.label
+    }
+
+    /// Rendering settings of the selected channel from the `omero` metadata
+    pub fn omero_channel(&self) -> Option<omero::OmeroChannel> {
+        omero::omero_channels(&self.attributes)
+            .into_iter()
+            .nth(self.channel_index())
+    }
+}
+
+/// Read a 2D slice of the array `array_name` in `store`,
+/// or of the resolution level fitting the crop size if no array name is given
+pub fn read_slice<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+) -> Result<Array2<f32>>
+where
+    TStore: zarrs::storage::ReadableStorageTraits
+        + zarrs::storage::ListableStorageTraits
+        + ?Sized
+        + 'static,
+{
+    Ok(read_slice_view(store, array_name, options)?.data)
+}
+
+/// Like [`read_slice`], keeping the slice position and image metadata
+pub fn read_slice_view<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+) -> Result<SliceView>
+where
+    TStore: zarrs::storage::ReadableStorageTraits
+        + zarrs::storage::ListableStorageTraits
+        + ?Sized
+        + 'static,
+{
+    let store = Arc::new(store::ConsolidatedStore::new(store, options.consolidated));
+    let array_path = resolve_array_path(&store, array_name, options)?;
+    read_array_slice(store, &array_path, options)
+}
+
+/// Like [`read_slice_view`] for the array at `array_name`,
+/// without navigating into series, wells or resolution levels
+pub fn read_array_slice<TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static>(
+    store: Arc<TStore>,
+    array_name: &str,
+    options: &ReadOptions,
+) -> Result<SliceView> {
+    let tracked = Arc::new(store::MissingChunks::new(store));
+    let store = tracked.clone();
+    let array_name = array_name.to_string();
+    let attributes = ngff::group_attributes(&store, ngff::parent_path(&array_name))?;
+    let array = open_array(store, &array_name)?;
+    let (axes, subset, step) = slice_subset(&array, &array_name, attributes.as_ref(), options)?;
+    let attributes = attributes.unwrap_or_default();
+    if step > 1 {
+        let [height, width] = options.size_limits();
+        info!(
+            "Downsampling Y and X by {} to fit within {}x{}",
+            step, height, width
+        );
+    }
+    let start = subset.start().to_vec();
+    let data_type = array.data_type().to_string();
+    let (decoded, exact) = decode_values(&array, &subset, step, options)?;
+    if let Some(exact) = exact.as_ref() {
+        if exact.iter().any(|value| value.abs() > EXACT_F64) {
+            warn!(
+                "Values of {} ({}) beyond 2^53 are rounded to f64",
+                array_name, data_type
+            );
+        }
+    }
+    let missing = tracked.missing();
+    if missing > 0 {
+        let in_view = array
+            .chunks_in_array_subset(&subset)?
+            .map_or(0, |chunks| chunks.num_elements());
+        warn!(
+            "{} of {} chunks in view are missing (fill={})",
+            missing,
+            in_view,
+            fill_value_json(&array)
+        );
+    }
+    Ok(SliceView {
+        data: decoded,
+        path: array_name,
+        axes,
+        start,
+        shape: array.shape().to_vec(),
+        data_type,
+        attributes,
+        step,
+        exact,
+    })
+}
+
+/// Axis names of the array at `array_name`, the subset of it holding the slice selected
+/// by `options` and the step between the pixels read along Y and X to fit the crop size
+fn slice_subset<TStore: ?Sized>(
+    array: &zarrs::array::Array<TStore>,
+    array_name: &str,
+    attributes: Option<&serde_json::Map<String, serde_json::Value>>,
+    options: &ReadOptions,
+) -> Result<(Option<Vec<String>>, zarrs::array_subset::ArraySubset, u64)> {
+    let array_shape = array.shape();
+    ensure_at_least_2d(array_shape)?;
+    let axes = attributes
+        .and_then(ngff::axis_names)
+        .filter(|axes| axes.len() == array_shape.len());
+    let empty = serde_json::Map::new();
+    let attributes = attributes.unwrap_or(&empty);
+    let slices = resolve_physical_slices(
+        options.slice_indices.as_deref().unwrap_or_default(),
+        attributes,
+        axes.as_deref(),
+        array_name.rsplit('/').next().unwrap_or_default(),
+    )?;
+    let indices = resolve_slice_indices(&slices, axes.as_deref(), array_shape.len() - 2)?;
+    let channel_labels = omero::channel_labels(attributes);
+    let channel = axes
+        .as_deref()
+        .and_then(|axes| ngff::channel_axis(attributes, axes))
+        .map(|axis| (axis, channel_labels.as_slice()));
+    let (start, shape) = start_and_shape_for_axes(
+        array_shape,
+        &indices,
+        axes.as_deref(),
+        channel,
+        &options.placement(),
+    )?;
+    let [height, width] = options.size_limits();
+    let ndims = shape.len();
+    // The same step along both, keeping the aspect ratio
+    let step = shape[ndims - 2]
+        .div_ceil(height.max(1))
+        .max(shape[ndims - 1].div_ceil(width.max(1)))
+        .max(1);
+    let subset = zarrs::array_subset::ArraySubset::new_with_start_shape(start, shape)?;
+    Ok((axes, subset, step))
+}
+
+/// What reading a slice takes, estimated from the chunks its subset intersects
+/// without reading any of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadPlan {
+    /// Path of the array the slice would be read from
+    pub path: String,
+    /// OME-NGFF axis names of the array, if known
+    pub axes: Option<Vec<String>>,
+    /// Shape of the whole array
+    pub shape: Vec<u64>,
+    pub start: Vec<u64>,
+    /// Shape of the subset read, before downsampling
+    pub subset_shape: Vec<u64>,
+    /// Dimension of the channel axis, if any
+    pub channel_axis: Option<usize>,
+    pub step: u64,
+    /// Number of planes read like this one, e.g. the channels of a composite
+    pub planes: u64,
+    /// Chunks (shards, if sharded) intersecting the subset of one plane
+    pub chunks: u64,
+    /// Size of those chunks decoded
+    pub chunk_bytes: u64,
+    /// Stored size of those chunks and how many of them are missing,
+    /// if they were sized from the store
+    pub stored: Option<(u64, u64)>,
+    /// Size of the decoded plane as 32-bit floats, and 64-bit ones for 64-bit integers,
+    /// after downsampling
+    pub decoded_bytes: u64,
+    /// Zarr data type of the array
+    pub data_type: String,
+}
+
+impl ReadPlan {
+    /// Bytes fetched from the store for all the planes: their stored size if known,
+    /// otherwise the decoded size of the chunks
+    pub fn estimated_bytes(&self) -> u64 {
+        self.stored.map_or(self.chunk_bytes, |(bytes, _)| bytes) * self.planes
+    }
+
+    /// Number of planes from `start` to `end` (the whole axis by default) along `axis`,
+    /// a name or a dimension, as read for a projection
+    pub fn axis_planes(&self, axis: &str, start: Option<u64>, end: Option<u64>) -> Result<u64> {
+        let (dimension, _) = composite::resolve_axis(
+            &self.shape,
+            self.axes.as_deref(),
+            axis,
+            "--project-axis",
+            "projection",
+        )?;
+        let size = self.shape[dimension];
+        Ok(end
+            .unwrap_or(size)
+            .min(size)
+            .saturating_sub(start.unwrap_or(0)))
+    }
+
+    /// JSON object with the subset, chunk counts and sizes in bytes
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "path": self.path,
+            "axes": self.axes,
+            "shape": self.shape,
+            "start": self.start,
+            "subset_shape": self.subset_shape,
+            "step": self.step,
+            "planes": self.planes,
+            "chunks": self.chunks * self.planes,
+            "chunk_bytes": self.chunk_bytes * self.planes,
+            "stored_bytes": self.stored.map(|(bytes, _)| bytes * self.planes),
+            "missing_chunks": self.stored.map(|(_, missing)| missing * self.planes),
+            "estimated_bytes": self.estimated_bytes(),
+            "decoded_bytes": self.decoded_bytes * self.planes,
+        })
+    }
+}
+
+impl std::fmt::Display for ReadPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use storagestats::format_bytes;
+        writeln!(f, "Array: {}", self.path)?;
+        let ranges: Vec<String> = self
+            .start
+            .iter()
+            .zip(&self.subset_shape)
+            .map(|(start, size)| format!("{}..{}", start, start + size))
+            .collect();
+        write!(f, "Subset: {}", ranges.join(", "))?;
+        if let Some(axes) = &self.axes {
+            write!(f, " ({})", axes.join(", "))?;
+        }
+        if self.step > 1 {
+            write!(f, ", downsampled by {} along Y and X", self.step)?;
+        }
+        writeln!(f)?;
+        if self.planes > 1 {
+            writeln!(f, "Planes: {}", self.planes)?;
+        }
+        writeln!(
+            f,
+            "Chunks: {} ({} decoded)",
+            self.chunks * self.planes,
+            format_bytes(self.chunk_bytes * self.planes)
+        )?;
+        match self.stored {
+            Some((bytes, missing)) if missing > 0 => writeln!(
+                f,
+                "Stored: {} ({} of the chunks are missing)",
+                format_bytes(bytes * self.planes),
+                missing * self.planes
+            )?,
+            Some((bytes, _)) => writeln!(f, "Stored: {}", format_bytes(bytes * self.planes))?,
+            None => writeln!(
+                f,
+                "Stored: at most {}, estimated from the decoded chunks",
+                format_bytes(self.estimated_bytes())
+            )?,
+        }
+        write!(
+            f,
+            "Decoded: {} as {}",
+            format_bytes(self.decoded_bytes * self.planes),
+            match is_wide_integer(&self.data_type) {
+                true => "32-bit and 64-bit floats",
+                false => "32-bit floats",
+            }
+        )
+    }
+}
+
+/// The [`ReadPlan`] of the slice that [`read_slice_view`] would read,
+/// sizing each chunk it intersects in the store if `sized` (cheap for local stores only)
+pub fn plan_read<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+    sized: bool,
+) -> Result<ReadPlan>
+where
+    TStore: zarrs::storage::ReadableStorageTraits
+        + zarrs::storage::ListableStorageTraits
+        + ?Sized
+        + 'static,
+{
+    use zarrs::storage::ReadableStorageTraits;
+    let store = Arc::new(store::ConsolidatedStore::new(store, options.consolidated));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let attributes = ngff::group_attributes(&store, ngff::parent_path(&path))?;
+    let array = open_array(store.clone(), &path)?;
+    let (axes, subset, step) = slice_subset(&array, &path, attributes.as_ref(), options)?;
+    let element_size = array.data_type().fixed_size().unwrap_or(0) as u64;
+    let chunk_grid = array.chunks_in_array_subset(&subset)?;
+    let mut chunks = 0;
+    let mut chunk_bytes = 0;
+    let (mut stored_bytes, mut missing) = (0, 0);
+    for indices in chunk_grid
+        .iter()
+        .flat_map(|grid| grid.indices().into_iter())
+    {
+        chunks += 1;
+        chunk_bytes += array
+            .chunk_shape(&indices)?
+            .iter()
+            .map(|size| size.get())
+            .product::<u64>()
+            * element_size;
+        if sized {
+            match store.size_key(&array.chunk_key(&indices))? {
+                Some(size) => stored_bytes += size,
+                None => missing += 1,
+            }
+        }
+    }
+    let ndims = subset.dimensionality();
+    let shape = subset.shape();
+    let decoded = shape[ndims - 2].div_ceil(step) * shape[ndims - 1].div_ceil(step);
+    let channel_axis = axes
+        .as_deref()
+        .zip(attributes.as_ref())
+        .and_then(|(axes, attributes)| ngff::channel_axis(attributes, axes));
+    let data_type = array.data_type().to_string();
+    Ok(ReadPlan {
+        path,
+        axes,
+        shape: array.shape().to_vec(),
+        start: subset.start().to_vec(),
+        subset_shape: shape.to_vec(),
+        channel_axis,
+        step,
+        planes: 1,
+        chunks,
+        chunk_bytes,
+        stored: sized.then_some((stored_bytes, missing)),
+        decoded_bytes: decoded * decoded_size(&data_type),
+        data_type,
+    })
+}
+
+/// Decode every `step`-th pixel along Y and X of `subset` with [`decode_with_options`],
+/// a band of chunk rows at a time so that the whole subset is never held at once
+pub(crate) fn decode_strided<U, TStore>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    step: u64,
+    options: &ReadOptions,
+) -> Result<Array2<U>>
+where
+    U: Sample,
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    if step <= 1 {
+        return decode_with_options(array, subset, options);
+    }
+    let ndims = subset.dimensionality();
+    let (y0, height) = (subset.start()[ndims - 2], subset.shape()[ndims - 2]);
+    let width = subset.shape()[ndims - 1];
+    let (rows, columns) = (height.div_ceil(step), width.div_ceil(step));
+    let mut output = Array2::from_elem((rows as usize, columns as usize), U::ZERO);
+    // Fewer rows than a chunk for very wide planes, bounding the memory of a band
+    let chunk_rows = array.chunk_shape(&vec![0; ndims])?[ndims - 2].get();
+    let band = chunk_rows.min((1 << 24) / width.max(1)).max(1);
+    let mut band_start = y0;
+    while band_start < y0 + height {
+        let band_end = ((band_start / band + 1) * band).min(y0 + height);
+        let first = y0 + (band_start - y0).div_ceil(step) * step;
+        if first < band_end {
+            let mut start = subset.start().to_vec();
+            let mut shape = subset.shape().to_vec();
+            start[ndims - 2] = first;
+            shape[ndims - 2] = band_end - first;
+            let band_subset = zarrs::array_subset::ArraySubset::new_with_start_shape(start, shape)?;
+            let decoded = decode_with_options(array, &band_subset, options)?;
+            let sampled = decoded.slice(ndarray::s![..;step as isize, ..;step as isize]);
+            let row = ((first - y0) / step) as usize;
+            output
+                .slice_mut(ndarray::s![row..row + sampled.nrows(), ..])
+                .assign(&sampled);
+        }
+        band_start = band_end;
+    }
+    Ok(output)
+}
+
+/// Decode with [`decode_strided`], into f64 as well for the exact values of 64-bit integers
+pub(crate) fn decode_values<TStore>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    step: u64,
+    options: &ReadOptions,
+) -> Result<(Array2<f32>, Option<Array2<f64>>)>
+where
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    if !is_wide_integer(&array.data_type().to_string()) {
+        return Ok((decode_strided(array, subset, step, options)?, None));
+    }
+    let exact: Array2<f64> = decode_strided(array, subset, step, options)?;
+    Ok((exact.mapv(|value| value as f32), Some(exact)))
+}
+
+/// Decode the crop of `view` at `start` along the other dimensions,
+/// downsampled by the same step, with [`decode_values`]
+pub(crate) fn decode_view_plane<TStore>(
+    array: &zarrs::array::Array<TStore>,
+    view: &SliceView,
+    start: Vec<u64>,
+    options: &ReadOptions,
+) -> Result<(Array2<f32>, Option<Array2<f64>>)>
+where
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    decode_values(array, &view.subset_at(start)?, view.step, options)
+}
+
+/// Decode `subset` with the concurrency and partial decoding of `options`
+pub(crate) fn decode_with_options<U, TStore>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    options: &ReadOptions,
+) -> Result<Array2<U>>
+where
+    U: Sample,
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    let ndims = subset.dimensionality();
+    decode_plane_with_options(array, subset, options, [ndims - 2, ndims - 1])
+}
+
+/// Like [`decode_with_options`] for the plane along `dims`, see [`decode_subset_plane`]
+pub(crate) fn decode_plane_with_options<U, TStore>(
+    array: &zarrs::array::Array<TStore>,
+    subset: &zarrs::array_subset::ArraySubset,
+    options: &ReadOptions,
+    dims: [usize; 2],
+) -> Result<Array2<U>>
+where
+    U: Sample,
+    TStore: zarrs::storage::ReadableStorageTraits + ?Sized + 'static,
+{
+    let concurrency = options.concurrency.max(1);
+    let mut codec_options = zarrs::array::codec::CodecOptions::default();
+    codec_options.set_concurrent_target(concurrency);
+    // Chunk requests are issued from the pool's threads, bounding requests in flight
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency)
+        .build()?;
+    pool.install(|| {
+        decode_subset_plane_as(array, subset, &codec_options, options.partial_decode, dims)
+    })
+}
+
+/// The fill value as declared in the array metadata
+pub(crate) fn fill_value_json<TStore: ?Sized>(array: &zarrs::array::Array<TStore>) -> String {
+    serde_json::to_value(array.metadata())
+        .ok()
+        .and_then(|metadata| metadata.get("fill_value").map(|value| value.to_string()))
+        .unwrap_or_else(|| "?".to_string())
+}
+
+/// Quantile of the values ignoring NaN, with infinities taken as the finite extrema,
+/// or NaN without finite values
+pub fn image_quantile<T: Sample>(array: &Array2<T>, q: f64) -> Result<T> {
+    timings::time(Stage::Quantiles, || quantile(array, q))
+}
+
+fn quantile<T: Sample>(array: &Array2<T>, q: f64) -> Result<T> {
+    let finite = || array.iter().copied().filter(|value| value.is_finite());
+    let min = finite().fold(T::NAN, T::min);
+    let max = finite().fold(T::NAN, T::max);
+    if min.is_nan() {
+        return Ok(T::NAN);
+    }
+    let values: Array1<T> = array
+        .iter()
+        .filter(|value| !value.is_nan())
+        .map(|&value| value.max(min).min(max))
+        .collect();
+    T::quantile_of(values, q)
+}
+
+/// Element type slices are decoded to, f32 for display or f64 to keep 64-bit integers exact
+pub trait Sample:
+    Copy + PartialOrd + fmt::Display + Into<serde_json::Value> + Send + Sync + 'static
+{
+    const ZERO: Self;
+    const NAN: Self;
+
+    fn from_i64(value: i64) -> Self;
+    fn from_u64(value: u64) -> Self;
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn is_nan(self) -> bool;
+    fn is_finite(self) -> bool;
+    /// The smaller of the two, the other one if either is NaN
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    /// The `q` quantile of values without NaN, at the nearest of them
+    fn quantile_of(values: Array1<Self>, q: f64) -> Result<Self>;
+}
+
+macro_rules! impl_sample {
+    ($t:ty) => {
+        impl Sample for $t {
+            const ZERO: Self = 0.0;
+            const NAN: Self = <$t>::NAN;
+
+            fn from_i64(value: i64) -> Self {
+                value as $t
+            }
+
+            fn from_u64(value: u64) -> Self {
+                value as $t
+            }
+
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn is_nan(self) -> bool {
+                <$t>::is_nan(self)
+            }
+
+            fn is_finite(self) -> bool {
+                <$t>::is_finite(self)
+            }
+
+            fn min(self, other: Self) -> Self {
+                <$t>::min(self, other)
+            }
+
+            fn max(self, other: Self) -> Self {
+                <$t>::max(self, other)
+            }
+
+            fn quantile_of(mut values: Array1<Self>, q: f64) -> Result<Self> {
+                Ok(values
+                    .quantile_axis_skipnan_mut(Axis(0), n64(q), &Nearest)?
+                    .into_scalar())
+            }
+        }
+    };
+}
+
+impl_sample!(f32);
+impl_sample!(f64);
+
+/// Largest magnitude up to which f64 holds every integer
+const EXACT_F64: f64 = (1u64 << 53) as f64;
+
+/// Whether a data type, e.g. `int64`, holds integers that f32 cannot represent
+/// from 2^24 on, decoded to f64 as well to keep them exact
+pub fn is_wide_integer(data_type: &str) -> bool {
+    matches!(data_type, "int64" | "uint64")
+}
+
+/// Bytes per pixel of a decoded slice, its f32 values and the f64 ones of 64-bit integers
+fn decoded_size(data_type: &str) -> u64 {
+    match is_wide_integer(data_type) {
+        true => (std::mem::size_of::<f32>() + std::mem::size_of::<f64>()) as u64,
+        false => std::mem::size_of::<f32>() as u64,
+    }
+}
+
+/// Whether any value changes when rounded to f32
+pub fn rounds_to_f32(exact: &Array2<f64>) -> bool {
+    exact
+        .iter()
+        .any(|&value| value as f32 as f64 != value && !value.is_nan())
+}
+
+/// Filter to resample slices with for display, as given to `--filter`
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Filter {
+    /// The closest pixel, keeping label ids and edges
+    Nearest,
+    /// Linear interpolation between the neighbouring pixels
+    Bilinear,
+    /// Windowed sinc over 3 pixels on each side, sharpest and slowest
+    Lanczos3,
+    /// The mean of the pixels covered when shrinking, like nearest when enlarging
+    Area,
+}
+
+impl std::str::FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "nearest" => Ok(Self::Nearest),
+            "bilinear" => Ok(Self::Bilinear),
+            "lanczos3" => Ok(Self::Lanczos3),
+            "area" => Ok(Self::Area),
+            _ => anyhow::bail!(
+                "Unknown filter '{}', use nearest, bilinear, lanczos3 or area",
+                s
+            ),
+        }
+    }
+}
+
+impl Filter {
+    /// Input positions and weights of each of `output` pixels resampled from `input`,
+    /// with kernels widened when shrinking so that every input pixel contributes
+    fn weights(self, input: usize, output: usize) -> Vec<Vec<(usize, f64)>> {
+        let ratio = input as f64 / output as f64;
+        let widen = ratio.max(1.0);
+        (0..output)
+            .map(|o| {
+                let nearest = || vec![((o * input / output).min(input - 1), 1.0)];
+                let (low, high) = (o as f64 * ratio, (o + 1) as f64 * ratio);
+                let center = (low + high) / 2.0;
+                let (first, last) = match self {
+                    Self::Nearest => return nearest(),
+                    Self::Area if ratio <= 1.0 => return nearest(),
+                    Self::Area => (low.floor(), high.ceil()),
+                    Self::Bilinear => (center - widen, center + widen),
+                    Self::Lanczos3 => (center - 3.0 * widen, center + 3.0 * widen),
+                };
+                let (first, last) = (
+                    first.floor().max(0.0) as usize,
+                    (last.ceil() as usize).min(input),
+                );
+                (first..last)
+                    .map(|i| {
+                        // Distance from the center of the input pixel, in output pixels
+                        let x = (i as f64 + 0.5 - center) / widen;
+                        let weight = match self {
+                            Self::Bilinear => (1.0 - x.abs()).max(0.0),
+                            Self::Lanczos3 => lanczos(x, 3.0),
+                            // The part of the input pixel covered by the output pixel
+                            _ => high.min((i + 1) as f64) - low.max(i as f64),
+                        };
+                        (i, weight)
+                    })
+                    .filter(|&(_, weight)| weight != 0.0)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn lanczos(x: f64, size: f64) -> f64 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= size {
+        return 0.0;
+    }
+    let pi_x = std::f64::consts::PI * x;
+    size * pi_x.sin() * (pi_x / size).sin() / (pi_x * pi_x)
+}
+
+/// Resample one axis of `data` with the weights of each output pixel,
+/// leaving out NaN values and NaN where there are only NaN values
+fn resample_axis(data: &Array2<f32>, axis: usize, weights: &[Vec<(usize, f64)>]) -> Array2<f32> {
+    let mut shape = [data.nrows(), data.ncols()];
+    shape[axis] = weights.len();
+    Array2::from_shape_fn(shape, |(row, column)| {
+        let (mut total, mut sum) = (0.0, 0.0);
+        let o = [row, column][axis];
+        for &(i, weight) in &weights[o] {
+            let index = if axis == 0 { [i, column] } else { [row, i] };
+            let value = data[index];
+            if !value.is_nan() {
+                total += value as f64 * weight;
+                sum += weight;
+            }
+        }
+        match sum.abs() > 1e-9 {
+            true => (total / sum) as f32,
+            false => f32::NAN,
+        }
+    })
+}
+
+/// Resample with `filter` to `rows` by `columns`, on the values before they are normalized
+pub fn resample(data: &Array2<f32>, rows: usize, columns: usize, filter: Filter) -> Array2<f32> {
+    let (height, width) = data.dim();
+    if filter == Filter::Nearest || height == 0 || width == 0 {
+        return resample_nearest(data, rows, columns);
+    }
+    let resampled = match rows == height {
+        true => data.clone(),
+        false => resample_axis(data, 0, &filter.weights(height, rows)),
+    };
+    match columns == width {
+        true => resampled,
+        false => resample_axis(&resampled, 1, &filter.weights(width, columns)),
+    }
+}
+
+/// Resample to `rows` by `columns` picking the pixel each output pixel falls in
+fn resample_nearest<T: Copy>(data: &Array2<T>, rows: usize, columns: usize) -> Array2<T> {
+    let (height, width) = data.dim();
+    Array2::from_shape_fn((rows, columns), |(row, column)| {
+        data[[
+            (row * height / rows).min(height - 1),
+            (column * width / columns).min(width - 1),
+        ]]
+    })
+}
+
+/// Resample with `filter` so that pixels of `aspect` height to width display square,
+/// stretching the coarser axis and shrinking both to stay within the `limits` along Y and X
+pub fn correct_aspect(
+    data: &Array2<f32>,
+    aspect: f64,
+    [max_height, max_width]: [u64; 2],
+    filter: Filter,
+) -> Array2<f32> {
+    let (rows, columns) = data.dim();
+    if (aspect - 1.0).abs() < 1e-3 || rows == 0 || columns == 0 {
+        return data.clone();
+    }
+    let (height, width) = if aspect > 1.0 {
+        (rows as f64 * aspect, columns as f64)
+    } else {
+        (rows as f64, columns as f64 / aspect)
+    };
+    let fit = (max_height as f64 / height)
+        .min(max_width as f64 / width)
+        .min(1.0);
+    let size = |length: f64| ((length * fit).round() as usize).max(1);
+    resample(data, size(height), size(width), filter)
+}
+
+/// The factor to zoom a `rows` by `columns` plane by, `zoom` if the result fits within
+/// `[max_height, max_width]`, or else the largest fitting (a whole number when enlarging)
+/// with a warning
+pub fn zoom_factor(
+    (rows, columns): (usize, usize),
+    zoom: f64,
+    [max_height, max_width]: [u64; 2],
+) -> f64 {
+    let largest =
+        (max_height as f64 / rows.max(1) as f64).min(max_width as f64 / columns.max(1) as f64);
+    if zoom <= largest {
+        return zoom;
+    }
+    let fitting = match largest >= 1.0 {
+        true => largest.floor(),
+        false => largest,
+    };
+    warn!(
+        "Zooming {}x would show the {}x{} pixels beyond {}x{}, zooming {}x instead",
+        zoom, rows, columns, max_height, max_width, fitting
+    );
+    fitting
+}
+
+/// Scale a plane by `factor`, enlarging with nearest neighbours so that pixels show
+/// as blocks and shrinking with `filter`
+pub fn zoom(data: &Array2<f32>, factor: f64, filter: Filter) -> Array2<f32> {
+    let (rows, columns) = data.dim();
+    let size = |length: usize| ((length as f64 * factor).round() as usize).max(1);
+    let filter = zoom_filter(factor, filter);
+    match (size(rows), size(columns)) {
+        (height, width) if (height, width) == (rows, columns) => data.clone(),
+        (height, width) => resample(data, height, width, filter),
+    }
+}
+
+/// The filter [`zoom`] resamples with, the nearest pixel when enlarging
+pub fn zoom_filter(factor: f64, filter: Filter) -> Filter {
+    match factor >= 1.0 {
+        true => Filter::Nearest,
+        false => filter,
+    }
+}
+
+/// Intensity statistics of a decoded slice
+#[derive(Debug, PartialEq)]
+pub struct SliceStats<T = f32> {
+    pub rows: usize,
+    pub columns: usize,
+    pub min: T,
+    pub max: T,
+    /// Mean and standard deviation of the finite values, NaN without any
+    pub mean: f64,
+    pub std: f64,
+    /// Pairs of quantile and value
+    pub quantiles: Vec<(f64, T)>,
+    pub zero_count: usize,
+    pub nan_count: usize,
+    /// Fraction of the values that are NaN, e.g. masked out
+    pub nan_fraction: f64,
+}
+
+/// Minimum, maximum, mean, standard deviation and the given quantiles of a decoded slice,
+/// ignoring NaN, with its zeros and NaN counted; all but the quantiles in one pass,
+/// totalled in f64 so that the sums of large integer images stay exact
+pub fn slice_stats<T: Sample>(decoded: &Array2<T>, quantiles: &[f64]) -> Result<SliceStats<T>> {
+    let (rows, columns) = decoded.dim();
+    let (mut min, mut max) = (T::NAN, T::NAN);
+    let (mut count, mut sum, mut mean, mut squares) = (0usize, 0.0f64, 0.0f64, 0.0f64);
+    let (mut zero_count, mut nan_count) = (0, 0);
+    for &value in decoded {
+        min = min.min(value);
+        max = max.max(value);
+        zero_count += (value == T::ZERO) as usize;
+        nan_count += value.is_nan() as usize;
+        if value.is_finite() {
+            // Welford's update, without the cancellation of a sum of squares
+            let value = value.to_f64();
+            count += 1;
+            sum += value;
+            let delta = value - mean;
+            mean += delta / count as f64;
+            squares += delta * (value - mean);
+        }
+    }
+    let (mean, std) = match count {
+        0 => (f64::NAN, f64::NAN),
+        // The sum is exact for integers, the running mean drifts in the last digits
+        _ => (sum / count as f64, (squares / count as f64).sqrt()),
+    };
+    Ok(SliceStats {
+        rows,
+        columns,
+        min,
+        max,
+        mean,
+        std,
+        quantiles: quantiles
+            .iter()
+            .map(|&q| Ok((q, image_quantile(decoded, q)?)))
+            .collect::<Result<_>>()?,
+        zero_count,
+        nan_count,
+        nan_fraction: nan_count as f64 / decoded.len().max(1) as f64,
+    })
+}
+
+impl<T: Sample> SliceStats<T> {
+    /// Fraction of the values that are zero
+    pub fn zero_fraction(&self) -> f64 {
+        self.zero_count as f64 / (self.rows * self.columns).max(1) as f64
+    }
+
+    /// One line per statistic, the 0.5 quantile as the median
+    pub fn format(&self) -> String {
+        let mut lines = vec![
+            format!("min     {}", self.min),
+            format!("max     {}", self.max),
+            format!("mean    {}", self.mean),
+            format!("std     {}", self.std),
+        ];
+        lines.extend(
+            self.quantiles
+                .iter()
+                .map(|(quantile, value)| match quantile {
+                    0.5 => format!("median  {}", value),
+                    _ => format!("{:<8}{}", format!("q{}", quantile), value),
+                }),
+        );
+        lines.push(format!(
+            "zeros   {} ({:.1}%)",
+            self.zero_count,
+            self.zero_fraction() * 100.0
+        ));
+        lines.push(format!(
+            "nan     {} ({:.1}%)",
+            self.nan_count,
+            self.nan_fraction * 100.0
+        ));
+        lines.join("\n")
+    }
+
+    /// JSON object with the shape, extrema, moments, quantiles and counts
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "shape": [self.rows, self.columns],
+            "min": self.min.into(),
+            "max": self.max.into(),
+            "mean": self.mean,
+            "std": self.std,
+            "quantiles": self
+                .quantiles
+                .iter()
+                .map(|&(q, value)| serde_json::json!({"quantile": q, "value": value.into()}))
+                .collect::<Vec<_>>(),
+            "zero_count": self.zero_count,
+            "zero_fraction": self.zero_fraction(),
+            "nan_count": self.nan_count,
+            "nan_fraction": self.nan_fraction,
+        })
+    }
+}
+
+/// Where the cut points of the normalization come from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowSource {
+    /// Between the quantiles at these fractions
+    Quantiles { low: f64, high: f64 },
+    /// The contrast window of the `omero` metadata
+    Omero,
+    /// Given on the command line, at least one of the cut points
+    Fixed,
+    /// The smallest and largest values
+    MinMax,
+    /// The mean plus or minus a number of standard deviations
+    ZScore,
+    /// The data taken as 8-bit values already
+    Unscaled,
+    /// Histogram equalization over the smallest to the largest values
+    Equalized,
+}
+
+impl fmt::Display for WindowSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WindowSource::Quantiles { low, high } => {
+                return write!(f, "quantiles {}-{}", low, high);
+            }
+            WindowSource::Omero => "omero",
+            WindowSource::Fixed => "fixed",
+            WindowSource::MinMax => "minmax",
+            WindowSource::ZScore => "zscore",
+            WindowSource::Unscaled => "none",
+            WindowSource::Equalized => "equalized",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The values mapped to black and white, in the units of the data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window<T = f32> {
+    pub min: T,
+    pub max: T,
+    pub source: WindowSource,
+}
+
+impl<T: Sample> Window<T> {
+    /// A window fixed at `min` or `max` where given, with the other end at the `low` or
+    /// `high` quantile of the data, rejecting a window left empty by a quantile end
+    ///
+    /// Both ends given are checked up front by [`normalizer::check_range`].
+    pub fn fixed(
+        decoded: &Array2<T>,
+        min: Option<T>,
+        max: Option<T>,
+        low: f64,
+        high: f64,
+    ) -> Result<Self> {
+        let from_quantile = min.is_none() || max.is_none();
+        let min = match min {
+            Some(min) => min,
+            None => image_quantile(decoded, low)?,
+        };
+        let max = match max {
+            Some(max) => max,
+            None => image_quantile(decoded, high)?,
+        };
+        if from_quantile && max <= min {
+            anyhow::bail!(
+                "The display range {} to {} is empty, the maximum must be above the minimum",
+                min,
+                max
+            );
+        }
+        Ok(Self {
+            min,
+            max,
+            source: WindowSource::Fixed,
+        })
+    }
+
+    /// JSON object with the cut points and where they come from
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "min": self.min.into(),
+            "max": self.max.into(),
+            "source": self.source.to_string(),
+        })
+    }
+}
+
+impl Window<f64> {
+    /// The cut points as the f32 of the values displayed
+    pub fn rounded(&self) -> Window {
+        Window {
+            min: self.min as f32,
+            max: self.max as f32,
+            source: self.source,
+        }
+    }
+}
+
+/// Scale to 8-bit, clipping at the `low` and `high` quantiles
+pub fn normalize(decoded: &Array2<f32>, low: f64, high: f64) -> Result<Array2<u8>> {
+    let min = image_quantile(decoded, low)?;
+    let max = image_quantile(decoded, high)?;
+    Ok(normalize_window(decoded, min, max))
+}
+
+/// Scale to 8-bit, mapping the window from `min` to `max` onto 0-255
+pub fn normalize_window(decoded: &Array2<f32>, min: f32, max: f32) -> Array2<u8> {
+    if max <= min || min.is_nan() || max.is_nan() {
+        // A flat image, e.g. a crop made up entirely of missing chunks, or only NaN
+        return Array2::zeros(decoded.dim());
+    }
+    decoded.mapv(|x| ((x.clamp(min, max) - min) / (max - min) * 255.0) as u8)
+}
+
+/// How intensities are spaced between the cut points of the normalization
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Scale {
+    #[default]
+    Linear,
+    /// `log1p` of the distance above the low cut point
+    Log,
+}
+
+impl std::str::FromStr for Scale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "linear" => Ok(Self::Linear),
+            "log" => Ok(Self::Log),
+            _ => anyhow::bail!("Unknown scale '{}', use linear or log", s),
+        }
+    }
+}
+
+/// Scale to 8-bit like [`normalize_window`], in the given scale,
+/// raising the scaled values to `1 / gamma` so that a gamma above 1 brightens dim structures
+pub fn normalize_display(
+    decoded: &Array2<f32>,
+    min: f32,
+    max: f32,
+    scale: Scale,
+    gamma: f32,
+) -> Array2<u8> {
+    if (gamma == 1.0 && scale == Scale::Linear) || max <= min || min.is_nan() || max.is_nan() {
+        return normalize_window(decoded, min, max);
+    }
+    let exponent = 1.0 / gamma;
+    // The cut points are applied in the transformed space
+    let range = match scale {
+        Scale::Linear => max - min,
+        Scale::Log => (max - min).ln_1p(),
+    };
+    decoded.mapv(|x| {
+        let above = x.clamp(min, max) - min;
+        let transformed = match scale {
+            Scale::Linear => above,
+            Scale::Log => above.max(0.0).ln_1p(),
+        };
+        let scaled = (transformed / range).clamp(0.0, 1.0);
+        (scaled.powf(exponent) * 255.0) as u8
+    })
+}
+
+/// Scale exact values to 8-bit like [`normalize_display`], measured from `min` in f64
+/// so that only their distances within the window are rounded
+pub fn normalize_exact(
+    exact: &Array2<f64>,
+    min: f64,
+    max: f64,
+    scale: Scale,
+    gamma: f32,
+) -> Array2<u8> {
+    if max <= min || min.is_nan() || max.is_nan() {
+        return Array2::zeros(exact.dim());
+    }
+    let above = exact.mapv(|x| (x.clamp(min, max) - min) as f32);
+    normalize_display(&above, 0.0, (max - min) as f32, scale, gamma)
+}
+
+/// Store a group with `attributes` at `path` of a test store
+#[cfg(test)]
+pub(crate) fn store_group(
+    store: &Arc<zarrs::storage::store::MemoryStore>,
+    path: &str,
+    attributes: serde_json::Value,
+) -> Result<()> {
+    zarrs::group::GroupBuilder::new()
+        .attributes(attributes.as_object().cloned().unwrap_or_default())
+        .build(store.clone(), path)?
+        .store_metadata()?;
+    Ok(())
+}
+
+/// A test store with `attributes` on its root group and a uint8 array at /0 of `shape`
+/// in `chunks`, each pixel 10 times its first index plus its second
+#[cfg(test)]
+pub(crate) fn image_store(
+    attributes: serde_json::Value,
+    shape: &[u64],
+    chunks: &[u64],
+) -> Result<Arc<zarrs::storage::store::MemoryStore>> {
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+
+    let store = Arc::new(zarrs::storage::store::MemoryStore::new());
+    store_group(&store, "/", attributes)?;
+    let array = ArrayBuilder::new(
+        shape.to_vec(),
+        chunks.to_vec(),
+        DataType::UInt8,
+        FillValue::from(0u8),
+    )
+    .build(store.clone(), "/0")?;
+    array.store_metadata()?;
+    let dim: Vec<usize> = shape.iter().map(|&size| size as usize).collect();
+    let data = ArrayD::from_shape_fn(IxDyn(&dim), |idx| (idx[0] * 10 + idx[1]) as u8);
+    array.store_array_subset_ndarray(&vec![0; shape.len()], data)?;
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_and_shape_default_slicing() -> Result<()> {
+        let array_shape = vec![5, 3, 256, 256]; // T, C, Y, X
+        let crop_size = 256u64;
+
+        let (start, shape) = start_and_shape(&array_shape, None, [crop_size; 2])?;
+
+        assert_eq!(start.len(), 4);
+        assert_eq!(start[0], 2);
+        assert_eq!(start[1], 1);
+        assert_eq!(start[2], 0);
+        assert_eq!(start[3], 0);
+
+        assert_eq!(shape[0], 1);
+        assert_eq!(shape[1], 1);
+        assert_eq!(shape[2], 256);
+        assert_eq!(shape[3], 256);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_and_shape_with_explicit_slicing() -> Result<()> {
+        let array_shape = vec![5, 3, 256, 256]; // T, C, Y, X
+        let crop_size = 256u64;
+        let slice_indices = vec![0, 2]; // Use first timepoint, third channel
+
+        let (start, _shape) = start_and_shape(&array_shape, Some(&slice_indices), [crop_size; 2])?;
+
+        assert_eq!(start[0], 0);
+        assert_eq!(start[1], 2);
+        assert_eq!(start[2], 0);
+        assert_eq!(start[3], 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_and_shape_with_crop() -> Result<()> {
+        let array_shape = vec![1, 1, 1024, 1024]; // T, C, Y, X
+        let crop_size = 512u64;
+
+        let (_start, shape) = start_and_shape(&array_shape, None, [crop_size; 2])?;
+
+        assert_eq!(shape[2], 512);
+        assert_eq!(shape[3], 512);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slice_message_names_channel() {
+        let axes: Vec<String> = ["t", "c", "y", "x"].map(String::from).to_vec();
+        let labels = vec![Some("phase".to_string()), None, Some("GFP".to_string())];
+        let channel = Some((1, labels.as_slice()));
+        let axes = MessageAxes::new(Some(&axes), 4).expect("named axes");
+        assert_eq!(
+            slice_message(Some(&axes), channel, 1, None, 2),
+            "Slicing channel 'GFP' (c=2)"
+        );
+        assert_eq!(
+            slice_message(Some(&axes), channel, 1, None, 1),
+            "Slicing c at index 1"
+        );
+        assert_eq!(
+            slice_message(Some(&axes), channel, 0, None, 2),
+            "Slicing t at index 2"
+        );
+        assert_eq!(
+            slice_message(None, None, 1, None, 2),
+            "Slicing dimension 1 at index 2"
+        );
+        let assumed = MessageAxes::new(None, 3);
+        assert_eq!(
+            slice_message(assumed.as_ref(), None, 0, None, 2),
+            "Slicing z (assumed) at index 2"
+        );
+        // Keywords are shown with the index they resolve to
+        assert_eq!(
+            slice_message(Some(&axes), channel, 1, Some(SliceValue::Last), 2),
+            "Slicing channel 'GFP' (c=last, index 2)"
+        );
+        assert_eq!(
+            slice_message(Some(&axes), channel, 0, Some(SliceValue::Middle), 4),
+            "Slicing t at middle (index 4)"
+        );
+    }
+
+    #[test]
+    fn test_resolve_named_slice_indices() -> Result<()> {
+        let axes: Vec<String> = ["t", "c", "z", "y", "x"].map(String::from).to_vec();
+        let slices: Vec<SliceIndex> = ["t=12", "Z=30"]
+            .iter()
+            .map(|s| s.parse())
+            .collect::<Result<_>>()?;
+        assert_eq!(
+            resolve_slice_indices(&slices, Some(&axes), 3)?,
+            vec![Some(12.into()), None, Some(30.into())]
+        );
+
+        let positional = vec![SliceIndex::Position(1.into())];
+        assert_eq!(
+            resolve_slice_indices(&positional, Some(&axes), 3)?,
+            vec![Some(1.into()), None, None]
+        );
+
+        let unknown = vec![SliceIndex::Axis("q".to_string(), 0.into())];
+        let err = resolve_slice_indices(&unknown, Some(&axes), 3).expect_err("unknown axis");
+        assert!(err.to_string().contains("t, c, z, y, x"));
+        let spatial = vec![SliceIndex::Axis("y".to_string(), 0.into())];
+        assert!(resolve_slice_indices(&spatial, Some(&axes), 3).is_err());
+        assert!(resolve_slice_indices(&slices, None, 3).is_err());
+        let mixed = vec![
+            SliceIndex::Position(0.into()),
+            SliceIndex::Axis("c".to_string(), 0.into()),
+        ];
+        assert!(resolve_slice_indices(&mixed, Some(&axes), 3).is_err());
+        assert!("t=".parse::<SliceIndex>().is_err());
+
+        let keywords: Vec<SliceIndex> = ["t=last", "z=middle", "c=first"]
+            .iter()
+            .map(|s| s.parse())
+            .collect::<Result<_>>()?;
+        assert_eq!(
+            resolve_slice_indices(&keywords, Some(&axes), 3)?,
+            vec![
+                Some(SliceValue::Last),
+                Some(SliceValue::First),
+                Some(SliceValue::Middle)
+            ]
+        );
+        let (start, _) = start_and_shape_for_axes(
+            &[5, 3, 9, 64, 64],
+            &resolve_slice_indices(&keywords, Some(&axes), 3)?,
+            Some(&axes),
+            None,
+            &roi::Placement::crop([64; 2]),
+        )?;
+        assert_eq!(start, vec![4, 0, 4, 0, 0]);
+        assert_eq!(
+            "last".parse::<SliceIndex>()?,
+            SliceIndex::Position(SliceValue::Last)
+        );
+        let err = "t=lst".parse::<SliceIndex>().expect_err("unknown keyword");
+        assert_eq!(
+            err.to_string(),
+            "Invalid slice index 'lst', use a number, first, last or middle"
+        );
+        let all = vec!["c=all".parse::<SliceIndex>()?];
+        assert_eq!(all[0], SliceIndex::All("c".to_string()));
+        let err = resolve_slice_indices(&all, Some(&axes), 3).expect_err("not one slice");
+        assert!(err
+            .to_string()
+            .contains("c=all only shows them as a montage"));
+        assert!("all".parse::<SliceIndex>().is_err());
+        // Units still follow a number
+        assert!(matches!("t=120s".parse()?, SliceIndex::Physical(..)));
+        assert!("=3".parse::<SliceIndex>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_slice_by_axis_name() -> Result<()> {
+        let attributes = serde_json::json!({"multiscales": [{"axes": [
+            {"name": "t"}, {"name": "c"}, {"name": "y"}, {"name": "x"}]}]});
+        let store = image_store(attributes, &[3, 2, 4, 4], &[1, 1, 4, 4])?;
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("c".to_string(), 0.into())]),
+            ..Default::default()
+        };
+        let decoded = read_slice(store, Some("/0"), &options)?;
+        // t defaults to the midpoint
+        assert_eq!(decoded[[0, 0]], 10.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_slice_by_physical_coordinate() -> Result<()> {
+        let attributes = serde_json::json!({"multiscales": [{
+            "axes": [
+                {"name": "t", "type": "time", "unit": "second"},
+                {"name": "z", "type": "space", "unit": "micrometer"},
+                {"name": "y"}, {"name": "x", "unit": "nanometer"}],
+            "datasets": [{"path": "0", "coordinateTransformations": [
+                {"type": "scale", "scale": [30, 2.5, 1, 200]},
+                {"type": "translation", "translation": [0, 5, 0, 0]}]}]}]});
+        let store = image_store(attributes, &[5, 8, 2, 2], &[1, 1, 2, 2])?;
+
+        let slices = |spec: &str| -> Result<ReadOptions> {
+            Ok(ReadOptions {
+                slice_indices: Some(spec.split(',').map(str::parse).collect::<Result<_>>()?),
+                ..Default::default()
+            })
+        };
+        // 2 min is index 4, 15um is (15 - 5) / 2.5 = index 4
+        let decoded = read_slice(store.clone(), Some("/0"), &slices("t=2min,z=15um")?)?;
+        assert_eq!(decoded[[0, 0]], 44.0);
+        // Plain integers stay indices
+        let decoded = read_slice(store.clone(), Some("/0"), &slices("t=1,z=12500nm")?)?;
+        assert_eq!(decoded[[0, 0]], 13.0);
+        let err = read_slice(store.clone(), Some("/0"), &slices("t=15um")?)
+            .expect_err("length on a time axis");
+        assert!(err.to_string().contains("the axis is in second (time)"));
+        assert!("t=3furlong".parse::<SliceIndex>().is_err());
+        assert!(read_slice(store.clone(), Some("/0"), &slices("z=1um")?).is_err());
+        let view = read_slice_view(store, Some("/0"), &ReadOptions::default())?;
+        assert_eq!(view.dataset_path(), "0");
+        assert!((view.pixel_size().unwrap_or_default() - 200e-9).abs() < 1e-15);
+        assert_eq!(view.pixel_aspect(), Some(1.0 / 200.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_slice_from_plate_well() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        store_group(
+            &store,
+            "/",
+            serde_json::json!({"plate": {
+                "rows": [{"name": "A"}, {"name": "B"}],
+                "columns": [{"name": "01"}, {"name": "02"}, {"name": "03"}],
+                "wells": [{"path": "A/01"}, {"path": "B/03"}]}}),
+        )?;
+        store_group(&store, "/B", serde_json::json!({}))?;
+        store_group(
+            &store,
+            "/B/03",
+            serde_json::json!({"well": {"images": [{"path": "0"}, {"path": "1"}]}}),
+        )?;
+        store_group(
+            &store,
+            "/B/03/1",
+            serde_json::json!({"multiscales": [{"datasets": [{"path": "0"}]}]}),
+        )?;
+        let array = ArrayBuilder::new(
+            vec![4, 4],
+            vec![4, 4],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/B/03/1/0")?;
+        array.store_metadata()?;
+        array.store_array_subset_ndarray(&[0, 0], ArrayD::from_elem(IxDyn(&[4, 4]), 9u8))?;
+
+        let well = |well: Option<&str>, field: Option<usize>| ReadOptions {
+            well: well.map(str::to_string),
+            field,
+            ..Default::default()
+        };
+        let decoded = read_slice(store.clone(), None, &well(Some("B3"), Some(1)))?;
+        assert_eq!(decoded[[0, 0]], 9.0);
+
+        let err = read_slice(store.clone(), None, &well(None, None)).expect_err("no well");
+        assert!(err.to_string().contains("A  x  .  ."));
+        let err = read_slice(store.clone(), None, &well(Some("A02"), None)).expect_err("missing");
+        assert!(err.to_string().contains("available wells: A01, B03"));
+        let err = read_slice(store, None, &well(Some("B03"), Some(2))).expect_err("field");
+        assert!(err.to_string().contains("has 2 fields"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_label_image() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let multiscales = serde_json::json!([{"datasets": [{"path": "0"}]}]);
+        store_group(&store, "/", serde_json::json!({"multiscales": multiscales}))?;
+        store_group(&store, "/labels", serde_json::json!({"labels": ["cells"]}))?;
+        store_group(
+            &store,
+            "/labels/cells",
+            serde_json::json!({"multiscales": multiscales, "image-label": {"version": "0.4"}}),
+        )?;
+        let array = ArrayBuilder::new(
+            vec![4, 4],
+            vec![4, 4],
+            DataType::UInt32,
+            FillValue::from(0u32),
+        )
+        .build(store.clone(), "/labels/cells/0")?;
+        array.store_metadata()?;
+        array.store_array_subset_ndarray(&[0, 0], ArrayD::from_elem(IxDyn(&[4, 4]), 70000u32))?;
+
+        assert_eq!(list_labels(&store, "/")?, vec!["cells"]);
+        let options = ReadOptions {
+            label: Some("cells".to_string()),
+            ..Default::default()
+        };
+        let view = read_slice_view(store.clone(), None, &options)?;
+        assert!(view.is_label());
+        assert_eq!(view.path, "/labels/cells/0");
+        assert_eq!(view.data[[0, 0]], 70000.0);
+        let options = ReadOptions {
+            label: Some("nuclei".to_string()),
+            ..Default::default()
+        };
+        let err = read_slice_view(store, None, &options).expect_err("unknown label");
+        assert!(err.to_string().contains("available labels: cells"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_downsampled() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![2, 10, 7],
+            vec![1, 2, 4],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        let data = ndarray::ArrayD::from_shape_fn(ndarray::IxDyn(&[2, 10, 7]), |idx| {
+            (idx[0] * 1000 + idx[1] * 10 + idx[2]) as u16
+        });
+        array.store_array_subset_ndarray(&[0, 0, 0], data)?;
+
+        // Every 3rd pixel of the whole plane to fit within 4 pixels
+        let options = ReadOptions {
+            crop_size: 4,
+            ..Default::default()
+        };
+        let view = read_slice_view(store.clone(), Some("/0"), &options)?;
+        assert_eq!(view.step, 3);
+        assert_eq!(view.data.dim(), (4, 3));
+        assert_eq!(view.data.row(1).to_vec(), vec![1030.0, 1033.0, 1036.0]);
+        assert_eq!(
+            view.data.column(0).to_vec(),
+            vec![1000.0, 1030.0, 1060.0, 1090.0]
+        );
+        assert_eq!(view.extent(), [(0, 10), (0, 7)]);
+        // The same pixels at another index
+        let array = open_array(store.clone(), "/0")?;
+        let (other, _) = decode_view_plane(&array, &view, vec![0, 0, 0], &options)?;
+        assert_eq!(other[[3, 2]], 96.0);
+
+        let cropped = ReadOptions {
+            fit: Fit::Crop,
+            ..options.clone()
+        };
+        let view = read_slice_view(store.clone(), Some("/0"), &cropped)?;
+        assert_eq!((view.step, view.data.dim()), (1, (4, 4)));
+        assert_eq!(view.extent(), [(0, 4), (0, 4)]);
+        // Cropped to each limit, or downsampled by the same step along both
+        let line = ReadOptions {
+            max_height: Some(3),
+            max_width: Some(10),
+            ..cropped.clone()
+        };
+        let view = read_slice_view(store.clone(), Some("/0"), &line)?;
+        assert_eq!(view.data.dim(), (3, 7));
+        let fitted = ReadOptions {
+            fit: Fit::Downsample,
+            max_height: Some(5),
+            ..line
+        };
+        let view = read_slice_view(store.clone(), Some("/0"), &fitted)?;
+        assert_eq!((view.step, view.data.dim()), (2, (5, 4)));
+        // A region downsampled to fit, however it would be cropped
+        let region = ReadOptions {
+            roi: Some("2:-2,1:".parse()?),
+            ..cropped
+        };
+        let view = read_slice_view(store.clone(), Some("/0"), &region)?;
+        assert_eq!((view.step, view.data.dim()), (2, (3, 3)));
+        assert_eq!(view.extent(), [(2, 8), (1, 7)]);
+        assert_eq!(view.data[[0, 0]], 1021.0);
+        assert!("squeeze".parse::<Fit>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_slice_view_omero_channel() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let attributes = serde_json::json!({
+            "multiscales": [{"axes": [{"name": "c"}, {"name": "y"}, {"name": "x"}]}],
+            "omero": {"channels": [
+                {"color": "FF0000", "window": {"start": 0, "end": 100}},
+                {"color": "00FF00", "window": {"start": 50, "end": 60}}]}});
+        store_group(&store, "/", attributes)?;
+        ArrayBuilder::new(
+            vec![2, 4, 4],
+            vec![1, 4, 4],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/0")?
+        .store_metadata()?;
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("c".to_string(), 1.into())]),
+            ..Default::default()
+        };
+        let view = read_slice_view(store, Some("/0"), &options)?;
+        assert_eq!(view.channel_index(), 1);
+        assert_eq!(
+            view.omero_channel(),
+            Some(omero::OmeroChannel {
+                label: None,
+                window: Some((50.0, 60.0)),
+                color: Some([0, 255, 0])
+            })
+        );
+        assert_eq!(
+            normalize_window(&array![[40.0, 55.0, 70.0]], 50.0, 60.0),
+            array![[0, 127, 255]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_slice_with_memory_store() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![3, 4, 8],
+            vec![1, 4, 4],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        let data = ArrayD::from_shape_fn(IxDyn(&[3, 4, 8]), |idx| {
+            (idx[0] * 100 + idx[1] * 8 + idx[2]) as u16
+        });
+        array.store_array_subset_ndarray(&[0, 0, 0], data)?;
+
+        let decoded = read_slice(store, Some("/0"), &ReadOptions::default())?;
+
+        assert_eq!(decoded.dim(), (4, 8));
+        assert_eq!(decoded[[0, 0]], 100.0);
+        assert_eq!(decoded[[3, 7]], 131.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_read() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![3, 4, 8],
+            vec![1, 4, 4],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        // One of the two chunks of the middle plane
+        array.store_chunk_elements(&[1, 0, 0], &[7u16; 16])?;
+
+        let plan = plan_read(store.clone(), Some("/0"), &ReadOptions::default(), true)?;
+        assert_eq!(
+            (plan.start.clone(), plan.subset_shape.clone()),
+            (vec![1, 0, 0], vec![1, 4, 8])
+        );
+        assert_eq!((plan.chunks, plan.chunk_bytes), (2, 64));
+        assert_eq!(plan.stored, Some((32, 1)));
+        assert_eq!((plan.estimated_bytes(), plan.decoded_bytes), (32, 128));
+        assert!(plan
+            .to_string()
+            .contains("Stored: 32 B (1 of the chunks are missing)"));
+        assert!(plan
+            .to_string()
+            .ends_with("Decoded: 128 B as 32-bit floats"));
+        assert_eq!(plan.axis_planes("0", Some(1), None)?, 2);
+
+        // Without sizing the chunks, downsampled to fit
+        let options = ReadOptions {
+            crop_size: 4,
+            ..Default::default()
+        };
+        let mut plan = plan_read(store, Some("/0"), &options, false)?;
+        plan.planes = 3;
+        assert_eq!((plan.step, plan.decoded_bytes), (2, 32));
+        assert_eq!(plan.estimated_bytes(), 3 * 64);
+        assert_eq!(plan.to_json()["chunks"], 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_chunks_read_as_fill_value() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+        use zarrs::storage::{StoreKey, WritableStorageTraits};
+
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![16, 16],
+            vec![8, 8],
+            DataType::UInt8,
+            FillValue::from(3u8),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        array.store_chunk_ndarray(&[0, 0], ArrayD::from_elem(IxDyn(&[8, 8]), 200u8))?;
+
+        let decoded = read_slice(store.clone(), Some("/0"), &ReadOptions::default())?;
+        assert_eq!(decoded[[0, 0]], 200.0);
+        assert_eq!(decoded[[15, 15]], 3.0);
+
+        // A crop of only missing chunks is a flat image
+        store.erase(&StoreKey::new("0/c/0/0")?)?;
+        let decoded = read_slice(store, Some("/0"), &ReadOptions::default())?;
+        assert!(decoded.iter().all(|&x| x == 3.0));
+        assert!(normalize(&decoded, 0.001, 0.999)?.iter().all(|&x| x == 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_level_fitting_crop_size() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let attributes = serde_json::json!({"multiscales": [{"datasets": [
+            {"path": "s0"}, {"path": "s1"}, {"path": "s2"}]}]});
+        store_group(&store, "/", attributes)?;
+        for (path, size) in [("/s0", 1024), ("/s1", 512), ("/s2", 256)] {
+            ArrayBuilder::new(
+                vec![size, size],
+                vec![64, 64],
+                DataType::UInt8,
+                FillValue::from(0u8),
+            )
+            .build(store.clone(), path)?
+            .store_metadata()?;
+        }
+
+        assert_eq!(select_level(&store, "/", [300; 2], Fit::Crop)?, "/s1");
+        assert_eq!(select_level(&store, "/", [256; 2], Fit::Crop)?, "/s2");
+        assert_eq!(select_level(&store, "/", [4096; 2], Fit::Crop)?, "/s0");
+        // The finest level fitting, or the coarsest if none does
+        assert_eq!(select_level(&store, "/", [300; 2], Fit::Downsample)?, "/s2");
+        assert_eq!(select_level(&store, "/", [512; 2], Fit::Downsample)?, "/s1");
+        assert_eq!(select_level(&store, "/", [100; 2], Fit::Downsample)?, "/s2");
+        assert_eq!(
+            select_level(&store, "/", [600, 2048], Fit::Downsample)?,
+            "/s1"
+        );
+        assert_eq!(select_level(&store, "/", [2048, 300], Fit::Crop)?, "/s1");
+        let decoded = read_slice(store.clone(), None, &ReadOptions::default())?;
+        assert_eq!(decoded.dim(), (1024, 1024));
+        // The pixels of a region are those of the finest level
+        let region = ReadOptions {
+            crop_size: 256,
+            roi: Some("0:100,0:100".parse()?),
+            ..Default::default()
+        };
+        assert_eq!(read_slice_view(store.clone(), None, &region)?.path, "/s0");
+        let finest = ReadOptions {
+            crop_size: 256,
+            fit: Fit::Crop,
+            finest: true,
+            ..Default::default()
+        };
+        assert_eq!(read_slice_view(store.clone(), None, &finest)?.path, "/s0");
+
+        assert_eq!(level_path(&store, "/", 2)?, "/s2");
+        let err = level_path(&store, "/", 3).expect_err("out of range");
+        assert_eq!(
+            err.to_string(),
+            "Level 3 is out of range, the image has 3 levels"
+        );
+        let options = ReadOptions {
+            level: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(read_slice(store, None, &options)?.dim(), (512, 512));
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover_image_below_root() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let attributes = serde_json::json!({"multiscales": [{"datasets": [{"path": "0"}]}]});
+        store_group(&store, "/run/image.zarr", attributes.clone())?;
+        ArrayBuilder::new(
+            vec![8, 8],
+            vec![8, 8],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/run/image.zarr/0")?
+        .store_metadata()?;
+
+        let view = read_slice_view(store.clone(), None, &ReadOptions::default())?;
+        assert_eq!(view.path, "/run/image.zarr/0");
+        let strict = ReadOptions {
+            discover: false,
+            ..Default::default()
+        };
+        assert!(read_slice_view(store.clone(), None, &strict).is_err());
+
+        store_group(&store, "/other.zarr", attributes)?;
+        let err = discover_image(&store, "/").expect_err("several images");
+        assert!(err.to_string().ends_with(
+            "found 2 images below it, open one of them directly:\n/other.zarr\n/run/image.zarr"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_level_without_multiscales() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::group::GroupBuilder;
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        GroupBuilder::new()
+            .build(store.clone(), "/")?
+            .store_metadata()?;
+        let err = select_level(&store, "/", [256; 2], Fit::Crop).expect_err("empty group");
+        assert!(err.to_string().ends_with("the group is empty"));
+        for path in ["/raw", "/scale0"] {
+            ArrayBuilder::new(
+                vec![8, 8],
+                vec![8, 8],
+                DataType::UInt8,
+                FillValue::from(0u8),
+            )
+            .build(store.clone(), path)?
+            .store_metadata()?;
+        }
+        let err = select_level(&store, "/", [256; 2], Fit::Crop).expect_err("no array at /0");
+        assert!(err.to_string().ends_with("--array-name: raw, scale0"));
+        ArrayBuilder::new(
+            vec![8, 8],
+            vec![8, 8],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/0")?
+        .store_metadata()?;
+        assert_eq!(select_level(&store, "/", [256; 2], Fit::Crop)?, "/0");
+        Ok(())
+    }
+
+    fn write_v2_fixture(
+        store: &Arc<zarrs::storage::store::MemoryStore>,
+        separator: &str,
+        data: ArrayD<u16>,
+    ) -> Result<()> {
+        use zarrs::storage::{StoreKey, WritableStorageTraits};
+
+        let metadata = format!(
+            r#"{{"zarr_format": 2, "shape": [2, 16, 16], "chunks": [1, 8, 8], "dtype": "<u2",
+            "compressor": {{"id": "blosc", "cname": "lz4", "clevel": 5, "shuffle": 1, "blocksize": 0}},
+            "fill_value": 0, "filters": null, "order": "C", "dimension_separator": "{}"}}"#,
+            separator
+        );
+        store.set(&StoreKey::new("0/.zarray")?, metadata.into_bytes().into())?;
+        let array = open_array(store.clone(), "/0")?;
+        array.store_array_subset_ndarray(&[0, 0, 0], data)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_zarr_v2_matches_v3() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+        use zarrs::storage::{ReadableStorageTraits, StoreKey};
+
+        let data = ArrayD::from_shape_fn(IxDyn(&[2, 16, 16]), |idx| {
+            (idx[0] * 1000 + idx[1] * 16 + idx[2]) as u16
+        });
+        let options = ReadOptions::default();
+
+        let v3_store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![2, 16, 16],
+            vec![1, 8, 8],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .build(v3_store.clone(), "/0")?;
+        array.store_metadata()?;
+        array.store_array_subset_ndarray(&[0, 0, 0], data.clone())?;
+        let expected = read_slice(v3_store, Some("/0"), &options)?;
+
+        for (separator, chunk_key) in [("/", "0/1/1/1"), (".", "0/1.1.1")] {
+            let v2_store = Arc::new(MemoryStore::new());
+            write_v2_fixture(&v2_store, separator, data.clone())?;
+            assert!(v2_store.get(&StoreKey::new(chunk_key)?)?.is_some());
+            let decoded = read_slice(v2_store, Some("/0"), &options)?;
+            assert_eq!(decoded, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_decode_matches_serial() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![64, 64],
+            vec![8, 8],
+            DataType::Float32,
+            FillValue::from(0f32),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        let data = ArrayD::from_shape_fn(IxDyn(&[64, 64]), |idx| (idx[0] * 64 + idx[1]) as f32);
+        array.store_array_subset_ndarray(&[0, 0], data)?;
+
+        let serial = ReadOptions {
+            concurrency: 1,
+            ..Default::default()
+        };
+        let concurrent = ReadOptions {
+            concurrency: 8,
+            ..Default::default()
+        };
+        assert_eq!(
+            read_slice(store.clone(), Some("/0"), &serial)?,
+            read_slice(store, Some("/0"), &concurrent)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_array_missing_metadata() -> Result<()> {
+        let store = Arc::new(zarrs::storage::store::MemoryStore::new());
+        let err = open_array(store, "/0").expect_err("empty store has no array");
+        assert!(err.to_string().contains("zarr.json"));
+        assert!(err.to_string().contains(".zarray"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_shape_validation_at_least_2d() -> Result<()> {
+        let array_shape = vec![256]; // Only 1D
+
+        let result = start_and_shape(&array_shape, None, [256; 2]);
+        assert!(result.is_err(), "Should reject 1D arrays");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slice_index_out_of_bounds() -> Result<()> {
+        let array_shape = vec![5, 3, 256, 256];
+        let slice_indices = vec![10]; // Out of bounds for dimension 0 (size 5)
+
+        let err = start_and_shape(&array_shape, Some(&slice_indices), [256; 2])
+            .expect_err("Should reject out-of-bounds indices");
+        assert_eq!(
+            err.to_string(),
+            "Slice index 10 is out of bounds for axis 'c' (size 5, valid 0..=4), \
+             assuming axes czyx without axes metadata"
+        );
+
+        let axes: Vec<String> = ["t", "c", "y", "x"].map(String::from).to_vec();
+        let err = start_and_shape_for_axes(
+            &[2, 3, 256, 256],
+            &[None, Some(5.into())],
+            Some(&axes),
+            None,
+            &roi::Placement::crop([256; 2]),
+        )
+        .expect_err("Should reject out-of-bounds indices");
+        assert_eq!(
+            err.to_string(),
+            "Slice index 5 is out of bounds for axis 'c' (size 3, valid 0..=2)"
+        );
+
+        let err = start_and_shape(&[2, 1, 1, 1, 1, 8, 8], Some(&[4]), [256; 2])
+            .expect_err("Should reject out-of-bounds indices");
+        assert_eq!(
+            err.to_string(),
+            "Slice index 4 is out of bounds for dimension 0 (size 2)"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_too_many_slice_indices() -> Result<()> {
+        let array_shape = vec![5, 3, 256, 256]; // 2 dimensions to slice (T and C)
+        let slice_indices = vec![0, 1, 2, 3]; // 4 indices, but only 2 allowed
+
+        let err = start_and_shape(&array_shape, Some(&slice_indices), [256; 2])
+            .expect_err("Should reject too many slice indices");
+        assert_eq!(
+            err.to_string(),
+            "Too many slice indices: got 4 for 2 non-spatial axes (c, z), \
+             assuming axes czyx without axes metadata"
+        );
+        let axes: Vec<String> = ["t", "c", "y", "x"].map(String::from).to_vec();
+        let positional = [0, 1, 2].map(|index| SliceIndex::Position(index.into()));
+        let err = resolve_slice_indices(&positional, Some(&axes), 2)
+            .expect_err("Should reject too many slice indices");
+        assert_eq!(
+            err.to_string(),
+            "Too many slice indices: got 3 for 2 non-spatial axes (t, c)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantile_computation() -> Result<()> {
+        use ndarray_stats::interpolate::Nearest;
+        use ndarray_stats::QuantileExt;
+        use noisy_float::types::n64;
+
+        let data =
+            Array2::from_shape_vec((10, 10), (0..100).map(|i| i as f32).collect::<Vec<_>>())?;
+
+        let q_low = data
+            .flatten()
+            .quantile_axis_skipnan_mut(ndarray::Axis(0), n64(0.1), &Nearest)?
+            .into_scalar();
+
+        let q_high = data
+            .flatten()
+            .quantile_axis_skipnan_mut(ndarray::Axis(0), n64(0.9), &Nearest)?
+            .into_scalar();
+
+        assert!(
+            q_low < q_high,
+            "Low quantile should be less than high quantile"
+        );
+        assert!(q_low >= 0.0, "Quantiles should be non-negative");
+        assert!(q_high <= 99.0, "High quantile should be reasonable");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_normalization() -> Result<()> {
+        let mut data = vec![0.0f32; 256];
+        for (i, val) in data.iter_mut().enumerate() {
+            *val = i as f32;
+        }
+
+        let array = Array2::from_shape_vec((16, 16), data)?;
+
+        let min = 10.0f32;
+        let max = 245.0f32;
+        let normalized = array.mapv(|x| ((x.clamp(min, max) - min) / (max - min) * 255.0) as u8);
+
+        let raw_vec = normalized.to_owned().into_raw_vec_and_offset().0;
+        assert_eq!(
+            raw_vec.len(),
+            256,
+            "Normalized array should have 256 elements"
+        );
+
+        assert_eq!(raw_vec[0], 0, "Value below min should be 0");
+        assert_eq!(raw_vec[255], 255, "Value above max should be 255");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_gamma() {
+        let data = array![[0.0, 25.0, 100.0, f32::NAN]];
+        assert_eq!(
+            normalize_display(&data, 0.0, 100.0, Scale::Linear, 1.0),
+            normalize_window(&data, 0.0, 100.0)
+        );
+        // sqrt(0.25) = 0.5
+        assert_eq!(
+            normalize_display(&data, 0.0, 100.0, Scale::Linear, 2.0)
+                .row(0)
+                .to_vec()[..3],
+            [0, 127, 255]
+        );
+        assert_eq!(
+            normalize_display(&data, 5.0, 5.0, Scale::Linear, 2.0),
+            Array2::<u8>::zeros((1, 4))
+        );
+    }
+
+    #[test]
+    fn test_fixed_window() -> Result<()> {
+        let data = Array2::from_shape_fn((10, 10), |(i, j)| (i * 10 + j) as f32);
+        let window = Window::fixed(&data, Some(0.0), Some(65535.0), 0.1, 0.9)?;
+        assert_eq!((window.min, window.max), (0.0, 65535.0));
+        assert_eq!(window.source, WindowSource::Fixed);
+        // The other end from the data
+        let window = Window::fixed(&data, None, Some(50.0), 0.0, 1.0)?;
+        assert_eq!(window.min, 0.0);
+        let err = Window::fixed(&data, Some(100.0), None, 0.0, 1.0).expect_err("empty window");
+        assert!(err
+            .to_string()
+            .contains("maximum must be above the minimum"));
+        assert!(normalizer::check_range(Some(5.0), Some(5.0)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_log() -> Result<()> {
+        // Counts over four orders of magnitude above an offset of 10
+        let data = array![[0.0, 10.0, 19.0, 109.0, 1009.0, 10009.0]];
+        let scaled = normalize_display(&data, 10.0, 10009.0, Scale::Log, 1.0);
+        let expected = |count: f32| (count.ln_1p() / 9999f32.ln_1p() * 255.0) as u8;
+        assert_eq!(
+            scaled.row(0).to_vec(),
+            vec![0, 0, expected(9.0), expected(99.0), expected(999.0), 255]
+        );
+        // Dim values are spread out, unlike the linear scale
+        assert!(scaled[[0, 2]] > 50);
+        assert_eq!(normalize_window(&data, 10.0, 10009.0)[[0, 2]], 0);
+        assert_eq!("log".parse::<Scale>()?, Scale::Log);
+        assert!("sqrt".parse::<Scale>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_correct_aspect() {
+        let data = Array2::from_shape_fn((2, 4), |(i, j)| (i * 4 + j) as f32);
+        // Pixels twice as tall as wide
+        let corrected = correct_aspect(&data, 2.0, [100; 2], Filter::Nearest);
+        assert_eq!(corrected.dim(), (4, 4));
+        assert_eq!(corrected.row(1), data.row(0));
+        assert_eq!(corrected.row(2), data.row(1));
+        // Stretching would exceed the display size, so both axes shrink
+        assert_eq!(
+            correct_aspect(&data, 4.0, [4; 2], Filter::Nearest).dim(),
+            (4, 2)
+        );
+        assert_eq!(
+            correct_aspect(&data, 0.5, [100; 2], Filter::Nearest).dim(),
+            (2, 8)
+        );
+        assert_eq!(
+            correct_aspect(&data, 1.0, [1; 2], Filter::Nearest).dim(),
+            (2, 4)
+        );
+    }
+
+    #[test]
+    fn test_resample_filters() -> Result<()> {
+        // Labels keep their ids with nearest neighbours, shrunk or enlarged
+        let labels = Array2::from_shape_fn((7, 9), |(i, j)| ((i / 3) * 100 + j / 2 * 7) as f32);
+        let ids: std::collections::HashSet<u32> = labels.iter().map(|&id| id as u32).collect();
+        for (rows, columns) in [(3, 4), (20, 13)] {
+            let resampled = resample(&labels, rows, columns, Filter::Nearest);
+            assert_eq!(resampled.dim(), (rows, columns));
+            assert!(resampled.iter().all(|&id| ids.contains(&(id as u32))));
+        }
+        // Shrinking by area averages the pixels covered
+        let data = Array2::from_shape_fn((4, 4), |(i, j)| (i * 4 + j) as f32);
+        let shrunk = resample(&data, 2, 2, Filter::Area);
+        assert_eq!(shrunk, ndarray::array![[2.5, 4.5], [10.5, 12.5]]);
+        assert_eq!(resample(&data, 3, 3, Filter::Area)[[1, 1]], 7.5);
+        // Constants stay constant with every filter and size
+        let constant = Array2::from_elem((5, 6), 3.0f32);
+        for filter in ["nearest", "bilinear", "lanczos3", "area"] {
+            for (rows, columns) in [(2, 3), (11, 17)] {
+                let resampled = resample(&constant, rows, columns, filter.parse()?);
+                assert!(resampled.iter().all(|&value| (value - 3.0).abs() < 1e-4));
+            }
+        }
+        // Bilinear interpolates between neighbours when enlarging
+        let ramp = ndarray::array![[0.0f32, 10.0]];
+        assert_eq!(
+            resample(&ramp, 1, 4, Filter::Bilinear),
+            ndarray::array![[0.0, 2.5, 7.5, 10.0]]
+        );
+        // NaN pixels are left out, and all NaN stays NaN
+        let mut masked = data.clone();
+        masked[[0, 0]] = f32::NAN;
+        masked[[0, 1]] = f32::NAN;
+        masked[[1, 0]] = f32::NAN;
+        masked[[1, 1]] = f32::NAN;
+        let shrunk = resample(&masked, 2, 2, Filter::Area);
+        assert!(shrunk[[0, 0]].is_nan());
+        assert_eq!(shrunk[[0, 1]], 4.5);
+        let error = "cubic".parse::<Filter>().expect_err("unknown filter");
+        assert!(error
+            .to_string()
+            .contains("nearest, bilinear, lanczos3 or area"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zoom() {
+        let data = ndarray::array![[1.0f32, 2.0], [3.0, 4.0]];
+        assert_eq!(zoom(&data, 1.0, Filter::Area), data);
+        let enlarged = zoom(&data, 3.0, Filter::Area);
+        assert_eq!(enlarged.dim(), (6, 6));
+        assert_eq!(enlarged.slice(s![..3, ..3]), Array2::from_elem((3, 3), 1.0));
+        assert_eq!(enlarged[[5, 5]], 4.0);
+        assert_eq!(zoom(&data, 0.5, Filter::Area), ndarray::array![[2.5]]);
+        assert_eq!(zoom_factor((100, 50), 4.0, [400, 800]), 4.0);
+        // Bounded by the display, whole when enlarging
+        assert_eq!(zoom_factor((100, 50), 8.0, [450, 800]), 4.0);
+        assert_eq!(zoom_factor((100, 50), 0.5, [25, 800]), 0.25);
+        assert_eq!(zoom_factor((1000, 50), 1.0, [250, 800]), 0.25);
+    }
+
+    #[test]
+    fn test_slice_stats_skip_nan() -> Result<()> {
+        let mut array = Array2::from_shape_fn((4, 5), |(i, j)| (i * 5 + j) as f32);
+        array[[0, 0]] = f32::NAN;
+        let stats = slice_stats(&array, &[0.0, 1.0])?;
+        assert_eq!((stats.rows, stats.columns), (4, 5));
+        assert_eq!((stats.min, stats.max), (1.0, 19.0));
+        assert_eq!(stats.quantiles, vec![(0.0, 1.0), (1.0, 19.0)]);
+        assert_eq!(stats.to_json()["quantiles"][1]["value"], 19.0);
+        assert_eq!(stats.nan_fraction, 0.05);
+        assert_eq!((stats.zero_count, stats.nan_count), (0, 1));
+        // Over the 19 values 1 to 19
+        assert_eq!(stats.mean, 10.0);
+        assert!((stats.std - 30f64.sqrt()).abs() < 1e-12);
+        assert_eq!(stats.to_json()["mean"], 10.0);
+        let text = slice_stats(&array, &[0.01, 0.5])?.format();
+        assert!(text.contains("\nq0.01   1\nmedian  10\n"), "{}", text);
+        assert!(text.ends_with("nan     1 (5.0%)"));
+        // Exact for large values of 16-bit images, where f32 sums would round
+        let bright = Array2::from_shape_fn((256, 256), |(i, j)| 65535.0 - ((i + j) % 2) as f32);
+        let stats = slice_stats(&bright, &[])?;
+        assert_eq!((stats.mean, stats.std), (65534.5, 0.5));
+        let zeros = slice_stats(&Array2::<f32>::zeros((2, 2)), &[])?;
+        assert_eq!((zeros.zero_count, zeros.zero_fraction()), (4, 1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_finite_values() -> Result<()> {
+        // Infinities count as the finite extrema
+        let array = array![[f32::NEG_INFINITY, 1.0, 2.0, f32::INFINITY, f32::NAN]];
+        assert_eq!(image_quantile(&array, 0.0)?, 1.0);
+        assert_eq!(image_quantile(&array, 1.0)?, 2.0);
+        let normalized = normalize_window(&array, 1.0, 2.0);
+        assert_eq!(normalized.row(0).to_vec(), vec![0, 0, 255, 255, 0]);
+        // Without finite values, no quantiles and a black image rather than a panic
+        let masked = Array2::from_elem((2, 2), f32::NAN);
+        let stats = slice_stats(&masked, &[0.5])?;
+        assert!(stats.quantiles[0].1.is_nan());
+        assert_eq!(stats.nan_fraction, 1.0);
+        assert_eq!(
+            normalize_window(&masked, f32::NAN, f32::NAN),
+            Array2::<u8>::zeros((2, 2))
+        );
+        assert_eq!(
+            normalize_display(&masked, f32::NAN, f32::NAN, Scale::Log, 2.0),
+            Array2::<u8>::zeros((2, 2))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_2d_reshape_from_multi_dimensional_array() -> Result<()> {
+        let data: Array4<f32> = Array4::zeros((1, 1, 256, 256)); // (T, C, Y, X)
+
+        let shape = data.shape();
+        let y = shape[shape.len() - 2];
+        let x = shape[shape.len() - 1];
+        let reshaped = data.to_shape((y, x))?.to_owned();
+
+        assert_eq!(reshaped.shape(), &[256, 256]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_wide_integers_exact() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        let base = 1i64 << 40;
+        let data = ArrayD::from_shape_fn(IxDyn(&[4, 4]), |idx| base + (idx[0] * 4 + idx[1]) as i64);
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![4, 4],
+            vec![2, 2],
+            DataType::Int64,
+            FillValue::from(0i64),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        array.store_array_subset_ndarray(&[0, 0], data)?;
+        let plan = plan_read(store.clone(), Some("/0"), &ReadOptions::default(), false)?;
+        assert_eq!(plan.decoded_bytes, 16 * 12);
+        assert!(plan
+            .to_string()
+            .ends_with("Decoded: 192 B as 32-bit and 64-bit floats"));
+        let mut view = read_array_slice(store, "/0", &ReadOptions::default())?;
+        let exact = view.exact.clone().expect("int64 is read exactly");
+        assert_eq!(exact[[3, 3]], (base + 15) as f64);
+        // f32 rounds the neighbouring values together
+        assert_eq!(view.data[[0, 0]], view.data[[0, 1]]);
+        assert!(rounds_to_f32(&exact));
+        assert_eq!(image_quantile(&exact, 1.0)?, (base + 15) as f64);
+        let stats = slice_stats(&exact, &[0.5])?;
+        assert_eq!((stats.min, stats.mean), (base as f64, base as f64 + 7.5));
+        let normalized =
+            normalize_exact(&exact, base as f64, (base + 15) as f64, Scale::Linear, 1.0);
+        assert_eq!(normalized.row(0).to_vec(), vec![0, 17, 34, 51]);
+
+        // Kept through the nearest pixels and resampling that keeps the shape, not a blend
+        view.set_resampled(view.data.clone(), Filter::Area);
+        assert!(view.exact.is_some());
+        view.set_resampled(resample(&view.data, 8, 8, Filter::Nearest), Filter::Nearest);
+        assert_eq!(
+            view.exact.as_ref().map(|exact| exact[[7, 7]]),
+            Some((base + 15) as f64)
+        );
+        view.set_resampled(resample(&view.data, 2, 2, Filter::Area), Filter::Area);
+        assert!(view.exact.is_none());
+
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![2, 2],
+            vec![2, 2],
+            DataType::UInt32,
+            FillValue::from(0u32),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        assert!(read_array_slice(store, "/0", &ReadOptions::default())?
+            .exact
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_float16_like_float32() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
+
+        // A gradient exact in half precision, with NaN, infinities and an unwritten chunk
+        let gradient = Array2::from_shape_fn((4, 8), |(i, j)| match (i, j) {
+            (0, 0) => f32::NAN,
+            (0, 1) => f32::INFINITY,
+            (0, 2) => f32::NEG_INFINITY,
+            _ => (i * 8 + j) as f32 / 16.0 - 1.0,
+        });
+        let read =
+            |data_type: DataType, fill_value: FillValue| -> Result<(Array2<f32>, Array2<u8>)> {
+                let store = Arc::new(MemoryStore::new());
+                let array =
+                    ArrayBuilder::new(vec![4, 12], vec![4, 4], data_type.clone(), fill_value)
+                        .build(store.clone(), "/0")?;
+                array.store_metadata()?;
+                match data_type {
+                    DataType::Float16 => array
+                        .store_array_subset_ndarray(&[0, 0], gradient.mapv(half::f16::from_f32))?,
+                    _ => array.store_array_subset_ndarray(&[0, 0], gradient.clone())?,
+                }
+                let decoded = read_slice(store, Some("/0"), &ReadOptions::default())?;
+                let normalized = normalize(&decoded, 0.001, 0.999)?;
+                Ok((decoded, normalized))
+            };
+        let (half, half_normalized) = read(DataType::Float16, FillValue::from(half::f16::NAN))?;
+        let (single, single_normalized) = read(DataType::Float32, FillValue::from(f32::NAN))?;
+        assert_eq!(half.dim(), (4, 12));
+        assert!(half[[0, 0]].is_nan() && half[[3, 11]].is_nan());
+        assert_eq!(
+            (half[[0, 1]], half[[0, 2]]),
+            (f32::INFINITY, f32::NEG_INFINITY)
+        );
+        assert_eq!(half[[3, 7]], 0.9375);
+        let same = |a: &f32, b: &f32| a == b || (a.is_nan() && b.is_nan());
+        assert!(half.iter().zip(&single).all(|(a, b)| same(a, b)));
+        assert_eq!(half_normalized, single_normalized);
+        Ok(())
+    }
+
+    #[test]
+    fn test_different_dtype_conversions() -> Result<()> {
+        let uint8_val: u8 = 200;
+        let uint16_val: u16 = 50000;
+        let int16_val: i16 = -1000;
+        let float64_val: f64 = 123.456;
+
+        let uint8_f32 = uint8_val as f32;
+        let uint16_f32 = uint16_val as f32;
+        let int16_f32 = int16_val as f32;
+        let float64_f32 = float64_val as f32;
+
+        assert!(uint8_f32 > 0.0);
+        assert!(uint16_f32 > 0.0);
+        assert!(int16_f32 < 0.0);
+        assert!((float64_f32 - 123.456).abs() < 0.01);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_to_raw_vector_conversion() -> Result<()> {
+        let normalized = Array2::from_shape_vec((16, 16), vec![128u8; 256])?;
+
+        let raw_vec = normalized.into_raw_vec_and_offset().0;
+
+        assert_eq!(raw_vec.len(), 256);
+        assert!(raw_vec.iter().all(|&v| v == 128));
+
+        Ok(())
+    }
+}
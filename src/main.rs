@@ -1,384 +1,3538 @@
-use anyhow::{Ok, Result};
-use clap::Parser;
-use image::{DynamicImage, GrayImage};
-use ndarray::prelude::*;
-use ndarray_stats::interpolate::Nearest;
-use ndarray_stats::QuantileExt;
-use noisy_float::types::n64;
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::vec;
-use zarrs::filesystem::FilesystemStore;
-use zarrs_http::HTTPStore;
-
-#[derive(Parser)]
+use anyhow::{Context, Ok, Result};
+use clap::{ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueHint};
+use clap_complete::Shell;
+use image::{DynamicImage, GrayImage, RgbImage};
+use ndarray::{Array2, ArrayView2};
+use peekzarr::animation::{self, FrameRange};
+use peekzarr::blocks::{self, Dither, Palette};
+use peekzarr::caption::{self, Caption};
+use peekzarr::chunkgrid::ChunkGrid;
+use peekzarr::colorbar::{self, Colorbar};
+use peekzarr::colormap::Colormap;
+use peekzarr::composite::ChannelSpec;
+use peekzarr::config::{self, Setting};
+use peekzarr::diff::{self, DiffStats};
+use peekzarr::error::{self, Error};
+use peekzarr::export;
+use peekzarr::histogram::Histogram;
+#[cfg(unix)]
+use peekzarr::interactive::{Action, Contrast, KeyReader, Navigation};
+use peekzarr::montage;
+use peekzarr::movie::{MovieFormat, MovieWriter};
+use peekzarr::normalizer::{self, Clip, Norm, Normalizer};
+use peekzarr::orientation::{Orientation, Rotation};
+use peekzarr::ortho;
+use peekzarr::probe::Probe;
+use peekzarr::profile::{self, Line, Profile};
+use peekzarr::projection::{self, IndexRange, Projected, Projection, ProjectionSpan};
+use peekzarr::pyramid;
+use peekzarr::roi::{Anchor, Offset, Roi};
+use peekzarr::scalebar::{self, ScaleBar};
+use peekzarr::sequence::{self, SliceSpec};
+use peekzarr::snapshot::{self, StreamFormat};
+use peekzarr::storagestats;
+use peekzarr::store::{self, Location, StoreOptions};
+use peekzarr::terminal::{self, Emit, Protocol, Support};
+use peekzarr::thumbnail;
+use peekzarr::timings::{self, Stage};
+use peekzarr::verbosity::{self, Verbosity};
+use peekzarr::{
+    composite, header, hierarchy, histogram, info, labels, plate, read_slice_view, validate, warn,
+    Filter, Fit, ReadOptions, ReadPlan, Scale, SliceIndex, SliceValue, SliceView, Window,
+};
+use std::collections::HashSet;
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Clone)]
 #[command(version, about = "Peek into OME-Zarr images in the terminal.")]
+#[command(group(
+    ArgGroup::new("files")
+        .args(["output", "output_dir", "export_raw", "write_thumbnail", "export_hist", "export_profile"])
+        .multiple(true)
+))]
+#[command(group(ArgGroup::new("difference").args(["diff", "diff_t"])))]
 struct Cli {
-    /// Path or URL to the OME-Zarr group containing arrays (FOV level in HCS)
-    image_path: PathBuf,
+    /// Path or URL (http://, https://, s3://, gs://, az://) to the OME-Zarr group
+    /// containing arrays (FOV level in HCS),
+    /// or a .zip archive with an optional group inside it (archive.zip#inner/group);
+    /// several are shown one after the other, and a quoted glob pattern (fovs/*.zarr)
+    /// is expanded if the shell left it
+    #[arg(
+        required_unless_present_any = [
+            "diagnose_terminal", "generate_completions", "show_config", "from_stdin"
+        ],
+        value_hint = ValueHint::AnyPath
+    )]
+    image_paths: Vec<String>,
+    /// The one of the image paths being shown
+    #[arg(skip)]
+    image_path: String,
+    /// Read the image paths or URLs from stdin, one per line, instead of the command line
+    #[arg(long, conflicts_with = "image_paths")]
+    from_stdin: bool,
     /// Name of the array (resolution level)
-    #[arg(short, long, default_value = "/0")]
-    array_name: String,
-    /// Indices to slice non-XY dimensions,
-    /// e.g. 0,1,2 for time, channel, Z
-    #[arg(short, long, value_delimiter = ',', value_parser = clap::value_parser!(u64))]
-    slice_indices: Option<Vec<u64>>,
+    /// [default: the finest level fitting the crop size, or covering it with --fit crop]
+    #[arg(short, long)]
+    array_name: Option<String>,
+    /// Index of the resolution level to read, 0 being the finest, however the levels are named
+    #[arg(long, conflicts_with = "array_name")]
+    level: Option<usize>,
+    /// Series to open in a bioformats2raw layout,
+    /// lists the available series if there are several and none is chosen
+    #[arg(long)]
+    series: Option<usize>,
+    /// Well to open in an HCS plate, e.g. B03,
+    /// shows the plate layout if none is chosen
+    #[arg(long)]
+    well: Option<String>,
+    /// Field of view to open in the well
+    #[arg(long, requires = "well")]
+    field: Option<usize>,
+    /// Render the label image (segmentation mask) with this name under the image's labels group,
+    /// lists the label images if no name is given
+    #[arg(long, num_args = 0..=1)]
+    label: Option<Option<String>>,
+    /// Print a histogram of the slice under the image, marking the normalization cut points,
+    /// with its extrema and quantiles
+    #[arg(long)]
+    histogram: bool,
+    /// Write the histogram of the slice before normalization to a CSV file, with a row
+    /// per bin of the channel index, bin_start, bin_end and the count of finite values
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["montage", "ortho", "animate", "plate_overview"]
+    )]
+    export_hist: Option<std::path::PathBuf>,
+    /// Print statistics of the values shown under the image, before normalization:
+    /// the extrema, mean, standard deviation, median, --low and --high quantiles,
+    /// and the zero and NaN pixels
+    #[arg(long)]
+    stats: bool,
+    /// Color the slice as a segmentation mask, one color per integer id,
+    /// even without label image metadata
+    #[arg(long, conflicts_with_all = ["cmap", "channels"])]
+    label_mode: bool,
+    /// Seed of the label colors, change it to reshuffle colors of neighbouring labels
+    #[arg(long, default_value = "0")]
+    label_seed: u64,
+    /// Color the slice with a colormap instead of the omero channel color
+    #[arg(long, value_enum, ignore_case = true, conflicts_with = "channels")]
+    cmap: Option<Colormap>,
+    /// Blend channels into a false-color composite, e.g. 0:magenta,1:green,2:blue,
+    /// each normalized separately; all channels in their omero colors if no list is given
+    #[arg(long, num_args = 0..=1, value_delimiter = ',', conflicts_with = "label")]
+    channels: Option<Vec<ChannelSpec>>,
+    /// Blend the label image with this name over the intensity image, shown in grayscale
+    #[arg(long, conflicts_with = "label")]
+    overlay_label: Option<String>,
+    /// Opacity of the label colors over the image with `--overlay-label`
+    #[arg(long, default_value = "0.4", value_parser = parse_alpha)]
+    overlay_alpha: f32,
+    /// Show the first field of every well in an HCS plate at low resolution,
+    /// in a grid following the plate layout
+    #[arg(long, conflicts_with = "well")]
+    plate_overview: bool,
+    /// Size in pixels of each well in the plate overview
+    #[arg(long, default_value = "64", value_parser = clap::value_parser!(u64).range(1..))]
+    well_size: u64,
+    /// Normalize each well of the plate overview separately instead of across the plate
+    #[arg(long)]
+    per_well_contrast: bool,
+    /// Show the slices at every index along an axis side by side, e.g. c for all channels
+    /// or z for a stack, in tiles shrunk to fit the crop size together; channels are
+    /// normalized separately and the tiles along other axes with one window
+    #[arg(
+        long,
+        value_name = "AXIS",
+        conflicts_with_all = ["channels", "overlay_label", "probe", "profile", "show_chunks", "json"]
+    )]
+    montage: Option<String>,
+    /// Normalize the frames of an animation, the channels of a montage, the slices of
+    /// --slices or several image paths at one window, the quantiles of all of them together,
+    /// instead of the first frame or each channel, slice or image
+    #[arg(long)]
+    shared_contrast: bool,
+    /// Take the shared contrast window of the frames of an animation, the tiles of a montage
+    /// or the wells of a plate overview from this many of them spread evenly, all by default
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    contrast_sample: Option<u64>,
+    /// Show every n-th index along the montage axis, e.g. 10 for every 10th z-plane
+    #[arg(
+        long,
+        default_value = "1",
+        requires = "montage",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    stride: u64,
+    /// Most tiles in a montage, with a larger stride if there would be more
+    #[arg(
+        long,
+        default_value = "64",
+        requires = "montage",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    max_tiles: u64,
+    /// Project the slices along --project-axis into one pixel by pixel: max for the
+    /// maximum intensity projection, min, mean, median (holding all the planes in memory)
+    /// or sum, normalized as a slice would be; or time-color for the maximum over time
+    /// colored by its timepoint with --cmap, hsv by default, and depth-color for the maximum
+    /// over z colored by its depth, turbo by default
+    #[arg(long, conflicts_with_all = ["channels", "montage", "probe"])]
+    project: Option<Projection>,
+    /// Axis to project along with --project, by name or dimension
+    /// [default: z, t for time-color]
+    #[arg(long, requires = "project")]
+    project_axis: Option<String>,
+    /// Indices to project along the axis, e.g. 20:60 for 20 to 59, all of them by default
+    #[arg(long, requires = "project")]
+    project_range: Option<IndexRange>,
+    /// Show the XY, XZ and YZ planes through a point together, the XZ plane below
+    /// and the YZ plane to the right, with Z stretched to its physical spacing;
+    /// the point is at the --slice indices, which also take y= and x= in pixels
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "channels", "montage", "project", "overlay_label", "probe", "profile", "show_chunks",
+            "json", "transpose", "flip_y", "flip_x", "rotate"
+        ]
+    )]
+    ortho: bool,
+    /// Play the slices along a non-spatial axis in place as an animation, e.g. t for a
+    /// time-lapse, all at one contrast window; Ctrl-C stops it
+    #[arg(
+        long,
+        value_name = "AXIS",
+        conflicts_with_all = [
+            "channels", "montage", "project", "ortho", "overlay_label", "probe", "profile",
+            "show_chunks", "histogram", "stats", "json"
+        ]
+    )]
+    animate: Option<String>,
+    /// Frames shown per second of an animation
+    #[arg(
+        long,
+        default_value = "5",
+        requires = "animate",
+        value_parser = parse_positive::<f64>
+    )]
+    fps: f64,
+    /// Indices along the animation axis, start:end with an optional :stride,
+    /// e.g. 0:100:5 for every 5th of the first 100, all of them by default
+    #[arg(long, requires = "animate")]
+    frames: Option<FrameRange>,
+    /// Play the animation again from the start until Ctrl-C
+    #[arg(long = "loop", requires = "animate")]
+    repeat: bool,
+    /// Read and show the slice again every SECONDS (2 without a value, as in --watch=5)
+    /// in place until Ctrl-C, with the time of the last update in the caption;
+    /// local stores are only read again once something in them was modified
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "2",
+        conflicts_with_all = [
+            "list", "tree", "info", "validate", "storage_stats", "dry_run", "json", "files",
+            "plate_overview", "montage", "ortho", "animate", "slices", "from_stdin"
+        ],
+        value_parser = parse_positive::<f64>
+    )]
+    watch: Option<f64>,
+    /// Step through the image with the keyboard after showing it, redrawn in place: the arrows
+    /// step through Z, "," and "." through T, c cycles the channels, + and - zoom, h, j, k
+    /// and l pan, the keys of --adjust change the contrast and q quits, printing the flags
+    /// showing the last view
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "list", "tree", "info", "validate", "storage_stats", "dry_run", "json", "files",
+            "plate_overview", "montage", "ortho", "animate", "slices", "from_stdin", "watch",
+            "channels"
+        ]
+    )]
+    interactive: bool,
+    /// Adjust the contrast of the slice with the keyboard after showing it, redrawn in place:
+    /// [ and ] move the low clip, { and } the high clip, g and G the gamma, i inverts, a takes
+    /// the automatic window again and q quits, printing --min, --max and --gamma as adjusted;
+    /// also in --interactive
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "list", "tree", "info", "validate", "storage_stats", "dry_run", "json", "files",
+            "plate_overview", "montage", "ortho", "animate", "slices", "from_stdin", "watch",
+            "channels", "interactive"
+        ]
+    )]
+    adjust: bool,
+    /// Show two images side by side, A on the left and B on the right, with the same slice
+    /// indices resolved against each and at one contrast window: the two image paths given,
+    /// or one image at two levels or arrays with --compare-level or --compare-array
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "list", "tree", "info", "validate", "storage_stats", "dry_run", "json",
+            "plate_overview", "montage", "ortho", "animate", "slices", "from_stdin", "watch",
+            "interactive", "adjust", "channels", "overlay_label", "probe", "profile",
+            "show_chunks", "histogram", "stats", "export_raw", "export_hist", "export_profile",
+            "write_thumbnail", "output_dir"
+        ]
+    )]
+    compare: bool,
+    /// Level of the image shown as B, with --compare and one image path
+    #[arg(long, requires = "compare", conflicts_with = "compare_array")]
+    compare_level: Option<usize>,
+    /// Array of the image shown as B, with --compare and one image path
+    #[arg(long, requires = "compare")]
+    compare_array: Option<String>,
+    /// Show the signed difference of this image minus the image path, read at the same
+    /// slice indices and crop, with the coolwarm colormap (or --cmap) centered at zero
+    /// over plus or minus the --high quantile of the absolute difference
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "list", "tree", "info", "validate", "storage_stats", "dry_run", "json",
+            "plate_overview", "montage", "ortho", "animate", "slices", "from_stdin", "watch",
+            "interactive", "adjust", "compare", "channels", "project", "overlay_label", "probe",
+            "profile", "show_chunks", "histogram", "stats", "export_raw", "export_hist",
+            "export_profile", "write_thumbnail", "output_dir", "label_mode"
+        ]
+    )]
+    diff: Option<String>,
+    /// Show the difference of two timepoints of the image, the second minus the first,
+    /// e.g. 5,6, as --diff does for two images
+    #[arg(
+        long,
+        value_name = "A,B",
+        value_delimiter = ',',
+        conflicts_with_all = [
+            "list", "tree", "info", "validate", "storage_stats", "dry_run", "json",
+            "plate_overview", "montage", "ortho", "animate", "slices", "from_stdin", "watch",
+            "interactive", "adjust", "compare", "channels", "project", "overlay_label", "probe",
+            "profile", "show_chunks", "histogram", "stats", "export_raw", "export_hist",
+            "export_profile", "write_thumbnail", "output_dir", "label_mode"
+        ]
+    )]
+    diff_t: Option<Vec<SliceValue>>,
+    /// Absolute difference above which a pixel counts as changed, with --diff or --diff-t
+    #[arg(
+        long,
+        default_value = "0",
+        requires = "difference",
+        value_parser = parse_non_negative
+    )]
+    diff_threshold: f32,
+    /// Clock time of the last read while watching, for the caption
+    #[arg(skip)]
+    updated: Option<String>,
+    /// Show several combinations of slice indices one after another, separated by
+    /// semicolons, e.g. "t=0,c=1;t=10,c=1;t=20,c=1", each with its caption; the array is
+    /// opened once and every combination checked before any is shown
+    #[arg(
+        long,
+        value_delimiter = ';',
+        conflicts_with_all = [
+            "slice_indices", "channels", "montage", "project", "ortho", "animate",
+            "plate_overview", "overlay_label", "probe", "profile", "show_chunks", "histogram",
+            "stats", "json", "dry_run", "files"
+        ]
+    )]
+    slices: Option<Vec<SliceSpec>>,
+    /// Write the image as shown to a .png, .jpg, .bmp or .tif file, with the caption drawn
+    /// under it, or with --animate the animation to an animated .gif or .webp file
+    /// instead of playing it
+    #[arg(long)]
+    output: Option<std::path::PathBuf>,
+    /// Write each image as shown to <name>.png in this directory, named after the last
+    /// component of its path (.gif with --animate), instead of printing it
+    #[arg(long, conflicts_with = "output", value_hint = ValueHint::DirPath)]
+    output_dir: Option<std::path::PathBuf>,
+    /// Write the values of the slice as decoded, before normalization, to a .tif
+    /// (16-bit for unsigned integers up to 16 bits, 32-bit float otherwise, with the pixel size)
+    /// or .npy file (in the data type of the array if the values fit it, float32 otherwise)
+    #[arg(
+        long,
+        conflicts_with_all = ["channels", "montage", "ortho", "animate", "plate_overview"]
+    )]
+    export_raw: Option<std::path::PathBuf>,
+    /// Write the image as shown, shrunk to --thumbnail-size, back into its image group:
+    /// as a PNG file for a name ending with .png, otherwise as a uint8 Y, X, RGB
+    /// Zarr array; the store must be local or on S3 with credentials
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "thumbnail.png",
+        value_name = "PATH",
+        conflicts_with_all = ["montage", "ortho", "animate", "plate_overview"]
+    )]
+    write_thumbnail: Option<String>,
+    /// Longest edge of the thumbnail in pixels
+    #[arg(
+        long,
+        default_value_t = 256,
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "write_thumbnail"
+    )]
+    thumbnail_size: u32,
+    /// Format of the image written to stdout instead of printing it when stdout is not
+    /// a terminal (e.g. piped to another program) and there is no --output: png or ppm
+    #[arg(long, default_value = "png")]
+    stdout_format: StreamFormat,
+    /// Only write the files given with --output, --export-raw, --write-thumbnail,
+    /// --export-hist or --export-profile, without printing the image
+    #[arg(long, requires = "files")]
+    no_display: bool,
+    /// Overwrite the --output, --export-raw, --write-thumbnail, --export-hist
+    /// and --export-profile files if they exist
+    #[arg(long, requires = "files")]
+    force: bool,
+    /// Indices to slice non-XY dimensions by axis name, e.g. t=12,c=1,z=30,
+    /// or by position, e.g. 0,1,2 for time, channel, Z; first, last and middle resolve
+    /// against the length of the axis, e.g. t=last,z=middle, and all along one named axis
+    /// shows every index as a montage, like --montage, e.g. c=all;
+    /// named axes also take physical coordinates with a unit, e.g. t=120s,z=15um
+    #[arg(
+        short,
+        long = "slice",
+        visible_alias = "slice-indices",
+        value_delimiter = ','
+    )]
+    slice_indices: Option<Vec<SliceIndex>>,
     /// Maximum size to display in each dimension
-    #[arg(short, long, default_value = "2560")]
-    crop_size: u64,
-    /// Lower quantile for normalization
-    #[arg(long, default_value = "0.001")]
+    /// [default: filling the terminal, or 2560 if stdout is not a terminal]
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    crop_size: Option<u64>,
+    /// Maximum width to display, along X [default: the crop size]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    max_width: Option<u64>,
+    /// Maximum height to display, along Y [default: the crop size]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    max_height: Option<u64>,
+    /// Region to show, y0:y1,x0:x1 in the pixels of the finest level (or of --level),
+    /// with negative ends counting from the end, downsampled if larger than the size limits
+    #[arg(long, allow_hyphen_values = true)]
+    roi: Option<Roi>,
+    /// Show the pixels of the array enlarged (with nearest neighbours) or shrunk by this factor,
+    /// e.g. 1 for pixel for pixel or 4 to see the pixels as blocks, cropping a window
+    /// of the finest level (or of --level, or the --roi) that fits the size limits once zoomed
+    #[arg(long, value_parser = parse_positive::<f32>)]
+    zoom: Option<f32>,
+    /// For planes larger than the crop size: downsample to show all of it, from a coarser
+    /// resolution level when there is one, or crop a window from the origin
+    #[arg(long, default_value = "downsample")]
+    fit: Fit,
+    /// Where to place the crop with --fit crop: topleft, center or bottomright
+    #[arg(long, default_value = "topleft")]
+    anchor: Anchor,
+    /// Shift of the crop from its anchor in pixels, dy,dx (e.g. -100,250),
+    /// clamped to stay within the array
+    #[arg(long, allow_hyphen_values = true)]
+    offset: Option<Offset>,
+    /// Lower quantile for normalization, as a fraction from 0 to 1
+    #[arg(long, default_value = "0.001", value_parser = parse_quantile)]
     low: f64,
-    /// Upper quantile for normalization
-    #[arg(long, default_value = "0.999")]
+    /// Upper quantile for normalization, as a fraction from 0 to 1
+    #[arg(long, default_value = "0.999", value_parser = parse_quantile)]
     high: f64,
+    /// Percent to clip from each tail of the values, e.g. 1 for the quantiles 0.01 and 0.99,
+    /// or the lower and upper percentiles, e.g. 0.5,99
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        conflicts_with_all = ["low", "high", "min", "max"]
+    )]
+    clip: Option<Clip>,
+    /// Value shown black (in the units of the data, e.g. 0 for uint16), instead of
+    /// the lower quantile or omero window; the other end is the quantile if only one is given
+    #[arg(long, allow_hyphen_values = true)]
+    min: Option<f64>,
+    /// Value shown white (in the units of the data, e.g. 65535 for uint16), instead of
+    /// the upper quantile or omero window
+    #[arg(long, allow_hyphen_values = true)]
+    max: Option<f64>,
+    /// Normalization of the values to the colors [default: quantile, or fixed with --min or --max]
+    #[arg(long, value_enum)]
+    norm: Option<Norm>,
+    /// Number of standard deviations around the mean shown with --norm zscore
+    #[arg(long, default_value = "3.0", value_parser = parse_positive::<f32>)]
+    zscore_k: f32,
+    /// Number of tiles along each axis equalized separately with --norm clahe
+    #[arg(long, default_value = "8", value_parser = clap::value_parser!(u32).range(1..))]
+    clahe_tiles: u32,
+    /// Limit of the histogram bins of a tile relative to their mean with --norm clahe,
+    /// lower to amplify noise less, e.g. 1 to leave the contrast unchanged
+    #[arg(long, default_value = "2.0", value_parser = parse_positive::<f32>)]
+    clahe_clip: f32,
+    /// Gamma applied to the normalized values before the colors, above 1 to brighten dim structures
+    #[arg(long, default_value = "1.0", value_parser = parse_positive::<f32>)]
+    gamma: f32,
+    /// Invert the normalized values, showing the low cut point white and the high one black,
+    /// e.g. for brightfield images on a dark terminal
+    #[arg(long)]
+    invert: bool,
+    /// Spacing of the intensities between the cut points: linear, or log for data spanning
+    /// orders of magnitude, with the quantiles still taken from the data as it is
+    #[arg(long, default_value = "linear")]
+    scale: Scale,
+    /// Ignore the contrast window and color in the `omero` metadata,
+    /// normalizing with the quantiles instead
+    #[arg(long)]
+    ignore_omero: bool,
+    /// Show pixels with their stored aspect ratio,
+    /// instead of resampling non-square pixels to their physical aspect ratio
+    #[arg(long)]
+    no_aspect_correction: bool,
+    /// Swap Y and X of the displayed plane, before any flips and rotation
+    #[arg(long)]
+    transpose: bool,
+    /// Mirror the displayed plane top to bottom, after --transpose and before --rotate
+    #[arg(long)]
+    flip_y: bool,
+    /// Mirror the displayed plane left to right, after --transpose and before --rotate
+    #[arg(long)]
+    flip_x: bool,
+    /// Rotate the displayed plane clockwise by 90, 180 or 270 degrees, after the flips
+    #[arg(long, default_value = "0")]
+    rotate: Rotation,
+    /// Filter to resize the decoded plane for display and export with
+    /// [default: area for intensities, nearest for labels]
+    #[arg(long, value_enum)]
+    filter: Option<Filter>,
+    /// Print images with a protocol instead of the one detected,
+    /// failing if the terminal does not support it
+    #[arg(long, value_enum, default_value = "auto")]
+    protocol: Protocol,
+    /// Dithering of images printed with blocks in 256 colors (when COLORTERM is not truecolor):
+    /// none, ordered or floyd-steinberg
+    #[arg(long, default_value = "ordered")]
+    dither: Dither,
+    /// Print the image as one self-contained escape sequence, inline-iterm or kitty,
+    /// instead of writing to the terminal with --protocol, e.g. over mosh
+    /// or redirected to a file to show with cat later
+    #[arg(long, conflicts_with = "animate")]
+    emit: Option<Emit>,
+    /// Print the terminal, its size, the graphics protocols detected
+    /// and the one images would be printed with
+    #[arg(long)]
+    diagnose_terminal: bool,
+    /// Print the completion script of a shell (bash, zsh, fish, elvish or powershell)
+    #[arg(long, hide = true, value_name = "SHELL")]
+    generate_completions: Option<Shell>,
+    /// Ignore the config file ($XDG_CONFIG_HOME/peekzarr/config.toml), whose keys are
+    /// the flags to default to, e.g. cmap = "magma", below PEEKZARR_* variables such as
+    /// PEEKZARR_CMAP=magma and the command line
+    #[arg(long)]
+    no_config: bool,
+    /// Print the value of each flag, merged from the command line, the environment,
+    /// the config file and the defaults, and where it came from
+    #[arg(long)]
+    show_config: bool,
+    /// Print only warnings and errors besides the image and the reports asked for
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Also print the store opened and the metadata read, and with -vv every chunk read
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Scale bar from the pixel size metadata: off, auto, or a length such as 50um
+    #[arg(long, default_value = "auto")]
+    scalebar: ScaleBar,
+    /// Colorbar labelled with the normalization cut points: off, auto, vertical or horizontal,
+    /// beside the image if there is room in the terminal and below it otherwise with auto
+    #[arg(long, num_args = 0..=1, default_value = "off", default_missing_value = "auto")]
+    colorbar: Colorbar,
+    /// Color of NaN pixels, e.g. masked out regions: a name (magenta, black, ...),
+    /// a hex color, or transparent to show the terminal background
+    #[arg(long, default_value = "magenta")]
+    nan_color: NanColor,
+    /// Fail if the image path is not an image group,
+    /// instead of looking for a single image up to two levels below it
+    #[arg(long)]
+    no_discover: bool,
+    /// Don't print the axis names, sizes and displayed indices before the image
+    #[arg(long)]
+    no_header: bool,
+    /// Caption printed under the image, with placeholders {path}, {level}, {channel},
+    /// {extent}, {min} and {max} (e.g. {min:.1}), and the index of any non-spatial axis, e.g. {t}
+    #[arg(long, conflicts_with = "no_caption")]
+    caption_template: Option<String>,
+    /// Don't print the caption with the path, level, indices, crop and window under the image
+    #[arg(long)]
+    no_caption: bool,
+    /// Draw the chunk boundaries of the array over the image (in cyan, shards in yellow)
+    /// and print how many chunks the crop intersects
+    #[arg(long)]
+    show_chunks: bool,
+    /// Print the value at a pixel given as y,x in the pixels of the whole array,
+    /// marked with a crosshair in the image; repeat for several pixels
+    #[arg(long)]
+    probe: Vec<Probe>,
+    /// Plot the values along a line of the crop under the image, row=<y> or col=<x>
+    /// in the pixels of the whole array, and mark the line in the image
+    #[arg(long)]
+    profile: Option<Line>,
+    /// Write the values along the --profile line to a CSV file, with their position
+    /// in the pixels of the array, their physical coordinate if the image has scale
+    /// metadata (in a column named after the unit, e.g. x_micrometer) and a column
+    /// of values per channel
+    #[arg(long, alias = "profile-csv", value_name = "FILE", requires = "profile")]
+    export_profile: Option<std::path::PathBuf>,
+    /// Access object storage without credentials (public buckets)
+    #[arg(long)]
+    anonymous: bool,
+    /// Maximum number of chunks fetched and decoded concurrently
+    /// [default: available parallelism]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    concurrency: Option<u64>,
+    /// Ignore consolidated metadata and read each metadata key from the store
+    #[arg(long)]
+    no_consolidated: bool,
+    /// Read whole chunks and shards instead of byte ranges of them,
+    /// for stores that don't support range requests
+    #[arg(long)]
+    no_partial_decode: bool,
+    /// Memory limit in MiB for caching chunk reads, 0 disables the cache
+    #[arg(long, default_value = "512")]
+    cache_mb: u64,
+    /// List the arrays (with shape, data type and chunk shape) and groups in the hierarchy,
+    /// marking multiscale resolution levels, instead of rendering
+    #[arg(long)]
+    list: bool,
+    /// Print the multiscale pyramid of the image as a tree instead of rendering: the shape,
+    /// chunk shape, number of chunks and downscale factor of each level, and the label images
+    #[arg(long)]
+    tree: bool,
+    /// Add the stored size and missing chunks of each level to --tree, from listing
+    /// every chunk key (slow for many chunks on object stores)
+    #[arg(long, requires = "tree")]
+    sizes: bool,
+    /// Print the shape, data type, chunking, codecs and fill value of the array,
+    /// without reading any chunks
+    #[arg(long)]
+    info: bool,
+    /// Check the OME-NGFF metadata of the image against its arrays and print the findings,
+    /// failing if there are any errors
+    #[arg(long)]
+    validate: bool,
+    /// Compare each pair of adjacent resolution levels over the physical region of the slice,
+    /// the finer averaged down by the scale factor between them, and print their mean
+    /// squared error and SSIM, failing if any pair is below --min-ssim
+    #[arg(long)]
+    check_pyramid: bool,
+    /// SSIM below which two levels are taken as not showing the same data, with
+    /// --check-pyramid
+    #[arg(long, default_value = "0.8", requires = "check_pyramid", value_parser = parse_ssim)]
+    min_ssim: f64,
+    /// Print the stored size of the chunks of the array, from listing the store,
+    /// its uncompressed size, the compression ratio and the number of missing chunks
+    #[arg(long)]
+    storage_stats: bool,
+    /// Report every resolution level with --storage-stats
+    #[arg(long, requires = "storage_stats")]
+    all_levels: bool,
+    /// Print what reading the slice would take instead of reading it: the chunks it
+    /// intersects, their stored size (sized on local stores, otherwise estimated from
+    /// their decoded size) and the decoded size of the slice
+    #[arg(
+        long,
+        conflicts_with_all = ["montage", "ortho", "animate", "plate_overview"]
+    )]
+    dry_run: bool,
+    /// Fail before reading the slice if its chunks would take more than this many MiB
+    /// to fetch, as estimated by --dry-run
+    #[arg(long, value_name = "MB", value_parser = clap::value_parser!(u64).range(1..))]
+    max_read_mb: Option<u64>,
+    /// Print how long each stage took at the end: opening the store and reading metadata,
+    /// fetching chunks (summed over concurrent requests), decoding them to f32, quantiles,
+    /// normalization and rendering, with the bytes read or decoded;
+    /// a "timings" field with --json
+    #[arg(
+        long,
+        conflicts_with_all = ["montage", "ortho", "animate", "plate_overview"]
+    )]
+    timings: bool,
+    /// Print a JSON document to stdout for `--list`, `--tree`, `--info`, `--validate`,
+    /// `--check-pyramid`, `--storage-stats` and `--dry-run`,
+    /// or the slice intensity statistics instead of rendering it
+    #[arg(long)]
+    json: bool,
+    /// Number of times to retry a read after a transient failure
+    /// (timeouts, dropped connections, server errors)
+    #[arg(long, default_value = "3")]
+    retries: u32,
 }
 
-fn ensure_at_least_2d(array_shape: &[u64]) -> Result<()> {
-    if array_shape.len() < 2 {
-        anyhow::bail!("Array must have at least 2 dimensions");
+fn parse_positive<T>(s: &str) -> Result<T>
+where
+    T: std::str::FromStr + Into<f64> + Copy,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value: T = s.parse()?;
+    let wide: f64 = value.into();
+    if !(wide > 0.0 && wide.is_finite()) {
+        anyhow::bail!("Must be a positive number");
     }
-    Ok(())
+    Ok(value)
 }
 
-fn push_index(start: &mut Vec<u64>, dimension: usize, value: u64) {
-    println!("Slicing dimension {} at index {}", dimension, value);
-    start.push(value);
+fn parse_non_negative(s: &str) -> Result<f32> {
+    let value: f32 = s.parse()?;
+    if !(value >= 0.0 && value.is_finite()) {
+        anyhow::bail!("Must be zero or a positive number");
+    }
+    Ok(value)
 }
 
-fn start_and_shape(
-    array_shape: &[u64],
-    slice_indices: Option<&[u64]>,
-    crop_size: u64,
-) -> Result<(Vec<u64>, Vec<u64>)> {
-    let ndims = array_shape.len();
-    ensure_at_least_2d(array_shape)?;
-    let ndims_to_be_sliced = ndims - 2;
-    let mut start: Vec<u64> = vec![];
-    if let Some(slice_indices) = slice_indices {
-        if slice_indices.len() > ndims_to_be_sliced {
-            anyhow::bail!(
-                "Too many slice indices provided. Expected {} but got {}",
-                ndims_to_be_sliced,
-                slice_indices.len()
-            );
+fn parse_quantile(s: &str) -> Result<f64> {
+    let quantile: f64 = s.parse()?;
+    if !(0.0..=1.0).contains(&quantile) {
+        anyhow::bail!("Quantile must be a fraction between 0 and 1, e.g. 0.01 for 1%");
+    }
+    Ok(quantile)
+}
+
+fn parse_alpha(s: &str) -> Result<f32> {
+    let alpha: f32 = s.parse()?;
+    if !(0.0..=1.0).contains(&alpha) {
+        anyhow::bail!("Opacity must be between 0 and 1");
+    }
+    Ok(alpha)
+}
+
+fn parse_ssim(s: &str) -> Result<f64> {
+    let ssim: f64 = s.parse()?;
+    if !(-1.0..=1.0).contains(&ssim) {
+        anyhow::bail!("SSIM must be between -1 and 1");
+    }
+    Ok(ssim)
+}
+
+/// Size to display at when there is no terminal to fill
+const DEFAULT_CROP_SIZE: u64 = 2560;
+
+/// Height and width filling most of the terminal, detected once,
+/// `None` if stdout is not a terminal
+fn terminal_limits(protocol: Protocol) -> Option<[u64; 2]> {
+    static LIMITS: OnceLock<Option<[u64; 2]>> = OnceLock::new();
+    *LIMITS.get_or_init(|| {
+        let size = terminal::terminal_size()?;
+        let graphics = match protocol {
+            Protocol::Blocks => false,
+            protocol => protocol.is_graphics(&Support::detect()),
+        };
+        Some(terminal::display_limits(&size, graphics))
+    })
+}
+
+impl Cli {
+    fn store_options(&self) -> StoreOptions {
+        StoreOptions {
+            anonymous: self.anonymous,
+            retries: self.retries,
         }
-        for (i, slice_index) in slice_indices.iter().enumerate() {
-            if *slice_index >= array_shape[i] {
-                anyhow::bail!(
-                    "Slice index {} is out of bounds for dimension {}",
-                    slice_index,
-                    i
-                );
+    }
+
+    fn read_options(&self) -> ReadOptions {
+        let mut limits = self.size_limits();
+        let mut fit = self.fit;
+        if let Some(zoom) = self.zoom {
+            // A window of pixels as they are, zoomed after reading
+            fit = Fit::Crop;
+            if self.roi.is_none() {
+                limits = limits.map(|limit| ((limit as f64 / zoom as f64).ceil() as u64).max(1));
             }
-            push_index(&mut start, i, *slice_index);
+        }
+        let [height, width] = limits;
+        ReadOptions {
+            slice_indices: self.slice_indices.clone(),
+            crop_size: height.max(width),
+            max_height: Some(height),
+            max_width: Some(width),
+            roi: self.roi,
+            anchor: self.anchor,
+            offset: self.offset.unwrap_or_default(),
+            fit,
+            series: self.series,
+            well: self.well.clone(),
+            field: self.field,
+            label: self.label.clone().flatten(),
+            level: self.level,
+            finest: self.zoom.is_some(),
+            discover: !self.no_discover,
+            concurrency: self
+                .concurrency
+                .map_or_else(peekzarr::default_concurrency, |n| n as usize),
+            consolidated: !self.no_consolidated,
+            partial_decode: !self.no_partial_decode,
         }
     }
-    for i in start.len()..(ndims_to_be_sliced) {
-        push_index(&mut start, i, array_shape[i] / 2);
+
+    /// Maximum sizes to display along Y and X, from the crop size if given
+    /// or the size of the terminal
+    fn size_limits(&self) -> [u64; 2] {
+        let [height, width] = match self.crop_size {
+            Some(size) => [size; 2],
+            None => terminal_limits(self.protocol).unwrap_or([DEFAULT_CROP_SIZE; 2]),
+        };
+        [
+            self.max_height.unwrap_or(height),
+            self.max_width.unwrap_or(width),
+        ]
     }
-    for _ in 0..2 {
-        start.push(0);
+
+    /// The larger of the size limits, bounding both axes of composed images
+    fn crop_size(&self) -> u64 {
+        let [height, width] = self.size_limits();
+        height.max(width)
     }
-    let mut shape = vec![1; ndims];
-    let axes = ["Y", "X"];
-    for i in 0..2 {
-        let full_size = array_shape[ndims_to_be_sliced + i];
-        shape[ndims_to_be_sliced + i] = if crop_size >= full_size {
-            full_size
-        } else {
-            println!("Cropping dimension {:?} size {:?}", axes[i], crop_size);
-            crop_size
+
+    /// Whether the image is written to stdout in --stdout-format instead of printed,
+    /// when stdout is not a terminal (unlike inside tmux) and there is no --output or --emit
+    fn streams_image(&self) -> bool {
+        self.output.is_none() && self.emit.is_none() && !std::io::stdout().is_terminal()
+    }
+
+    /// Print a line shown with the image, to stderr when the image is streamed to stdout
+    fn print_text(&self, text: impl std::fmt::Display) {
+        match self.streams_image() {
+            true => eprintln!("{}", text),
+            false => println!("{}", text),
+        }
+    }
+
+    /// How to print images, relative to the cursor with the chosen protocol
+    fn viuer_config(&self) -> viuer::Config {
+        viuer::Config {
+            absolute_offset: false,
+            ..self.protocol.viuer_config()
+        }
+    }
+
+    /// Print an image at the cursor, drawn with half blocks when no graphics protocol is used,
+    /// returning its width and height in cells
+    fn print_image(&self, image: &DynamicImage, conf: &viuer::Config) -> Result<(u32, u32)> {
+        // Blocks are drawn without querying the terminal
+        let blocks = match self.protocol {
+            Protocol::Blocks => true,
+            protocol => protocol.chosen(&Support::detect()) == Protocol::Blocks,
+        };
+        if !blocks {
+            return Ok(viuer::print(image, conf)?);
+        }
+        let (columns, rows) = viuer::terminal_size();
+        let image = match conf.transparent {
+            true => image.clone(),
+            false => DynamicImage::ImageRgb8(image.to_rgb8()),
         };
+        // A row is left for the prompt, as viuer does
+        let cells = (columns, rows.saturating_sub(1));
+        let (text, size) = blocks::render_fit(&image, cells, Palette::detect(), self.dither);
+        print!("{}", text);
+        Ok(size)
     }
-    Ok((start, shape))
-}
-
-fn decode_subset<TStore: zarrs::storage::ReadableStorageTraits + 'static>(
-    array: &zarrs::array::Array<TStore>,
-    subset: &zarrs::array_subset::ArraySubset,
-) -> Result<Array2<f32>> {
-    use zarrs::array::DataType;
-    let dtype = array.data_type();
-    let decoded = match dtype {
-        DataType::Int8 => array
-            .retrieve_array_subset_ndarray::<i8>(&subset)?
-            .mapv(|x| x as f32),
-        DataType::Int16 => array
-            .retrieve_array_subset_ndarray::<i16>(&subset)?
-            .mapv(|x| x as f32),
-        DataType::Int32 => array
-            .retrieve_array_subset_ndarray::<i32>(&subset)?
-            .mapv(|x| x as f32),
-        DataType::Int64 => array
-            .retrieve_array_subset_ndarray::<i64>(&subset)?
-            .mapv(|x| x as f32),
-        DataType::UInt8 => array
-            .retrieve_array_subset_ndarray::<u8>(&subset)?
-            .mapv(|x| x as f32),
-        DataType::UInt16 => array
-            .retrieve_array_subset_ndarray::<u16>(&subset)?
-            .mapv(|x| x as f32),
-        DataType::UInt32 => array
-            .retrieve_array_subset_ndarray::<u32>(&subset)?
-            .mapv(|x| x as f32),
-        DataType::UInt64 => array
-            .retrieve_array_subset_ndarray::<u64>(&subset)?
-            .mapv(|x| x as f32),
-        DataType::Float32 => array.retrieve_array_subset_ndarray::<f32>(&subset)?,
-        DataType::Float64 => array
-            .retrieve_array_subset_ndarray::<f64>(&subset)?
-            .mapv(|x| x as f32),
-        _ => anyhow::bail!("Unsupported data type: {:?}", dtype),
-    };
-    let shape = decoded.shape();
-    let y = shape[shape.len() - 2];
-    let x = shape[shape.len() - 1];
-    let reshaped = decoded.to_shape((y, x))?.to_owned();
-    Ok(reshaped)
-}
-
-fn read_image_with_store<TStore: zarrs::storage::ReadableStorageTraits + 'static>(
-    cli: &Cli,
-    store: Arc<TStore>,
-) -> Result<Array2<f32>> {
-    let array = zarrs::array::Array::open(store, &cli.array_name)?;
-    let array_shape = array.shape();
-    let (start, shape) =
-        start_and_shape(&array_shape, cli.slice_indices.as_deref(), cli.crop_size)?;
-    let subset = zarrs::array_subset::ArraySubset::new_with_start_shape(start, shape)?;
-    let decoded = decode_subset(&array, &subset)?;
-    Ok(decoded)
-}
-
-fn read_image(cli: &Cli) -> Result<Array2<f32>> {
-    let path_str = cli.image_path.to_string_lossy();
-    if path_str.to_ascii_lowercase().starts_with("http://")
-        || path_str.to_ascii_lowercase().starts_with("https://")
-    {
-        let store = Arc::new(HTTPStore::new(path_str.as_ref())?);
-        read_image_with_store(cli, store)
+
+    fn orientation(&self) -> Orientation {
+        Orientation {
+            transpose: self.transpose,
+            flip_y: self.flip_y,
+            flip_x: self.flip_x,
+            rotate: self.rotate,
+        }
+    }
+
+    /// The filter to resize a plane with, never mixing ids of label images
+    fn filter(&self, view: &SliceView) -> Filter {
+        match self.label_mode || view.is_label() {
+            true => Filter::Nearest,
+            false => self.filter.unwrap_or(Filter::Area),
+        }
+    }
+}
+
+/// How NaN pixels are shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NanColor {
+    Color([u8; 3]),
+    /// The terminal background
+    Transparent,
+}
+
+impl std::str::FromStr for NanColor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.trim() == "transparent" {
+            return Ok(Self::Transparent);
+        }
+        match composite::parse_color(s) {
+            Some(color) => Ok(Self::Color(color)),
+            None => anyhow::bail!(
+                "Unknown color '{}', use a name such as magenta or black, a hex color, or transparent",
+                s
+            ),
+        }
+    }
+}
+
+/// The slices read for rendering
+#[derive(Clone)]
+struct Image {
+    /// The slice, or one per channel of a composite
+    views: Vec<SliceView>,
+    /// Color of each channel of a composite
+    colors: Option<Vec<[u8; 3]>>,
+    /// Label image to blend over the slice
+    overlay: Option<Array2<f32>>,
+    /// Metadata of the array, for drawing its chunk grid
+    chunks: Option<info::ArrayInfo>,
+    /// Pixels probed, with their values in each channel
+    probes: Vec<(Probe, Vec<String>)>,
+    /// The planes the slice was projected from
+    projection: Option<ProjectionSpan>,
+    /// Position of the plane of each pixel of a color-coded projection
+    positions: Option<Array2<f32>>,
+}
+
+impl Image {
+    /// Pixels that are NaN in any channel, `None` if there are none
+    fn nan_mask(&self) -> Option<Array2<bool>> {
+        let mut mask = Array2::from_elem(self.views[0].data.dim(), false);
+        for view in &self.views {
+            mask.zip_mut_with(&view.data, |nan, value| *nan |= value.is_nan());
+        }
+        mask.iter().any(|&nan| nan).then_some(mask)
+    }
+}
+
+/// The store of the image, with the chunk cache
+type ImageStore = Arc<
+    store::CachedStore<store::TimedStore<Arc<dyn zarrs::storage::ReadableListableStorageTraits>>>,
+>;
+
+/// Open the store of the image for [`read_image_from`] and the other modes reading it
+fn open_image_store(cli: &Cli) -> Result<ImageStore> {
+    let store = timings::time(Stage::Metadata, || {
+        store::open_store(&cli.image_path, &cli.store_options())
+    })?;
+    // The chunk cache is shared by all reads from this store, timed below it
+    // so that cached chunks do not count as retrieved
+    let store = store::TimedStore::new(store);
+    Ok(Arc::new(store::CachedStore::new(
+        store,
+        cli.cache_mb.saturating_mul(1 << 20),
+    )))
+}
+
+/// Read the slice or the channels of a composite,
+/// and the label image to overlay on it if requested
+fn read_image(cli: &Cli) -> Result<Image> {
+    read_image_from(cli, open_image_store(cli)?)
+}
+
+/// Read the image as [`read_image`] does from a store already open,
+/// whose cached chunks are not read again
+fn read_image_from(cli: &Cli, store: ImageStore) -> Result<Image> {
+    let options = cli.read_options();
+    let array_name = cli.array_name.as_deref();
+    let (mut projection, mut positions) = (None, None);
+    let (views, colors) = match &cli.channels {
+        Some(channels) => {
+            let channels = composite::read_channels(
+                store.clone(),
+                array_name,
+                &options,
+                // All channels if no list is given
+                (!channels.is_empty()).then_some(channels.as_slice()),
+                cli.ignore_omero,
+            )
+            .with_context(|| format!("Failed to read channels from {}", cli.image_path))?;
+            let (views, colors) = channels.into_iter().unzip();
+            (views, Some(colors))
+        }
+        None => match cli.project {
+            Some(kind) => {
+                let projected = projection::read_projection(
+                    store.clone(),
+                    array_name,
+                    &options,
+                    kind,
+                    cli.project_axis.as_deref().unwrap_or(kind.default_axis()),
+                    cli.project_range.unwrap_or_default(),
+                )
+                .with_context(|| format!("Failed to project from {}", cli.image_path))?;
+                let Projected {
+                    view,
+                    mut span,
+                    positions: planes,
+                } = projected;
+                if kind.is_color_coded() {
+                    span.colormap = Some(cli.cmap.unwrap_or(kind.default_colormap()));
+                }
+                projection = Some(span);
+                positions = planes;
+                (vec![view], None)
+            }
+            None => {
+                let view = read_slice_view(store.clone(), array_name, &options)
+                    .with_context(|| format!("Failed to read from {}", cli.image_path))?;
+                (vec![view], None)
+            }
+        },
+    };
+    let chunks = cli
+        .show_chunks
+        .then(|| info::array_info(store.clone(), Some(&views[0].path), &options))
+        .transpose()
+        .with_context(|| format!("Failed to read metadata from {}", cli.image_path))?;
+    let probes = cli
+        .probe
+        .iter()
+        .map(|&probe| {
+            probe.check(&views[0])?;
+            let values = views
+                .iter()
+                .map(|view| probe.read_value(store.clone(), view))
+                .collect::<Result<_>>()?;
+            Ok((probe, values))
+        })
+        .collect::<Result<_>>()?;
+    let overlay = match &cli.overlay_label {
+        Some(label) => Some(
+            labels::read_overlay_label(store, &views[0], label, &options)
+                .with_context(|| format!("Failed to read label image '{}'", label))?,
+        ),
+        None => None,
+    };
+    Ok(Image {
+        views,
+        colors,
+        overlay,
+        chunks,
+        probes,
+        projection,
+        positions,
+    })
+}
+
+/// Print the arrays and groups in the hierarchy, one per line
+fn list_image(cli: &Cli) -> Result<()> {
+    let store = store::open_store(&cli.image_path, &cli.store_options())?;
+    let store = Arc::new(store::ConsolidatedStore::new(store, !cli.no_consolidated));
+    let nodes = hierarchy::list_nodes(&store, "/", 3)?;
+    if cli.json {
+        let nodes: Vec<_> = nodes.iter().map(hierarchy::Node::to_json).collect();
+        return print_json("nodes", nodes.into());
+    }
+    for node in nodes {
+        println!("{}", node);
+    }
+    Ok(())
+}
+
+/// Print the resolution levels and label images of the image as a tree
+fn tree_image(cli: &Cli) -> Result<()> {
+    let store = store::open_store(&cli.image_path, &cli.store_options())?;
+    let store = Arc::new(store::ConsolidatedStore::new(store, !cli.no_consolidated));
+    let group = peekzarr::resolve_image_path(&store, &cli.read_options())?;
+    let tree = hierarchy::pyramid_tree(&store, &group, cli.sizes)
+        .with_context(|| format!("Failed to read the pyramid of {}", cli.image_path))?;
+    if cli.json {
+        return print_json("tree", tree.to_json());
+    }
+    println!("{}", tree);
+    Ok(())
+}
+
+/// What reading the slice would take, for every channel of a composite
+/// or plane of a projection
+fn plan_read(cli: &Cli) -> Result<ReadPlan> {
+    let store = store::open_store(&cli.image_path, &cli.store_options())?;
+    // A stat per chunk on local stores, a request per chunk on remote ones
+    let sized = matches!(
+        store::parse_location(&cli.image_path)?,
+        Location::Filesystem(_) | Location::Zip(..)
+    );
+    let mut plan =
+        peekzarr::plan_read(store, cli.array_name.as_deref(), &cli.read_options(), sized)
+            .with_context(|| format!("Failed to plan the read from {}", cli.image_path))?;
+    plan.planes = match (&cli.channels, cli.project) {
+        (Some(channels), _) if !channels.is_empty() => channels.len() as u64,
+        (Some(_), _) => plan.channel_axis.map_or(1, |axis| plan.shape[axis]),
+        (None, Some(kind)) => {
+            let axis = cli.project_axis.as_deref().unwrap_or(kind.default_axis());
+            let range = cli.project_range.unwrap_or_default();
+            plan.axis_planes(axis, range.start, range.end)?
+        }
+        (None, None) => 1,
+    };
+    Ok(plan)
+}
+
+/// Print the metadata of the array that would be rendered
+fn info_image(cli: &Cli) -> Result<()> {
+    let store = store::open_store(&cli.image_path, &cli.store_options())?;
+    let info = info::array_info(store, cli.array_name.as_deref(), &cli.read_options())
+        .with_context(|| format!("Failed to read metadata from {}", cli.image_path))?;
+    if cli.json {
+        return print_json("array", info.to_json());
+    }
+    println!("{}", info);
+    Ok(())
+}
+
+/// Print the storage statistics of the array, or of every level
+fn storage_stats_image(cli: &Cli) -> Result<()> {
+    let store = store::open_store(&cli.image_path, &cli.store_options())?;
+    let stats = storagestats::image_storage_stats(
+        store,
+        cli.array_name.as_deref(),
+        &cli.read_options(),
+        cli.all_levels,
+    )
+    .with_context(|| format!("Failed to size the chunks of {}", cli.image_path))?;
+    if cli.json {
+        let values: Vec<_> = stats
+            .iter()
+            .map(storagestats::StorageStats::to_json)
+            .collect();
+        return print_json("storage", values.into());
+    }
+    let text: Vec<_> = stats.iter().map(ToString::to_string).collect();
+    println!("{}", text.join("\n\n"));
+    Ok(())
+}
+
+/// Print the findings of checking the image metadata, failing if any is an error
+fn validate_image(cli: &Cli) -> Result<()> {
+    let store = store::open_store(&cli.image_path, &cli.store_options())?;
+    let findings = validate::validate(store, &cli.read_options())
+        .with_context(|| format!("Failed to validate {}", cli.image_path))?;
+    if cli.json {
+        let values: Vec<_> = findings.iter().map(validate::Finding::to_json).collect();
+        print_json("findings", values.into())?;
+    } else if findings.is_empty() {
+        println!("No problems found");
     } else {
-        let store = Arc::new(FilesystemStore::new(&cli.image_path)?);
-        read_image_with_store(cli, store)
+        for finding in &findings {
+            println!("{}", finding);
+        }
+    }
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == validate::Severity::Error)
+        .count();
+    if errors > 0 {
+        anyhow::bail!(
+            "Found {} errors in the metadata of {}",
+            errors,
+            cli.image_path
+        );
+    }
+    Ok(())
+}
+
+/// Print the comparison of each pair of adjacent levels, failing if any differ
+fn check_pyramid_image(cli: &Cli) -> Result<()> {
+    let store = store::open_store(&cli.image_path, &cli.store_options())?;
+    let checks = pyramid::check_pyramid(
+        store,
+        cli.array_name.as_deref(),
+        &cli.read_options(),
+        cli.min_ssim,
+    )
+    .with_context(|| format!("Failed to check the pyramid of {}", cli.image_path))?;
+    if cli.json {
+        let values: Vec<_> = checks.iter().map(pyramid::LevelCheck::to_json).collect();
+        print_json("levels", values.into())?;
+    } else {
+        for check in &checks {
+            println!("{}", check);
+        }
+    }
+    let failed: Vec<String> = checks
+        .iter()
+        .filter(|check| !check.passed)
+        .map(|check| format!("{} and {}", check.levels.0, check.levels.1))
+        .collect();
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "Levels {} of {} differ, with an SSIM below {}",
+            failed.join(", "),
+            cli.image_path,
+            cli.min_ssim
+        );
+    }
+    Ok(())
+}
+
+/// Print a versioned JSON document with `value` under `key`
+fn print_json(key: &str, value: serde_json::Value) -> Result<()> {
+    let mut document = serde_json::json!({"schema_version": peekzarr::JSON_SCHEMA_VERSION});
+    document[key] = value;
+    if timings::enabled() {
+        document["timings"] = timings::to_json();
+    }
+    // Written rather than printed, so that a closed pipe is an error instead of a panic
+    writeln!(
+        std::io::stdout().lock(),
+        "{}",
+        serde_json::to_string_pretty(&document)?
+    )?;
+    Ok(())
+}
+
+/// The normalization of the slice from `--norm`: by default at `--min` and `--max` if given,
+/// or the omero window, or the quantiles
+fn normalizer(cli: &Cli, view: &SliceView) -> Result<Box<dyn Normalizer>> {
+    let fixed = cli.min.is_some() || cli.max.is_some();
+    let norm = cli
+        .norm
+        .unwrap_or(if fixed { Norm::Fixed } else { Norm::Quantile });
+    if fixed != (norm == Norm::Fixed) {
+        anyhow::bail!("--min and --max go with --norm fixed, which needs at least one of them");
+    }
+    let omero = (!cli.ignore_omero).then(|| view.omero_channel()).flatten();
+    Ok(match norm {
+        Norm::Quantile => match omero.and_then(|channel| channel.window) {
+            Some((min, max)) => Box::new(normalizer::Omero { min, max }),
+            None => Box::new(normalizer::Quantile {
+                low: cli.low,
+                high: cli.high,
+            }),
+        },
+        Norm::MinMax => Box::new(normalizer::MinMax),
+        Norm::Fixed => Box::new(normalizer::Fixed {
+            min: cli.min,
+            max: cli.max,
+            low: cli.low,
+            high: cli.high,
+        }),
+        Norm::ZScore => Box::new(normalizer::ZScore { k: cli.zscore_k }),
+        Norm::None => Box::new(normalizer::Unscaled),
+        Norm::Equalize => Box::new(normalizer::Equalize),
+        Norm::Clahe => Box::new(normalizer::Clahe {
+            tiles: cli.clahe_tiles as usize,
+            clip: cli.clahe_clip,
+        }),
+    })
+}
+
+/// Values mapped to black and white
+fn display_window(cli: &Cli, view: &SliceView) -> Result<Window> {
+    match exact_window(cli, view)? {
+        Some(window) => Ok(window.rounded()),
+        None => normalizer(cli, view)?.window(&view.data),
+    }
+}
+
+/// The window of the exact values of a 64-bit integer slice, if the normalization has one
+fn exact_window(cli: &Cli, view: &SliceView) -> Result<Option<Window<f64>>> {
+    match &view.exact {
+        Some(exact) => normalizer(cli, view)?.exact_window(exact),
+        None => Ok(None),
+    }
+}
+
+/// The window as JSON, with the exact cut points of a 64-bit integer slice
+fn window_json(cli: &Cli, view: &SliceView) -> Result<serde_json::Value> {
+    Ok(match exact_window(cli, view)? {
+        Some(window) => window.to_json(),
+        None => display_window(cli, view)?.to_json(),
+    })
+}
+
+/// Size in metres of a displayed pixel along X, after the aspect correction resampled
+/// the `read` rows and columns of the view and the orientation turned them
+fn displayed_pixel_size(cli: &Cli, view: &SliceView, read: (usize, usize)) -> Option<f64> {
+    let size = view.pixel_size()?;
+    let columns = view.data.ncols() as f64;
+    Some(match cli.orientation().swaps_axes() {
+        // Columns of the display run along Y of the array
+        true => size * view.pixel_aspect().unwrap_or(1.0) * read.0 as f64 / columns,
+        false => size * read.1 as f64 / columns,
+    })
+}
+
+/// The options at the window of the planes together, as if given with --min and --max,
+/// for frames or tiles sharing one contrast, unless the window is empty
+fn shared_cli(cli: &Cli, view: &SliceView, planes: &[ArrayView2<f32>]) -> Result<Option<Cli>> {
+    let window = normalizer(cli, view)?.window(&normalizer::pooled(planes)?)?;
+    Ok((window.max > window.min).then(|| Cli {
+        min: Some(window.min.into()),
+        max: Some(window.max.into()),
+        norm: Some(Norm::Fixed),
+        ..cli.clone()
+    }))
+}
+
+/// Scale the slice to 8-bit, inverted if requested
+fn normalize_view(cli: &Cli, view: &SliceView) -> Result<Array2<u8>> {
+    timings::time(Stage::Normalization, || normalize_slice(cli, view))
+}
+
+fn normalize_slice(cli: &Cli, view: &SliceView) -> Result<Array2<u8>> {
+    let normalizer = normalizer(cli, view)?;
+    let exact = match &view.exact {
+        Some(exact) => {
+            let normalized = normalizer.normalize_exact(exact, cli.scale, cli.gamma)?;
+            if normalized.is_none() && peekzarr::rounds_to_f32(exact) {
+                warn!(
+                    "Values of {} ({}) are rounded to f32 by --norm {}",
+                    view.path,
+                    view.data_type,
+                    format!("{:?}", cli.norm.unwrap_or(Norm::Quantile)).to_lowercase()
+                );
+            }
+            normalized
+        }
+        None => None,
+    };
+    let (mut normalized, _) = match exact {
+        Some(normalized) => normalized,
+        None => normalizer.normalize(&view.data, cli.scale, cli.gamma)?,
+    };
+    // After the gamma curve, before the colormap or channel color
+    if cli.invert {
+        normalized.mapv_inplace(|value| 255 - value);
     }
+    Ok(normalized)
+}
+
+/// Histogram of the slice as read, before aspect correction and normalization,
+/// one per channel of a composite
+fn format_histograms(cli: &Cli, image: &Image) -> Result<String> {
+    let histograms = image
+        .views
+        .iter()
+        .map(|view| {
+            let stats = peekzarr::slice_stats(&view.data, &[cli.low, cli.high])?;
+            let histogram = histogram::format_histogram(
+                &view.data,
+                view.is_integer(),
+                &stats,
+                display_window(cli, view).map(|window| (window.min, window.max))?,
+            );
+            Ok(match (&image.colors, &image.projection) {
+                (Some(_), _) => format!("Channel {}:\n{}", view.channel_index(), histogram),
+                (None, Some(span)) => format!("{}:\n{}", span, histogram),
+                (None, None) => histogram,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(histograms.join("\n"))
+}
+
+/// Statistics of the slice as JSON, of the exact values of a 64-bit integer slice
+fn stats_json(view: &SliceView, quantiles: &[f64]) -> Result<serde_json::Value> {
+    Ok(match &view.exact {
+        Some(exact) => peekzarr::slice_stats(exact, quantiles)?.to_json(),
+        None => peekzarr::slice_stats(&view.data, quantiles)?.to_json(),
+    })
+}
+
+/// Statistics of each slice shown, saying which channel or projection they describe
+fn format_stats(cli: &Cli, image: &Image) -> Result<String> {
+    let stats = image
+        .views
+        .iter()
+        .map(|view| {
+            let quantiles = [cli.low, 0.5, cli.high];
+            let stats = match &view.exact {
+                Some(exact) => peekzarr::slice_stats(exact, &quantiles)?.format(),
+                None => peekzarr::slice_stats(&view.data, &quantiles)?.format(),
+            };
+            let channel = format!("channel {}", view.channel_index());
+            let subject = match (&image.colors, &image.projection) {
+                (Some(_), Some(span)) => format!("{} of the {}", channel, span),
+                (Some(_), None) => channel,
+                (None, Some(span)) => format!("the {}", span),
+                (None, None) => "the slice".to_string(),
+            };
+            Ok(format!("Statistics of {}:\n{}", subject, stats))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(stats.join("\n"))
 }
 
-fn image_quantile(array: &Array2<f32>, q: f64) -> Result<f32> {
-    let quantile = array
+/// The omero channel color to tint the slice with, unless a colormap is given
+fn tint(cli: &Cli, view: &SliceView) -> Option<[u8; 3]> {
+    // Label colors are blended over a grayscale image
+    (cli.cmap.is_none() && !cli.ignore_omero && cli.overlay_label.is_none())
+        .then(|| view.omero_channel()?.color)
         .flatten()
-        .quantile_axis_skipnan_mut(Axis(0), n64(q), &Nearest)?
-        .into_scalar();
-    Ok(quantile)
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let decoded = read_image(&cli)?;
-    let (rows, columns) = decoded.dim();
-    let min = image_quantile(&decoded, cli.low)?;
-    let max = image_quantile(&decoded, cli.high)?;
-    let normalized = decoded.mapv(|x| ((x.clamp(min, max) - min) / (max - min) * 255.0) as u8);
+/// The colors of evenly spaced values from the low to the high cut point, for the colorbar
+fn display_lut(cli: &Cli, view: &SliceView, (low, high): (f32, f32)) -> Vec<[u8; 3]> {
+    let mut lut = match (cli.cmap, tint(cli, view)) {
+        (Some(cmap), _) => cmap.lut(),
+        (None, Some(color)) => (0..=255u16)
+            .map(|value| color.map(|c| (value * c as u16 / 255) as u8))
+            .collect(),
+        (None, None) => Colormap::Gray.lut(),
+    };
+    if cli.gamma == 1.0 && cli.scale == Scale::Linear && !cli.invert {
+        return lut;
+    }
+    if cli.invert {
+        lut.reverse();
+    }
+    // Follow the scale and gamma curve of the normalization
+    let ramp = Array2::from_shape_fn((1, 256), |(_, i)| low + (high - low) * i as f32 / 255.0);
+    peekzarr::normalize_display(&ramp, low, high, cli.scale, cli.gamma)
+        .iter()
+        .map(|&value| lut[value as usize])
+        .collect()
+}
+
+/// Scale the slice to 8-bit with the omero window (or the quantiles),
+/// tinted with the omero channel color if there is one,
+/// or blend the channels of a composite
+fn render(cli: &Cli, image: &Image) -> Result<DynamicImage> {
+    if let Some(colors) = &image.colors {
+        let channels = image
+            .views
+            .iter()
+            .zip(colors)
+            .map(|(view, &color)| Ok((normalize_view(cli, view)?, color)))
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(DynamicImage::ImageRgb8(composite::blend(&channels)?));
+    }
+    let view = &image.views[0];
+    if let (Some(positions), Some(span)) = (&image.positions, &image.projection) {
+        let colormap = span.colormap.unwrap_or(span.projection.default_colormap());
+        let planes = span.end - span.start;
+        let normalized = normalize_view(cli, view)?;
+        let image = projection::color_code(&normalized, positions, planes, colormap);
+        return Ok(DynamicImage::ImageRgb8(image));
+    }
+    let (rows, columns) = view.data.dim();
+    if cli.label_mode || view.is_label() {
+        // Label ids are colored as they are, never normalized
+        let data = match &view.exact {
+            Some(exact) => labels::colorize_labels(exact, cli.label_seed),
+            None => labels::colorize_labels(&view.data, cli.label_seed),
+        };
+        return RgbImage::from_raw(columns as u32, rows as u32, data)
+            .map(DynamicImage::ImageRgb8)
+            .ok_or_else(|| anyhow::anyhow!("Failed to create image from raw data"));
+    }
+    let normalized = normalize_view(cli, view)?;
+    if let Some(cmap) = cli.cmap.filter(|&cmap| cmap != Colormap::Gray) {
+        return Ok(DynamicImage::ImageRgb8(cmap.apply(&normalized)));
+    }
+    let tint = tint(cli, view);
     let data = normalized
         .as_standard_layout()
         .to_owned()
         .into_raw_vec_and_offset()
         .0;
+    let image = match tint {
+        Some(color) => {
+            let data = data
+                .iter()
+                .flat_map(|&value| color.map(|c| (value as u16 * c as u16 / 255) as u8))
+                .collect();
+            RgbImage::from_raw(columns as u32, rows as u32, data).map(DynamicImage::ImageRgb8)
+        }
+        None => {
+            GrayImage::from_raw(columns as u32, rows as u32, data).map(DynamicImage::ImageLuma8)
+        }
+    };
+    image.ok_or_else(|| anyhow::anyhow!("Failed to create image from raw data"))
+}
+
+/// Print the names of the label images of the image, one per line
+fn list_labels(cli: &Cli) -> Result<()> {
+    let store = store::open_store(&cli.image_path, &cli.store_options())?;
+    let store = Arc::new(store::ConsolidatedStore::new(store, !cli.no_consolidated));
+    let group = peekzarr::resolve_image_path(&store, &cli.read_options())?;
+    let labels = peekzarr::list_labels(&store, &group)?;
+    if labels.is_empty() {
+        anyhow::bail!("No label images found in {}", cli.image_path);
+    }
+    for label in labels {
+        println!("{}", label);
+    }
+    Ok(())
+}
+
+/// Show a grid of well thumbnails for an HCS plate
+fn plate_overview(cli: &Cli) -> Result<()> {
+    let store = open_image_store(cli)?;
+    let well_size = cli.well_size as usize;
+    let filter = match cli.label_mode {
+        true => Filter::Nearest,
+        false => cli.filter.unwrap_or(Filter::Area),
+    };
+    let overview = plate::read_plate_overview(store, &cli.read_options(), well_size, filter)
+        .with_context(|| format!("Failed to read plate from {}", cli.image_path))?;
+    let montage = overview.montage(
+        well_size,
+        cli.low,
+        cli.high,
+        cli.per_well_contrast,
+        cli.contrast_sample.map(|n| n as usize),
+    )?;
+    let (rows, columns) = montage.dim();
+    let data = montage.into_raw_vec_and_offset().0;
     let image = GrayImage::from_raw(columns as u32, rows as u32, data)
         .ok_or_else(|| anyhow::anyhow!("Failed to create image from raw data"))?;
-    let image = DynamicImage::ImageLuma8(image);
-    let conf = viuer::Config {
-        absolute_offset: false,
-        ..Default::default()
+    let conf = cli.viuer_config();
+    show_image(cli, &DynamicImage::ImageLuma8(image), &conf, None)
+}
+
+/// The options to show the axis given as `all` to --slice (e.g. c=all) as a montage with,
+/// as --montage would, failing for more than one such axis or flags --montage excludes
+fn all_slice_montage(cli: &Cli) -> Result<Option<(Cli, String)>> {
+    let slices = cli.slice_indices.as_deref().unwrap_or_default();
+    let all: Vec<&SliceIndex> = slices
+        .iter()
+        .filter(|slice| matches!(slice, SliceIndex::All(_)))
+        .collect();
+    let axis = match all.as_slice() {
+        [] => return Ok(None),
+        [SliceIndex::All(axis)] => axis.clone(),
+        _ => {
+            let all: Vec<String> = all.iter().map(ToString::to_string).collect();
+            anyhow::bail!(
+                "Only one axis can be all at a time, got {}",
+                all.join(" and ")
+            );
+        }
     };
-    viuer::print(&image, &conf)?;
-    Ok(())
+    let excluded = [
+        (cli.montage.is_some(), "--montage"),
+        (cli.channels.is_some(), "--channels"),
+        (cli.project.is_some(), "--project"),
+        (cli.ortho, "--ortho"),
+        (cli.animate.is_some(), "--animate"),
+        (cli.plate_overview, "--plate-overview"),
+        (cli.overlay_label.is_some(), "--overlay-label"),
+        (!cli.probe.is_empty(), "--probe"),
+        (cli.profile.is_some(), "--profile"),
+        (cli.show_chunks, "--show-chunks"),
+        (cli.json, "--json"),
+        (cli.dry_run, "--dry-run"),
+        (cli.timings, "--timings"),
+        (cli.export_raw.is_some(), "--export-raw"),
+        (cli.write_thumbnail.is_some(), "--write-thumbnail"),
+        (cli.export_hist.is_some(), "--export-hist"),
+    ];
+    if let Some((_, flag)) = excluded.iter().find(|(given, _)| *given) {
+        anyhow::bail!(
+            "Cannot combine {}=all, shown as a montage, with {}",
+            axis,
+            flag
+        );
+    }
+    let montage_cli = Cli {
+        slice_indices: Some(
+            slices
+                .iter()
+                .filter(|slice| !matches!(slice, SliceIndex::All(_)))
+                .cloned()
+                .collect(),
+        ),
+        montage: Some(axis.clone()),
+        ..cli.clone()
+    };
+    Ok(Some((montage_cli, axis)))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Show the slices at every index along an axis in a grid of tiles
+fn montage_image(cli: &Cli, axis: &str) -> Result<()> {
+    let store = open_image_store(cli)?;
+    let montage = montage::read_montage(
+        store,
+        cli.array_name.as_deref(),
+        &cli.read_options(),
+        axis,
+        cli.stride,
+        cli.max_tiles as usize,
+    )
+    .with_context(|| format!("Failed to read a montage from {}", cli.image_path))?;
+    let first = &montage.views[0];
+    if !cli.no_header {
+        cli.print_text(header::format_header(first));
+    }
+    let extent = first.extent();
+    // At the window of all tiles together
+    let shared = if cli.shared_contrast || !montage.channels {
+        let sample = cli.contrast_sample.map(|n| n as usize);
+        let data: Vec<_> = normalizer::sample_evenly(montage.views.len(), sample)
+            .into_iter()
+            .map(|i| montage.views[i].data.view())
+            .collect();
+        shared_cli(cli, first, &data)?
+    } else {
+        None
+    };
+    let tile_cli = shared.as_ref().unwrap_or(cli);
+    let grid = montage::grid_shape(montage.views.len());
+    let count = montage.views.len();
+    let mut tiles = vec![];
+    for mut view in montage.views {
+        let filter = cli.filter(&view);
+        if let Some(aspect) = view.pixel_aspect().filter(|_| !cli.no_aspect_correction) {
+            let data = peekzarr::correct_aspect(&view.data, aspect, cli.size_limits(), filter);
+            view.set_resampled(data, filter);
+        }
+        view.orient(&cli.orientation());
+        let (rows, columns) = montage::tile_size(view.data.dim(), grid, cli.crop_size() as usize);
+        view.set_resampled(
+            peekzarr::resample(&view.data, rows, columns, filter),
+            filter,
+        );
+        let image = Image {
+            views: vec![view],
+            colors: None,
+            overlay: None,
+            chunks: None,
+            probes: vec![],
+            projection: None,
+            positions: None,
+        };
+        tiles.push(render(tile_cli, &image)?.to_rgb8());
+    }
+    let image = montage::assemble(&tiles, &montage.labels, grid.1);
+    let caption = (!cli.no_caption).then(|| {
+        let [(y0, y1), (x0, x1)] = extent;
+        format!(
+            "{}  {} tiles along {}  y={}..{} x={}..{}",
+            cli.image_path, count, montage.axis, y0, y1, x0, x1
+        )
+    });
+    let conf = cli.viuer_config();
+    show_image(
+        cli,
+        &DynamicImage::ImageRgb8(image),
+        &conf,
+        caption.as_deref(),
+    )
+}
 
-    #[test]
-    fn test_start_and_shape_default_slicing() -> Result<()> {
-        let array_shape = vec![5, 3, 256, 256]; // T, C, Y, X
-        let crop_size = 256u64;
+/// Show two images side by side at one contrast window, B resampled to the size of A
+/// if they differ, each captioned with its side
+fn compare_images(cli: &Cli, paths: &[String]) -> Result<()> {
+    let second = cli.compare_level.is_some() || cli.compare_array.is_some();
+    let sides = match paths {
+        [a, b] if !second => [a, b].map(|path| Cli {
+            image_path: path.clone(),
+            ..cli.clone()
+        }),
+        [path] if second => [
+            Cli {
+                image_path: path.clone(),
+                ..cli.clone()
+            },
+            Cli {
+                image_path: path.clone(),
+                level: cli.compare_level,
+                array_name: cli.compare_array.clone(),
+                ..cli.clone()
+            },
+        ],
+        _ => anyhow::bail!(
+            "--compare takes two image paths, or one with --compare-level or --compare-array"
+        ),
+    };
+    if let Some(path) = &cli.output {
+        snapshot::check_output(path, cli.force)?;
+    }
+    let views = sides
+        .iter()
+        .map(|side| {
+            check_read_limit(side)?;
+            Ok(read_image(side)?.views.swap_remove(0))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if !cli.no_header {
+        for (label, view) in ["A", "B"].iter().zip(&views) {
+            cli.print_text(format!("{}  {}", label, header::format_header(view)));
+        }
+    }
+    // At the window of both together, so that differences are in the data
+    let planes: Vec<_> = views.iter().map(|view| view.data.view()).collect();
+    let fixed = shared_cli(cli, &views[0], &planes)?;
+    let mut tiles = vec![];
+    let mut captions = vec![];
+    for ((label, side), view) in ["A", "B"].iter().zip(&sides).zip(views) {
+        let side_cli = match &fixed {
+            Some(fixed) => Cli {
+                min: fixed.min,
+                max: fixed.max,
+                norm: fixed.norm,
+                ..side.clone()
+            },
+            None => side.clone(),
+        };
+        let nearest = side_cli.filter(&view) == Filter::Nearest;
+        let (image, caption) = frame_image(&side_cli, view)?;
+        let mut tile = image.to_rgb8();
+        if let Some(first) = tiles.first().map(RgbImage::dimensions) {
+            if tile.dimensions() != first {
+                warn!(
+                    "B is {}x{} pixels as shown, resampling it to the {}x{} of A",
+                    tile.width(),
+                    tile.height(),
+                    first.0,
+                    first.1
+                );
+                let filter = match nearest {
+                    true => image::imageops::FilterType::Nearest,
+                    false => image::imageops::FilterType::Triangle,
+                };
+                tile = image::imageops::resize(&tile, first.0, first.1, filter);
+            }
+        }
+        tiles.push(tile);
+        captions.extend(caption.map(|caption| format!("{}  {}", label, caption)));
+    }
+    let labels = [Some("A".to_string()), Some("B".to_string())];
+    let image = DynamicImage::ImageRgb8(montage::assemble(&tiles, &labels, 2));
+    // The captions are drawn under each other in files, and printed on their own lines
+    if let Some(path) = &cli.output {
+        let file = captions.iter().fold(image.to_rgb8(), |file, caption| {
+            caption::append_caption(&file, caption)
+        });
+        snapshot::write_image(&DynamicImage::ImageRgb8(file), path, cli.force)?;
+    }
+    if cli.no_display {
+        return Ok(());
+    }
+    let terminal_cli = Cli {
+        output: None,
+        ..cli.clone()
+    };
+    show_image(&terminal_cli, &image, &cli.viuer_config(), None)?;
+    for caption in captions {
+        cli.print_text(caption);
+    }
+    Ok(())
+}
 
-        let (start, shape) = start_and_shape(&array_shape, None, crop_size)?;
+/// Show the signed difference of two images, or of two timepoints of one image,
+/// with the diverging colormap at a window centered at zero, and print its metrics
+fn diff_images(cli: &Cli) -> Result<()> {
+    let (a, b) = match (&cli.diff, &cli.diff_t) {
+        (Some(other), _) => (
+            cli.clone(),
+            Cli {
+                image_path: other.clone(),
+                ..cli.clone()
+            },
+        ),
+        (None, Some(timepoints)) => {
+            let [first, second] = timepoints.as_slice() else {
+                anyhow::bail!("--diff-t takes two timepoints, e.g. 5,6");
+            };
+            let slices = cli.slice_indices.as_deref().unwrap_or_default();
+            if slices.iter().any(|slice| match slice {
+                SliceIndex::Axis(axis, _) | SliceIndex::Physical(axis, _, _) => axis == "t",
+                _ => false,
+            }) {
+                anyhow::bail!(
+                    "Cannot combine --diff-t, which selects the timepoints, with t in --slice"
+                );
+            }
+            let at = |t: SliceValue| {
+                let mut slices = slices.to_vec();
+                slices.push(SliceIndex::Axis("t".to_string(), t));
+                Cli {
+                    slice_indices: Some(slices),
+                    ..cli.clone()
+                }
+            };
+            (at(*first), at(*second))
+        }
+        (None, None) => unreachable!("shown without --diff or --diff-t"),
+    };
+    if let Some(path) = &cli.output {
+        snapshot::check_output(path, cli.force)?;
+    }
+    let mut views = [&a, &b]
+        .iter()
+        .map(|side| {
+            check_read_limit(side)?;
+            Ok(read_image(side)?.views.swap_remove(0))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let (view_b, view_a) = (views.pop().unwrap(), views.pop().unwrap());
+    if !cli.no_header {
+        for (label, view) in [("A", &view_a), ("B", &view_b)] {
+            cli.print_text(format!("{}  {}", label, header::format_header(view)));
+        }
+    }
+    let data = diff::difference(&view_a, &view_b)?;
+    let stats = DiffStats::new(&data, cli.diff_threshold, cli.high)?;
+    let (min, max) = stats.window();
+    // The timepoints are named with the difference rather than among the indices
+    let t_axis = (view_a.axes.as_deref())
+        .and_then(|axes| axes.iter().position(|axis| axis == "t"))
+        .filter(|&t| cli.diff_t.is_some() && t + 2 < view_a.start.len());
+    let caption = (!cli.no_caption)
+        .then(|| -> Result<String> {
+            let mut caption = match t_axis {
+                Some(_) => Caption::new(&a.image_path, &view_a, None, cli.scale),
+                None => Caption::new(
+                    &format!("{} - {}", b.image_path, a.image_path),
+                    &view_a,
+                    None,
+                    cli.scale,
+                ),
+            };
+            let mut subtracted = String::new();
+            if let Some(t) = t_axis {
+                caption.indices.remove(t);
+                subtracted = format!("  t={} - t={}", view_b.start[t], view_a.start[t]);
+            }
+            let caption = match &cli.caption_template {
+                Some(template) => caption.format(template)?,
+                None => caption.to_string(),
+            };
+            Ok(format!(
+                "{}{}  [±{} at the {} quantile of |diff|]",
+                caption, subtracted, stats.clip, stats.quantile
+            ))
+        })
+        .transpose()?;
+    let diff_cli = Cli {
+        min: Some(min.into()),
+        max: Some(max.into()),
+        norm: Some(Norm::Fixed),
+        cmap: Some(cli.cmap.unwrap_or(Colormap::Coolwarm)),
+        no_caption: true,
+        ..cli.clone()
+    };
+    let view = SliceView {
+        data,
+        data_type: "float32".to_string(),
+        exact: None,
+        ..view_a
+    };
+    let (image, _) = frame_image(&diff_cli, view)?;
+    if let Some(path) = &cli.output {
+        let file = match &caption {
+            Some(caption) => {
+                DynamicImage::ImageRgb8(caption::append_caption(&image.to_rgb8(), caption))
+            }
+            None => image.clone(),
+        };
+        snapshot::write_image(&file, path, cli.force)?;
+    }
+    if !cli.no_display {
+        let terminal_cli = Cli {
+            output: None,
+            ..cli.clone()
+        };
+        show_image(&terminal_cli, &image, &cli.viuer_config(), None)?;
+        if let Some(caption) = caption {
+            cli.print_text(caption);
+        }
+    }
+    cli.print_text(format!("Difference: {}", stats));
+    Ok(())
+}
 
-        assert_eq!(start.len(), 4);
-        assert_eq!(start[0], 2);
-        assert_eq!(start[1], 1);
-        assert_eq!(start[2], 0);
-        assert_eq!(start[3], 0);
+/// Show the XY, XZ and YZ planes through a point in one image
+fn ortho_image(cli: &Cli) -> Result<()> {
+    let store = open_image_store(cli)?;
+    let ortho = ortho::read_ortho(store, cli.array_name.as_deref(), &cli.read_options())
+        .with_context(|| format!("Failed to read orthogonal views from {}", cli.image_path))?;
+    if !cli.no_header {
+        cli.print_text(header::format_header(&ortho.xy));
+    }
+    // At the window of the three planes together
+    let planes = [ortho.xy.data.view(), ortho.xz.view(), ortho.yz.view()];
+    let fixed = shared_cli(cli, &ortho.xy, &planes)?;
+    let plane_cli = fixed.as_ref().unwrap_or(cli);
+    let [z, y, x] = ortho.display_sizes(!cli.no_aspect_correction, cli.crop_size());
+    let planes = [
+        (ortho.xy.data.clone(), (y, x)),
+        (ortho.xz.clone(), (z, x)),
+        (ortho.yz.clone(), (y, z)),
+    ];
+    let mut images = vec![];
+    for (data, (rows, columns)) in planes {
+        let image = Image {
+            views: vec![SliceView {
+                data: peekzarr::resample(&data, rows, columns, cli.filter(&ortho.xy)),
+                exact: None,
+                ..ortho.xy.clone()
+            }],
+            colors: None,
+            overlay: None,
+            chunks: None,
+            probes: vec![],
+            projection: None,
+            positions: None,
+        };
+        images.push(render(plane_cli, &image)?.to_rgb8());
+    }
+    let image = ortho::compose(&images[0], &images[1], &images[2]);
+    let caption = (!cli.no_caption).then(|| {
+        let [cz, cy, cx] = ortho.center;
+        let [(y0, y1), (x0, x1)] = ortho.xy.extent();
+        let (z0, z1) = ortho.z_extent;
+        format!(
+            "{}  through z={} y={} x={}  z={}..{} y={}..{} x={}..{}",
+            cli.image_path, cz, cy, cx, z0, z1, y0, y1, x0, x1
+        )
+    });
+    let conf = cli.viuer_config();
+    show_image(
+        cli,
+        &DynamicImage::ImageRgb8(image),
+        &conf,
+        caption.as_deref(),
+    )
+}
 
-        assert_eq!(shape[0], 1);
-        assert_eq!(shape[1], 1);
-        assert_eq!(shape[2], 256);
-        assert_eq!(shape[3], 256);
+/// Write the image to --output with the caption drawn under it, as in movies,
+/// and print it and its caption unless --no-display
+fn show_image(
+    cli: &Cli,
+    image: &DynamicImage,
+    conf: &viuer::Config,
+    caption: Option<&str>,
+) -> Result<()> {
+    if let Some(path) = &cli.output {
+        let file = match caption {
+            Some(caption) => {
+                &DynamicImage::ImageRgb8(caption::append_caption(&image.to_rgb8(), caption))
+            }
+            None => image,
+        };
+        snapshot::write_image(file, path, cli.force)?;
+    }
+    if cli.no_display {
+        return Ok(());
+    }
+    if let Some(emit) = cli.emit {
+        // In one write, so that the sequence is not split
+        let png = snapshot::encode(image, StreamFormat::Png)?;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(emit.sequence(&png).as_bytes())?;
+        stdout.flush()?;
+    } else if cli.streams_image() {
+        let bytes = snapshot::encode(image, cli.stdout_format)?;
+        std::io::stdout().write_all(&bytes)?;
+    } else {
+        cli.print_image(image, conf)?;
+    }
+    if let Some(caption) = caption {
+        cli.print_text(caption);
+    }
+    Ok(())
+}
 
-        Ok(())
+/// Show the slice of each combination of --slices in order with its caption,
+/// all at one contrast window with --shared-contrast
+fn sequence_image(cli: &Cli, specs: &[SliceSpec]) -> Result<()> {
+    // Chunks shared by several combinations are read once through the cache
+    let store = open_image_store(cli)?;
+    let sequence =
+        sequence::open_sequence(store, cli.array_name.as_deref(), &cli.read_options(), specs)
+            .with_context(|| format!("Failed to read slices from {}", cli.image_path))?;
+    let mut views = vec![sequence.read(0)?];
+    let shared = match cli.shared_contrast {
+        true => {
+            for i in 1..sequence.len() {
+                views.push(sequence.read(i)?);
+            }
+            let planes: Vec<_> = views.iter().map(|view| view.data.view()).collect();
+            shared_cli(cli, &views[0], &planes)?
+        }
+        false => None,
+    };
+    let slice_cli = shared.as_ref().unwrap_or(cli);
+    if !cli.no_header {
+        cli.print_text(header::format_header(&views[0]));
+    }
+    let conf = cli.viuer_config();
+    let mut views = views.into_iter();
+    for i in 0..sequence.len() {
+        // Read as they are shown, unless already read for the shared window
+        let view = match views.next() {
+            Some(view) => view,
+            None => sequence.read(i)?,
+        };
+        let (image, caption) = frame_image(slice_cli, view)?;
+        show_image(slice_cli, &image, &conf, caption.as_deref())?;
     }
+    Ok(())
+}
 
-    #[test]
-    fn test_start_and_shape_with_explicit_slicing() -> Result<()> {
-        let array_shape = vec![5, 3, 256, 256]; // T, C, Y, X
-        let crop_size = 256u64;
-        let slice_indices = vec![0, 2]; // Use first timepoint, third channel
+/// Render a frame of an animation with its NaN pixels and scale bar, and its caption
+fn frame_image(cli: &Cli, mut view: SliceView) -> Result<(DynamicImage, Option<String>)> {
+    let labels = cli.label_mode || view.is_label();
+    let caption = if cli.no_caption {
+        None
+    } else {
+        let window = (!labels).then(|| display_window(cli, &view)).transpose()?;
+        let caption = Caption::new(&cli.image_path, &view, window, cli.scale);
+        Some(match &cli.caption_template {
+            Some(template) => caption.format(template)?,
+            None => caption.to_string(),
+        })
+    };
+    let read = view.data.dim();
+    let filter = cli.filter(&view);
+    if let Some(aspect) = view.pixel_aspect().filter(|_| !cli.no_aspect_correction) {
+        let data = peekzarr::correct_aspect(&view.data, aspect, cli.size_limits(), filter);
+        view.set_resampled(data, filter);
+    }
+    if let Some(zoom) = cli.zoom {
+        let factor = peekzarr::zoom_factor(view.data.dim(), zoom as f64, cli.size_limits());
+        let filter = peekzarr::zoom_filter(factor, filter);
+        view.set_resampled(peekzarr::zoom(&view.data, factor, filter), filter);
+    }
+    view.orient(&cli.orientation());
+    let frame = Image {
+        views: vec![view],
+        colors: None,
+        overlay: None,
+        chunks: None,
+        probes: vec![],
+        projection: None,
+        positions: None,
+    };
+    let mut rgb = render(cli, &frame)?.to_rgb8();
+    if let Some(mask) = frame.nan_mask() {
+        // Drawn over the last frame, so transparent pixels are black
+        let color = match cli.nan_color {
+            NanColor::Color(color) => color,
+            NanColor::Transparent => [0, 0, 0],
+        };
+        for ((row, column), _) in mask.indexed_iter().filter(|(_, &nan)| nan) {
+            rgb.put_pixel(column as u32, row as u32, image::Rgb(color));
+        }
+    }
+    let view = &frame.views[0];
+    if cli.scalebar != ScaleBar::Off {
+        if let Some(pixel_size) = displayed_pixel_size(cli, view, read) {
+            scalebar::draw_scale_bar(&mut rgb, pixel_size, cli.scalebar);
+        }
+    }
+    Ok((DynamicImage::ImageRgb8(rgb), caption))
+}
+
+/// Show the frames along an axis one after another in the same place in the terminal,
+/// moving the cursor back up over the last frame, until the end or Ctrl-C
+fn animate_image(cli: &Cli, axis: &str) -> Result<()> {
+    if let Some(path) = &cli.output {
+        MovieFormat::from_path(path)?;
+    }
+    if cli.streams_image() {
+        anyhow::bail!(
+            "Animations are played in a terminal, write one to a .gif or .webp file with --output"
+        );
+    }
+    // Chunks overlapping several frames are read once through the cache
+    let store = open_image_store(cli)?;
+    let animation = animation::open_animation(
+        store,
+        cli.array_name.as_deref(),
+        &cli.read_options(),
+        axis,
+        cli.frames.unwrap_or_default(),
+    )
+    .with_context(|| format!("Failed to read frames from {}", cli.image_path))?;
+    let count = animation.indices.len();
+    // At one window for all frames so that they do not flicker
+    let fixed = {
+        let mut planes = vec![animation.first.data.clone()];
+        if cli.shared_contrast {
+            let sampled = normalizer::sample_evenly(count, cli.contrast_sample.map(|n| n as usize));
+            info!(
+                "Sampling {} of {} frames along {} for the shared contrast window",
+                sampled.len(),
+                count,
+                animation.axis
+            );
+            for &i in &sampled[1..] {
+                planes.push(animation.read_frame(animation.indices[i])?.data);
+            }
+        }
+        let planes: Vec<_> = planes.iter().map(|plane| plane.view()).collect();
+        shared_cli(cli, &animation.first, &planes)?
+    };
+    let frame_cli = fixed.as_ref().unwrap_or(cli);
+    // The first frame is kept, the others read again as they are shown
+    let frame = |i: usize, index: u64| match i {
+        0 => Ok(animation.first.clone()),
+        _ => animation.read_frame(index),
+    };
+    let interval = Duration::from_secs_f64(1.0 / cli.fps);
+    if let Some(path) = &cli.output {
+        let mut writer = MovieWriter::create(path, interval, cli.repeat)?;
+        animation::catch_interrupt();
+        info!("Writing {} frames to {}", count, path.display());
+        for (i, &index) in animation.indices.iter().enumerate() {
+            if animation::interrupted() {
+                anyhow::bail!("Interrupted, {} was not written", path.display());
+            }
+            let (image, caption) = frame_image(frame_cli, frame(i, index)?)?;
+            let image = match caption {
+                Some(caption) => caption::append_caption(&image.to_rgb8(), &caption),
+                None => image.to_rgb8(),
+            };
+            writer.add_frame(&image)?;
+        }
+        // Encoded one frame at a time, and moved in place once complete
+        return writer.finish();
+    }
+    if !cli.no_header {
+        cli.print_text(header::format_header(&animation.first));
+    }
+    let conf = cli.viuer_config();
+    animation::catch_interrupt();
+    let mut stdout = std::io::stdout();
+    // The cursor is hidden while playing and shown again however it stops
+    write!(stdout, "\x1b[?25l")?;
+    let mut lines = 0;
+    let played = (|| {
+        loop {
+            for (i, &index) in animation.indices.iter().enumerate() {
+                let started = Instant::now();
+                let (image, caption) = frame_image(frame_cli, frame(i, index)?)?;
+                if lines > 0 {
+                    write!(stdout, "\x1b[{}A\r", lines)?;
+                }
+                let (_, height) = cli.print_image(&image, &conf)?;
+                lines = height;
+                if let Some(caption) = caption {
+                    // Cleared first, as the last caption may be longer
+                    writeln!(stdout, "\x1b[2K{}", caption)?;
+                    lines += 1;
+                }
+                stdout.flush()?;
+                while started.elapsed() < interval && !animation::interrupted() {
+                    let left = interval.saturating_sub(started.elapsed());
+                    std::thread::sleep(left.min(Duration::from_millis(20)));
+                }
+                if animation::interrupted() {
+                    return Ok(());
+                }
+            }
+            if !cli.repeat {
+                return Ok(());
+            }
+        }
+    })();
+    write!(stdout, "\x1b[?25h")?;
+    stdout.flush()?;
+    played
+}
 
-        let (start, _shape) = start_and_shape(&array_shape, Some(&slice_indices), crop_size)?;
+/// Show the slice again in place every `seconds` until Ctrl-C, on the alternate screen so that
+/// the scrollback is left as it was; a failure after the first read is shown under the last
+/// image, which is kept until the next read succeeds
+fn watch_image(cli: &Cli, seconds: f64) -> Result<()> {
+    if cli.streams_image() {
+        anyhow::bail!("Watching needs a terminal, stdout is not one");
+    }
+    let interval = Duration::from_secs_f64(seconds);
+    check_read_limit(cli)?;
+    let mut modified = store::last_modified(&cli.image_path)?;
+    let read = read_image(cli)?;
+    // The level and slices chosen are reported once, not over the image at every update
+    if !verbosity::enabled(Verbosity::Verbose) {
+        verbosity::set(Verbosity::Quiet);
+    }
+    animation::catch_interrupt();
+    let mut stdout = std::io::stdout();
+    // The alternate screen and the hidden cursor are restored however it stops
+    write!(stdout, "\x1b[?1049h\x1b[?25l")?;
+    let watched = (|| {
+        let mut read = Some(read);
+        loop {
+            let updated = caption::clock_time(std::time::SystemTime::now());
+            let frame_cli = Cli {
+                updated: Some(updated.clone()),
+                ..cli.clone()
+            };
+            let shown = (read.take())
+                .map_or_else(|| read_image(&frame_cli), Result::Ok)
+                .and_then(|read| {
+                    write!(stdout, "\x1b[H\x1b[2J")?;
+                    show_read(&frame_cli, read, None)
+                });
+            if let Err(err) = shown {
+                write!(
+                    stdout,
+                    "\r\x1b[2KFailed to update at {}: {:#}",
+                    updated, err
+                )?;
+            }
+            stdout.flush()?;
+            loop {
+                let started = Instant::now();
+                while started.elapsed() < interval && !animation::interrupted() {
+                    let left = interval.saturating_sub(started.elapsed());
+                    std::thread::sleep(left.min(Duration::from_millis(20)));
+                }
+                if animation::interrupted() {
+                    return Ok(());
+                }
+                let now = store::last_modified(&cli.image_path)?;
+                if now.is_none() || now != modified {
+                    modified = now;
+                    break;
+                }
+            }
+        }
+    })();
+    write!(stdout, "\x1b[?25h\x1b[?1049l")?;
+    stdout.flush()?;
+    watched
+}
 
-        assert_eq!(start[0], 0);
-        assert_eq!(start[1], 2);
-        assert_eq!(start[2], 0);
-        assert_eq!(start[3], 0);
+/// Size along Y and X of the plane a --roi is in: of the finest level,
+/// unless a level or an array is given
+#[cfg(unix)]
+fn roi_plane(cli: &Cli, store: &ImageStore, view: &SliceView) -> Result<[u64; 2]> {
+    let datasets = peekzarr::ngff::dataset_paths(&view.attributes);
+    let shape = match datasets.filter(|_| cli.level.is_none() && cli.array_name.is_none()) {
+        Some(paths) => {
+            let group = peekzarr::ngff::parent_path(&view.path);
+            let finest = peekzarr::join_path(group, &paths[0]);
+            peekzarr::open_array(store.clone(), &finest)?
+                .shape()
+                .to_vec()
+        }
+        None => view.shape.clone(),
+    };
+    let ndims = shape.len();
+    Ok([shape[ndims - 2], shape[ndims - 1]])
+}
 
-        Ok(())
+/// Show the slice, then step through the image and adjust its contrast with the keyboard
+/// until q, redrawing it in place on the alternate screen with a status line under it,
+/// and print the flags showing the last view; with --adjust only the contrast keys apply.
+/// The contrast window of the first slice is kept until a or a change of channel,
+/// adjustments are rendered from the slice already read and chunks come from the cache
+#[cfg(unix)]
+fn interactive_image(cli: &Cli) -> Result<()> {
+    if cli.streams_image() || !std::io::stdin().is_terminal() {
+        anyhow::bail!("Interactive mode needs a terminal, stdin or stdout is not one");
+    }
+    check_read_limit(cli)?;
+    let store = open_image_store(cli)?;
+    let first = read_image_from(cli, store.clone())?;
+    let view = &first.views[0];
+    let plane = roi_plane(cli, &store, view)?;
+    let region = match cli.roi {
+        Some(roi) => [roi.y.resolve(plane[0], "y")?, roi.x.resolve(plane[1], "x")?],
+        None => [(0, plane[0]), (0, plane[1])],
+    };
+    let mut navigation = Navigation::new(view, plane, region);
+    let mut contrast: Option<Contrast> = None;
+    let help = match cli.interactive {
+        true => "arrows z  ,. t  c channel  +- zoom  hjkl pan  [] low  {} high  gG gamma  i invert  a auto  q quit",
+        false => "[] low  {} high  gG gamma  i invert  a auto  q quit",
+    };
+    // The level and slices chosen are not reported over the image at every step
+    if !verbosity::enabled(Verbosity::Verbose) {
+        verbosity::set(Verbosity::Quiet);
     }
+    let keys = KeyReader::new()?;
+    let mut stdout = std::io::stdout();
+    // The alternate screen and the hidden cursor are restored however it stops
+    write!(stdout, "\x1b[?1049h\x1b[?25l")?;
+    let navigated = (|| {
+        let (mut read, mut reread, mut recontrast) = (Result::Ok(first), false, true);
+        loop {
+            let navigated_cli = Cli {
+                slice_indices: Some(navigation.slice_indices()),
+                roi: navigation.roi(),
+                ..cli.clone()
+            };
+            if reread {
+                read = read_image_from(&navigated_cli, store.clone());
+            }
+            let failure = match &read {
+                Result::Ok(image) => {
+                    let view = &image.views[0];
+                    if recontrast {
+                        let labels = cli.label_mode || view.is_label();
+                        contrast = (display_window(&navigated_cli, view).ok())
+                            .filter(|window| window.max > window.min && !labels)
+                            .map(|window| Contrast::new(window, cli.gamma, cli.invert));
+                        recontrast = false;
+                    }
+                    let frame_cli = match contrast {
+                        Some(contrast) => Cli {
+                            min: Some(contrast.min.into()),
+                            max: Some(contrast.max.into()),
+                            norm: Some(Norm::Fixed),
+                            gamma: contrast.gamma,
+                            invert: contrast.invert,
+                            ..navigated_cli
+                        },
+                        None => navigated_cli,
+                    };
+                    write!(stdout, "\x1b[H\x1b[2J")?;
+                    // Rendered from a copy, so that adjustments do not read the slice again
+                    show_read(&frame_cli, image.clone(), None)
+                        .err()
+                        .map(|err| format!("{:#}", err))
+                }
+                Err(err) => Some(format!("{:#}", err)),
+            };
+            let status = match (failure, contrast) {
+                (Some(err), _) => format!("{}  Failed: {}", navigation.status(), err),
+                (None, Some(contrast)) => {
+                    format!("{}  {}  {}", navigation.status(), contrast, help)
+                }
+                (None, None) => format!("{}  {}", navigation.status(), help),
+            };
+            write!(stdout, "\r\x1b[2K{}", status)?;
+            stdout.flush()?;
+            reread = false;
+            let mut redraw = false;
+            while !reread && !redraw {
+                for action in keys.read_keys()?.into_iter().filter_map(Action::from_key) {
+                    match action {
+                        Action::Quit => return Ok(()),
+                        Action::AutoContrast => (recontrast, redraw) = (true, true),
+                        action if action.navigates() => {
+                            if cli.interactive && navigation.apply(action) {
+                                reread = true;
+                                recontrast |= action == Action::Channel;
+                            }
+                        }
+                        action => {
+                            if let Some(contrast) = &mut contrast {
+                                redraw |= contrast.apply(action);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })();
+    write!(stdout, "\x1b[?25h\x1b[?1049l")?;
+    stdout.flush()?;
+    navigated?;
+    // To paste into a command showing the same view
+    let mut flags = vec![];
+    if cli.interactive && !navigation.indices.is_empty() {
+        let indices: Vec<String> = (navigation.slice_indices().iter())
+            .map(ToString::to_string)
+            .collect();
+        flags.push(format!("--slice {}", indices.join(",")));
+    }
+    if let Some(roi) = navigation.roi() {
+        flags.push(format!("--roi {}", roi));
+    }
+    flags.extend(contrast.map(|contrast| contrast.flags()));
+    if !flags.is_empty() {
+        println!("{}", flags.join(" "));
+    }
+    Ok(())
+}
 
-    #[test]
-    fn test_start_and_shape_with_crop() -> Result<()> {
-        let array_shape = vec![1, 1, 1024, 1024]; // T, C, Y, X
-        let crop_size = 512u64;
+#[cfg(not(unix))]
+fn interactive_image(_cli: &Cli) -> Result<()> {
+    anyhow::bail!("Interactive mode is only supported in Unix terminals")
+}
 
-        let (_start, shape) = start_and_shape(&array_shape, None, crop_size)?;
+/// Flags that cannot be set in the config file or the environment
+const NOT_SETTINGS: [&str; 2] = ["no_config", "show_config"];
 
-        assert_eq!(shape[2], 512);
-        assert_eq!(shape[3], 512);
+/// The command line, with the flags it leaves out set from `PEEKZARR_*` variables
+/// or else the config file, the matches it was parsed from and those settings
+fn parse_cli() -> Result<(Cli, ArgMatches, Vec<Setting>)> {
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let command = Cli::command();
+    let matches = command.clone().get_matches_from(&args);
+    let path = match matches.get_flag("no_config") {
+        true => None,
+        false => config::config_path(),
+    };
+    let table = match &path {
+        Some(path) => config::read_config(path)?,
+        None => None,
+    };
+    let config = path.as_deref().zip(table.as_ref());
+    let env = config::env_vars();
+    let settings = config::settings(&command, &matches, &NOT_SETTINGS, &env, config)?;
+    let matches = command.get_matches_from(config::with_settings(&args, &settings));
+    let mut cli = Cli::from_arg_matches(&matches)?;
+    cli.image_path = cli.image_paths.first().cloned().unwrap_or_default();
+    if let Some(clip) = cli.clip {
+        (cli.low, cli.high) = clip.quantiles();
+    }
+    normalizer::check_quantiles(cli.low, cli.high)?;
+    normalizer::check_range(cli.min, cli.max)?;
+    Ok((cli, matches, settings))
+}
 
-        Ok(())
+/// Where the config file is read from, for `--show-config`
+fn config_header(cli: &Cli) -> String {
+    match (cli.no_config, config::config_path()) {
+        (true, _) => "# Config file ignored with --no-config".to_string(),
+        (false, None) => "# No config file, neither XDG_CONFIG_HOME nor HOME is set".to_string(),
+        (false, Some(path)) if path.is_file() => format!("# Config file {}", path.display()),
+        (false, Some(path)) => format!("# Config file {} (not found)", path.display()),
     }
+}
 
-    #[test]
-    fn test_array_shape_validation_at_least_2d() -> Result<()> {
-        let array_shape = vec![256]; // Only 1D
+/// Exit code of a failure: 3 to 7 for those told apart, 1 for any other
+/// (invalid arguments exit with 2 before anything is run)
+fn exit_code(err: &anyhow::Error) -> u8 {
+    match error::find(err) {
+        Some(Error::StoreNotFound { .. }) => 3,
+        Some(Error::ArrayNotFound { .. }) => 4,
+        Some(Error::UnsupportedDataType { .. }) => 5,
+        Some(Error::InvalidSlice(_)) => 6,
+        Some(Error::Io(_)) => 7,
+        None if err.chain().any(|cause| cause.is::<std::io::Error>()) => 7,
+        None => 1,
+    }
+}
 
-        let result = start_and_shape(&array_shape, None, 256);
-        assert!(result.is_err(), "Should reject 1D arrays");
+/// Whether the error is stdout closed early, e.g. piped into `head`, which ends the output
+/// the reader asked for rather than failing
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    let broken = |io: &std::io::Error| io.kind() == std::io::ErrorKind::BrokenPipe;
+    match error::find(err) {
+        Some(Error::Io(io)) => broken(io),
+        _ => err
+            .chain()
+            .any(|cause| cause.downcast_ref::<std::io::Error>().is_some_and(broken)),
+    }
+}
 
-        Ok(())
+fn main() -> ExitCode {
+    match run() {
+        Err(err) if is_broken_pipe(&err) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(exit_code(&err))
+        }
+        _ => ExitCode::SUCCESS,
     }
+}
 
-    #[test]
-    fn test_slice_index_out_of_bounds() -> Result<()> {
-        let array_shape = vec![5, 3, 256, 256];
-        let slice_indices = vec![10]; // Out of bounds for dimension 0 (size 5)
+fn run() -> Result<()> {
+    let (cli, matches, settings) = parse_cli()?;
+    verbosity::set(Verbosity::from_flags(cli.quiet, cli.verbose));
+    if cli.show_config {
+        println!("{}", config_header(&cli));
+        println!(
+            "{}",
+            config::format_settings(&Cli::command(), &matches, &NOT_SETTINGS, &settings)
+        );
+        return Ok(());
+    }
+    if let Some(shell) = cli.generate_completions {
+        clap_complete::generate(
+            shell,
+            &mut Cli::command(),
+            "peekzarr",
+            &mut std::io::stdout(),
+        );
+        return Ok(());
+    }
+    if cli.timings {
+        timings::enable();
+    }
+    if cli.diagnose_terminal {
+        println!("{}", terminal::diagnose(cli.protocol));
+        return Ok(());
+    }
+    // Forced protocols are checked before anything is read, detection is left to viuer
+    if !matches!(cli.protocol, Protocol::Auto | Protocol::Blocks) {
+        cli.protocol
+            .check(&Support::detect(), &terminal::terminal_name())?;
+    }
+    let paths = match cli.from_stdin {
+        true => read_paths(std::io::stdin().lock())?,
+        false => cli.image_paths.clone(),
+    };
+    let paths = store::expand_globs(&paths)?;
+    if cli.watch.is_some() && paths.len() > 1 {
+        anyhow::bail!("Cannot watch several images, give one image path");
+    }
+    if cli.compare {
+        return compare_images(&cli, &paths);
+    }
+    if cli.diff.is_some() || cli.diff_t.is_some() {
+        let [path] = paths.as_slice() else {
+            anyhow::bail!("Cannot take the difference of several images, give one image path");
+        };
+        return diff_images(&Cli {
+            image_path: path.clone(),
+            ..cli
+        });
+    }
+    if (cli.interactive || cli.adjust) && paths.len() > 1 {
+        anyhow::bail!("Cannot step through several images, give one image path");
+    }
+    match paths.as_slice() {
+        [] => anyhow::bail!("No image paths on stdin"),
+        [path] if cli.output_dir.is_none() => show(&Cli {
+            image_path: path.clone(),
+            ..cli
+        }),
+        paths => show_each(&cli, paths),
+    }
+}
 
-        let result = start_and_shape(&array_shape, Some(&slice_indices), 256);
-        assert!(result.is_err(), "Should reject out-of-bounds indices");
+/// The non-empty lines of `reader`, trimmed
+fn read_paths(reader: impl std::io::BufRead) -> Result<Vec<String>> {
+    let mut paths = vec![];
+    for line in reader.lines() {
+        let line = line.context("Cannot read image paths from stdin")?;
+        if !line.trim().is_empty() {
+            paths.push(line.trim().to_string());
+        }
+    }
+    Ok(paths)
+}
 
-        Ok(())
+/// The file in `dir` each image is written to, named after its path,
+/// with a number added to names taken by an earlier image
+fn output_files(
+    dir: &std::path::Path,
+    paths: &[String],
+    extension: &str,
+) -> Vec<std::path::PathBuf> {
+    let mut taken = HashSet::new();
+    paths
+        .iter()
+        .map(|path| {
+            let name = store::image_name(path);
+            let mut file = format!("{}.{}", name, extension);
+            for n in 2.. {
+                if taken.insert(file.clone()) {
+                    break;
+                }
+                file = format!("{}-{}.{}", name, n, extension);
+            }
+            dir.join(file)
+        })
+        .collect()
+}
+
+/// Show each of several images in turn with its caption, going on past those that fail;
+/// with --shared-contrast all slices are read first and shown at one window
+fn show_each(cli: &Cli, paths: &[String]) -> Result<()> {
+    let files = [
+        ("--output", cli.output.is_some()),
+        ("--export-raw", cli.export_raw.is_some()),
+        ("--export-hist", cli.export_hist.is_some()),
+        ("--export-profile", cli.export_profile.is_some()),
+    ];
+    if let Some((flag, _)) = files.iter().find(|(_, given)| *given) {
+        anyhow::bail!(
+            "Cannot write {} for several images, give one image path",
+            flag
+        );
+    }
+    let outputs = match &cli.output_dir {
+        Some(dir) => {
+            let extension = if cli.animate.is_some() { "gif" } else { "png" };
+            output_files(dir, paths, extension)
+                .into_iter()
+                .map(Some)
+                .collect()
+        }
+        None => vec![None; paths.len()],
+    };
+    let image_cli = |i: usize| Cli {
+        image_path: paths[i].clone(),
+        output: outputs[i].clone(),
+        no_display: cli.no_display || cli.output_dir.is_some(),
+        ..cli.clone()
+    };
+    let progress = |i: usize, path: &String| info!("[{}/{}] {}", i + 1, paths.len(), path);
+    let mut failed = vec![];
+    let mut fail = |path: &String, err: anyhow::Error| {
+        warn!("Error: {:?}", err);
+        failed.push(path.clone());
+    };
+    // Modes not showing one slice keep their own meaning of --shared-contrast,
+    // e.g. over the tiles of a montage
+    let modes = [
+        cli.list,
+        cli.tree,
+        cli.info,
+        cli.validate,
+        cli.check_pyramid,
+        cli.storage_stats,
+        cli.label == Some(None),
+        cli.dry_run,
+        cli.plate_overview,
+        cli.ortho,
+        cli.montage.is_some(),
+        cli.animate.is_some(),
+        cli.slices.is_some(),
+        all_slice_montage(cli)?.is_some(),
+    ];
+    if cli.shared_contrast && !modes.contains(&true) {
+        if cli.channels.is_some() {
+            anyhow::bail!("Cannot share one contrast window between composites of several images");
+        }
+        let mut reads = vec![];
+        for (i, path) in paths.iter().enumerate() {
+            progress(i, path);
+            let image_cli = image_cli(i);
+            let read = (image_cli.output.iter())
+                .try_for_each(|output| snapshot::check_output(output, cli.force).map(|_| ()))
+                .and_then(|_| check_read_limit(&image_cli))
+                .and_then(|_| open_thumbnail_store(&image_cli))
+                .and_then(|writable| Ok((read_image(&image_cli)?, writable)));
+            match read {
+                Result::Ok((read, writable)) => reads.push((image_cli, read, writable)),
+                Err(err) => fail(path, err),
+            }
+        }
+        let planes: Vec<_> = reads
+            .iter()
+            .map(|(_, read, _)| read.views[0].data.view())
+            .collect();
+        let shared = match reads.first() {
+            Some((first_cli, read, _)) => shared_cli(first_cli, &read.views[0], &planes)?,
+            None => None,
+        };
+        for (image_cli, read, writable) in reads {
+            let path = image_cli.image_path.clone();
+            let image_cli = match &shared {
+                Some(shared) => Cli {
+                    image_path: image_cli.image_path,
+                    output: image_cli.output,
+                    ..shared.clone()
+                },
+                None => image_cli,
+            };
+            if let Err(err) = show_read(&image_cli, read, writable) {
+                fail(&path, err);
+            }
+        }
+    } else {
+        for (i, path) in paths.iter().enumerate() {
+            progress(i, path);
+            if let Err(err) = show(&image_cli(i)) {
+                fail(path, err);
+            }
+        }
+    }
+    info!(
+        "{} of {} images shown, {} failed",
+        paths.len() - failed.len(),
+        paths.len(),
+        failed.len()
+    );
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "Failed to show {} of {} images: {}",
+            failed.len(),
+            paths.len(),
+            failed.join(", ")
+        );
     }
+    Ok(())
+}
 
-    #[test]
-    fn test_too_many_slice_indices() -> Result<()> {
-        let array_shape = vec![5, 3, 256, 256]; // 2 dimensions to slice (T and C)
-        let slice_indices = vec![0, 1, 2, 3]; // 4 indices, but only 2 allowed
+/// Show the image at `cli.image_path` as the flags ask
+fn show(cli: &Cli) -> Result<()> {
+    if cli.list {
+        return list_image(cli);
+    }
+    if cli.tree {
+        return tree_image(cli);
+    }
+    if cli.info {
+        return info_image(cli);
+    }
+    if cli.validate {
+        return validate_image(cli);
+    }
+    if cli.check_pyramid {
+        return check_pyramid_image(cli);
+    }
+    if cli.storage_stats {
+        return storage_stats_image(cli);
+    }
+    if cli.label == Some(None) {
+        return list_labels(cli);
+    }
+    // Checked before anything is read, movies are checked when animating
+    if let Some(path) = cli.output.as_ref().filter(|_| cli.animate.is_none()) {
+        snapshot::check_output(path, cli.force)?;
+    }
+    if let Some(path) = &cli.export_raw {
+        export::check_raw_output(path, cli.force)?;
+    }
+    for path in [&cli.export_hist, &cli.export_profile]
+        .into_iter()
+        .flatten()
+    {
+        export::check_csv_output(path, cli.force)?;
+    }
+    let writable = open_thumbnail_store(cli)?;
+    if let Some((montage_cli, axis)) = all_slice_montage(cli)? {
+        return montage_image(&montage_cli, &axis);
+    }
+    if cli.dry_run {
+        let plan = plan_read(cli)?;
+        if cli.json {
+            return print_json("read", plan.to_json());
+        }
+        println!("{}", plan);
+        return Ok(());
+    }
+    if cli.plate_overview {
+        return plate_overview(cli);
+    }
+    if let Some(axis) = &cli.montage {
+        return montage_image(cli, axis);
+    }
+    if cli.ortho {
+        return ortho_image(cli);
+    }
+    if let Some(axis) = &cli.animate {
+        return animate_image(cli, axis);
+    }
+    if let Some(specs) = &cli.slices {
+        return sequence_image(cli, specs);
+    }
+    if let Some(seconds) = cli.watch {
+        return watch_image(cli, seconds);
+    }
+    if cli.interactive || cli.adjust {
+        return interactive_image(cli);
+    }
+    check_read_limit(cli)?;
+    let read = read_image(cli)?;
+    show_read(cli, read, writable)
+}
 
-        let result = start_and_shape(&array_shape, Some(&slice_indices), 256);
-        assert!(result.is_err(), "Should reject too many slice indices");
+/// The store to write the thumbnail of --write-thumbnail to,
+/// opened to refuse read-only backends before anything is read
+fn open_thumbnail_store(
+    cli: &Cli,
+) -> Result<Option<Arc<dyn zarrs::storage::ReadableWritableListableStorageTraits>>> {
+    cli.write_thumbnail
+        .as_ref()
+        .map(|_| store::open_writable_store(&cli.image_path, &cli.store_options()))
+        .transpose()
+}
 
-        Ok(())
+/// Fail with --max-read-mb if the slice would read more from the store
+fn check_read_limit(cli: &Cli) -> Result<()> {
+    let Some(limit) = cli.max_read_mb else {
+        return Ok(());
+    };
+    let plan = plan_read(cli)?;
+    let estimate = plan.estimated_bytes();
+    if estimate > limit.saturating_mul(1 << 20) {
+        anyhow::bail!(
+            "Reading the slice would fetch about {} from {} chunks of {}, above \
+             --max-read-mb {}; narrow it with --roi or --crop-size, pick a coarser --level, \
+             or raise the limit (see --dry-run)",
+            storagestats::format_bytes(estimate),
+            plan.chunks * plan.planes,
+            plan.path,
+            limit
+        );
     }
+    Ok(())
+}
 
-    #[test]
-    fn test_quantile_computation() -> Result<()> {
-        use ndarray_stats::interpolate::Nearest;
-        use ndarray_stats::QuantileExt;
-        use noisy_float::types::n64;
+/// Print the slice read, or write it out, with everything drawn over it,
+/// and its thumbnail to the `writable` store with --write-thumbnail
+fn show_read(
+    cli: &Cli,
+    mut read: Image,
+    writable: Option<Arc<dyn zarrs::storage::ReadableWritableListableStorageTraits>>,
+) -> Result<()> {
+    if let Some(path) = &cli.export_raw {
+        export::write_raw(&read.views[0], path, cli.force)?;
+        let csv = cli.export_hist.is_some() || cli.export_profile.is_some();
+        if cli.no_display && cli.output.is_none() && cli.write_thumbnail.is_none() && !csv {
+            return Ok(());
+        }
+    }
+    let quantiles = [cli.low, 0.5, cli.high];
+    if cli.json && read.colors.is_some() {
+        let channels = read
+            .views
+            .iter()
+            .map(|view| {
+                let mut stats = stats_json(view, &quantiles)?;
+                stats["channel"] = view.channel_index().into();
+                stats["window"] = window_json(cli, view)?;
+                Ok(stats)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        return print_json("channels", channels.into());
+    }
+    if cli.json {
+        let mut stats = stats_json(&read.views[0], &quantiles)?;
+        stats["window"] = window_json(cli, &read.views[0])?;
+        if let Some(span) = &read.projection {
+            stats["projection"] = span.to_json();
+        }
+        return print_json("stats", stats);
+    }
+    let finite = |view: &SliceView| view.data.iter().any(|value| value.is_finite());
+    if !read.views.iter().any(finite) {
+        anyhow::bail!(
+            "Nothing to show in the slice of {}: its values are all NaN or infinite, \
+             e.g. a masked out region, try another slice or crop",
+            cli.image_path
+        );
+    }
+    if !cli.no_header {
+        cli.print_text(header::format_header(&read.views[0]));
+    }
+    let caption = if cli.no_caption {
+        None
+    } else {
+        let view = &read.views[0];
+        let labels = read.colors.is_none() && (cli.label_mode || view.is_label());
+        let window = (!labels).then(|| display_window(cli, view)).transpose()?;
+        let caption = Caption {
+            projection: read.projection.clone(),
+            updated: cli.updated.clone(),
+            ..Caption::new(&cli.image_path, view, window, cli.scale)
+        };
+        Some(match &cli.caption_template {
+            Some(template) => caption.format(template)?,
+            None => caption.to_string(),
+        })
+    };
+    let extent = read.views[0].extent();
+    let profiles = cli
+        .profile
+        .map(|line| {
+            read.views
+                .iter()
+                .map(|view| Profile::new(view, line))
+                .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?;
+    if let (Some(profiles), Some(path)) = (&profiles, &cli.export_profile) {
+        let names: Vec<String> = match read.colors {
+            Some(_) => read
+                .views
+                .iter()
+                .map(|view| format!("c{}", view.channel_index()))
+                .collect(),
+            None => vec!["value".to_string()],
+        };
+        export::write_csv(path, &profile::format_csv(profiles, &names), cli.force)?;
+        info!("Wrote the profile to {}", path.display());
+    }
+    if let Some(path) = &cli.export_hist {
+        let histograms: Vec<_> = read
+            .views
+            .iter()
+            .filter_map(|view| {
+                Histogram::new(&view.data, histogram::BINS, view.is_integer())
+                    .map(|histogram| (view.channel_index(), histogram))
+            })
+            .collect();
+        export::write_csv(path, &histogram::format_csv(&histograms), cli.force)?;
+        info!("Wrote the histogram to {}", path.display());
+    }
+    let chunk_grid = read
+        .chunks
+        .as_ref()
+        .map(|info| ChunkGrid::new(info, extent));
+    let histograms = cli
+        .histogram
+        .then(|| format_histograms(cli, &read))
+        .transpose()?;
+    let stats = cli.stats.then(|| format_stats(cli, &read)).transpose()?;
+    let read_dim = read.views[0].data.dim();
+    if let Some(aspect) = read.views[0]
+        .pixel_aspect()
+        .filter(|_| !cli.no_aspect_correction)
+    {
+        for view in &mut read.views {
+            let filter = cli.filter(view);
+            let data = peekzarr::correct_aspect(&view.data, aspect, cli.size_limits(), filter);
+            view.set_resampled(data, filter);
+        }
+        // Ids of labels and planes are never blended
+        let nearest = |data: Array2<f32>| {
+            peekzarr::correct_aspect(&data, aspect, cli.size_limits(), Filter::Nearest)
+        };
+        read.overlay = read.overlay.map(nearest);
+        read.positions = read.positions.map(nearest);
+    }
+    if let Some(zoom) = cli.zoom {
+        let factor =
+            peekzarr::zoom_factor(read.views[0].data.dim(), zoom as f64, cli.size_limits());
+        for view in &mut read.views {
+            let filter = peekzarr::zoom_filter(factor, cli.filter(view));
+            view.set_resampled(peekzarr::zoom(&view.data, factor, filter), filter);
+        }
+        let nearest = |data: Array2<f32>| peekzarr::zoom(&data, factor, Filter::Nearest);
+        read.overlay = read.overlay.map(nearest);
+        read.positions = read.positions.map(nearest);
+    }
+    let orientation = cli.orientation();
+    if !orientation.is_identity() {
+        for view in &mut read.views {
+            view.orient(&orientation);
+        }
+        read.overlay = read.overlay.map(|labels| orientation.apply(&labels));
+        read.positions = read
+            .positions
+            .map(|positions| orientation.apply(&positions));
+    }
+    let view = &read.views[0];
+    let mut image = render(cli, &read)?;
+    let nan_mask = read.nan_mask();
+    if let Some(mask) = &nan_mask {
+        // Transparent pixels are cut out at the end, once everything is drawn
+        let color = match cli.nan_color {
+            NanColor::Color(color) => color,
+            NanColor::Transparent => [0, 0, 0],
+        };
+        let mut rgb = image.to_rgb8();
+        for ((row, column), _) in mask.indexed_iter().filter(|(_, &nan)| nan) {
+            rgb.put_pixel(column as u32, row as u32, image::Rgb(color));
+        }
+        image = DynamicImage::ImageRgb8(rgb);
+    }
+    if let Some(overlay) = &read.overlay {
+        let mut rgb = image.to_rgb8();
+        labels::overlay_labels(&mut rgb, overlay, cli.overlay_alpha, cli.label_seed);
+        image = DynamicImage::ImageRgb8(rgb);
+    }
+    let profile = profiles.as_ref().and_then(|profiles| profiles.first());
+    if chunk_grid.is_some() || !read.probes.is_empty() || profile.is_some() {
+        // Marks placed by array coordinates are drawn on the plane as it was decoded
+        let mut rgb = orientation.invert_image(&image.to_rgb8());
+        if let Some(grid) = &chunk_grid {
+            grid.draw(&mut rgb);
+        }
+        for (probe, _) in &read.probes {
+            probe.draw(&mut rgb, extent);
+        }
+        if let Some(profile) = profile {
+            profile.draw(&mut rgb, extent);
+        }
+        image = DynamicImage::ImageRgb8(orientation.apply_image(&rgb));
+    }
+    if cli.scalebar != ScaleBar::Off {
+        match displayed_pixel_size(cli, view, read_dim) {
+            Some(pixel_size) => {
+                let mut rgb = image.to_rgb8();
+                let drawn = scalebar::draw_scale_bar(&mut rgb, pixel_size, cli.scalebar);
+                match (drawn, cli.scalebar) {
+                    (Some(_), _) => {}
+                    (None, ScaleBar::Length(_)) => {
+                        warn!("The scale bar does not fit in the image, skipping it")
+                    }
+                    (None, _) => info!("The scale bar does not fit in the image, skipping it"),
+                }
+                image = DynamicImage::ImageRgb8(rgb);
+            }
+            None if matches!(cli.scalebar, ScaleBar::Length(_)) => {
+                warn!("No pixel size in the metadata, skipping the scale bar");
+            }
+            None => {}
+        }
+    }
+    if cli.colorbar != Colorbar::Off {
+        if read.colors.is_some() || cli.label_mode || view.is_label() {
+            warn!("No colorbar for composites and label images, skipping it");
+        } else {
+            let vertical = match cli.colorbar {
+                Colorbar::Auto => {
+                    // Terminal cells are two pixels tall
+                    let (columns, rows) = viuer::terminal_size();
+                    let room = columns as f64 / (2.0 * rows as f64);
+                    (image.width() as f64) < room * image.height() as f64
+                }
+                colorbar => colorbar == Colorbar::Vertical,
+            };
+            let bar = match read
+                .projection
+                .as_ref()
+                .filter(|_| read.positions.is_some())
+            {
+                // The colors of the planes, from the first to the last
+                Some(span) => {
+                    let colormap = span.colormap.unwrap_or(span.projection.default_colormap());
+                    let colors = projection::plane_colors(span.end - span.start, colormap);
+                    let (first, last) = span.plane_labels(view);
+                    colorbar::append_labelled_colorbar(
+                        &image.to_rgb8(),
+                        &colors,
+                        [&first, "", &last],
+                        vertical,
+                    )
+                }
+                None => {
+                    let window = display_window(cli, view)?;
+                    let cuts = (window.min, window.max);
+                    let lut = display_lut(cli, view, cuts);
+                    colorbar::append_colorbar(&image.to_rgb8(), &lut, cuts, cli.scale, vertical)
+                }
+            };
+            image = DynamicImage::ImageRgb8(bar);
+        }
+    }
+    let transparent = cli.nan_color == NanColor::Transparent && nan_mask.is_some();
+    if let Some(mask) = nan_mask.filter(|_| transparent) {
+        let mut rgba = image.to_rgba8();
+        for ((row, column), _) in mask.indexed_iter().filter(|(_, &nan)| nan) {
+            rgba.get_pixel_mut(column as u32, row as u32).0[3] = 0;
+        }
+        image = DynamicImage::ImageRgba8(rgba);
+    }
+    let conf = viuer::Config {
+        transparent,
+        ..cli.viuer_config()
+    };
+    if let (Some(name), Some(store)) = (&cli.write_thumbnail, &writable) {
+        if view.path == "/" {
+            anyhow::bail!(
+                "{} is an array, open its image group to write a thumbnail into it",
+                cli.image_path
+            );
+        }
+        let thumbnail = thumbnail::thumbnail(&image, cli.thumbnail_size);
+        let group = peekzarr::ngff::parent_path(&view.path);
+        let path =
+            thumbnail::write_thumbnail(store, group, name, &thumbnail, &view.path, cli.force)?;
+        info!(
+            "Wrote a {}x{} thumbnail to {}",
+            thumbnail.width(),
+            thumbnail.height(),
+            path
+        );
+    }
+    timings::time(Stage::Rendering, || {
+        show_image(cli, &image, &conf, caption.as_deref())
+    })?;
+    if let Some(grid) = chunk_grid {
+        cli.print_text(grid);
+    }
+    for (probe, values) in &read.probes {
+        let values: Vec<String> = match read.colors {
+            // Labelled with the channel of each value
+            Some(_) => values
+                .iter()
+                .zip(&read.views)
+                .map(|(value, view)| {
+                    let channel = view
+                        .channel_name()
+                        .unwrap_or_else(|| format!("c={}", view.channel_index()));
+                    format!("{} ({})", value, channel)
+                })
+                .collect(),
+            None => values.clone(),
+        };
+        cli.print_text(format!(
+            "Probe y={} x={}: {}",
+            probe.y,
+            probe.x,
+            values.join(", ")
+        ));
+    }
+    if let Some(profiles) = profiles {
+        let (columns, _) = viuer::terminal_size();
+        for (profile, view) in profiles.iter().zip(&read.views) {
+            if read.colors.is_some() {
+                cli.print_text(format!("Channel {}:", view.channel_index()));
+            }
+            cli.print_text(profile.format(columns as usize));
+        }
+    }
+    if let Some(histograms) = histograms {
+        cli.print_text(histograms);
+    }
+    if let Some(stats) = stats {
+        cli.print_text(stats);
+    }
+    if cli.timings {
+        eprintln!("{}", timings::format_report());
+    }
+    Ok(())
+}
 
-        let data =
-            Array2::from_shape_vec((10, 10), (0..100).map(|i| i as f32).collect::<Vec<_>>())?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let q_low = data
-            .flatten()
-            .quantile_axis_skipnan_mut(ndarray::Axis(0), n64(0.1), &Nearest)?
-            .into_scalar();
+    #[test]
+    fn test_cli_read_options() {
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "-s", "0,2", "--concurrency", "3"]);
+        let options = cli.read_options();
+        assert_eq!(
+            options.slice_indices,
+            Some(vec![
+                SliceIndex::Position(0.into()),
+                SliceIndex::Position(2.into())
+            ])
+        );
+        assert_eq!(options.concurrency, 3);
+        assert!(options.consolidated);
+        assert!(options.partial_decode);
+        assert_eq!(cli.cache_mb, 512);
+        assert!(Cli::try_parse_from(["peekzarr", "image.zarr", "--concurrency", "0"]).is_err());
+
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--slice", "t=12,c=1"]);
+        assert_eq!(
+            cli.read_options().slice_indices,
+            Some(vec![
+                SliceIndex::Axis("t".to_string(), 12.into()),
+                SliceIndex::Axis("c".to_string(), 1.into())
+            ])
+        );
+        assert!(Cli::try_parse_from(["peekzarr", "image.zarr", "--slice-indices", "0,1"]).is_ok());
 
-        let q_high = data
-            .flatten()
-            .quantile_axis_skipnan_mut(ndarray::Axis(0), n64(0.9), &Nearest)?
-            .into_scalar();
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--label"]);
+        assert_eq!(cli.label, Some(None));
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--label", "cells"]);
+        assert_eq!(cli.read_options().label.as_deref(), Some("cells"));
 
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--overlay-label", "nuclei"]);
+        assert_eq!(cli.overlay_alpha, 0.4);
         assert!(
-            q_low < q_high,
-            "Low quantile should be less than high quantile"
+            Cli::try_parse_from(["peekzarr", "x", "--overlay-label", "a", "--label", "b"]).is_err()
         );
-        assert!(q_low >= 0.0, "Quantiles should be non-negative");
-        assert!(q_high <= 99.0, "High quantile should be reasonable");
+        assert!(Cli::try_parse_from(["peekzarr", "x", "--overlay-alpha", "1.5"]).is_err());
+        assert!(Cli::try_parse_from(["peekzarr", "x", "--level", "1", "-a", "/1"]).is_err());
 
-        Ok(())
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--channels"]);
+        assert_eq!(cli.channels, Some(vec![]));
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--channels", "0:magenta,2"]);
+        let channels = cli.channels.unwrap_or_default();
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].color, Some([255, 0, 255]));
+
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--cmap", "viridis"]);
+        assert_eq!(cli.cmap, Some(Colormap::Viridis));
+        assert!(Cli::try_parse_from(["peekzarr", "image.zarr", "--cmap", "jet"]).is_err());
+        let cli = Cli::parse_from(["peekzarr", "x", "--cmap", "Grey", "--norm", "minmax"]);
+        assert_eq!(cli.cmap, Some(Colormap::Gray));
+        assert_eq!(cli.norm, Some(Norm::MinMax));
+        let cli = Cli::parse_from([
+            "peekzarr",
+            "x",
+            "--filter",
+            "lanczos3",
+            "--protocol",
+            "iterm",
+        ]);
+        assert_eq!(cli.filter, Some(Filter::Lanczos3));
+        assert_eq!(cli.protocol, Protocol::Iterm);
+        let cli = Cli::parse_from([
+            "peekzarr",
+            "image.zarr",
+            "--label-mode",
+            "--label-seed",
+            "7",
+        ]);
+        assert!(cli.label_mode);
+        assert_eq!(cli.label_seed, 7);
+        assert!(!cli.histogram);
+        assert_eq!(cli.gamma, 1.0);
+        assert_eq!(cli.scale, Scale::Linear);
+        let cli = Cli::parse_from(["peekzarr", "x", "--min", "-10", "--max", "65535"]);
+        assert_eq!((cli.min, cli.max), (Some(-10.0), Some(65535.0)));
+        for gamma in ["0", "-1", "inf"] {
+            assert!(Cli::try_parse_from(["peekzarr", "image.zarr", "--gamma", gamma]).is_err());
+        }
+        assert_eq!(cli.colorbar, Colorbar::Off);
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--colorbar"]);
+        assert_eq!(cli.colorbar, Colorbar::Auto);
+        assert_eq!(cli.nan_color, NanColor::Color([255, 0, 255]));
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--nan-color", "transparent"]);
+        assert_eq!(cli.nan_color, NanColor::Transparent);
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--nan-color", "black"]);
+        assert_eq!(cli.nan_color, NanColor::Color([0, 0, 0]));
+        assert!(Cli::try_parse_from(["peekzarr", "image.zarr", "--nan-color", "nope"]).is_err());
+        assert!(Cli::try_parse_from([
+            "peekzarr",
+            "image.zarr",
+            "--no-caption",
+            "--caption-template",
+            "{path}"
+        ])
+        .is_err());
+    }
+
+    fn view(data: Array2<f32>, attributes: serde_json::Value) -> SliceView {
+        SliceView {
+            shape: vec![data.nrows() as u64, data.ncols() as u64],
+            data,
+            path: "/0".to_string(),
+            axes: None,
+            start: vec![0, 0],
+            data_type: "float32".to_string(),
+            attributes: attributes.as_object().cloned().unwrap_or_default(),
+            step: 1,
+            exact: None,
+        }
     }
 
     #[test]
-    fn test_image_normalization() -> Result<()> {
-        let mut data = vec![0.0f32; 256];
-        for (i, val) in data.iter_mut().enumerate() {
-            *val = i as f32;
+    fn test_completions() {
+        let cli = Cli::parse_from(["peekzarr", "--generate-completions", "bash"]);
+        assert_eq!(cli.generate_completions, Some(Shell::Bash));
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut Cli::command(), "peekzarr", &mut script);
+        let script = String::from_utf8_lossy(&script);
+        // Values of the enums are completed
+        assert!(script.contains("gray viridis magma inferno plasma turbo hsv coolwarm"));
+        assert!(script.contains("quantile minmax fixed zscore none equalize clahe"));
+    }
+
+    #[test]
+    fn test_watch_interval() {
+        let cli = Cli::parse_from(["peekzarr", "--watch", "image.zarr"]);
+        assert_eq!(
+            (cli.watch, cli.image_paths.as_slice()),
+            (Some(2.0), &["image.zarr".to_string()][..])
+        );
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--watch=0.5"]);
+        assert_eq!(cli.watch, Some(0.5));
+        for watch in ["--watch=0", "--watch=-1", "--watch=nan"] {
+            let kind = Cli::try_parse_from(["peekzarr", "image.zarr", watch])
+                .err()
+                .map(|error| error.kind());
+            assert_eq!(
+                kind,
+                Some(clap::error::ErrorKind::ValueValidation),
+                "{}",
+                watch
+            );
+        }
+        let cli = Cli::parse_from(["peekzarr", "image.zarr", "--animate", "t", "--fps", "2.5"]);
+        assert_eq!(cli.fps, 2.5);
+        for fps in ["--fps=0", "--fps=-1", "--fps=nan", "--fps=inf"] {
+            let args = ["peekzarr", "image.zarr", "--animate", "t", fps];
+            let kind = Cli::try_parse_from(args).err().map(|error| error.kind());
+            assert_eq!(
+                kind,
+                Some(clap::error::ErrorKind::ValueValidation),
+                "{}",
+                fps
+            );
         }
+        assert!(
+            Cli::try_parse_from(["peekzarr", "image.zarr", "--watch", "--montage", "t"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_compare_flags() {
+        assert!(Cli::try_parse_from(["peekzarr", "a.zarr", "--compare-level", "1"]).is_err());
+        assert!(Cli::try_parse_from([
+            "peekzarr",
+            "a.zarr",
+            "b.zarr",
+            "--compare",
+            "--montage",
+            "t"
+        ])
+        .is_err());
+        let cli = Cli::parse_from(["peekzarr", "a.zarr", "--compare", "--compare-array", "s2"]);
+        assert_eq!(cli.compare_array.as_deref(), Some("s2"));
+    }
 
-        let array = Array2::from_shape_vec((16, 16), data)?;
+    #[test]
+    fn test_clip_flags() {
+        let cli = Cli::parse_from(["peekzarr", "a.zarr", "--clip", "1,99.5"]);
+        assert_eq!(cli.clip.map(|clip| clip.quantiles()), Some((0.01, 0.995)));
+        for args in [
+            &["--clip", "1", "--low", "0.1"][..],
+            &["--clip", "1", "--max", "100"],
+            &["--clip", "60"],
+            &["--high", "99"],
+        ] {
+            let args = ["peekzarr", "a.zarr"].iter().chain(args);
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+    }
 
-        let min = 10.0f32;
-        let max = 245.0f32;
-        let normalized = array.mapv(|x| ((x.clamp(min, max) - min) / (max - min) * 255.0) as u8);
+    #[test]
+    fn test_check_pyramid_flags() {
+        let cli = Cli::parse_from(["peekzarr", "a.zarr", "--check-pyramid"]);
+        assert_eq!(cli.min_ssim, 0.8);
+        assert!(Cli::try_parse_from(["peekzarr", "a.zarr", "--min-ssim", "0.9"]).is_err());
+        let cli = Cli::parse_from(["peekzarr", "a.zarr", "--check-pyramid", "--min-ssim=-0.5"]);
+        assert_eq!(cli.min_ssim, -0.5);
+        for ssim in ["nan", "5", "-3", "inf"] {
+            let args = ["peekzarr", "a.zarr", "--check-pyramid", "--min-ssim", ssim];
+            assert!(Cli::try_parse_from(args).is_err(), "{}", ssim);
+        }
+    }
 
-        let raw_vec = normalized.to_owned().into_raw_vec_and_offset().0;
+    #[test]
+    fn test_diff_flags() {
+        let cli = Cli::parse_from(["peekzarr", "a.zarr", "--diff-t", "5,last"]);
         assert_eq!(
-            raw_vec.len(),
-            256,
-            "Normalized array should have 256 elements"
+            cli.diff_t,
+            Some(vec![SliceValue::Index(5), SliceValue::Last])
         );
+        assert_eq!(cli.diff_threshold, 0.0);
+        let cli = Cli::parse_from([
+            "peekzarr",
+            "a.zarr",
+            "--diff",
+            "b.zarr",
+            "--diff-threshold",
+            "2.5",
+        ]);
+        assert_eq!(cli.diff_threshold, 2.5);
+        for args in [
+            &["--diff", "b.zarr", "--diff-t", "0,1"][..],
+            &["--diff-threshold", "10"],
+            &["--diff", "b.zarr", "--compare"],
+            &["--diff", "b.zarr", "--diff-threshold=-1"],
+            &["--diff", "b.zarr", "--diff-threshold", "nan"],
+        ] {
+            let args = ["peekzarr", "a.zarr"].iter().chain(args);
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+    }
 
-        assert_eq!(raw_vec[0], 0, "Value below min should be 0");
-        assert_eq!(raw_vec[255], 255, "Value above max should be 255");
-
+    #[test]
+    fn test_read_paths() -> Result<()> {
+        let input = "a.zarr\n\n  s3://bucket/b.zarr \r\nc.zip#d\n";
+        assert_eq!(
+            read_paths(input.as_bytes())?,
+            ["a.zarr", "s3://bucket/b.zarr", "c.zip#d"]
+        );
         Ok(())
     }
 
     #[test]
-    fn test_2d_reshape_from_multi_dimensional_array() -> Result<()> {
-        let data: Array4<f32> = Array4::zeros((1, 1, 256, 256)); // (T, C, Y, X)
+    fn test_output_files() {
+        let paths = ["x/fov.zarr", "y/fov.ome.zarr", "z/fov.zarr", "w/0"].map(String::from);
+        let files = output_files(std::path::Path::new("out"), &paths, "png");
+        let names: Vec<_> = files
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            ["out/fov.png", "out/fov-2.png", "out/fov-3.png", "out/0.png"]
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::storage::store::MemoryStore;
 
-        let shape = data.shape();
-        let y = shape[shape.len() - 2];
-        let x = shape[shape.len() - 1];
-        let reshaped = data.to_shape((y, x))?.to_owned();
+        let store = Arc::new(MemoryStore::new());
+        ArrayBuilder::new(
+            vec![2, 4, 4],
+            vec![1, 4, 4],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/0")?
+        .store_metadata()?;
+        ArrayBuilder::new(
+            vec![1, 4, 4],
+            vec![1, 4, 4],
+            DataType::Bool,
+            FillValue::from(false),
+        )
+        .build(store.clone(), "/mask")?
+        .store_metadata()?;
+        let read = |array: &str, slice: &str| {
+            let options = ReadOptions {
+                slice_indices: Some(vec![slice.parse()?]),
+                ..Default::default()
+            };
+            peekzarr::read_slice(store.clone(), Some(array), &options)
+        };
+        let code = |result: Result<Array2<f32>>| exit_code(&result.expect_err("failure"));
+
+        let missing = store::open_store("/nonexistent/image.zarr", &StoreOptions::default());
+        assert_eq!(exit_code(&missing.err().expect("no store")), 3);
+        let error = read("/1", "0").expect_err("no array");
+        assert_eq!(exit_code(&error), 4);
+        assert!(error.to_string().ends_with("the group has: 0, mask"));
+        assert_eq!(code(read("/mask", "0")), 5);
+        assert_eq!(code(read("/0", "5")), 6);
+        assert_eq!(code(read("/0", "z=0")), 6);
 
-        assert_eq!(reshaped.shape(), &[256, 256]);
+        // Physical coordinates that do not fit the axis units are invalid slices too
+        let store = Arc::new(MemoryStore::new());
+        let attributes = serde_json::json!({"multiscales": [{
+            "axes": [
+                {"name": "t", "type": "time", "unit": "second"},
+                {"name": "c", "type": "channel"},
+                {"name": "z", "type": "space", "unit": "micrometer"},
+                {"name": "y"}, {"name": "x"}],
+            "datasets": [{"path": "0", "coordinateTransformations": [
+                {"type": "scale", "scale": [1, 1, 1, 1, 1]}]}]}]});
+        zarrs::group::GroupBuilder::new()
+            .attributes(attributes.as_object().cloned().unwrap_or_default())
+            .build(store.clone(), "/")?
+            .store_metadata()?;
+        ArrayBuilder::new(
+            vec![2, 2, 2, 4, 4],
+            vec![1, 1, 1, 4, 4],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/0")?
+        .store_metadata()?;
+        let read = |slice: &str| {
+            let options = ReadOptions {
+                slice_indices: Some(vec![slice.parse()?]),
+                ..Default::default()
+            };
+            peekzarr::read_slice(store.clone(), Some("/0"), &options)
+        };
+        assert_eq!(code(read("t=1um")), 6);
+        assert_eq!(code(read("c=1um")), 6);
+        let error = read("z=-5um").expect_err("before the first index");
+        assert_eq!(exit_code(&error), 6);
+        assert!(error
+            .to_string()
+            .ends_with("before the first index at 0 µm"));
 
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        let io = anyhow::Error::from(io).context("Failed");
+        assert_eq!(exit_code(&io), 7);
+        assert!(!is_broken_pipe(&io));
+        let pipe = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed");
+        assert!(is_broken_pipe(&anyhow::Error::from(pipe).context("Failed")));
+        assert_eq!(exit_code(&anyhow::anyhow!("Anything else")), 1);
         Ok(())
     }
 
     #[test]
-    fn test_different_dtype_conversions() -> Result<()> {
-        let uint8_val: u8 = 200;
-        let uint16_val: u16 = 50000;
-        let int16_val: i16 = -1000;
-        let float64_val: f64 = 123.456;
-
-        let uint8_f32 = uint8_val as f32;
-        let uint16_f32 = uint16_val as f32;
-        let int16_f32 = int16_val as f32;
-        let float64_f32 = float64_val as f32;
-
-        assert!(uint8_f32 > 0.0);
-        assert!(uint16_f32 > 0.0);
-        assert!(int16_f32 < 0.0);
-        assert!((float64_f32 - 123.456).abs() < 0.01);
+    fn test_read_limit() -> Result<()> {
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+        use zarrs::filesystem::FilesystemStore;
 
+        let directory =
+            std::env::temp_dir().join(format!("peekzarr-read-limit-{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let store = Arc::new(FilesystemStore::new(&directory)?);
+        let array = ArrayBuilder::new(
+            vec![2048, 1024],
+            vec![2048, 1024],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store, "/0")?;
+        array.store_metadata()?;
+        array.store_chunk_elements(&[0, 0], &vec![1u8; 2048 * 1024])?;
+        let path = directory.display().to_string();
+        let cli = |limit: &str| Cli {
+            image_path: path.clone(),
+            ..Cli::parse_from(["peekzarr", &path, "--max-read-mb", limit])
+        };
+        let limited = |limit: &str| check_read_limit(&cli(limit));
+        assert!(limited("1").is_err());
+        assert!(limited("3").is_ok());
+        // Limits too large to count in bytes saturate instead of wrapping to 0
+        assert!(limited(&(1u64 << 44).to_string()).is_ok());
+        let limit = cli(&u64::MAX.to_string());
+        assert_eq!(limit.max_read_mb, Some(u64::MAX));
+        assert!(check_read_limit(&limit).is_ok());
+        std::fs::remove_dir_all(directory)?;
         Ok(())
     }
 
     #[test]
-    fn test_image_to_raw_vector_conversion() -> Result<()> {
-        let normalized = Array2::from_shape_vec((16, 16), vec![128u8; 256])?;
-
-        let raw_vec = normalized.into_raw_vec_and_offset().0;
+    fn test_all_slice_montage() -> Result<()> {
+        let cli = Cli::parse_from(["peekzarr", "x", "-s", "t=3,c=all"]);
+        let (montage_cli, axis) = all_slice_montage(&cli)?.expect("c=all");
+        assert_eq!(axis, "c");
+        assert_eq!(montage_cli.montage.as_deref(), Some("c"));
+        assert_eq!(
+            montage_cli.slice_indices,
+            Some(vec![SliceIndex::Axis("t".to_string(), 3.into())])
+        );
+        let cli = Cli::parse_from(["peekzarr", "x", "-s", "c=all,z=all"]);
+        assert!(all_slice_montage(&cli).is_err());
+        let cli = Cli::parse_from(["peekzarr", "x", "-s", "z=all", "--profile", "row=1"]);
+        let err = all_slice_montage(&cli)
+            .err()
+            .expect("excluded by --montage");
+        assert!(err.to_string().ends_with("with --profile"));
+        assert!(all_slice_montage(&Cli::parse_from(["peekzarr", "x"]))?.is_none());
+        Ok(())
+    }
 
-        assert_eq!(raw_vec.len(), 256);
-        assert!(raw_vec.iter().all(|&v| v == 128));
+    #[test]
+    fn test_invert_order() -> Result<()> {
+        let omero = serde_json::json!({
+            "omero": {"channels": [{"window": {"start": 0, "end": 100}, "color": "00FF00"}]}
+        });
+        let view = view(ndarray::array![[0.0, 25.0, 100.0, 200.0]], omero);
+        // Inverted after the gamma curve: sqrt(0.25) = 0.5
+        let cli = Cli::parse_from(["peekzarr", "x", "--invert", "--gamma", "2"]);
+        assert_eq!(
+            normalize_view(&cli, &view)?.row(0).to_vec(),
+            vec![255, 128, 0, 0]
+        );
+        // Within the omero window, tinted after inverting
+        let image = Image {
+            views: vec![view],
+            colors: None,
+            overlay: None,
+            chunks: None,
+            probes: vec![],
+            projection: None,
+            positions: None,
+        };
+        let rendered = render(&cli, &image)?.to_rgb8();
+        assert_eq!(rendered.get_pixel(0, 0).0, [0, 255, 0]);
+        assert_eq!(rendered.get_pixel(2, 0).0, [0, 0, 0]);
+        // The colormap is reversed: the low cut point takes the top of the colormap
+        let cli = Cli::parse_from(["peekzarr", "x", "--invert", "--cmap", "viridis"]);
+        let rendered = render(&cli, &image)?.to_rgb8();
+        let viridis = Colormap::Viridis.lut();
+        assert_eq!(rendered.get_pixel(0, 0).0, viridis[255]);
+        assert_eq!(rendered.get_pixel(2, 0).0, viridis[0]);
+        let lut = display_lut(&cli, &image.views[0], (0.0, 100.0));
+        assert_eq!(lut[0], viridis[255]);
 
+        // A fixed range replaces the omero window
+        let cli = Cli::parse_from(["peekzarr", "x", "--min", "0", "--max", "200"]);
+        let window = display_window(&cli, &image.views[0])?;
+        assert_eq!(
+            (window.min, window.max, window.source),
+            (0.0, 200.0, peekzarr::WindowSource::Fixed)
+        );
+        assert_eq!(
+            normalize_view(&cli, &image.views[0])?.row(0).to_vec(),
+            vec![0, 31, 127, 255]
+        );
+        let cli = Cli::parse_from(["peekzarr", "x", "--min", "300"]);
+        assert!(display_window(&cli, &image.views[0]).is_err());
+        let cli = Cli::parse_from(["peekzarr", "x", "--min", "0", "--norm", "zscore"]);
+        assert!(normalizer(&cli, &image.views[0]).is_err());
+        let cli = Cli::parse_from(["peekzarr", "x", "--norm", "fixed"]);
+        assert!(normalizer(&cli, &image.views[0]).is_err());
+        // Other normalizations ignore the omero window
+        let cli = Cli::parse_from(["peekzarr", "x", "--norm", "minmax"]);
+        let window = display_window(&cli, &image.views[0])?;
+        assert_eq!((window.min, window.max), (0.0, 200.0));
+        // Equalized values are inverted like the others
+        let cli = Cli::parse_from(["peekzarr", "x", "--norm", "equalize", "--invert"]);
+        assert_eq!(
+            normalize_view(&cli, &image.views[0])?.row(0).to_vec(),
+            vec![255, 170, 85, 0]
+        );
         Ok(())
     }
 }
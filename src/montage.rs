@@ -0,0 +1,263 @@
+use crate::composite::{check_free_axis, read_along_axis, resolve_axis};
+use crate::scalebar::{draw_text, fill, text_width};
+use crate::{ngff, open_array, resolve_array_path, warn, ReadOptions, SliceView};
+use anyhow::Result;
+use image::{Rgb, RgbImage};
+use std::sync::Arc;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// Color of the lines between tiles
+pub const SEPARATOR_COLOR: [u8; 3] = [128, 128, 128];
+
+/// Slices at every index along an axis, to show side by side
+#[derive(Debug)]
+pub struct Montage {
+    pub views: Vec<SliceView>,
+    /// Name of the axis, or its dimension if the axes are not named
+    pub axis: String,
+    /// Whether the axis is the channel axis, with tiles normalized separately by default
+    pub channels: bool,
+    /// Labels of the tiles, the `omero` channel labels along the channel axis
+    /// and the index otherwise, e.g. `z=10`
+    pub labels: Vec<Option<String>>,
+}
+
+/// Every `stride`-th index up to `size`, with a larger stride if that makes more than
+/// `max_tiles`, returning the stride used
+pub fn sampled_indices(size: u64, stride: u64, max_tiles: usize) -> (Vec<u64>, u64) {
+    let max_tiles = max_tiles.max(1) as u64;
+    let stride = stride.max(1).max(size.div_ceil(max_tiles));
+    ((0..size).step_by(stride as usize).collect(), stride)
+}
+
+/// Read the slice at every `stride`-th index along `axis`, a name of the OME-NGFF axes
+/// or a dimension, in at most `max_tiles`, with the other slice indices of `options`
+pub fn read_montage<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+    axis: &str,
+    stride: u64,
+    max_tiles: usize,
+) -> Result<Montage>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let attributes = ngff::group_attributes(&store, ngff::parent_path(&path))?.unwrap_or_default();
+    let shape = open_array(store.clone(), &path)?.shape().to_vec();
+    let ndims = shape.len();
+    let axes = ngff::axis_names(&attributes).filter(|axes| axes.len() == ndims);
+    let (dimension, name) = resolve_axis(&shape, axes.as_deref(), axis, "--montage", "montage")?;
+    check_free_axis(options, dimension, name, "--montage", "montage axis")?;
+    let axis = name.map_or_else(|| dimension.to_string(), str::to_string);
+    let size = shape[dimension];
+    let (indices, used) = sampled_indices(size, stride, max_tiles);
+    if used != stride.max(1) {
+        warn!(
+            "Every {} of {} indices along {} would make more than {} tiles, \
+             showing every {} instead",
+            ordinal(stride.max(1)),
+            size,
+            axis,
+            max_tiles,
+            ordinal(used)
+        );
+    }
+    let views = read_along_axis(store, &path, &shape, options, dimension, name, &indices)?;
+    let channels = axes
+        .as_deref()
+        .and_then(|axes| ngff::channel_axis(&attributes, axes))
+        == Some(dimension);
+    let labels = views
+        .iter()
+        .zip(&indices)
+        .map(|(view, index)| {
+            let label = view.channel_name().filter(|_| channels);
+            Some(label.unwrap_or_else(|| format!("{}={}", axis, index)))
+        })
+        .collect();
+    Ok(Montage {
+        views,
+        axis,
+        channels,
+        labels,
+    })
+}
+
+/// `index`, `2nd`, `3rd`, `10th`... for the stride in messages
+fn ordinal(stride: u64) -> String {
+    let suffix = match (stride % 10, stride % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    match stride {
+        1 => "index".to_string(),
+        _ => format!("{}{}", stride, suffix),
+    }
+}
+
+/// Rows and columns of a near-square grid of `count` tiles
+pub fn grid_shape(count: usize) -> (usize, usize) {
+    let columns = (count as f64).sqrt().ceil().max(1.0) as usize;
+    (count.div_ceil(columns).max(1), columns)
+}
+
+/// Size of tiles of `size` shrunk to fit a grid of `grid` tiles, with lines between them,
+/// in `max_size` along both axes
+pub fn tile_size(size: (usize, usize), grid: (usize, usize), max_size: usize) -> (usize, usize) {
+    let room = |count: usize| max_size.saturating_sub(count - 1) / count;
+    let fit = (room(grid.0) as f64 / size.0 as f64)
+        .min(room(grid.1) as f64 / size.1 as f64)
+        .min(1.0);
+    let shrink = |length: usize| ((length as f64 * fit).floor() as usize).max(1);
+    (shrink(size.0), shrink(size.1))
+}
+
+/// Lay out tiles of the same size in rows of `columns`, with 1-pixel lines between them
+/// and each label in the top left corner of its tile if it fits
+pub fn assemble(tiles: &[RgbImage], labels: &[Option<String>], columns: usize) -> RgbImage {
+    let Some(first) = tiles.first() else {
+        return RgbImage::new(0, 0);
+    };
+    let (width, height) = (first.width() as usize, first.height() as usize);
+    let rows = tiles.len().div_ceil(columns);
+    let mut montage = RgbImage::from_pixel(
+        (columns * (width + 1) - 1) as u32,
+        (rows * (height + 1) - 1) as u32,
+        Rgb(SEPARATOR_COLOR),
+    );
+    // Cells left over in the last row stay black
+    fill(
+        &mut montage,
+        (tiles.len() % columns) * (width + 1),
+        (rows - 1) * (height + 1),
+        (columns - tiles.len() % columns) % columns * (width + 1),
+        height,
+        Rgb([0, 0, 0]),
+    );
+    for (i, tile) in tiles.iter().enumerate() {
+        let (left, top) = ((i % columns) * (width + 1), (i / columns) * (height + 1));
+        image::imageops::replace(&mut montage, tile, left as i64, top as i64);
+        let Some(label) = labels.get(i).and_then(Option::as_ref) else {
+            continue;
+        };
+        let label = label.to_uppercase();
+        let label_width = text_width(&label);
+        if label_width + 2 <= width && height >= 7 {
+            fill(&mut montage, left, top, label_width + 2, 7, Rgb([0, 0, 0]));
+            draw_text(&mut montage, &label, left + 1, top + 1, 1);
+        }
+    }
+    montage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_store;
+    use crate::SliceIndex;
+
+    #[test]
+    fn test_grid() {
+        assert_eq!(grid_shape(1), (1, 1));
+        assert_eq!(grid_shape(3), (2, 2));
+        assert_eq!(grid_shape(4), (2, 2));
+        assert_eq!(grid_shape(5), (2, 3));
+        assert_eq!(grid_shape(10), (3, 4));
+        // Two rows of 100 pixels with a line between them in 101 pixels
+        assert_eq!(tile_size((100, 50), (2, 2), 101), (50, 25));
+        assert_eq!(tile_size((100, 50), (1, 1), 2560), (100, 50));
+        assert_eq!(tile_size((100, 50), (3, 3), 8), (2, 1));
+    }
+
+    #[test]
+    fn test_sampled_indices() {
+        assert_eq!(sampled_indices(5, 2, 64), (vec![0, 2, 4], 2));
+        assert_eq!(sampled_indices(3, 0, 64), (vec![0, 1, 2], 1));
+        // A 1000-plane stack in at most 64 tiles
+        let (indices, stride) = sampled_indices(1000, 10, 64);
+        assert_eq!(stride, 16);
+        assert_eq!(indices.len(), 63);
+        assert_eq!(ordinal(16), "16th");
+        assert_eq!(ordinal(2), "2nd");
+        assert_eq!(ordinal(11), "11th");
+    }
+
+    #[test]
+    fn test_assemble() {
+        let tiles = vec![RgbImage::from_pixel(20, 10, Rgb([255, 0, 0])); 3];
+        let labels = vec![
+            Some("dapi".to_string()),
+            None,
+            Some("a long label".to_string()),
+        ];
+        let montage = assemble(&tiles, &labels, 2);
+        assert_eq!(montage.dimensions(), (41, 21));
+        assert_eq!(montage.get_pixel(20, 0).0, SEPARATOR_COLOR);
+        assert_eq!(montage.get_pixel(0, 10).0, SEPARATOR_COLOR);
+        assert_eq!(montage.get_pixel(21, 5).0, [255, 0, 0]);
+        // The missing fourth tile
+        assert_eq!(montage.get_pixel(30, 15).0, [0, 0, 0]);
+        // A label on a black background, and none where it does not fit
+        assert_eq!(montage.get_pixel(0, 0).0, [0, 0, 0]);
+        assert!((0..7).any(|row| montage.get_pixel(1, row).0 == [255, 255, 255]));
+        assert_eq!(montage.get_pixel(0, 11).0, [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_read_montage() -> Result<()> {
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [{"name": "c", "type": "channel"}, {"name": "z"}, {"name": "y"}, {"name": "x"}],
+                "datasets": [{"path": "0"}]
+            }],
+            "omero": {"channels": [{"label": "DAPI"}, {"label": "GFP"}]}
+        });
+        let store = image_store(attributes, &[2, 3, 4, 4], &[1, 1, 4, 4])?;
+
+        let options = ReadOptions::default();
+        let montage = read_montage(store.clone(), None, &options, "c", 1, 64)?;
+        assert_eq!(montage.axis, "c");
+        assert!(montage.channels);
+        assert_eq!(
+            montage.labels,
+            vec![Some("DAPI".to_string()), Some("GFP".to_string())]
+        );
+        // At the middle of z
+        assert_eq!(montage.views[1].data[[0, 0]], 11.0);
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("c".to_string(), 1.into())]),
+            ..Default::default()
+        };
+        let montage = read_montage(store.clone(), None, &options, "Z", 1, 64)?;
+        assert_eq!(montage.views.len(), 3);
+        assert_eq!(montage.views[2].data[[0, 0]], 12.0);
+        assert!(!montage.channels);
+        assert_eq!(montage.labels[1].as_deref(), Some("z=1"));
+        // Every other plane
+        let montage = read_montage(store.clone(), None, &options, "z", 2, 64)?;
+        let labels: Vec<_> = montage.labels.iter().flatten().collect();
+        assert_eq!(labels, ["z=0", "z=2"]);
+        assert_eq!(montage.views[1].data[[0, 0]], 12.0);
+
+        let error =
+            read_montage(store.clone(), None, &options, "c", 1, 64).expect_err("sliced axis");
+        assert_eq!(
+            error.to_string(),
+            "Cannot combine --montage with a slice index for the montage axis 'c'"
+        );
+        assert!(read_montage(store.clone(), None, &options, "y", 1, 64).is_err());
+        let error = read_montage(store, None, &options, "t", 1, 64).expect_err("no t axis");
+        assert!(error.to_string().contains("the axes are c, z, y, x"));
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::{Context, Result};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::codecs::webp::WebPEncoder;
+use image::{Delay, ExtendedColorType, Frame, RgbImage};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Format of an animated file, from its extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MovieFormat {
+    Gif,
+    WebP,
+}
+
+impl MovieFormat {
+    /// The format of `path` from its extension, `.gif` or `.webp`
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("gif") => Ok(Self::Gif),
+            Some("webp") => Ok(Self::WebP),
+            _ => anyhow::bail!(
+                "Unknown movie format of {}, use a .gif or .webp file",
+                path.display()
+            ),
+        }
+    }
+}
+
+enum Encoder {
+    Gif(GifEncoder<BufWriter<File>>),
+    /// Frames are appended to the RIFF container as they come,
+    /// with its size written in when finished
+    WebP {
+        file: BufWriter<File>,
+        repeat: bool,
+        size: Option<(u32, u32)>,
+    },
+}
+
+/// An animated GIF or WebP file written frame by frame to a temporary file next to it,
+/// which replaces the file once finished and is removed if it is not
+pub struct MovieWriter {
+    encoder: Option<Encoder>,
+    path: PathBuf,
+    temporary: PathBuf,
+    delay: Duration,
+}
+
+impl MovieWriter {
+    /// Start writing to `path` with `delay` between frames, played over and over
+    /// if `repeat` or once otherwise
+    pub fn create(path: &Path, delay: Duration, repeat: bool) -> Result<Self> {
+        let format = MovieFormat::from_path(path)?;
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        let temporary = path.with_file_name(name);
+        let file = BufWriter::new(
+            File::create(&temporary)
+                .with_context(|| format!("Failed to create {}", temporary.display()))?,
+        );
+        let mut writer = Self {
+            encoder: None,
+            path: path.to_path_buf(),
+            temporary,
+            delay,
+        };
+        writer.encoder = Some(match format {
+            MovieFormat::Gif => {
+                let mut encoder = GifEncoder::new_with_speed(file, 10);
+                // Played once without the looping extension
+                if repeat {
+                    encoder.set_repeat(Repeat::Infinite)?;
+                }
+                Encoder::Gif(encoder)
+            }
+            MovieFormat::WebP => Encoder::WebP {
+                file,
+                repeat,
+                size: None,
+            },
+        });
+        Ok(writer)
+    }
+
+    /// Encode the next frame, the same size as the first
+    pub fn add_frame(&mut self, image: &RgbImage) -> Result<()> {
+        let delay = self.delay;
+        match self.encoder.as_mut() {
+            Some(Encoder::Gif(encoder)) => {
+                let rgba = image::DynamicImage::ImageRgb8(image.clone()).to_rgba8();
+                let delay = Delay::from_saturating_duration(delay);
+                encoder.encode_frame(Frame::from_parts(rgba, 0, 0, delay))?;
+            }
+            Some(Encoder::WebP { file, repeat, size }) => {
+                let dimensions = image.dimensions();
+                match size {
+                    Some(size) if *size != dimensions => {
+                        anyhow::bail!("Frames of a movie must all be the same size")
+                    }
+                    Some(_) => {}
+                    None => {
+                        write_webp_header(file, dimensions, *repeat)?;
+                        *size = Some(dimensions);
+                    }
+                }
+                write_webp_frame(file, image, delay)?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Finish the file and move it in place of `path`
+    pub fn finish(mut self) -> Result<()> {
+        match self.encoder.take() {
+            // The trailer is written and the file flushed as the encoder is dropped
+            Some(Encoder::Gif(encoder)) => drop(encoder),
+            Some(Encoder::WebP { mut file, size, .. }) => {
+                if size.is_none() {
+                    anyhow::bail!("No frames to write to {}", self.path.display());
+                }
+                let length = file.stream_position()?;
+                file.seek(SeekFrom::Start(4))?;
+                file.write_all(&(length as u32 - 8).to_le_bytes())?;
+                file.flush()?;
+            }
+            None => {}
+        }
+        std::fs::rename(&self.temporary, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+impl Drop for MovieWriter {
+    fn drop(&mut self) {
+        // Not finished, e.g. after an error
+        if self.encoder.is_some() {
+            self.encoder = None;
+            let _ = std::fs::remove_file(&self.temporary);
+        }
+    }
+}
+
+/// Append a 24-bit little-endian integer, as in the extended WebP chunks
+fn push_u24(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes()[..3]);
+}
+
+/// The RIFF header, with its size filled in when finished, and the `VP8X` and `ANIM`
+/// chunks of an animation on a `width` by `height` canvas
+fn write_webp_header(
+    file: &mut impl Write,
+    (width, height): (u32, u32),
+    repeat: bool,
+) -> Result<()> {
+    let mut bytes = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+    bytes.extend_from_slice(&10u32.to_le_bytes());
+    // Only the animation flag
+    bytes.extend_from_slice(&[0x02, 0, 0, 0]);
+    push_u24(&mut bytes, width - 1);
+    push_u24(&mut bytes, height - 1);
+    bytes.extend_from_slice(b"ANIM");
+    bytes.extend_from_slice(&6u32.to_le_bytes());
+    // A black background, and 0 loops for ever
+    bytes.extend_from_slice(&[0, 0, 0, 255]);
+    bytes.extend_from_slice(&u16::from(!repeat).to_le_bytes());
+    file.write_all(&bytes)?;
+    Ok(())
+}
+
+/// An `ANMF` chunk with the frame encoded losslessly, shown for `delay`
+fn write_webp_frame(file: &mut impl Write, image: &RgbImage, delay: Duration) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let mut still = vec![];
+    WebPEncoder::new_lossless(&mut still).encode(
+        image.as_raw(),
+        width,
+        height,
+        ExtendedColorType::Rgb8,
+    )?;
+    // The VP8L chunk after the 12 bytes of the RIFF header
+    let chunk = &still[12..];
+    let mut bytes = b"ANMF".to_vec();
+    bytes.extend_from_slice(&(16 + chunk.len() as u32).to_le_bytes());
+    push_u24(&mut bytes, 0);
+    push_u24(&mut bytes, 0);
+    push_u24(&mut bytes, width - 1);
+    push_u24(&mut bytes, height - 1);
+    push_u24(&mut bytes, (delay.as_millis() as u32).min(0xff_ffff));
+    // Drawn over the canvas without blending
+    bytes.push(0x02);
+    bytes.extend_from_slice(chunk);
+    file.write_all(&bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::codecs::webp::WebPDecoder;
+    use image::{AnimationDecoder, Rgb};
+    use std::io::BufReader;
+
+    fn frames() -> Vec<RgbImage> {
+        (0..3)
+            .map(|i| RgbImage::from_pixel(6, 4, Rgb([i * 100, 0, 0])))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_movie() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("peekzarr-movie-{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let delay = Duration::from_millis(100);
+        for name in ["movie.gif", "movie.webp"] {
+            let path = directory.join(name);
+            let mut writer = MovieWriter::create(&path, delay, true)?;
+            for frame in frames() {
+                writer.add_frame(&frame)?;
+            }
+            assert!(!path.exists());
+            writer.finish()?;
+            let reader = BufReader::new(File::open(&path)?);
+            let decoded = match name {
+                "movie.gif" => GifDecoder::new(reader)?.into_frames().collect_frames()?,
+                _ => WebPDecoder::new(reader)?.into_frames().collect_frames()?,
+            };
+            assert_eq!(decoded.len(), 3);
+            assert_eq!(decoded[2].buffer().get_pixel(1, 1).0, [200, 0, 0, 255]);
+            assert_eq!(decoded[1].delay(), Delay::from_saturating_duration(delay));
+        }
+        // Removed unless finished
+        let path = directory.join("unfinished.webp");
+        let mut writer = MovieWriter::create(&path, delay, false)?;
+        writer.add_frame(&frames()[0])?;
+        assert!(writer.add_frame(&RgbImage::new(2, 2)).is_err());
+        drop(writer);
+        assert!(!path.exists() && !directory.join("unfinished.webp.tmp").exists());
+        let error = MovieFormat::from_path(Path::new("movie.mp4")).expect_err("mp4");
+        assert!(error.to_string().contains("use a .gif or .webp file"));
+        std::fs::remove_dir_all(directory)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,732 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use zarrs::group::Group;
+use zarrs::node::{meta_key_v2_attributes, NodePath};
+use zarrs::storage::{ReadableStorageTraits, StoreKey};
+
+/// Read the attributes of the group at `path`,
+/// or `None` if there is no group metadata there
+pub fn group_attributes<TStore: ReadableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+    path: &str,
+) -> Result<Option<Map<String, Value>>> {
+    if let Ok(group) = Group::open(store.clone(), path) {
+        return Ok(Some(group.attributes().clone()));
+    }
+    // Some v2 writers omit `.zgroup` and only write `.zattrs`
+    let key = meta_key_v2_attributes(&NodePath::new(path)?);
+    match store.get(&key)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+        None => Ok(None),
+    }
+}
+
+/// OME-NGFF attributes of a group, unwrapping the `ome` namespace used since 0.5
+pub fn ome_attributes(attributes: &Map<String, Value>) -> &Map<String, Value> {
+    attributes
+        .get("ome")
+        .and_then(Value::as_object)
+        .unwrap_or(attributes)
+}
+
+/// An axis of a multiscale image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis {
+    pub name: String,
+    /// `space`, `time`, `channel` or a custom type (0.4+)
+    pub kind: Option<String>,
+    /// UDUNITS-2 unit, e.g. `micrometer` (0.4+)
+    pub unit: Option<String>,
+}
+
+/// A resolution level of a multiscale image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    pub path: String,
+    /// Values of the `scale` coordinate transformation (0.4+)
+    pub scale: Option<Vec<f64>>,
+    /// Values of the `translation` coordinate transformation (0.4+)
+    pub translation: Option<Vec<f64>>,
+}
+
+/// The first multiscale image of a group, normalized across OME-NGFF versions:
+/// attributes under `ome` (0.5) or at the top level (0.4 and earlier),
+/// axes as objects (0.4+) or plain names (0.3)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multiscale {
+    /// Version from `ome.version` (0.5) or the multiscale's `version` (0.4 and earlier)
+    pub version: Option<String>,
+    /// `None` if missing (0.2 and earlier) or malformed
+    pub axes: Option<Vec<Axis>>,
+    /// Resolution levels, finest first, empty if missing or any has no path
+    pub datasets: Vec<Dataset>,
+    /// Scale and translation applying to all of the datasets
+    pub scale: Option<Vec<f64>>,
+    pub translation: Option<Vec<f64>>,
+}
+
+/// Values of the first `scale` and `translation` in `coordinateTransformations` entries
+fn parse_transformations(transformations: Option<&Value>) -> (Option<Vec<f64>>, Option<Vec<f64>>) {
+    let find = |kind: &str| {
+        transformations?
+            .as_array()?
+            .iter()
+            .find(|transformation| {
+                transformation.get("type").and_then(Value::as_str) == Some(kind)
+            })?
+            .get(kind)?
+            .as_array()?
+            .iter()
+            .map(Value::as_f64)
+            .collect::<Option<Vec<f64>>>()
+    };
+    (find("scale"), find("translation"))
+}
+
+fn parse_axis(axis: &Value) -> Option<Axis> {
+    let text = |key: &str| axis.get(key).and_then(Value::as_str).map(str::to_string);
+    match axis {
+        Value::String(name) => Some(Axis {
+            name: name.clone(),
+            kind: None,
+            unit: None,
+        }),
+        _ => Some(Axis {
+            name: text("name")?,
+            kind: text("type"),
+            unit: text("unit"),
+        }),
+    }
+}
+
+/// Parse the first multiscale image of a group, `None` if there is none
+pub fn multiscale(attributes: &Map<String, Value>) -> Option<Multiscale> {
+    let ome = ome_attributes(attributes);
+    let multiscale = ome.get("multiscales")?.as_array()?.first()?;
+    let version = ome
+        .get("version")
+        .filter(|_| attributes.contains_key("ome"))
+        .or_else(|| multiscale.get("version"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let axes = multiscale
+        .get("axes")
+        .and_then(Value::as_array)
+        .and_then(|axes| axes.iter().map(parse_axis).collect());
+    let datasets = multiscale
+        .get("datasets")
+        .and_then(Value::as_array)
+        .and_then(|datasets| {
+            datasets
+                .iter()
+                .map(|dataset| {
+                    let (scale, translation) =
+                        parse_transformations(dataset.get("coordinateTransformations"));
+                    Some(Dataset {
+                        path: dataset.get("path")?.as_str()?.to_string(),
+                        scale,
+                        translation,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let (scale, translation) = parse_transformations(multiscale.get("coordinateTransformations"));
+    Some(Multiscale {
+        version,
+        axes,
+        datasets,
+        scale,
+        translation,
+    })
+}
+
+/// Names of the axes of the first multiscale image,
+/// from `axes` objects (0.4+) or plain strings (0.3)
+pub fn axis_names(attributes: &Map<String, Value>) -> Option<Vec<String>> {
+    let axes = multiscale(attributes)?.axes?;
+    Some(axes.into_iter().map(|axis| axis.name).collect())
+}
+
+/// Paths of the resolution levels of the first multiscale image, finest first
+pub fn dataset_paths(attributes: &Map<String, Value>) -> Option<Vec<String>> {
+    let datasets = multiscale(attributes)?.datasets;
+    (!datasets.is_empty()).then(|| datasets.into_iter().map(|dataset| dataset.path).collect())
+}
+
+/// Path of the group containing the node at `path`
+pub fn parent_path(path: &str) -> &str {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some((parent, _)) if !parent.is_empty() => parent,
+        _ => "/",
+    }
+}
+
+/// Axis names for the array at `array_path` from the multiscales metadata of its parent group
+pub fn array_axis_names<TStore: ReadableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+    array_path: &str,
+) -> Result<Option<Vec<String>>> {
+    Ok(group_attributes(store, parent_path(array_path))?
+        .as_ref()
+        .and_then(axis_names))
+}
+
+/// Units of the axes of the first multiscale image, `None` for axes without one
+pub fn axis_units(attributes: &Map<String, Value>) -> Option<Vec<Option<String>>> {
+    let axes = multiscale(attributes)?.axes?;
+    Some(axes.into_iter().map(|axis| axis.unit).collect())
+}
+
+/// Scale and translation mapping indices of the resolution level at `dataset_path`
+/// to physical coordinates, combining the dataset and multiscale transformations
+pub fn physical_transform(
+    attributes: &Map<String, Value>,
+    dataset_path: &str,
+    ndims: usize,
+) -> Option<(Vec<f64>, Vec<f64>)> {
+    let multiscale = multiscale(attributes)?;
+    let dataset = multiscale
+        .datasets
+        .iter()
+        .find(|dataset| dataset.path == dataset_path)?;
+    // Transformations of the wrong length are ignored
+    let values = |values: &Option<Vec<f64>>, default: f64| match values {
+        Some(values) if values.len() == ndims => values.clone(),
+        _ => vec![default; ndims],
+    };
+    let (scale, translation) = (
+        values(&dataset.scale, 1.0),
+        values(&dataset.translation, 0.0),
+    );
+    let global_scale = values(&multiscale.scale, 1.0);
+    let global_translation = values(&multiscale.translation, 0.0);
+    Some((
+        scale
+            .iter()
+            .zip(&global_scale)
+            .map(|(s, g)| s * g)
+            .collect(),
+        (0..ndims)
+            .map(|i| translation[i] * global_scale[i] + global_translation[i])
+            .collect(),
+    ))
+}
+
+/// Physical quantity measured by a unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Length,
+    Time,
+}
+
+impl std::fmt::Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Length => write!(f, "length"),
+            Self::Time => write!(f, "time"),
+        }
+    }
+}
+
+/// Quantity and size in metres or seconds of a unit,
+/// by OME-NGFF (UDUNITS-2) name or common abbreviation
+pub fn parse_unit(unit: &str) -> Option<(Quantity, f64)> {
+    use Quantity::{Length, Time};
+    let unit = unit.trim().to_lowercase();
+    let parsed = match unit.as_str() {
+        "å" | "angstrom" => (Length, 1e-10),
+        "pm" | "picometer" | "picometre" => (Length, 1e-12),
+        "nm" | "nanometer" | "nanometre" => (Length, 1e-9),
+        "um" | "µm" | "μm" | "micrometer" | "micrometre" | "micron" => (Length, 1e-6),
+        "mm" | "millimeter" | "millimetre" => (Length, 1e-3),
+        "cm" | "centimeter" | "centimetre" => (Length, 1e-2),
+        "m" | "meter" | "metre" => (Length, 1.0),
+        "ps" | "picosecond" => (Time, 1e-12),
+        "ns" | "nanosecond" => (Time, 1e-9),
+        "us" | "µs" | "μs" | "microsecond" => (Time, 1e-6),
+        "ms" | "millisecond" => (Time, 1e-3),
+        "s" | "sec" | "second" => (Time, 1.0),
+        "min" | "minute" => (Time, 60.0),
+        "h" | "hr" | "hour" => (Time, 3600.0),
+        "d" | "day" => (Time, 86400.0),
+        // Plurals, e.g. `seconds` and `microns`
+        plural if plural.len() > 3 && plural.ends_with('s') => {
+            return parse_unit(&plural[..plural.len() - 1])
+        }
+        _ => return None,
+    };
+    Some(parsed)
+}
+
+/// Position of the channel axis among `axes`,
+/// by axis type where declared (0.4+) and by the name `c` otherwise
+pub fn channel_axis(attributes: &Map<String, Value>, axes: &[String]) -> Option<usize> {
+    let typed = multiscale(attributes)
+        .and_then(|multiscale| multiscale.axes)
+        .and_then(|axes| {
+            axes.iter()
+                .position(|axis| axis.kind.as_deref() == Some("channel"))
+        });
+    typed.or_else(|| axes.iter().position(|axis| axis == "c"))
+}
+
+/// Names of the label images listed by the `labels` attribute of a labels group
+pub fn label_names(attributes: &Map<String, Value>) -> Vec<String> {
+    ome_attributes(attributes)
+        .get("labels")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Whether the group is a label image, with `image-label` metadata
+pub fn is_label_image(attributes: &Map<String, Value>) -> bool {
+    ome_attributes(attributes).contains_key("image-label")
+}
+
+/// Layout of an HCS plate from the `plate` metadata
+#[derive(Debug, PartialEq)]
+pub struct Plate {
+    pub rows: Vec<String>,
+    pub columns: Vec<String>,
+    /// Paths of the acquired wells, e.g. `B/03`
+    pub wells: Vec<String>,
+}
+
+/// Parse the `plate` metadata of a group
+pub fn plate(attributes: &Map<String, Value>) -> Option<Plate> {
+    let plate = ome_attributes(attributes).get("plate")?;
+    let names = |key: &str| -> Vec<String> {
+        plate
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.get("name")?.as_str().map(str::to_string))
+            .collect()
+    };
+    let wells = plate
+        .get("wells")?
+        .as_array()?
+        .iter()
+        .filter_map(|well| well.get("path")?.as_str().map(str::to_string))
+        .collect();
+    Some(Plate {
+        rows: names("rows"),
+        columns: names("columns"),
+        wells,
+    })
+}
+
+impl Plate {
+    /// Path of the well named like `B03`, `b3` or `B/03`
+    pub fn find_well(&self, name: &str) -> Option<&str> {
+        let (row, column) = match name.split_once('/') {
+            Some(parts) => parts,
+            None => name.split_at(name.find(|c: char| c.is_ascii_digit())?),
+        };
+        let same_column = |other: &str| match (column.parse::<u64>(), other.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => column == other,
+        };
+        self.wells.iter().map(String::as_str).find(|path| {
+            path.split_once('/').is_some_and(|(well_row, well_column)| {
+                well_row.eq_ignore_ascii_case(row) && same_column(well_column)
+            })
+        })
+    }
+
+    /// Names of the acquired wells, e.g. `B03`
+    pub fn well_names(&self) -> Vec<String> {
+        self.wells
+            .iter()
+            .map(|path| path.replace('/', ""))
+            .collect()
+    }
+
+    /// Grid of the plate marking acquired wells with `x` and missing ones with `.`
+    pub fn format_layout(&self) -> String {
+        let width = self.columns.iter().map(String::len).max().unwrap_or(1);
+        let row_width = self.rows.iter().map(String::len).max().unwrap_or(1);
+        let mut lines = vec![format!(
+            "{:row_width$} {}",
+            "",
+            self.columns
+                .iter()
+                .map(|column| format!("{:>width$}", column))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )];
+        for row in &self.rows {
+            let cells: Vec<String> = self
+                .columns
+                .iter()
+                .map(|column| {
+                    let acquired = self.wells.contains(&format!("{}/{}", row, column));
+                    format!("{:>width$}", if acquired { "x" } else { "." })
+                })
+                .collect();
+            lines.push(format!("{:row_width$} {}", row, cells.join(" ")));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Paths of the images (fields of view) in a well group
+pub fn well_images(attributes: &Map<String, Value>) -> Vec<String> {
+    ome_attributes(attributes)
+        .get("well")
+        .and_then(|well| well.get("images"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|image| image.get("path")?.as_str().map(str::to_string))
+        .collect()
+}
+
+/// A series (image) in a bioformats2raw layout
+#[derive(Debug, PartialEq)]
+pub struct Series {
+    pub path: String,
+    pub name: Option<String>,
+}
+
+/// Version of the bioformats2raw layout declared by the root group attributes
+pub fn bioformats2raw_layout(attributes: &Map<String, Value>) -> Option<u64> {
+    ome_attributes(attributes)
+        .get("bioformats2raw.layout")
+        .and_then(Value::as_u64)
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Names of the `Image` elements in OME-XML, in document order
+pub fn parse_image_names(xml: &str) -> Vec<Option<String>> {
+    let mut names = vec![];
+    let mut rest = xml;
+    while let Some(position) = rest.find("<Image") {
+        rest = &rest[position + "<Image".len()..];
+        // Skip other elements sharing the prefix, e.g. `<ImageRef>`
+        if !rest.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            continue;
+        }
+        let tag = &rest[..rest.find('>').unwrap_or(rest.len())];
+        let name = tag
+            .split_once(" Name=\"")
+            .and_then(|(_, value)| value.split_once('"'))
+            .map(|(value, _)| unescape_xml(value));
+        names.push(name);
+    }
+    names
+}
+
+/// List the series of a bioformats2raw layout,
+/// from the `OME` group's `series` attribute or by probing numbered groups
+pub fn list_series<TStore: ReadableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+) -> Result<Vec<Series>> {
+    let paths: Vec<String> = match group_attributes(store, "/OME")?
+        .as_ref()
+        .map(ome_attributes)
+        .and_then(|attributes| attributes.get("series"))
+        .and_then(Value::as_array)
+    {
+        Some(series) => series
+            .iter()
+            .filter_map(|path| path.as_str().map(str::to_string))
+            .collect(),
+        None => {
+            let mut paths = vec![];
+            while group_attributes(store, &format!("/{}", paths.len()))?.is_some() {
+                paths.push(paths.len().to_string());
+            }
+            paths
+        }
+    };
+    let names = match store.get(&StoreKey::new("OME/METADATA.ome.xml")?)? {
+        Some(xml) => parse_image_names(&String::from_utf8_lossy(&xml)),
+        None => vec![],
+    };
+    Ok(paths
+        .into_iter()
+        .enumerate()
+        .map(|(i, path)| Series {
+            path,
+            name: names.get(i).cloned().flatten(),
+        })
+        .collect())
+}
+
+/// Describe the available series, one per line
+pub fn format_series(series: &[Series]) -> String {
+    series
+        .iter()
+        .enumerate()
+        .map(|(i, s)| match &s.name {
+            Some(name) => format!("  {}: /{} ({})", i, s.path, name),
+            None => format!("  {}: /{}", i, s.path),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bioformats2raw_layout_versions() -> Result<()> {
+        let v04: Map<String, Value> = serde_json::from_str(r#"{"bioformats2raw.layout": 3}"#)?;
+        assert_eq!(bioformats2raw_layout(&v04), Some(3));
+        let v05: Map<String, Value> =
+            serde_json::from_str(r#"{"ome": {"version": "0.5", "bioformats2raw.layout": 3}}"#)?;
+        assert_eq!(bioformats2raw_layout(&v05), Some(3));
+        let plain: Map<String, Value> = serde_json::from_str(r#"{"multiscales": []}"#)?;
+        assert_eq!(bioformats2raw_layout(&plain), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_axis_names() -> Result<()> {
+        let v04: Map<String, Value> = serde_json::from_str(
+            r#"{"multiscales": [{"axes": [
+                {"name": "t", "type": "time"}, {"name": "c", "type": "channel"},
+                {"name": "y", "type": "space"}, {"name": "x", "type": "space"}]}]}"#,
+        )?;
+        assert_eq!(
+            axis_names(&v04),
+            Some(vec!["t".into(), "c".into(), "y".into(), "x".into()])
+        );
+        let v03: Map<String, Value> =
+            serde_json::from_str(r#"{"multiscales": [{"axes": ["z", "y", "x"]}]}"#)?;
+        assert_eq!(
+            axis_names(&v03),
+            Some(vec!["z".into(), "y".into(), "x".into()])
+        );
+        let v05: Map<String, Value> = serde_json::from_str(
+            r#"{"ome": {"multiscales": [{"axes": [{"name": "y"}, {"name": "x"}]}]}}"#,
+        )?;
+        assert_eq!(axis_names(&v05), Some(vec!["y".into(), "x".into()]));
+        let plain: Map<String, Value> = serde_json::from_str(r#"{"multiscales": [{}]}"#)?;
+        assert_eq!(axis_names(&plain), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiscale_versions() -> Result<()> {
+        // `.zattrs` of an OME-NGFF 0.4 image, as written by ome-zarr-py
+        let v04: Map<String, Value> = serde_json::from_str(
+            r#"{"multiscales": [{
+                "version": "0.4",
+                "name": "example",
+                "axes": [
+                    {"name": "c", "type": "channel"},
+                    {"name": "y", "type": "space", "unit": "micrometer"},
+                    {"name": "x", "type": "space", "unit": "micrometer"}
+                ],
+                "datasets": [
+                    {"path": "0", "coordinateTransformations": [
+                        {"type": "scale", "scale": [1.0, 0.5, 0.5]}]},
+                    {"path": "1", "coordinateTransformations": [
+                        {"type": "scale", "scale": [1.0, 1.0, 1.0]},
+                        {"type": "translation", "translation": [0.0, 0.25, 0.25]}]}
+                ],
+                "coordinateTransformations": [{"type": "scale", "scale": [1.0, 2.0, 2.0]}],
+                "type": "gaussian"
+            }],
+            "omero": {"channels": [{"label": "DAPI", "color": "0000FF"}]}}"#,
+        )?;
+        // `attributes` of the `zarr.json` of an OME-NGFF 0.5 image
+        let v05: Map<String, Value> = serde_json::from_str(
+            r#"{"ome": {
+                "version": "0.5",
+                "multiscales": [{
+                    "name": "example",
+                    "axes": [
+                        {"name": "c", "type": "channel"},
+                        {"name": "y", "type": "space", "unit": "micrometer"},
+                        {"name": "x", "type": "space", "unit": "micrometer"}
+                    ],
+                    "datasets": [
+                        {"path": "0", "coordinateTransformations": [
+                            {"type": "scale", "scale": [1.0, 0.5, 0.5]}]},
+                        {"path": "1", "coordinateTransformations": [
+                            {"type": "scale", "scale": [1.0, 1.0, 1.0]},
+                            {"type": "translation", "translation": [0.0, 0.25, 0.25]}]}
+                    ],
+                    "coordinateTransformations": [{"type": "scale", "scale": [1.0, 2.0, 2.0]}]
+                }],
+                "omero": {"channels": [{"label": "DAPI", "color": "0000FF"}]}}}"#,
+        )?;
+        let v04 = multiscale(&v04).ok_or_else(|| anyhow::anyhow!("no 0.4 multiscale"))?;
+        let v05 = multiscale(&v05).ok_or_else(|| anyhow::anyhow!("no 0.5 multiscale"))?;
+        assert_eq!(v04.version.as_deref(), Some("0.4"));
+        assert_eq!(v05.version.as_deref(), Some("0.5"));
+        // Both normalize to the same image apart from the version
+        assert_eq!(
+            Multiscale {
+                version: None,
+                ..v04.clone()
+            },
+            Multiscale {
+                version: None,
+                ..v05
+            }
+        );
+        let axes = v04.axes.as_deref().unwrap_or_default();
+        assert_eq!(
+            axes[1],
+            Axis {
+                name: "y".to_string(),
+                kind: Some("space".to_string()),
+                unit: Some("micrometer".to_string())
+            }
+        );
+        assert_eq!(v04.datasets[1].translation, Some(vec![0.0, 0.25, 0.25]));
+        assert_eq!(v04.scale, Some(vec![1.0, 2.0, 2.0]));
+
+        // 0.3 has axis names only and no coordinate transformations
+        let v03: Map<String, Value> = serde_json::from_str(
+            r#"{"multiscales": [{"version": "0.3", "axes": ["z", "y", "x"],
+                "datasets": [{"path": "0"}, {"path": "1"}]}]}"#,
+        )?;
+        let v03 = multiscale(&v03).ok_or_else(|| anyhow::anyhow!("no 0.3 multiscale"))?;
+        assert_eq!(v03.axes.map(|axes| axes[0].kind.clone()), Some(None));
+        assert_eq!(v03.datasets[1].scale, None);
+        assert_eq!(multiscale(&Map::new()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dataset_paths() -> Result<()> {
+        let attributes: Map<String, Value> = serde_json::from_str(
+            r#"{"multiscales": [{"datasets": [{"path": "s0"}, {"path": "s1"}]}]}"#,
+        )?;
+        assert_eq!(
+            dataset_paths(&attributes),
+            Some(vec!["s0".to_string(), "s1".to_string()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_physical_transform() -> Result<()> {
+        let attributes: Map<String, Value> = serde_json::from_str(
+            r#"{"multiscales": [{
+                "axes": [{"name": "t", "unit": "second"}, {"name": "y"}, {"name": "x"}],
+                "datasets": [
+                    {"path": "0", "coordinateTransformations": [
+                        {"type": "scale", "scale": [30, 0.5, 0.5]}]},
+                    {"path": "1", "coordinateTransformations": [
+                        {"type": "scale", "scale": [30, 1, 1]},
+                        {"type": "translation", "translation": [10, 0.25, 0.25]}]}],
+                "coordinateTransformations": [{"type": "scale", "scale": [2, 1, 1]}]}]}"#,
+        )?;
+        assert_eq!(
+            physical_transform(&attributes, "1", 3),
+            Some((vec![60.0, 1.0, 1.0], vec![20.0, 0.25, 0.25]))
+        );
+        assert_eq!(physical_transform(&attributes, "2", 3), None);
+        assert_eq!(
+            axis_units(&attributes),
+            Some(vec![Some("second".to_string()), None, None])
+        );
+        assert_eq!(parse_unit("um"), Some((Quantity::Length, 1e-6)));
+        assert_eq!(parse_unit("Minutes"), Some((Quantity::Time, 60.0)));
+        assert_eq!(parse_unit("furlong"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_plate_layout() -> Result<()> {
+        let attributes: Map<String, Value> = serde_json::from_str(
+            r#"{"plate": {
+                "rows": [{"name": "A"}, {"name": "B"}],
+                "columns": [{"name": "01"}, {"name": "02"}, {"name": "03"}],
+                "wells": [{"path": "A/01"}, {"path": "B/03"}]}}"#,
+        )?;
+        let plate = plate(&attributes).ok_or_else(|| anyhow::anyhow!("no plate"))?;
+        assert_eq!(plate.find_well("B03"), Some("B/03"));
+        assert_eq!(plate.find_well("b3"), Some("B/03"));
+        assert_eq!(plate.find_well("A/1"), Some("A/01"));
+        assert_eq!(plate.find_well("A02"), None);
+        assert_eq!(plate.find_well("A"), None);
+        assert_eq!(plate.well_names(), vec!["A01", "B03"]);
+        assert_eq!(plate.format_layout(), "  01 02 03\nA  x  .  .\nB  .  .  x");
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_axis() -> Result<()> {
+        let attributes: Map<String, Value> = serde_json::from_str(
+            r#"{"multiscales": [{"axes": [
+                {"name": "ch", "type": "channel"}, {"name": "y"}, {"name": "x"}]}]}"#,
+        )?;
+        let axes = ["ch".to_string(), "y".to_string(), "x".to_string()];
+        assert_eq!(channel_axis(&attributes, &axes), Some(0));
+        let untyped = ["t".to_string(), "c".to_string(), "x".to_string()];
+        assert_eq!(channel_axis(&Map::new(), &untyped), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_image_names() {
+        let xml = r#"<OME><Image ID="Image:0" Name="Well A1 &amp; B1"><Pixels/></Image>
+            <ImageRef ID="Image:0"/><Image ID="Image:1"></Image>
+            <Image ID="Image:2" Name="label"/></OME>"#;
+        assert_eq!(
+            parse_image_names(xml),
+            vec![
+                Some("Well A1 & B1".to_string()),
+                None,
+                Some("label".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_series_from_store() -> Result<()> {
+        use zarrs::storage::store::MemoryStore;
+        use zarrs::storage::WritableStorageTraits;
+
+        let store = Arc::new(MemoryStore::new());
+        let set = |key: &str, value: &str| -> Result<()> {
+            store.set(&StoreKey::new(key)?, value.as_bytes().to_vec().into())?;
+            Ok(())
+        };
+        set(".zgroup", r#"{"zarr_format": 2}"#)?;
+        set(".zattrs", r#"{"bioformats2raw.layout": 3}"#)?;
+        set("0/.zgroup", r#"{"zarr_format": 2}"#)?;
+        set("1/.zgroup", r#"{"zarr_format": 2}"#)?;
+        set(
+            "OME/METADATA.ome.xml",
+            r#"<OME><Image ID="Image:0" Name="first"/><Image ID="Image:1" Name="second"/></OME>"#,
+        )?;
+
+        let series = list_series(&store)?;
+        assert_eq!(
+            series,
+            vec![
+                Series {
+                    path: "0".to_string(),
+                    name: Some("first".to_string())
+                },
+                Series {
+                    path: "1".to_string(),
+                    name: Some("second".to_string())
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,746 @@
+use crate::{
+    image_quantile, normalize_display, normalize_exact, Sample, Scale, Window, WindowSource,
+};
+use anyhow::Result;
+use ndarray::{Array2, ArrayView2};
+
+/// How to choose the values of a slice mapped to black and white
+pub trait Normalizer {
+    /// The window of the decoded slice
+    fn window(&self, decoded: &Array2<f32>) -> Result<Window>;
+
+    /// Scale to 8-bit within the window in the given scale and gamma,
+    /// returning the window used
+    fn normalize(
+        &self,
+        decoded: &Array2<f32>,
+        scale: Scale,
+        gamma: f32,
+    ) -> Result<(Array2<u8>, Window)> {
+        let window = self.window(decoded)?;
+        let normalized = normalize_display(decoded, window.min, window.max, scale, gamma);
+        Ok((normalized, window))
+    }
+
+    /// The window of the exact values of a 64-bit integer slice,
+    /// `None` for a normalization of the values displayed only
+    fn exact_window(&self, _exact: &Array2<f64>) -> Result<Option<Window<f64>>> {
+        Ok(None)
+    }
+
+    /// Like [`Normalizer::normalize`] for the exact values of a slice, with the window
+    /// in f64, `None` without [`Normalizer::exact_window`]
+    fn normalize_exact(
+        &self,
+        exact: &Array2<f64>,
+        scale: Scale,
+        gamma: f32,
+    ) -> Result<Option<(Array2<u8>, Window)>> {
+        let Some(window) = self.exact_window(exact)? else {
+            return Ok(None);
+        };
+        let normalized = normalize_exact(exact, window.min, window.max, scale, gamma);
+        Ok(Some((normalized, window.rounded())))
+    }
+}
+
+/// The names of the strategies, as given to `--norm`
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Norm {
+    /// Between the --low and --high quantiles, or the omero window
+    Quantile,
+    /// Between the smallest and largest values
+    #[value(name = "minmax")]
+    MinMax,
+    /// At --min and --max, or the quantile at the end not given
+    Fixed,
+    /// The mean ± --zscore-k standard deviations
+    #[value(name = "zscore")]
+    ZScore,
+    /// The values as they are, for data from 0 to 255
+    None,
+    /// Histogram equalization
+    Equalize,
+    /// Histogram equalization in tiles, with limited contrast
+    Clahe,
+}
+
+impl std::str::FromStr for Norm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "quantile" => Ok(Self::Quantile),
+            "minmax" => Ok(Self::MinMax),
+            "fixed" => Ok(Self::Fixed),
+            "zscore" => Ok(Self::ZScore),
+            "none" => Ok(Self::None),
+            "equalize" => Ok(Self::Equalize),
+            "clahe" => Ok(Self::Clahe),
+            _ => anyhow::bail!(
+                "Unknown normalization '{}', \
+                 use quantile, minmax, fixed, zscore, none, equalize or clahe",
+                s
+            ),
+        }
+    }
+}
+
+/// The quantiles given with `--clip`, in percent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clip {
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Clip {
+    /// The lower and upper quantile, as fractions like `--low` and `--high`
+    pub fn quantiles(&self) -> (f64, f64) {
+        (self.low / 100.0, self.high / 100.0)
+    }
+}
+
+impl std::str::FromStr for Clip {
+    type Err = anyhow::Error;
+
+    /// Parse `1`, clipping 1 percent from each tail, or the percentiles `1,99.5`
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |value: &str| -> Result<f64> {
+            match value.trim().parse::<f64>() {
+                Ok(value) if (0.0..=100.0).contains(&value) => Ok(value),
+                _ => anyhow::bail!(
+                    "Invalid clip '{}', expected a percentage from 0 to 100, e.g. 1 or 1,99.5",
+                    s
+                ),
+            }
+        };
+        let (low, high) = match s.split_once(',') {
+            Some((low, high)) => (parse(low)?, parse(high)?),
+            None => {
+                let tail = parse(s)?;
+                (tail, 100.0 - tail)
+            }
+        };
+        if low >= high {
+            anyhow::bail!(
+                "Invalid clip '{}', the lower percentile {} must be below the upper {}",
+                s,
+                low,
+                high
+            );
+        }
+        Ok(Self { low, high })
+    }
+}
+
+/// Check that the quantiles from `--low` and `--high` are in order
+pub fn check_quantiles(low: f64, high: f64) -> Result<()> {
+    if low >= high {
+        anyhow::bail!(
+            "The lower quantile {} must be below the upper quantile {}, \
+             --low and --high are fractions like 0.01 and 0.99",
+            low,
+            high
+        );
+    }
+    Ok(())
+}
+
+/// Check that a display range from both `--min` and `--max` is not empty
+pub fn check_range(min: Option<f64>, max: Option<f64>) -> Result<()> {
+    if let (Some(min), Some(max)) = (min, max) {
+        if max <= min {
+            anyhow::bail!(
+                "The display range {} to {} is empty, --max must be above --min",
+                min,
+                max
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Finite values of a slice, skipping NaN and infinities
+fn finite<T: Sample>(decoded: &Array2<T>) -> impl Iterator<Item = T> + '_ {
+    decoded.iter().copied().filter(|value| value.is_finite())
+}
+
+/// Between the `low` and `high` quantiles
+pub struct Quantile {
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Quantile {
+    fn window_of<T: Sample>(&self, decoded: &Array2<T>) -> Result<Window<T>> {
+        Ok(Window {
+            min: image_quantile(decoded, self.low)?,
+            max: image_quantile(decoded, self.high)?,
+            source: WindowSource::Quantiles {
+                low: self.low,
+                high: self.high,
+            },
+        })
+    }
+}
+
+impl Normalizer for Quantile {
+    fn window(&self, decoded: &Array2<f32>) -> Result<Window> {
+        self.window_of(decoded)
+    }
+
+    fn exact_window(&self, exact: &Array2<f64>) -> Result<Option<Window<f64>>> {
+        self.window_of(exact).map(Some)
+    }
+}
+
+/// The contrast window of the `omero` metadata
+pub struct Omero {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Normalizer for Omero {
+    fn window(&self, _decoded: &Array2<f32>) -> Result<Window> {
+        Ok(Window {
+            min: self.min,
+            max: self.max,
+            source: WindowSource::Omero,
+        })
+    }
+
+    fn exact_window(&self, _exact: &Array2<f64>) -> Result<Option<Window<f64>>> {
+        Ok(Some(Window {
+            min: self.min as f64,
+            max: self.max as f64,
+            source: WindowSource::Omero,
+        }))
+    }
+}
+
+/// Between the smallest and largest finite values
+pub struct MinMax;
+
+fn minmax<T: Sample>(decoded: &Array2<T>) -> Window<T> {
+    Window {
+        min: finite(decoded).fold(T::NAN, T::min),
+        max: finite(decoded).fold(T::NAN, T::max),
+        source: WindowSource::MinMax,
+    }
+}
+
+impl Normalizer for MinMax {
+    fn window(&self, decoded: &Array2<f32>) -> Result<Window> {
+        Ok(minmax(decoded))
+    }
+
+    fn exact_window(&self, exact: &Array2<f64>) -> Result<Option<Window<f64>>> {
+        Ok(Some(minmax(exact)))
+    }
+}
+
+/// At the given values, with the `low` or `high` quantile for an end not given
+pub struct Fixed {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Normalizer for Fixed {
+    fn window(&self, decoded: &Array2<f32>) -> Result<Window> {
+        let [min, max] = [self.min, self.max].map(|value| value.map(|value| value as f32));
+        Window::fixed(decoded, min, max, self.low, self.high)
+    }
+
+    fn exact_window(&self, exact: &Array2<f64>) -> Result<Option<Window<f64>>> {
+        Window::fixed(exact, self.min, self.max, self.low, self.high).map(Some)
+    }
+}
+
+/// The mean plus or minus `k` standard deviations of the finite values
+pub struct ZScore {
+    pub k: f32,
+}
+
+impl ZScore {
+    fn window_of<T: Sample>(&self, decoded: &Array2<T>) -> Window<f64> {
+        let (count, sum) = finite(decoded).fold((0usize, 0f64), |(count, sum), value| {
+            (count + 1, sum + value.to_f64())
+        });
+        let mean = sum / count as f64;
+        let variance = finite(decoded)
+            .map(|value| (value.to_f64() - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+        let spread = self.k as f64 * variance.sqrt();
+        Window {
+            min: mean - spread,
+            max: mean + spread,
+            source: WindowSource::ZScore,
+        }
+    }
+}
+
+impl Normalizer for ZScore {
+    fn window(&self, decoded: &Array2<f32>) -> Result<Window> {
+        Ok(self.window_of(decoded).rounded())
+    }
+
+    fn exact_window(&self, exact: &Array2<f64>) -> Result<Option<Window<f64>>> {
+        Ok(Some(self.window_of(exact)))
+    }
+}
+
+/// The data as it is, assumed to be from 0 to 255 already and only clamped
+pub struct Unscaled;
+
+impl Normalizer for Unscaled {
+    fn window(&self, _decoded: &Array2<f32>) -> Result<Window> {
+        Ok(Window {
+            min: 0.0,
+            max: 255.0,
+            source: WindowSource::Unscaled,
+        })
+    }
+
+    fn normalize(
+        &self,
+        decoded: &Array2<f32>,
+        scale: Scale,
+        gamma: f32,
+    ) -> Result<(Array2<u8>, Window)> {
+        let window = self.window(decoded)?;
+        let normalized = if scale == Scale::Linear && gamma == 1.0 {
+            decoded.mapv(|value| value.clamp(0.0, 255.0) as u8)
+        } else {
+            normalize_display(decoded, window.min, window.max, scale, gamma)
+        };
+        Ok((normalized, window))
+    }
+}
+
+/// Number of histogram bins of the equalizations
+const BINS: usize = 256;
+
+/// Bin of a value among [`BINS`] over the window, with infinities in the end bins,
+/// `None` for NaN
+fn bin(value: f32, window: &Window) -> Option<usize> {
+    if value.is_nan() {
+        return None;
+    }
+    if window.max <= window.min {
+        return Some(0);
+    }
+    let scaled = (value - window.min) / (window.max - window.min) * (BINS - 1) as f32;
+    Some((scaled.max(0.0) as usize).min(BINS - 1))
+}
+
+/// Counts of the values other than NaN in each bin over the window
+fn histogram(decoded: &Array2<f32>, window: &Window) -> Vec<u64> {
+    let mut histogram = vec![0; BINS];
+    for bin in decoded.iter().filter_map(|&value| bin(value, window)) {
+        histogram[bin] += 1;
+    }
+    histogram
+}
+
+/// Cumulative distribution of a histogram as a mapping of bins onto `0..=1`,
+/// starting at 0 from the first bin with values; all 0 if they are in a single bin
+fn cdf_mapping(histogram: &[u64]) -> Vec<f32> {
+    let total: u64 = histogram.iter().sum();
+    let first = histogram
+        .iter()
+        .copied()
+        .find(|&count| count > 0)
+        .unwrap_or(0);
+    let mut cumulative = 0;
+    histogram
+        .iter()
+        .map(|&count| {
+            cumulative += count;
+            if total == first {
+                0.0
+            } else {
+                cumulative.saturating_sub(first) as f32 / (total - first) as f32
+            }
+        })
+        .collect()
+}
+
+/// Map equalized fractions to 8-bit with the gamma curve, not-a-number to black
+fn finish(equalized: Array2<f32>, gamma: f32) -> Array2<u8> {
+    let exponent = 1.0 / gamma;
+    equalized.mapv(|fraction| {
+        let fraction = if fraction.is_nan() { 0.0 } else { fraction };
+        (fraction.clamp(0.0, 1.0).powf(exponent) * 255.0) as u8
+    })
+}
+
+/// Between the smallest and largest finite values, as equalized
+fn equalized_window(decoded: &Array2<f32>) -> Result<Window> {
+    Ok(Window {
+        source: WindowSource::Equalized,
+        ..MinMax.window(decoded)?
+    })
+}
+
+/// Global histogram equalization, spreading the values evenly over the 8-bit range
+pub struct Equalize;
+
+impl Normalizer for Equalize {
+    fn window(&self, decoded: &Array2<f32>) -> Result<Window> {
+        equalized_window(decoded)
+    }
+
+    /// The scale does not change an equalization, which only depends on the order of values
+    fn normalize(
+        &self,
+        decoded: &Array2<f32>,
+        _scale: Scale,
+        gamma: f32,
+    ) -> Result<(Array2<u8>, Window)> {
+        let window = self.window(decoded)?;
+        let mapping = cdf_mapping(&histogram(decoded, &window));
+        let equalized = decoded.mapv(|value| bin(value, &window).map_or(f32::NAN, |b| mapping[b]));
+        Ok((finish(equalized, gamma), window))
+    }
+}
+
+/// Contrast limited adaptive histogram equalization: equalized on a grid of `tiles` by `tiles`,
+/// with bins clipped at `clip` times their mean count, blending the mappings of nearby tiles
+pub struct Clahe {
+    pub tiles: usize,
+    pub clip: f32,
+}
+
+impl Clahe {
+    /// Clip the bins at the limit, spreading the excess evenly over all bins
+    fn clip_histogram(&self, histogram: &mut [u64]) {
+        let total: u64 = histogram.iter().sum();
+        let limit = ((self.clip as f64 * total as f64 / BINS as f64).ceil() as u64).max(1);
+        let excess: u64 = histogram
+            .iter()
+            .map(|&count| count.saturating_sub(limit))
+            .sum();
+        let (share, remainder) = (excess / BINS as u64, excess % BINS as u64);
+        for (i, count) in histogram.iter_mut().enumerate() {
+            *count = (*count).min(limit) + share + u64::from((i as u64) < remainder);
+        }
+    }
+}
+
+impl Normalizer for Clahe {
+    fn window(&self, decoded: &Array2<f32>) -> Result<Window> {
+        equalized_window(decoded)
+    }
+
+    fn normalize(
+        &self,
+        decoded: &Array2<f32>,
+        _scale: Scale,
+        gamma: f32,
+    ) -> Result<(Array2<u8>, Window)> {
+        let window = self.window(decoded)?;
+        let (rows, columns) = decoded.dim();
+        let grid = (
+            self.tiles.clamp(1, rows.max(1)),
+            self.tiles.clamp(1, columns.max(1)),
+        );
+        let tile = (
+            rows.div_ceil(grid.0).max(1),
+            columns.div_ceil(grid.1).max(1),
+        );
+        // Each tile is binned over its own range, to resolve faint texture in dark tiles
+        let mut tiles = vec![];
+        for tile_row in 0..grid.0 {
+            for tile_column in 0..grid.1 {
+                let rows = tile_row * tile.0..((tile_row + 1) * tile.0).min(rows);
+                let columns = tile_column * tile.1..((tile_column + 1) * tile.1).min(columns);
+                let data = decoded.slice(ndarray::s![rows, columns]).to_owned();
+                let range = MinMax.window(&data)?;
+                let mut histogram = histogram(&data, &range);
+                // A uniform tile keeps all its values in one bin, mapped to black
+                if histogram.iter().filter(|&&count| count > 0).count() > 1 {
+                    self.clip_histogram(&mut histogram);
+                }
+                tiles.push((range, cdf_mapping(&histogram)));
+            }
+        }
+        // Position between the centers of the tiles along an axis, and the weight of the next
+        let between = |position: usize, size: usize, count: usize| {
+            let center =
+                ((position as f32 + 0.5) / size as f32 - 0.5).clamp(0.0, (count - 1) as f32);
+            let before = center.floor() as usize;
+            (before, (before + 1).min(count - 1), center - before as f32)
+        };
+        let equalized = Array2::from_shape_fn((rows, columns), |(row, column)| {
+            let value = decoded[[row, column]];
+            if value.is_nan() {
+                return f32::NAN;
+            }
+            let (top, bottom, down) = between(row, tile.0, grid.0);
+            let (left, right, across) = between(column, tile.1, grid.1);
+            let at = |tile_row: usize, tile_column: usize| {
+                let (range, mapping) = &tiles[tile_row * grid.1 + tile_column];
+                bin(value, range).map_or(0.0, |bin| mapping[bin])
+            };
+            let upper = at(top, left) * (1.0 - across) + at(top, right) * across;
+            let lower = at(bottom, left) * (1.0 - across) + at(bottom, right) * across;
+            upper * (1.0 - down) + lower * down
+        });
+        Ok((finish(equalized, gamma), window))
+    }
+}
+
+/// Positions of `samples` of `count` frames or tiles spread evenly from the first,
+/// or of all of them, for a contrast window shared between them
+pub fn sample_evenly(count: usize, samples: Option<usize>) -> Vec<usize> {
+    match samples {
+        Some(samples) if samples < count => (0..samples.max(1))
+            .map(|i| i * count / samples.max(1))
+            .collect(),
+        _ => (0..count).collect(),
+    }
+}
+
+/// The values of the planes together in one row, to take a shared contrast window of
+pub fn pooled(planes: &[ArrayView2<f32>]) -> Result<Array2<f32>> {
+    let values: Vec<f32> = planes
+        .iter()
+        .flat_map(|plane| plane.iter().copied())
+        .collect();
+    Ok(Array2::from_shape_vec((1, values.len()), values)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_clip() -> Result<()> {
+        let clip: Clip = "1".parse()?;
+        assert_eq!(
+            clip,
+            Clip {
+                low: 1.0,
+                high: 99.0
+            }
+        );
+        assert_eq!(clip.quantiles(), (0.01, 0.99));
+        assert_eq!("0.5, 99".parse::<Clip>()?.quantiles(), (0.005, 0.99));
+        for invalid in ["50", "99,1", "-1", "2,101", "one"] {
+            assert!(invalid.parse::<Clip>().is_err(), "{}", invalid);
+        }
+        assert!(check_quantiles(0.01, 0.99).is_ok());
+        let error = check_quantiles(0.9, 0.1).unwrap_err().to_string();
+        assert!(error.starts_with("The lower quantile 0.9 must be below the upper quantile 0.1"));
+        assert!(check_range(Some(0.0), Some(255.0)).is_ok());
+        assert!(check_range(Some(300.0), None).is_ok());
+        let error = check_range(Some(10.0), Some(5.0)).unwrap_err().to_string();
+        assert!(error.starts_with("The display range 10 to 5 is empty"));
+        Ok(())
+    }
+
+    fn ramp() -> Array2<f32> {
+        Array2::from_shape_fn((10, 10), |(i, j)| (i * 10 + j) as f32)
+    }
+
+    fn constant() -> Array2<f32> {
+        Array2::from_elem((4, 4), 7.0)
+    }
+
+    #[test]
+    fn test_quantile() -> Result<()> {
+        let quantile = Quantile {
+            low: 0.1,
+            high: 0.9,
+        };
+        let (normalized, window) = quantile.normalize(&ramp(), Scale::Linear, 1.0)?;
+        assert_eq!((window.min, window.max), (10.0, 89.0));
+        assert_eq!(normalized[[0, 0]], 0);
+        assert_eq!(normalized[[9, 9]], 255);
+        let (normalized, window) = quantile.normalize(&constant(), Scale::Linear, 1.0)?;
+        assert_eq!((window.min, window.max), (7.0, 7.0));
+        assert_eq!(normalized, Array2::<u8>::zeros((4, 4)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_minmax() -> Result<()> {
+        let data = array![[f32::NAN, -5.0, 0.0, f32::INFINITY, 15.0]];
+        let (normalized, window) = MinMax.normalize(&data, Scale::Linear, 1.0)?;
+        assert_eq!((window.min, window.max), (-5.0, 15.0));
+        assert_eq!(normalized.row(0).to_vec(), vec![0, 0, 63, 255, 255]);
+        let (normalized, _) = MinMax.normalize(&constant(), Scale::Linear, 1.0)?;
+        assert_eq!(normalized, Array2::<u8>::zeros((4, 4)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed() -> Result<()> {
+        let fixed = Fixed {
+            min: Some(0.0),
+            max: Some(198.0),
+            low: 0.0,
+            high: 1.0,
+        };
+        let (normalized, window) = fixed.normalize(&ramp(), Scale::Linear, 1.0)?;
+        assert_eq!(window.source, WindowSource::Fixed);
+        assert_eq!(normalized[[9, 9]], 127);
+        // A constant image is shown at its place in the fixed range
+        let (normalized, _) = fixed.normalize(&constant(), Scale::Linear, 1.0)?;
+        assert_eq!(normalized[[0, 0]], 9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_exact_window() -> Result<()> {
+        let base = (1u64 << 40) as f64;
+        let exact = array![[base, base + 1.0, f64::NAN, base + 2.0]];
+        let (normalized, window) = MinMax
+            .normalize_exact(&exact, Scale::Linear, 1.0)?
+            .expect("minmax has an exact window");
+        assert_eq!(normalized.row(0).to_vec(), vec![0, 127, 0, 255]);
+        assert_eq!(window.source, WindowSource::MinMax);
+        let quantile = Quantile {
+            low: 0.0,
+            high: 0.5,
+        };
+        let window = quantile.exact_window(&exact)?.expect("exact quantiles");
+        assert_eq!((window.min, window.max), (base, base + 1.0));
+        let fixed = Fixed {
+            min: Some(base + 1.0),
+            max: None,
+            low: 0.0,
+            high: 1.0,
+        };
+        let window = fixed.exact_window(&exact)?.expect("exact fixed window");
+        assert_eq!((window.min, window.max), (base + 1.0, base + 2.0));
+        // Equalization only works on the values displayed
+        assert!(Equalize
+            .normalize_exact(&exact, Scale::Linear, 1.0)?
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zscore() -> Result<()> {
+        let data = array![[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]];
+        // Mean 5 and standard deviation 2
+        let window = ZScore { k: 1.5 }.window(&data)?;
+        assert_eq!((window.min, window.max), (2.0, 8.0));
+        let (normalized, _) = ZScore { k: 1.5 }.normalize(&data, Scale::Linear, 1.0)?;
+        assert_eq!(normalized[[0, 0]], 0);
+        assert_eq!(normalized[[0, 7]], 255);
+        let (normalized, window) = ZScore { k: 3.0 }.normalize(&constant(), Scale::Linear, 1.0)?;
+        assert_eq!((window.min, window.max), (7.0, 7.0));
+        assert_eq!(normalized, Array2::<u8>::zeros((4, 4)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unscaled() -> Result<()> {
+        let data = array![[-3.0, 0.0, 17.0, 254.9, 255.0, 300.0]];
+        let (normalized, window) = Unscaled.normalize(&data, Scale::Linear, 1.0)?;
+        assert_eq!(window.source, WindowSource::Unscaled);
+        assert_eq!(normalized.row(0).to_vec(), vec![0, 0, 17, 254, 255, 255]);
+        let (normalized, _) = Unscaled.normalize(&constant(), Scale::Linear, 1.0)?;
+        assert_eq!(normalized, Array2::from_elem((4, 4), 7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_omero_and_names() -> Result<()> {
+        let omero = Omero {
+            min: 50.0,
+            max: 60.0,
+        };
+        let (normalized, _) = omero.normalize(&array![[40.0, 55.0, 70.0]], Scale::Linear, 1.0)?;
+        assert_eq!(normalized.row(0).to_vec(), vec![0, 127, 255]);
+        assert_eq!("zscore".parse::<Norm>()?, Norm::ZScore);
+        assert!("sigmoid".parse::<Norm>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_equalize() -> Result<()> {
+        // Mostly dark values with a few bright ones
+        let data =
+            Array2::from_shape_fn((1, 8), |(_, j)| if j < 6 { j as f32 * 0.5 } else { 1000.0 });
+        let (normalized, window) = Equalize.normalize(&data, Scale::Linear, 1.0)?;
+        assert_eq!((window.min, window.max), (0.0, 1000.0));
+        assert_eq!(window.source, WindowSource::Equalized);
+        // The dark values share a bin, the bright ones take the rest of the range
+        assert_eq!(normalized.row(0).to_vec(), vec![0, 0, 0, 0, 0, 0, 255, 255]);
+        let ramp = ramp();
+        let (normalized, _) = Equalize.normalize(&ramp, Scale::Linear, 1.0)?;
+        assert_eq!(normalized[[0, 0]], 0);
+        assert_eq!(normalized[[9, 9]], 255);
+        // An evenly spread ramp stays about linear
+        assert!((normalized[[5, 0]] as i32 - 128).abs() <= 3);
+        for degenerate in [constant(), array![[f32::NAN, f32::NAN]]] {
+            let (normalized, _) = Equalize.normalize(&degenerate, Scale::Linear, 1.0)?;
+            assert!(normalized.iter().all(|&value| value == 0));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_clahe() -> Result<()> {
+        // A dark half and a bright half, each with a little texture
+        let data = Array2::from_shape_fn((32, 32), |(i, j)| {
+            let texture = ((i * 7 + j * 3) % 5) as f32;
+            if j < 16 {
+                texture
+            } else {
+                1000.0 + texture
+            }
+        });
+        let clahe = Clahe {
+            tiles: 2,
+            clip: 4.0,
+        };
+        let (normalized, _) = clahe.normalize(&data, Scale::Linear, 1.0)?;
+        let spread = |columns: std::ops::Range<usize>| {
+            let values: Vec<u8> = columns
+                .flat_map(|column| (0..32).map(move |row| (row, column)))
+                .map(|index| normalized[index])
+                .collect();
+            values.iter().max().copied().unwrap_or(0) - values.iter().min().copied().unwrap_or(0)
+        };
+        // The texture of the dark half is visible, unlike with a global stretch
+        assert!(spread(0..8) > 100);
+        assert!(spread(24..32) > 100);
+        let (global, _) = MinMax.normalize(&data, Scale::Linear, 1.0)?;
+        assert!(global
+            .slice(ndarray::s![.., 0..8])
+            .iter()
+            .all(|&value| value <= 1));
+
+        let (normalized, _) = clahe.normalize(&constant(), Scale::Linear, 1.0)?;
+        assert!(normalized.iter().all(|&value| value == 0));
+        let single = Clahe {
+            tiles: 8,
+            clip: 2.0,
+        };
+        let (normalized, _) = single.normalize(&array![[1.0, 2.0]], Scale::Linear, 1.0)?;
+        assert_eq!(normalized.dim(), (1, 2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_window() -> Result<()> {
+        assert_eq!(sample_evenly(10, Some(4)), vec![0, 2, 5, 7]);
+        assert_eq!(sample_evenly(3, Some(8)), vec![0, 1, 2]);
+        assert_eq!(sample_evenly(3, None), vec![0, 1, 2]);
+        let planes = [ramp(), constant()];
+        let views: Vec<_> = planes.iter().map(|plane| plane.view()).collect();
+        let values = pooled(&views)?;
+        assert_eq!(values.dim(), (1, 116));
+        let window = MinMax.window(&values)?;
+        assert_eq!((window.min, window.max), (0.0, 99.0));
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use crate::ngff::ome_attributes;
+use serde_json::{Map, Value};
+
+/// Rendering settings of a channel in the `omero` metadata
+#[derive(Debug, Clone, PartialEq)]
+pub struct OmeroChannel {
+    /// Name of the channel, e.g. `GFP`
+    pub label: Option<String>,
+    /// Contrast limits from `window.start` and `window.end`
+    pub window: Option<(f32, f32)>,
+    /// RGB color parsed from the hex `color`
+    pub color: Option<[u8; 3]>,
+}
+
+fn parse_window(window: &Value) -> Option<(f32, f32)> {
+    let start = window.get("start")?.as_f64()? as f32;
+    let end = window.get("end")?.as_f64()? as f32;
+    (start < end).then_some((start, end))
+}
+
+pub(crate) fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let color = color.trim_start_matches('#');
+    if color.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(color.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Per-channel rendering settings from `omero.channels`, empty if absent,
+/// with malformed labels, windows or colors left out
+pub fn omero_channels(attributes: &Map<String, Value>) -> Vec<OmeroChannel> {
+    ome_attributes(attributes)
+        .get("omero")
+        .and_then(|omero| omero.get("channels"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|channel| OmeroChannel {
+            label: channel
+                .get("label")
+                .and_then(Value::as_str)
+                .filter(|label| !label.is_empty())
+                .map(str::to_string),
+            window: channel.get("window").and_then(parse_window),
+            color: channel
+                .get("color")
+                .and_then(Value::as_str)
+                .and_then(parse_color),
+        })
+        .collect()
+}
+
+/// Labels of the channels in `omero.channels`, `None` where missing
+pub fn channel_labels(attributes: &Map<String, Value>) -> Vec<Option<String>> {
+    omero_channels(attributes)
+        .into_iter()
+        .map(|channel| channel.label)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_omero_channels() -> Result<()> {
+        let attributes: Map<String, Value> = serde_json::from_str(
+            r#"{"omero": {"channels": [
+                {"label": "GFP", "color": "00FF80", "window": {"start": 10, "end": 200.5}},
+                {"label": "", "color": "red", "window": {"start": 5, "end": 5}},
+                {}]}}"#,
+        )?;
+        assert_eq!(
+            omero_channels(&attributes),
+            vec![
+                OmeroChannel {
+                    label: Some("GFP".to_string()),
+                    window: Some((10.0, 200.5)),
+                    color: Some([0, 255, 128])
+                },
+                OmeroChannel {
+                    label: None,
+                    window: None,
+                    color: None
+                },
+                OmeroChannel {
+                    label: None,
+                    window: None,
+                    color: None
+                },
+            ]
+        );
+        assert_eq!(
+            channel_labels(&attributes),
+            vec![Some("GFP".to_string()), None, None]
+        );
+        let v05: Map<String, Value> =
+            serde_json::from_str(r#"{"ome": {"omero": {"channels": [{"label": "DAPI"}]}}}"#)?;
+        assert_eq!(channel_labels(&v05), vec![Some("DAPI".to_string())]);
+        assert!(omero_channels(&serde_json::from_str(r#"{"omero": 1}"#)?).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::Result;
+use image::imageops;
+use image::RgbImage;
+use ndarray::{s, Array2};
+
+/// Clockwise rotation of the displayed plane, as given to `--rotate`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl std::str::FromStr for Rotation {
+    type Err = anyhow::Error;
+
+    /// Parse `0`, `90`, `180` or `270` degrees
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "0" => Ok(Self::None),
+            "90" => Ok(Self::Quarter),
+            "180" => Ok(Self::Half),
+            "270" => Ok(Self::ThreeQuarters),
+            _ => anyhow::bail!("Invalid rotation '{}', use 90, 180 or 270 degrees", s),
+        }
+    }
+}
+
+impl Rotation {
+    /// The rotation undoing this one
+    fn inverse(self) -> Self {
+        match self {
+            Self::Quarter => Self::ThreeQuarters,
+            Self::ThreeQuarters => Self::Quarter,
+            rotation => rotation,
+        }
+    }
+}
+
+/// How to turn the decoded plane for display, to match the orientation of other viewers:
+/// transposed first, then flipped, then rotated clockwise
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Orientation {
+    pub transpose: bool,
+    pub flip_y: bool,
+    pub flip_x: bool,
+    pub rotate: Rotation,
+}
+
+impl Orientation {
+    /// Whether the plane is shown as it is
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether rows of the displayed plane run along X of the array
+    pub fn swaps_axes(&self) -> bool {
+        let quarter = matches!(self.rotate, Rotation::Quarter | Rotation::ThreeQuarters);
+        self.transpose != quarter
+    }
+
+    /// Turn a plane (of values, labels or a mask) for display
+    pub fn apply<T: Clone>(&self, data: &Array2<T>) -> Array2<T> {
+        let mut view = data.view();
+        if self.transpose {
+            view = view.reversed_axes();
+        }
+        if self.flip_y {
+            view = view.slice_move(s![..;-1, ..]);
+        }
+        if self.flip_x {
+            view = view.slice_move(s![.., ..;-1]);
+        }
+        // Clockwise, a quarter turn is the transpose with the columns reversed
+        view = match self.rotate {
+            Rotation::None => view,
+            Rotation::Quarter => view.reversed_axes().slice_move(s![.., ..;-1]),
+            Rotation::Half => view.slice_move(s![..;-1, ..;-1]),
+            Rotation::ThreeQuarters => view.reversed_axes().slice_move(s![..;-1, ..]),
+        };
+        view.as_standard_layout().into_owned()
+    }
+
+    /// Turn an image rendered from the plane as it was decoded, like [`Orientation::apply`]
+    pub fn apply_image(&self, image: &RgbImage) -> RgbImage {
+        let mut image = image.clone();
+        if self.transpose {
+            image = transpose(&image);
+        }
+        if self.flip_y {
+            imageops::flip_vertical_in_place(&mut image);
+        }
+        if self.flip_x {
+            imageops::flip_horizontal_in_place(&mut image);
+        }
+        rotate(&image, self.rotate)
+    }
+
+    /// Turn an image of the displayed plane back to the orientation it was decoded in
+    pub fn invert_image(&self, image: &RgbImage) -> RgbImage {
+        let mut image = rotate(image, self.rotate.inverse());
+        if self.flip_x {
+            imageops::flip_horizontal_in_place(&mut image);
+        }
+        if self.flip_y {
+            imageops::flip_vertical_in_place(&mut image);
+        }
+        match self.transpose {
+            true => transpose(&image),
+            false => image,
+        }
+    }
+}
+
+/// Swap the rows and columns of an image
+fn transpose(image: &RgbImage) -> RgbImage {
+    RgbImage::from_fn(image.height(), image.width(), |x, y| *image.get_pixel(y, x))
+}
+
+fn rotate(image: &RgbImage, rotation: Rotation) -> RgbImage {
+    match rotation {
+        Rotation::None => image.clone(),
+        Rotation::Quarter => imageops::rotate90(image),
+        Rotation::Half => imageops::rotate180(image),
+        Rotation::ThreeQuarters => imageops::rotate270(image),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_apply() -> Result<()> {
+        let data = array![[1, 2, 3], [4, 5, 6]];
+        let turn = |orientation: Orientation| orientation.apply(&data);
+        assert_eq!(turn(Orientation::default()), data);
+        let transposed = Orientation {
+            transpose: true,
+            ..Default::default()
+        };
+        assert_eq!(turn(transposed), array![[1, 4], [2, 5], [3, 6]]);
+        let flipped = Orientation {
+            flip_x: true,
+            ..Default::default()
+        };
+        assert_eq!(turn(flipped), array![[3, 2, 1], [6, 5, 4]]);
+        let quarter = Orientation {
+            rotate: "90".parse()?,
+            ..Default::default()
+        };
+        assert_eq!(turn(quarter), array![[4, 1], [5, 2], [6, 3]]);
+        assert!(quarter.swaps_axes() && transposed.swaps_axes());
+        // Transposed, then flipped top to bottom, then turned back a quarter: half a turn
+        let combined = Orientation {
+            transpose: true,
+            flip_y: true,
+            flip_x: false,
+            rotate: "270".parse()?,
+        };
+        assert_eq!(turn(combined), array![[6, 5, 4], [3, 2, 1]]);
+        assert!(!combined.swaps_axes());
+        assert!("45".parse::<Rotation>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_image() {
+        let data = Array2::from_shape_fn((2, 3), |(row, column)| (row * 3 + column) as u8);
+        let image = RgbImage::from_fn(3, 2, |x, y| image::Rgb([data[[y as usize, x as usize]]; 3]));
+        for transpose in [false, true] {
+            for flip_x in [false, true] {
+                for rotate in ["0", "90", "180", "270"] {
+                    let orientation = Orientation {
+                        transpose,
+                        flip_y: !flip_x,
+                        flip_x,
+                        rotate: rotate.parse().expect("rotation"),
+                    };
+                    let turned = orientation.apply(&data);
+                    let image_turned = orientation.apply_image(&image);
+                    let (rows, columns) = turned.dim();
+                    assert_eq!(image_turned.dimensions(), (columns as u32, rows as u32));
+                    for ((row, column), &value) in turned.indexed_iter() {
+                        let pixel = image_turned.get_pixel(column as u32, row as u32);
+                        assert_eq!(pixel.0[0], value);
+                    }
+                    assert_eq!(orientation.invert_image(&image_turned), image);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use crate::montage::SEPARATOR_COLOR;
+use crate::scalebar::fill;
+use crate::{
+    decode_plane_with_options, ngff, open_array, read_array_slice, resolve_array_path, ReadOptions,
+    SliceIndex, SliceView,
+};
+use anyhow::Result;
+use image::{Rgb, RgbImage};
+use ndarray::{s, Array2};
+use std::sync::Arc;
+use zarrs::array_subset::ArraySubset;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// The XY, XZ and YZ planes through a point of a volume
+#[derive(Debug)]
+pub struct Ortho {
+    /// The XY slice through the point
+    pub xy: SliceView,
+    /// The XZ plane through the row of the point, along the X extent of the crop
+    pub xz: Array2<f32>,
+    /// The YZ plane through the column of the point, along the Y extent of the crop,
+    /// with Z along the columns
+    pub yz: Array2<f32>,
+    /// Dimension of the Z axis
+    pub z_axis: usize,
+    /// Start and end of the planes along Z
+    pub z_extent: (u64, u64),
+    /// The point along Z, Y and X
+    pub center: [u64; 3],
+}
+
+/// The dimension of the Z axis, the one named `z` or the one before Y without axis names
+fn z_axis(axes: Option<&[String]>, ndims: usize) -> Result<usize> {
+    match axes {
+        Some(axes) => match axes.iter().position(|name| name.eq_ignore_ascii_case("z")) {
+            Some(z) if z + 2 < ndims => Ok(z),
+            _ => anyhow::bail!("--ortho needs a z axis, the axes are {}", axes.join(", ")),
+        },
+        None if ndims >= 3 => Ok(ndims - 3),
+        None => anyhow::bail!("--ortho needs a volume, the array has 2 dimensions"),
+    }
+}
+
+/// At most `size` of `length` indices centered on `center` where possible
+fn centered((center, length): (u64, u64), size: u64) -> (u64, u64) {
+    let size = size.min(length);
+    let start = center.saturating_sub(size / 2).min(length - size);
+    (start, start + size)
+}
+
+impl Ortho {
+    /// Sizes along Z, Y and X to display the planes at, with pixels resampled to the
+    /// physical spacing of the axes unless `correct` is false, stretching coarser axes
+    /// and shrinking all to fit the XY and YZ planes side by side in `max_size`,
+    /// and the XY and XZ planes above each other
+    pub fn display_sizes(&self, correct: bool, max_size: u64) -> [usize; 3] {
+        let (z0, z1) = self.z_extent;
+        let [(y0, y1), (x0, x1)] = self.xy.extent();
+        let ratios = match correct {
+            true => [
+                self.xy.spacing_ratio(self.z_axis).unwrap_or(1.0),
+                self.xy.pixel_aspect().unwrap_or(1.0),
+                1.0,
+            ],
+            false => [1.0; 3],
+        };
+        let finest = ratios.iter().copied().fold(f64::INFINITY, f64::min);
+        let lengths = [z1 - z0, y1 - y0, x1 - x0];
+        let sizes: Vec<f64> = (0..3)
+            .map(|i| lengths[i] as f64 * ratios[i] / finest)
+            .collect();
+        let (z, y, x) = (sizes[0], sizes[1], sizes[2]);
+        let fit = (max_size as f64 / (y.max(x) + z)).min(1.0);
+        [z, y, x].map(|length| ((length * fit).round() as usize).max(1))
+    }
+}
+
+/// Read the planes through the point at the slice indices of `options`, the middle of Z
+/// by default, and at `y=` and `x=` among them in the pixels of the whole array,
+/// the middle of the crop by default, with Z cropped like Y and X around the point
+pub fn read_ortho<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+) -> Result<Ortho>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let attributes = ngff::group_attributes(&store, ngff::parent_path(&path))?.unwrap_or_default();
+    let array = open_array(store.clone(), &path)?;
+    let shape = array.shape().to_vec();
+    let ndims = shape.len();
+    let axes = ngff::axis_names(&attributes).filter(|axes| axes.len() == ndims);
+    let z = z_axis(axes.as_deref(), ndims)?;
+    let spatial = |slice: &SliceIndex| -> Option<usize> {
+        let (SliceIndex::Axis(name, _) | SliceIndex::Physical(name, ..)) = slice else {
+            return None;
+        };
+        let names = match &axes {
+            Some(axes) => [axes[ndims - 2].as_str(), axes[ndims - 1].as_str()],
+            None => ["y", "x"],
+        };
+        names
+            .iter()
+            .position(|axis| axis.eq_ignore_ascii_case(name))
+    };
+    let mut point = [None, None];
+    let mut slices = vec![];
+    for slice in options.slice_indices.as_deref().unwrap_or_default() {
+        match (spatial(slice), slice) {
+            (Some(i), SliceIndex::Axis(_, index)) => {
+                point[i] = Some(index.resolve(shape[ndims - 2 + i]))
+            }
+            (Some(_), _) => {
+                anyhow::bail!("Give the point of --ortho along Y and X in pixels, e.g. y=120,x=64")
+            }
+            (None, _) => slices.push(slice.clone()),
+        }
+    }
+    let xy_options = ReadOptions {
+        slice_indices: Some(slices),
+        consolidated: false,
+        ..options.clone()
+    };
+    let xy = read_array_slice(store, &path, &xy_options)?;
+    let [(y0, y1), (x0, x1)] = xy.extent();
+    let cy = point[0].unwrap_or((y0 + y1) / 2);
+    let cx = point[1].unwrap_or((x0 + x1) / 2);
+    if !(y0..y1).contains(&cy) || !(x0..x1).contains(&cx) {
+        anyhow::bail!(
+            "The point at y={} x={} of --ortho is outside the crop y={}..{} x={}..{}",
+            cy,
+            cx,
+            y0,
+            y1,
+            x0,
+            x1
+        );
+    }
+    let cz = xy.start[z];
+    let (z0, z1) = centered((cz, shape[z]), options.crop_size);
+    let plane = |position: (usize, u64),
+                 (axis, (start, end)): (usize, (u64, u64))|
+     -> Result<Array2<f32>> {
+        let mut plane_start = xy.start.clone();
+        let mut plane_shape = vec![1; ndims];
+        plane_start[z] = z0;
+        plane_shape[z] = z1 - z0;
+        plane_start[position.0] = position.1;
+        plane_start[axis] = start;
+        plane_shape[axis] = end - start;
+        let subset = ArraySubset::new_with_start_shape(plane_start, plane_shape)?;
+        let dims = if axis == ndims - 1 {
+            [z, axis]
+        } else {
+            [axis, z]
+        };
+        let plane = decode_plane_with_options(&array, &subset, options, dims)?;
+        // Along Y or X at the step of the XY plane, Z as it is
+        let step = xy.step as isize;
+        Ok(match axis == ndims - 1 {
+            true => plane.slice(s![.., ..;step]).to_owned(),
+            false => plane.slice(s![..;step, ..]).to_owned(),
+        })
+    };
+    let xz = plane((ndims - 2, cy), (ndims - 1, (x0, x1)))?;
+    let yz = plane((ndims - 1, cx), (ndims - 2, (y0, y1)))?;
+    Ok(Ortho {
+        xy,
+        xz,
+        yz,
+        z_axis: z,
+        z_extent: (z0, z1),
+        center: [cz, cy, cx],
+    })
+}
+
+/// Lay out the XY plane with the XZ plane below it and the YZ plane to its right,
+/// with 1-pixel lines between them
+pub fn compose(xy: &RgbImage, xz: &RgbImage, yz: &RgbImage) -> RgbImage {
+    let (width, height) = (xy.width() + 1 + yz.width(), xy.height() + 1 + xz.height());
+    let mut image = RgbImage::from_pixel(width, height, Rgb(SEPARATOR_COLOR));
+    fill(
+        &mut image,
+        xy.width() as usize + 1,
+        xy.height() as usize + 1,
+        yz.width() as usize,
+        xz.height() as usize,
+        Rgb([0, 0, 0]),
+    );
+    image::imageops::replace(&mut image, xy, 0, 0);
+    image::imageops::replace(&mut image, xz, 0, xy.height() as i64 + 1);
+    image::imageops::replace(&mut image, yz, xy.width() as i64 + 1, 0);
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_group;
+    use crate::Fit;
+    use ndarray::{ArrayD, IxDyn};
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    fn store() -> Result<Arc<MemoryStore>> {
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [
+                    {"name": "c", "type": "channel"},
+                    {"name": "z", "type": "space", "unit": "micrometer"},
+                    {"name": "y", "type": "space", "unit": "micrometer"},
+                    {"name": "x", "type": "space", "unit": "micrometer"}
+                ],
+                "datasets": [{
+                    "path": "0",
+                    "coordinateTransformations": [{"type": "scale", "scale": [1.0, 2.0, 0.5, 0.5]}]
+                }]
+            }]
+        });
+        let store = Arc::new(MemoryStore::new());
+        store_group(&store, "/", attributes)?;
+        let array = ArrayBuilder::new(
+            vec![2, 4, 6, 8],
+            vec![1, 2, 3, 4],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        let data = ArrayD::from_shape_fn(IxDyn(&[2, 4, 6, 8]), |idx| {
+            (idx[0] * 1000 + idx[1] * 100 + idx[2] * 10 + idx[3]) as u16
+        });
+        array.store_array_subset_ndarray(&[0, 0, 0, 0], data)?;
+        Ok(store)
+    }
+
+    #[test]
+    fn test_read_ortho() -> Result<()> {
+        let store = store()?;
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("c".to_string(), 1.into())]),
+            ..Default::default()
+        };
+        let ortho = read_ortho(store.clone(), None, &options)?;
+        assert_eq!(ortho.center, [2, 3, 4]);
+        assert_eq!(ortho.xy.data[[1, 2]], 1212.0);
+        // Z along the rows of XZ and the columns of YZ
+        assert_eq!(ortho.xz.dim(), (4, 8));
+        assert_eq!(ortho.xz[[1, 5]], 1135.0);
+        assert_eq!(ortho.yz.dim(), (6, 4));
+        assert_eq!(ortho.yz[[5, 3]], 1354.0);
+        // Z spacing 4 times X, Y the same
+        assert_eq!(ortho.display_sizes(true, 2560), [16, 6, 8]);
+        assert_eq!(ortho.display_sizes(false, 2560), [4, 6, 8]);
+        assert_eq!(ortho.display_sizes(true, 12), [8, 3, 4]);
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![
+                SliceIndex::Axis("x".to_string(), 1.into()),
+                SliceIndex::Axis("z".to_string(), 0.into()),
+                SliceIndex::Axis("y".to_string(), 2.into()),
+            ]),
+            crop_size: 3,
+            fit: Fit::Crop,
+            ..Default::default()
+        };
+        let ortho = read_ortho(store.clone(), None, &options)?;
+        assert_eq!(ortho.center, [0, 2, 1]);
+        assert_eq!(ortho.z_extent, (0, 3));
+        assert_eq!(ortho.xz.dim(), (3, 3));
+        assert_eq!(ortho.yz[[0, 2]], 1201.0);
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("y".to_string(), 5.into())]),
+            crop_size: 3,
+            fit: Fit::Crop,
+            ..Default::default()
+        };
+        let error = read_ortho(store, None, &options).expect_err("outside the crop");
+        assert_eq!(
+            error.to_string(),
+            "The point at y=5 x=1 of --ortho is outside the crop y=0..3 x=0..3"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compose() {
+        assert_eq!(centered((2, 4), 2560), (0, 4));
+        assert_eq!(centered((9, 10), 4), (6, 10));
+        assert_eq!(centered((5, 10), 4), (3, 7));
+        let xy = RgbImage::from_pixel(8, 6, Rgb([255, 0, 0]));
+        let xz = RgbImage::from_pixel(8, 2, Rgb([0, 255, 0]));
+        let yz = RgbImage::from_pixel(2, 6, Rgb([0, 0, 255]));
+        let image = compose(&xy, &xz, &yz);
+        assert_eq!(image.dimensions(), (11, 9));
+        assert_eq!(image.get_pixel(0, 7).0, [0, 255, 0]);
+        assert_eq!(image.get_pixel(9, 0).0, [0, 0, 255]);
+        assert_eq!(image.get_pixel(8, 3).0, SEPARATOR_COLOR);
+        assert_eq!(image.get_pixel(10, 8).0, [0, 0, 0]);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::normalizer::{pooled, sample_evenly};
+use crate::{image_quantile, join_path, ngff, normalize, normalize_window, read_array_slice};
+use crate::{info, resample, warn, Filter, ReadOptions};
+use anyhow::Result;
+use ndarray::prelude::*;
+use std::sync::Arc;
+use zarrs::storage::ReadableStorageTraits;
+
+/// Thumbnails of the wells of an HCS plate, laid out by row and column
+#[derive(Debug)]
+pub struct PlateOverview {
+    pub rows: usize,
+    pub columns: usize,
+    /// Thumbnail of each well in row-major order, `None` for wells not acquired or unreadable
+    pub tiles: Vec<Option<Array2<f32>>>,
+}
+
+/// Shrink or enlarge with `filter` to fit within `size` by `size`, keeping the aspect ratio
+fn fit(data: &Array2<f32>, size: usize, filter: Filter) -> Array2<f32> {
+    let (rows, columns) = data.dim();
+    let scale = size as f64 / rows.max(columns).max(1) as f64;
+    let length = |length: usize| ((length as f64 * scale).round() as usize).max(1);
+    resample(data, length(rows), length(columns), filter)
+}
+
+/// Read field 0 of each well at its coarsest resolution level,
+/// with thumbnails resized with `filter` to fit in `well_size` pixels
+pub fn read_plate_overview<TStore: ReadableStorageTraits + ?Sized + 'static>(
+    store: Arc<TStore>,
+    options: &ReadOptions,
+    well_size: usize,
+    filter: Filter,
+) -> Result<PlateOverview> {
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let Some(plate) = ngff::group_attributes(&store, "/")?
+        .as_ref()
+        .and_then(ngff::plate)
+    else {
+        anyhow::bail!("A plate overview requires an HCS plate at the image path");
+    };
+    let (rows, columns) = (plate.rows.len(), plate.columns.len());
+    let mut tiles = vec![None; rows * columns];
+    // The whole coarsest level is read, and metadata is already consolidated above
+    let well_options = ReadOptions {
+        crop_size: u64::MAX,
+        consolidated: false,
+        well: None,
+        field: None,
+        ..options.clone()
+    };
+    for path in &plate.wells {
+        let Some((row, column)) = path.split_once('/').and_then(|(row, column)| {
+            let row = plate.rows.iter().position(|name| name == row)?;
+            Some((row, plate.columns.iter().position(|name| name == column)?))
+        }) else {
+            warn!("Skipping well {}: not in the plate rows and columns", path);
+            continue;
+        };
+        let well_group = format!("/{}", path);
+        let Some(image) = ngff::group_attributes(&store, &well_group)?
+            .as_ref()
+            .map(ngff::well_images)
+            .and_then(|images| images.into_iter().next())
+        else {
+            warn!("Skipping well {}: no fields of view", path);
+            continue;
+        };
+        let image = join_path(&well_group, &image);
+        let coarsest = ngff::group_attributes(&store, &image)?
+            .as_ref()
+            .and_then(ngff::dataset_paths)
+            .and_then(|levels| levels.last().cloned())
+            .unwrap_or_else(|| "0".to_string());
+        info!("Reading well {}", path);
+        match read_array_slice(store.clone(), &join_path(&image, &coarsest), &well_options) {
+            Ok(view) => tiles[row * columns + column] = Some(fit(&view.data, well_size, filter)),
+            Err(err) => warn!("Skipping well {}: {:#}", path, err),
+        }
+    }
+    Ok(PlateOverview {
+        rows,
+        columns,
+        tiles,
+    })
+}
+
+impl PlateOverview {
+    /// Arrange the normalized thumbnails in the plate grid, leaving missing wells black;
+    /// contrast limits are the quantiles over all wells (or `sample` of them spread evenly)
+    /// unless `per_well`
+    pub fn montage(
+        &self,
+        well_size: usize,
+        low: f64,
+        high: f64,
+        per_well: bool,
+        sample: Option<usize>,
+    ) -> Result<Array2<u8>> {
+        let tiles: Vec<_> = self.tiles.iter().flatten().collect();
+        if tiles.iter().all(|tile| tile.is_empty()) {
+            anyhow::bail!("None of the wells in the plate could be read");
+        }
+        let sampled: Vec<_> = sample_evenly(tiles.len(), sample)
+            .into_iter()
+            .map(|i| tiles[i].view())
+            .collect();
+        let values = pooled(&sampled)?;
+        let (min, max) = (
+            image_quantile(&values, low)?,
+            image_quantile(&values, high)?,
+        );
+        let gap = (well_size / 16).max(1);
+        let cell = well_size + gap;
+        let mut montage = Array2::zeros((self.rows * cell - gap, self.columns * cell - gap));
+        for (i, tile) in self.tiles.iter().enumerate() {
+            let Some(tile) = tile else {
+                continue;
+            };
+            let normalized = if per_well {
+                normalize(tile, low, high)?
+            } else {
+                normalize_window(tile, min, max)
+            };
+            let (height, width) = normalized.dim();
+            let top = i / self.columns * cell + (well_size - height) / 2;
+            let left = i % self.columns * cell + (well_size - width) / 2;
+            montage
+                .slice_mut(s![top..top + height, left..left + width])
+                .assign(&normalized);
+        }
+        Ok(montage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_group;
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    #[test]
+    fn test_plate_overview() -> Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        store_group(
+            &store,
+            "/",
+            serde_json::json!({"plate": {
+                "rows": [{"name": "A"}, {"name": "B"}],
+                "columns": [{"name": "1"}, {"name": "2"}],
+                "wells": [{"path": "A/1"}, {"path": "B/2"}]}}),
+        )?;
+        for (well, value) in [("A/1", 10u8), ("B/2", 200u8)] {
+            store_group(
+                &store,
+                &format!("/{}", well),
+                serde_json::json!({"well": {"images": [{"path": "0"}]}}),
+            )?;
+            store_group(
+                &store,
+                &format!("/{}/0", well),
+                serde_json::json!({"multiscales": [{"datasets": [{"path": "0"}, {"path": "1"}]}]}),
+            )?;
+            for (level, size) in [("0", 16), ("1", 8)] {
+                let array = ArrayBuilder::new(
+                    vec![size, size],
+                    vec![size, size],
+                    DataType::UInt8,
+                    FillValue::from(0u8),
+                )
+                .build(store.clone(), &format!("/{}/0/{}", well, level))?;
+                array.store_metadata()?;
+                let data =
+                    ArrayD::from_shape_fn(IxDyn(&[size as usize; 2]), |idx| value + idx[1] as u8);
+                array.store_array_subset_ndarray(&[0, 0], data)?;
+            }
+        }
+
+        let overview = read_plate_overview(store, &ReadOptions::default(), 4, Filter::Area)?;
+        assert_eq!((overview.rows, overview.columns), (2, 2));
+        // The coarsest level is read and shrunk to the well size
+        assert_eq!(overview.tiles[0].as_ref().map(Array2::dim), Some((4, 4)));
+        assert!(overview.tiles[1].is_none() && overview.tiles[2].is_none());
+
+        let montage = overview.montage(4, 0.0, 1.0, false, None)?;
+        assert_eq!(montage.dim(), (9, 9));
+        // The dim well stays dim with contrast across the plate
+        assert_eq!(montage[[0, 0]], 0);
+        assert!(montage[[8, 8]] > 200);
+        assert_eq!(montage[[4, 4]], 0);
+        let per_well = overview.montage(4, 0.0, 1.0, true, None)?;
+        assert_eq!(per_well[[0, 3]], 255);
+        // At the window of the first well alone
+        let sampled = overview.montage(4, 0.0, 1.0, false, Some(1))?;
+        assert_eq!((sampled[[0, 3]], sampled[[8, 8]]), (255, 255));
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use crate::error::Error;
+use crate::scalebar::fill;
+use crate::{open_array, SliceView};
+use anyhow::Result;
+use image::{Rgb, RgbImage};
+use std::fmt::Display;
+use std::sync::Arc;
+use zarrs::array::{Array, DataType, ElementOwned};
+use zarrs::array_subset::ArraySubset;
+use zarrs::storage::ReadableStorageTraits;
+
+/// Color of the crosshair marking a probe
+pub const PROBE_COLOR: [u8; 3] = [255, 0, 0];
+
+/// A pixel to read and mark, in the pixel coordinates of the whole array along Y and X
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Probe {
+    pub y: u64,
+    pub x: u64,
+}
+
+impl std::str::FromStr for Probe {
+    type Err = anyhow::Error;
+
+    /// Parse `y,x`, e.g. `120,64`
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = s
+            .split_once(',')
+            .and_then(|(y, x)| Some((y.trim().parse().ok()?, x.trim().parse().ok()?)));
+        match parsed {
+            Some((y, x)) => Ok(Self { y, x }),
+            None => anyhow::bail!("Invalid probe '{}', expected y,x in pixels, e.g. 120,64", s),
+        }
+    }
+}
+
+impl Probe {
+    /// Fail unless the probe is within the crop of the slice
+    pub fn check(&self, view: &SliceView) -> Result<()> {
+        let [(y0, y1), (x0, x1)] = view.extent();
+        if !(y0..y1).contains(&self.y) || !(x0..x1).contains(&self.x) {
+            anyhow::bail!(
+                "The probe at y={} x={} is outside the crop y={}..{} x={}..{}, \
+                 which starts at y={} x={} in the pixels of the whole array",
+                self.y,
+                self.x,
+                y0,
+                y1,
+                x0,
+                x1,
+                y0,
+                x0
+            );
+        }
+        Ok(())
+    }
+
+    /// The value at the probe in the array of the slice, at its non-spatial indices,
+    /// formatted in the data type of the array rather than as `f32`
+    pub fn read_value<TStore: ReadableStorageTraits + ?Sized + 'static>(
+        &self,
+        store: Arc<TStore>,
+        view: &SliceView,
+    ) -> Result<String> {
+        let array = open_array(store, &view.path)?;
+        let ndims = view.start.len();
+        let mut start = view.start[..ndims - 2].to_vec();
+        start.extend([self.y, self.x]);
+        let subset = ArraySubset::new_with_start_shape(start, vec![1; ndims])?;
+        match array.data_type() {
+            DataType::Int8 => element::<i8, _>(&array, &subset),
+            DataType::Int16 => element::<i16, _>(&array, &subset),
+            DataType::Int32 => element::<i32, _>(&array, &subset),
+            DataType::Int64 => element::<i64, _>(&array, &subset),
+            DataType::UInt8 => element::<u8, _>(&array, &subset),
+            DataType::UInt16 => element::<u16, _>(&array, &subset),
+            DataType::UInt32 => element::<u32, _>(&array, &subset),
+            DataType::UInt64 => element::<u64, _>(&array, &subset),
+            DataType::Float16 => element::<half::f16, _>(&array, &subset),
+            DataType::Float32 => element::<f32, _>(&array, &subset),
+            DataType::Float64 => element::<f64, _>(&array, &subset),
+            dtype => anyhow::bail!(Error::UnsupportedDataType {
+                data_type: dtype.to_string()
+            }),
+        }
+    }
+
+    /// Draw a crosshair centered on the probe in the image of the crop `extent`,
+    /// which may be resampled from the crop, leaving the probed pixel itself visible
+    pub fn draw(&self, image: &mut RgbImage, extent: [(u64, u64); 2]) {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let [(y0, y1), (x0, x1)] = extent;
+        let position = |value: u64, start: u64, end: u64, pixels: usize| {
+            ((value - start) as f64 + 0.5) * pixels as f64 / (end - start) as f64
+        };
+        let row = position(self.y, y0, y1, height) as usize;
+        let column = position(self.x, x0, x1, width) as usize;
+        let color = Rgb(PROBE_COLOR);
+        let (gap, arm) = (1, 3);
+        // Start and length of the arm before a position, shortened at the edge of the image
+        let before = |position: usize| {
+            let start = position.saturating_sub(gap + arm);
+            (start, position.saturating_sub(gap) - start)
+        };
+        let (left, length) = before(column);
+        fill(image, left, row, length, 1, color);
+        fill(image, column + gap + 1, row, arm, 1, color);
+        let (top, length) = before(row);
+        fill(image, column, top, 1, length, color);
+        fill(image, column, row + gap + 1, 1, arm, color);
+    }
+}
+
+/// The single element of `subset` as the element type of the array
+fn element<T, TStore>(array: &Array<TStore>, subset: &ArraySubset) -> Result<String>
+where
+    T: ElementOwned + Display,
+    TStore: ReadableStorageTraits + ?Sized + 'static,
+{
+    let values = array.retrieve_array_subset_elements::<T>(subset)?;
+    Ok(values.first().map(ToString::to_string).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+    use zarrs::array::{ArrayBuilder, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    #[test]
+    fn test_probe() -> Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![2, 4, 4],
+            vec![1, 4, 4],
+            DataType::UInt32,
+            FillValue::from(0u32),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        // Beyond the precision of f32
+        let values: Vec<u32> = (0..32).map(|i| 16_777_217 + i).collect();
+        array.store_array_subset_elements(&array.subset_all(), &values)?;
+
+        let view = SliceView {
+            data: Array2::zeros((2, 4)),
+            path: "/0".to_string(),
+            axes: None,
+            start: vec![1, 2, 0],
+            shape: vec![2, 4, 4],
+            data_type: "uint32".to_string(),
+            attributes: Default::default(),
+            step: 1,
+            exact: None,
+        };
+        let probe: Probe = "3, 1".parse()?;
+        probe.check(&view)?;
+        assert_eq!(probe.read_value(store, &view)?, "16777246");
+
+        let outside = Probe { y: 1, x: 1 };
+        let error = outside.check(&view).expect_err("outside the crop");
+        assert!(error.to_string().contains("starts at y=2 x=0"));
+        assert!("3".parse::<Probe>().is_err());
+        assert!("a,1".parse::<Probe>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_draw_probe() {
+        let mut image = RgbImage::new(16, 16);
+        // Shown at twice the size of the crop
+        Probe { y: 4, x: 2 }.draw(&mut image, [(0, 8), (0, 8)]);
+        assert_eq!(image.get_pixel(5, 9).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(5, 11).0, PROBE_COLOR);
+        assert_eq!(image.get_pixel(3, 9).0, PROBE_COLOR);
+        assert_eq!(image.get_pixel(5, 10).0, [0, 0, 0]);
+        // Shorter arms at the edge
+        let mut image = RgbImage::new(8, 8);
+        Probe { y: 0, x: 1 }.draw(&mut image, [(0, 8), (0, 8)]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(3, 0).0, PROBE_COLOR);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::histogram::BLOCKS;
+use crate::scalebar::fill;
+use crate::{ngff, SliceView};
+use anyhow::Result;
+use image::{Rgb, RgbImage};
+
+/// Color of the sampled line drawn over the image
+pub const PROFILE_COLOR: [u8; 3] = [255, 128, 0];
+
+/// A row or column of the whole array, in its pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Line {
+    Row(u64),
+    Column(u64),
+}
+
+impl std::str::FromStr for Line {
+    type Err = anyhow::Error;
+
+    /// Parse `row=512` or `col=100`
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = s.split_once('=').and_then(|(axis, index)| {
+            let index = index.trim().parse().ok()?;
+            match axis.trim() {
+                "row" | "y" => Some(Self::Row(index)),
+                "col" | "column" | "x" => Some(Self::Column(index)),
+                _ => None,
+            }
+        });
+        match parsed {
+            Some(line) => Ok(line),
+            None => anyhow::bail!("Invalid profile '{}', expected row=<y> or col=<x>", s),
+        }
+    }
+}
+
+/// Values along a line of a decoded slice, before normalization
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub line: Line,
+    /// Position in the array of the first value along the line
+    pub start: u64,
+    /// Pixels of the array between values, above 1 for a downsampled slice
+    pub step: u64,
+    pub values: Vec<f32>,
+    /// Scale and translation from positions along the line to physical coordinates,
+    /// if the image has coordinate transformations
+    pub physical: Option<(f64, f64)>,
+    /// Unit of the axis along the line, e.g. `micrometer`
+    pub unit: Option<String>,
+}
+
+impl Profile {
+    /// The line of the crop of the slice, failing if it is outside the crop
+    pub fn new(view: &SliceView, line: Line) -> Result<Self> {
+        let [(y0, y1), (x0, x1)] = view.extent();
+        let (name, index, (start, end), along) = match line {
+            Line::Row(y) => ("Row", y, (y0, y1), x0),
+            Line::Column(x) => ("Column", x, (x0, x1), y0),
+        };
+        if !(start..end).contains(&index) {
+            anyhow::bail!(
+                "{} {} is outside the crop y={}..{} x={}..{}",
+                name,
+                index,
+                y0,
+                y1,
+                x0,
+                x1
+            );
+        }
+        let offset = ((index - start) / view.step) as usize;
+        let values = match line {
+            Line::Row(_) => view.data.row(offset).to_vec(),
+            Line::Column(_) => view.data.column(offset).to_vec(),
+        };
+        let ndims = view.start.len();
+        let axis = match line {
+            Line::Row(_) => ndims - 1,
+            Line::Column(_) => ndims - 2,
+        };
+        let physical = ngff::physical_transform(&view.attributes, view.dataset_path(), ndims)
+            .map(|(scale, translation)| (scale[axis], translation[axis]));
+        let unit =
+            ngff::axis_units(&view.attributes).and_then(|units| units.get(axis).cloned().flatten());
+        Ok(Self {
+            line,
+            start: along,
+            step: view.step,
+            values,
+            physical,
+            unit,
+        })
+    }
+
+    /// Position in the array of the value at `index` along the line
+    fn position(&self, index: usize) -> u64 {
+        self.start + index as u64 * self.step
+    }
+
+    /// Name of the axis along the line
+    fn along(&self) -> &'static str {
+        match self.line {
+            Line::Row(_) => "x",
+            Line::Column(_) => "y",
+        }
+    }
+
+    /// Block characters of the values, averaged down to at most `width`,
+    /// from the smallest to the largest finite value, blank for NaN
+    pub fn sparkline(&self, width: usize) -> String {
+        let width = width.clamp(1, self.values.len().max(1));
+        let finite = || {
+            self.values
+                .iter()
+                .copied()
+                .filter(|value| value.is_finite())
+        };
+        let min = finite().fold(f32::INFINITY, f32::min);
+        let max = finite().fold(f32::NEG_INFINITY, f32::max);
+        (0..width)
+            .map(|column| {
+                let bin = &self.values[column * self.values.len() / width
+                    ..((column + 1) * self.values.len() / width).max(column + 1)];
+                let values: Vec<f32> = bin.iter().copied().filter(|v| v.is_finite()).collect();
+                if values.is_empty() {
+                    return ' ';
+                }
+                let mean = values.iter().sum::<f32>() / values.len() as f32;
+                let scaled = if max > min {
+                    (mean - min) / (max - min)
+                } else {
+                    0.0
+                };
+                // The lowest block for the minimum, so that it stays visible
+                BLOCKS[1 + (scaled * 7.0).round() as usize]
+            })
+            .collect()
+    }
+
+    /// The sparkline in at most `width` characters, followed by the line sampled
+    /// and its extrema with their positions
+    pub fn format(&self, width: usize) -> String {
+        let (name, index) = match self.line {
+            Line::Row(y) => ("row", y),
+            Line::Column(x) => ("col", x),
+        };
+        let extremum = |better: fn(f32, f32) -> bool| {
+            self.values
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| value.is_finite())
+                .fold(None, |best: Option<(usize, f32)>, (i, &value)| match best {
+                    Some((_, top)) if !better(value, top) => best,
+                    _ => Some((i, value)),
+                })
+        };
+        let end = self.position(self.values.len().saturating_sub(1)) + 1;
+        let mut summary = format!(
+            "{} {}, {}={}..{}",
+            name,
+            index,
+            self.along(),
+            self.start,
+            end
+        );
+        let extrema = [
+            ("min", extremum(|a, b| a < b)),
+            ("max", extremum(|a, b| a > b)),
+        ];
+        for (label, extremum) in extrema {
+            if let Some((i, value)) = extremum {
+                let position = self.position(i);
+                summary.push_str(&format!(
+                    "  {} {} at {}={}",
+                    label,
+                    value,
+                    self.along(),
+                    position
+                ));
+            }
+        }
+        format!("{}\n{}", self.sparkline(width), summary)
+    }
+
+    /// Draw the line over the image of the crop `extent`, which may be resampled from the crop
+    pub fn draw(&self, image: &mut RgbImage, extent: [(u64, u64); 2]) {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let [(y0, y1), (x0, x1)] = extent;
+        let position = |value: u64, start: u64, end: u64, pixels: usize| {
+            (((value - start) as f64 + 0.5) * pixels as f64 / (end - start) as f64) as usize
+        };
+        let color = Rgb(PROFILE_COLOR);
+        match self.line {
+            Line::Row(y) => fill(image, 0, position(y, y0, y1, height), width, 1, color),
+            Line::Column(x) => fill(image, position(x, x0, x1, width), 0, 1, height, color),
+        }
+    }
+}
+
+/// Profiles along the same line as CSV: the position along it in the pixels of the array
+/// (`x` or `y`), the physical coordinate (`x_<unit>`, or `x_physical` without a unit)
+/// if the image has coordinate transformations, and a column of values per profile
+/// named by `names`
+pub fn format_csv(profiles: &[Profile], names: &[String]) -> String {
+    let Some(first) = profiles.first() else {
+        return String::new();
+    };
+    let mut header = vec![first.along().to_string()];
+    if first.physical.is_some() {
+        let unit = first.unit.as_deref().unwrap_or("physical");
+        header.push(format!("{}_{}", first.along(), unit));
+    }
+    header.extend(names.iter().cloned());
+    let mut lines = vec![header.join(",")];
+    for i in 0..first.values.len() {
+        let position = first.position(i);
+        let mut line = position.to_string();
+        if let Some((scale, translation)) = first.physical {
+            line.push_str(&format!(",{}", translation + position as f64 * scale));
+        }
+        for profile in profiles {
+            line.push_str(&format!(",{}", profile.values[i]));
+        }
+        lines.push(line);
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array2;
+
+    fn view() -> SliceView {
+        SliceView {
+            data: Array2::from_shape_fn((4, 8), |(i, j)| (i * 10 + j) as f32),
+            path: "/0".to_string(),
+            axes: None,
+            start: vec![0, 100, 16],
+            shape: vec![1, 256, 256],
+            data_type: "uint16".to_string(),
+            attributes: Default::default(),
+            step: 1,
+            exact: None,
+        }
+    }
+
+    #[test]
+    fn test_profile() -> Result<()> {
+        let view = view();
+        let row = Profile::new(&view, "row=101".parse()?)?;
+        assert_eq!(row.start, 16);
+        assert_eq!(row.values, (10..18).map(|v| v as f32).collect::<Vec<_>>());
+        assert_eq!(row.sparkline(8), "▁▂▃▄▅▆▇█");
+        assert_eq!(row.sparkline(4).chars().count(), 4);
+        assert_eq!(
+            row.format(80),
+            "▁▂▃▄▅▆▇█\nrow 101, x=16..24  min 10 at x=16  max 17 at x=23"
+        );
+        let column = Profile::new(&view, "col=17".parse()?)?;
+        assert_eq!(column.values, vec![1.0, 11.0, 21.0, 31.0]);
+        assert_eq!(
+            format_csv(&[column], &["value".to_string()]),
+            "y,value\n100,1\n101,11\n102,21\n103,31\n"
+        );
+
+        // Physical coordinates from the scale and translation of the level
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [
+                    {"name": "c", "type": "channel"},
+                    {"name": "y", "type": "space", "unit": "micrometer"},
+                    {"name": "x", "type": "space", "unit": "micrometer"}
+                ],
+                "datasets": [{"path": "0", "coordinateTransformations": [
+                    {"type": "scale", "scale": [1.0, 0.5, 0.25]},
+                    {"type": "translation", "translation": [0.0, 10.0, 0.0]}
+                ]}]
+            }]
+        });
+        let scaled = SliceView {
+            attributes: attributes.as_object().cloned().unwrap_or_default(),
+            ..view.clone()
+        };
+        let column = Profile::new(&scaled, Line::Column(17))?;
+        assert_eq!(column.physical, Some((0.5, 10.0)));
+        assert_eq!(
+            format_csv(&[column], &["value".to_string()]),
+            "y,y_micrometer,value\n100,60,1\n101,60.5,11\n102,61,21\n103,61.5,31\n"
+        );
+
+        let error = Profile::new(&view, Line::Row(99)).expect_err("above the crop");
+        assert_eq!(
+            error.to_string(),
+            "Row 99 is outside the crop y=100..104 x=16..24"
+        );
+        assert!("z=3".parse::<Line>().is_err());
+
+        let flat = Profile {
+            line: Line::Row(0),
+            start: 0,
+            step: 1,
+            values: vec![2.0, f32::NAN, 2.0],
+            physical: None,
+            unit: None,
+        };
+        assert_eq!(flat.sparkline(3), "▁ ▁");
+        Ok(())
+    }
+
+    #[test]
+    fn test_draw_profile() -> Result<()> {
+        let view = view();
+        let mut image = RgbImage::new(8, 4);
+        Profile::new(&view, Line::Column(18))?.draw(&mut image, view.extent());
+        assert_eq!(image.get_pixel(2, 0).0, PROFILE_COLOR);
+        assert_eq!(image.get_pixel(2, 3).0, PROFILE_COLOR);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 0]);
+        Ok(())
+    }
+}
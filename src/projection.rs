@@ -0,0 +1,616 @@
+use crate::colormap::Colormap;
+use crate::composite::{check_free_axis, read_along_axis, resolve_axis};
+use crate::scalebar::format_length;
+use crate::{decode_strided, info, ngff, open_array, resolve_array_path, ReadOptions, SliceView};
+use anyhow::Result;
+use image::RgbImage;
+use ndarray::{Array2, Zip};
+use std::fmt;
+use std::sync::Arc;
+use zarrs::array_subset::ArraySubset;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// The per-pixel reductions across the planes of a projection, as given to `--project`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Max,
+    Min,
+    Mean,
+    /// Holds every plane of the range in memory, unlike the others
+    Median,
+    Sum,
+    /// The maximum, colored by the plane it is in with a cyclic colormap,
+    /// e.g. to follow moving objects over time
+    TimeColor,
+    /// The maximum, colored by the plane it is in, to tell structures at different depths apart
+    DepthColor,
+}
+
+impl std::str::FromStr for Projection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "max" => Ok(Self::Max),
+            "min" => Ok(Self::Min),
+            "mean" => Ok(Self::Mean),
+            "median" => Ok(Self::Median),
+            "sum" => Ok(Self::Sum),
+            "time-color" => Ok(Self::TimeColor),
+            "depth-color" => Ok(Self::DepthColor),
+            _ => anyhow::bail!(
+                "Unknown projection '{}', \
+                 use max, min, mean, median, sum, time-color or depth-color",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Max => "max",
+            Self::Min => "min",
+            Self::Mean => "mean",
+            Self::Median => "median",
+            Self::Sum => "sum",
+            Self::TimeColor => "time-color",
+            Self::DepthColor => "depth-color",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Projection {
+    /// Reduce the planes pixel by pixel, ignoring NaN unless a pixel is NaN in every plane,
+    /// with means and sums totalled in f64
+    pub fn project<I>(self, planes: I) -> Result<Array2<f32>>
+    where
+        I: IntoIterator<Item = Result<Array2<f32>>>,
+    {
+        let mut planes = planes.into_iter();
+        let Some(first) = planes.next().transpose()? else {
+            anyhow::bail!("No planes to project");
+        };
+        match self {
+            Self::TimeColor | Self::DepthColor => {
+                let (projected, _) = project_argmax(std::iter::once(Ok(first)).chain(planes))?;
+                Ok(projected)
+            }
+            Self::Max | Self::Min => {
+                let mut projected = first;
+                for plane in planes {
+                    projected.zip_mut_with(&plane?, |value, &next| {
+                        *value = match self {
+                            Self::Max => value.max(next),
+                            _ => value.min(next),
+                        }
+                    });
+                }
+                Ok(projected)
+            }
+            Self::Mean | Self::Sum => {
+                let mut total = Array2::<f64>::zeros(first.dim());
+                let mut count = Array2::<u32>::zeros(first.dim());
+                for plane in std::iter::once(Ok(first)).chain(planes) {
+                    Zip::from(&mut total).and(&mut count).and(&plane?).for_each(
+                        |total, count, &value| {
+                            if !value.is_nan() {
+                                *total += value as f64;
+                                *count += 1;
+                            }
+                        },
+                    );
+                }
+                Ok(Zip::from(&total)
+                    .and(&count)
+                    .map_collect(|&total, &count| match count {
+                        0 => f32::NAN,
+                        count if self == Self::Mean => (total / count as f64) as f32,
+                        _ => total as f32,
+                    }))
+            }
+            Self::Median => {
+                let planes = std::iter::once(Ok(first))
+                    .chain(planes)
+                    .collect::<Result<Vec<_>>>()?;
+                let mut values = Vec::with_capacity(planes.len());
+                Ok(Array2::from_shape_fn(planes[0].dim(), |index| {
+                    values.clear();
+                    values.extend(planes.iter().map(|plane| plane[index]));
+                    values.retain(|value| !value.is_nan());
+                    median(&mut values)
+                }))
+            }
+        }
+    }
+
+    /// Whether the projection of integers may have a fractional part
+    fn is_fractional(self) -> bool {
+        matches!(self, Self::Mean | Self::Median)
+    }
+
+    /// Whether pixels are colored by the plane of their maximum
+    pub fn is_color_coded(self) -> bool {
+        matches!(self, Self::TimeColor | Self::DepthColor)
+    }
+
+    /// The colormap of a color-coded projection unless given
+    pub fn default_colormap(self) -> Colormap {
+        match self {
+            Self::TimeColor => Colormap::Hsv,
+            _ => Colormap::Turbo,
+        }
+    }
+
+    /// The axis projected along unless given
+    pub fn default_axis(self) -> &'static str {
+        match self {
+            Self::TimeColor => "t",
+            _ => "z",
+        }
+    }
+}
+
+/// The maximum of each pixel across the planes, ignoring NaN, and the position of the
+/// plane it is first found in, holding only those two planes
+pub fn project_argmax<I>(planes: I) -> Result<(Array2<f32>, Array2<f32>)>
+where
+    I: IntoIterator<Item = Result<Array2<f32>>>,
+{
+    let mut planes = planes.into_iter();
+    let Some(mut projected) = planes.next().transpose()? else {
+        anyhow::bail!("No planes to project");
+    };
+    let mut positions = Array2::<f32>::zeros(projected.dim());
+    for (i, plane) in planes.enumerate() {
+        Zip::from(&mut projected)
+            .and(&mut positions)
+            .and(&plane?)
+            .for_each(|value, position, &next| {
+                if next > *value || (value.is_nan() && !next.is_nan()) {
+                    *value = next;
+                    *position = (i + 1) as f32;
+                }
+            });
+    }
+    Ok((projected, positions))
+}
+
+/// The color of each of `planes` planes through `colormap`, evenly spaced from its start
+/// to its end, or around a cyclic colormap short of its start
+pub fn plane_colors(planes: u64, colormap: Colormap) -> Vec<[u8; 3]> {
+    let lut = colormap.lut();
+    let steps = match colormap.is_cyclic() {
+        true => planes as f64,
+        false => planes.saturating_sub(1).max(1) as f64,
+    };
+    (0..planes.max(1))
+        .map(|plane| lut[((plane as f64 * 255.0 / steps).round() as usize).min(255)])
+        .collect()
+}
+
+/// Color each pixel from its position among `planes` planes through `colormap`,
+/// at the brightness of its normalized value
+pub fn color_code(
+    normalized: &Array2<u8>,
+    positions: &Array2<f32>,
+    planes: u64,
+    colormap: Colormap,
+) -> RgbImage {
+    let colors = plane_colors(planes, colormap);
+    let (rows, columns) = normalized.dim();
+    RgbImage::from_fn(columns as u32, rows as u32, |x, y| {
+        let (row, column) = (y as usize, x as usize);
+        let color = colors[(positions[[row, column]] as usize).min(colors.len() - 1)];
+        let brightness = normalized[[row, column]] as u16;
+        image::Rgb(color.map(|c| (c as u16 * brightness / 255) as u8))
+    })
+}
+
+/// The median of `values`, reordering them, the mean of the two middle values
+/// for an even count and NaN for none
+fn median(values: &mut [f32]) -> f32 {
+    let (middle, odd) = (values.len() / 2, values.len() % 2 == 1);
+    if values.is_empty() {
+        return f32::NAN;
+    }
+    let (below, &mut upper, _) = values.select_nth_unstable_by(middle, f32::total_cmp);
+    if odd {
+        return upper;
+    }
+    let lower = below.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    (lower + upper) / 2.0
+}
+
+/// Indices along the projection axis, `start:end` with either end left out for the
+/// first or last index, e.g. `20:60`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct IndexRange {
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+impl std::str::FromStr for IndexRange {
+    type Err = anyhow::Error;
+
+    /// Parse `20:60`, `20:` or `:60`
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |index: &str| -> Option<Option<u64>> {
+            match index.trim() {
+                "" => Some(None),
+                index => index.parse().ok().map(Some),
+            }
+        };
+        let parsed = s
+            .split_once(':')
+            .and_then(|(start, end)| Some((parse(start)?, parse(end)?)));
+        match parsed {
+            Some((start, end)) => Ok(Self { start, end }),
+            None => anyhow::bail!("Invalid range '{}', expected start:end, e.g. 20:60", s),
+        }
+    }
+}
+
+/// The planes a projected slice was reduced from, for captions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectionSpan {
+    pub projection: Projection,
+    /// Dimension of the projection axis
+    pub dimension: usize,
+    /// Name of the axis, or its dimension if the axes are not named
+    pub axis: String,
+    /// First index along the axis
+    pub start: u64,
+    /// End of the indices along the axis, exclusive
+    pub end: u64,
+    /// Colormap of the planes of a color-coded projection
+    pub colormap: Option<Colormap>,
+}
+
+/// A projected slice, with the planes it was projected from
+#[derive(Debug)]
+pub struct Projected {
+    /// The projection, at the start of the range along the axis
+    pub view: SliceView,
+    pub span: ProjectionSpan,
+    /// Position in the range of the plane of the maximum of each pixel,
+    /// for color-coded projections
+    pub positions: Option<Array2<f32>>,
+}
+
+impl ProjectionSpan {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": self.projection.to_string(),
+            "axis": self.axis,
+            "start": self.start,
+            "end": self.end,
+            "colormap": self.colormap.map(|colormap| colormap.to_string()),
+        })
+    }
+}
+
+impl ProjectionSpan {
+    /// Labels of the first and last plane for the colorbar of a color-coded projection,
+    /// their physical position if the axis has a length unit, e.g. `12 µm`, or `Z=20` otherwise
+    pub fn plane_labels(&self, view: &SliceView) -> (String, String) {
+        let ndims = view.start.len();
+        let unit = ngff::axis_units(&view.attributes)
+            .and_then(|units| units.get(self.dimension).cloned().flatten())
+            .and_then(|unit| ngff::parse_unit(&unit));
+        let transform = ngff::physical_transform(&view.attributes, view.dataset_path(), ndims);
+        let label = |index: u64| match (unit, &transform) {
+            (Some((ngff::Quantity::Length, size)), Some((scale, translation))) => {
+                let position =
+                    (index as f64 * scale[self.dimension] + translation[self.dimension]) * size;
+                match position {
+                    0.0 => "0".to_string(),
+                    _ if position < 0.0 => format!("-{}", format_length(-position)),
+                    _ => format_length(position),
+                }
+            }
+            _ => format!("{}={}", self.axis, index).to_uppercase(),
+        };
+        (label(self.start), label(self.end.saturating_sub(1)))
+    }
+}
+
+impl fmt::Display for ProjectionSpan {
+    /// E.g. `max projection z=20..60` or `time-color projection t=0..20 (hsv)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} projection {}={}..{}",
+            self.projection, self.axis, self.start, self.end
+        )?;
+        if let Some(colormap) = self.colormap {
+            write!(f, " ({})", colormap)?;
+        }
+        Ok(())
+    }
+}
+
+/// Project the slices at the indices of `range` along `axis`, a name of the OME-NGFF axes
+/// or a dimension, with the other slice indices of `options`, reading one plane at a time
+pub fn read_projection<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+    projection: Projection,
+    axis: &str,
+    range: IndexRange,
+) -> Result<Projected>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let attributes = ngff::group_attributes(&store, ngff::parent_path(&path))?.unwrap_or_default();
+    let array = open_array(store.clone(), &path)?;
+    let shape = array.shape().to_vec();
+    let ndims = shape.len();
+    let axes = ngff::axis_names(&attributes).filter(|axes| axes.len() == ndims);
+    let (dimension, name) = resolve_axis(
+        &shape,
+        axes.as_deref(),
+        axis,
+        "--project-axis",
+        "projection",
+    )?;
+    check_free_axis(options, dimension, name, "--project", "projection axis")?;
+    let axis = name.map_or_else(|| dimension.to_string(), str::to_string);
+    let size = shape[dimension];
+    let (start, end) = (range.start.unwrap_or(0), range.end.unwrap_or(size));
+    if start >= end || end > size {
+        anyhow::bail!(
+            "The range {}..{} for --project-range is empty or outside the {} indices along {}",
+            start,
+            end,
+            size,
+            axis
+        );
+    }
+    info!(
+        "Taking the {} projection of {} planes along {}",
+        projection,
+        end - start,
+        axis
+    );
+    let mut views = read_along_axis(store, &path, &shape, options, dimension, name, &[start])?;
+    let mut view = views.remove(0);
+    // Decoded plane by plane as they are folded in,
+    // so that memory use does not grow with the range
+    let plane_shape = view.subset_at(view.start.clone())?.shape().to_vec();
+    let rest = (start + 1..end).map(|index| {
+        let mut plane_start = view.start.clone();
+        plane_start[dimension] = index;
+        let subset = ArraySubset::new_with_start_shape(plane_start, plane_shape.clone())?;
+        decode_strided(&array, &subset, view.step, options)
+    });
+    let planes = std::iter::once(Ok(std::mem::take(&mut view.data))).chain(rest);
+    let positions = if projection.is_color_coded() {
+        let (projected, positions) = project_argmax(planes)?;
+        view.data = projected;
+        Some(positions)
+    } else {
+        view.data = projection.project(planes)?;
+        None
+    };
+    // Folded from the planes as f32
+    view.exact = None;
+    if projection.is_fractional() {
+        view.data_type = "float32".to_string();
+    }
+    let span = ProjectionSpan {
+        projection,
+        dimension,
+        axis,
+        start,
+        end,
+        colormap: None,
+    };
+    Ok(Projected {
+        view,
+        span,
+        positions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_store;
+    use crate::SliceIndex;
+    use ndarray::{ArrayD, IxDyn};
+
+    #[test]
+    fn test_parse_projection() -> Result<()> {
+        assert_eq!("max".parse::<Projection>()?, Projection::Max);
+        assert!("maximum".parse::<Projection>().is_err());
+        let depth = "depth-color".parse::<Projection>()?;
+        assert_eq!(depth.to_string(), "depth-color");
+        assert_eq!(
+            (depth.default_axis(), depth.default_colormap()),
+            ("z", Colormap::Turbo)
+        );
+        assert_eq!(
+            "20:60".parse::<IndexRange>()?,
+            IndexRange {
+                start: Some(20),
+                end: Some(60)
+            }
+        );
+        assert_eq!(":60".parse::<IndexRange>()?.start, None);
+        assert_eq!("20:".parse::<IndexRange>()?.end, None);
+        assert!("20".parse::<IndexRange>().is_err());
+        assert!("a:b".parse::<IndexRange>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project() -> Result<()> {
+        let nan = f32::NAN;
+        let planes = [
+            vec![1.0, 0.0, nan, 4.0],
+            vec![0.0, 2.0, nan, 1.0],
+            vec![5.0, nan, nan, 2.0],
+            vec![3.0, 4.0, nan, 3.0],
+        ];
+        let project = |projection: Projection| -> Result<Vec<f32>> {
+            let planes = planes
+                .iter()
+                .map(|plane| Ok(Array2::from_shape_vec((1, 4), plane.clone())?));
+            Ok(projection.project(planes)?.into_raw_vec_and_offset().0)
+        };
+        let same = |a: Vec<f32>, b: &[f32]| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| a == b || (a.is_nan() && b.is_nan()))
+        };
+        assert!(same(project(Projection::Max)?, &[5.0, 4.0, nan, 4.0]));
+        assert!(same(project(Projection::Min)?, &[0.0, 0.0, nan, 1.0]));
+        assert!(same(project(Projection::Sum)?, &[9.0, 6.0, nan, 10.0]));
+        assert!(same(project(Projection::Mean)?, &[2.25, 2.0, nan, 2.5]));
+        assert!(same(project(Projection::Median)?, &[2.0, 2.0, nan, 2.5]));
+
+        // Totals beyond the precision of f32 while accumulating
+        let planes = (0..3).map(|i| match i {
+            0 => Ok(Array2::from_elem((1, 1), 16_777_216.0)),
+            _ => Ok(Array2::from_elem((1, 1), 1.0)),
+        });
+        assert_eq!(Projection::Sum.project(planes)?[[0, 0]], 16_777_218.0);
+        assert!(Projection::Max.project(std::iter::empty()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_color_code() -> Result<()> {
+        let planes = [
+            vec![1.0, f32::NAN, 3.0],
+            vec![2.0, 5.0, 3.0],
+            vec![0.0, 4.0, 9.0],
+        ]
+        .map(|plane| Ok(Array2::from_shape_vec((1, 3), plane)?));
+        let (projected, positions) = project_argmax(planes)?;
+        assert_eq!(projected.into_raw_vec_and_offset().0, vec![2.0, 5.0, 9.0]);
+        // The first of equal maxima
+        assert_eq!(positions.into_raw_vec_and_offset().0, vec![1.0, 1.0, 2.0]);
+
+        let normalized = Array2::from_shape_vec((1, 3), vec![255, 128, 0])?;
+        let positions = Array2::from_shape_vec((1, 3), vec![0.0, 1.0, 2.0])?;
+        let image = color_code(&normalized, &positions, 3, Colormap::Hsv);
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0]);
+        // A third of the way around at half brightness
+        assert_eq!(image.get_pixel(1, 0).0, [1, 128, 0]);
+        assert_eq!(image.get_pixel(2, 0).0, [0, 0, 0]);
+        // Across the whole of a colormap that is not cyclic
+        let image = color_code(&normalized.mapv(|_| 255), &positions, 3, Colormap::Gray);
+        assert_eq!(image.get_pixel(2, 0).0, [255, 255, 255]);
+        assert_eq!(
+            plane_colors(4, Colormap::Gray),
+            vec![[0; 3], [85; 3], [170; 3], [255; 3]]
+        );
+        assert_eq!(plane_colors(4, Colormap::Hsv)[2], Colormap::Hsv.lut()[128]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_projection() -> Result<()> {
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [{"name": "c", "type": "channel"}, {"name": "z"}, {"name": "y"}, {"name": "x"}],
+                "datasets": [{"path": "0"}]
+            }]
+        });
+        let store = image_store(attributes, &[2, 3, 4, 4], &[1, 1, 4, 4])?;
+        let array = zarrs::array::Array::open(store.clone(), "/0")?;
+        // The brightest plane differs between rows
+        let data = ArrayD::from_shape_fn(IxDyn(&[2, 3, 4, 4]), |idx| {
+            (idx[0] * 100 + (idx[1] + idx[2]) % 3 * 10 + idx[3]) as u8
+        });
+        array.store_array_subset_ndarray(&[0, 0, 0, 0], data)?;
+
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("c".to_string(), 1.into())]),
+            ..Default::default()
+        };
+        let Projected { view, span, .. } = read_projection(
+            store.clone(),
+            None,
+            &options,
+            Projection::Max,
+            "z",
+            IndexRange::default(),
+        )?;
+        assert_eq!(span.to_string(), "max projection z=0..3");
+        assert!(view.data.column(2).iter().all(|&value| value == 122.0));
+        let projected = read_projection(
+            store.clone(),
+            None,
+            &options,
+            Projection::TimeColor,
+            "z",
+            IndexRange::default(),
+        )?;
+        assert_eq!(projected.view.data, view.data);
+        // The brightest plane of each row
+        let positions = projected.positions.expect("color-coded");
+        assert_eq!(positions.column(0).to_vec(), vec![2.0, 1.0, 0.0, 2.0]);
+        let labels = projected.span.plane_labels(&projected.view);
+        assert_eq!(labels, ("Z=0".to_string(), "Z=2".to_string()));
+        // At the physical depths of the planes with a z unit
+        let mut view = projected.view;
+        view.attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [{"name": "c"}, {"name": "z", "unit": "micrometer"}, {"name": "y"}, {"name": "x"}],
+                "datasets": [{"path": "0", "coordinateTransformations": [
+                    {"type": "scale", "scale": [1.0, 2.0, 0.5, 0.5]},
+                    {"type": "translation", "translation": [0.0, -1.0, 0.0, 0.0]}
+                ]}]
+            }]
+        })
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+        let labels = projected.span.plane_labels(&view);
+        assert_eq!(labels, ("-1 µm".to_string(), "3 µm".to_string()));
+
+        let range = "0:2".parse()?;
+        let Projected { view, span, .. } =
+            read_projection(store.clone(), None, &options, Projection::Max, "1", range)?;
+        assert_eq!((span.dimension, span.start, span.end), (1, 0, 2));
+        assert_eq!(span.to_json()["type"], "max");
+        assert_eq!(view.start, vec![1, 0, 0, 0]);
+        let rows: Vec<f32> = view.data.column(0).to_vec();
+        assert_eq!(rows, vec![110.0, 120.0, 120.0, 110.0]);
+
+        let range = "2:5".parse()?;
+        let error = read_projection(store.clone(), None, &options, Projection::Max, "z", range)
+            .expect_err("past the end");
+        assert_eq!(
+            error.to_string(),
+            "The range 2..5 for --project-range is empty or outside the 3 indices along z"
+        );
+        let error = read_projection(
+            store,
+            None,
+            &options,
+            Projection::Max,
+            "c",
+            IndexRange::default(),
+        )
+        .expect_err("sliced axis");
+        assert_eq!(
+            error.to_string(),
+            "Cannot combine --project with a slice index for the projection axis 'c'"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,445 @@
+use crate::{
+    decode_plane_with_options, join_path, ngff, open_array, resample, resolve_array_path,
+    slice_subset, Filter, ReadOptions,
+};
+use anyhow::Result;
+use ndarray::Array2;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+use zarrs::array_subset::ArraySubset;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// Side of the square windows SSIM is averaged over, as in scikit-image
+const SSIM_WINDOW: usize = 7;
+
+/// The comparison of two adjacent resolution levels over the same physical region
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelCheck {
+    /// Indices of the finer and the coarser level
+    pub levels: (usize, usize),
+    /// Dataset paths of the finer and the coarser level
+    pub paths: (String, String),
+    /// Scale factor from the finer to the coarser level along Y and X
+    pub factor: [u64; 2],
+    /// Start and end along Y and X of the region compared, in pixels of the coarser level
+    pub extent: [(u64, u64); 2],
+    /// Mean squared error of the downsampled finer level, in squared units of the data
+    pub mse: f64,
+    pub ssim: f64,
+    /// Whether the SSIM is at least the threshold
+    pub passed: bool,
+}
+
+impl LevelCheck {
+    /// JSON object with the levels, region and metrics
+    pub fn to_json(&self) -> Value {
+        let [(y0, y1), (x0, x1)] = self.extent;
+        serde_json::json!({
+            "levels": [self.levels.0, self.levels.1],
+            "paths": [self.paths.0, self.paths.1],
+            "factor": self.factor,
+            "extent": {"y": [y0, y1], "x": [x0, x1]},
+            "mse": self.mse,
+            "ssim": self.ssim,
+            "passed": self.passed,
+        })
+    }
+}
+
+impl fmt::Display for LevelCheck {
+    /// E.g. `levels 0 and 1 (0, 1)  2x2  y=0..32 x=0..32  MSE 1.25  SSIM 0.9981  ok`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [(y0, y1), (x0, x1)] = self.extent;
+        write!(
+            f,
+            "levels {} and {} ({}, {})  {}x{}  y={}..{} x={}..{}  MSE {:.6}  SSIM {:.4}  {}",
+            self.levels.0,
+            self.levels.1,
+            self.paths.0,
+            self.paths.1,
+            self.factor[0],
+            self.factor[1],
+            y0,
+            y1,
+            x0,
+            x1,
+            self.mse,
+            self.ssim,
+            match self.passed {
+                true => "ok",
+                false => "MISMATCH",
+            }
+        )
+    }
+}
+
+/// Compare each pair of adjacent levels of the image over the physical region of the slice
+/// selected by `options`: the finer level is averaged over blocks of the scale factor
+/// between the two (from their `coordinateTransformations`) and compared to the coarser
+/// level, passing at an SSIM of at least `min_ssim`
+pub fn check_pyramid<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+    min_ssim: f64,
+) -> Result<Vec<LevelCheck>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let group = ngff::parent_path(&path);
+    let attributes = ngff::group_attributes(&store, group)?.unwrap_or_default();
+    let Some(multiscale) = ngff::multiscale(&attributes) else {
+        anyhow::bail!("No multiscales metadata in group {}", group);
+    };
+    let datasets = multiscale.datasets;
+    if datasets.len() < 2 {
+        anyhow::bail!("The image has a single resolution level, there is no pyramid to check");
+    }
+    let Some(shown) = datasets
+        .iter()
+        .position(|dataset| join_path(group, &dataset.path) == path)
+    else {
+        anyhow::bail!(
+            "The array {} is not a resolution level of the multiscales metadata",
+            path
+        );
+    };
+    let array = open_array(store.clone(), &path)?;
+    let ndims = array.shape().len();
+    let (_, subset, _) = slice_subset(&array, &path, Some(&attributes), options)?;
+    let mut transforms = vec![];
+    for (level, dataset) in datasets.iter().enumerate() {
+        let transform = ngff::physical_transform(&attributes, &dataset.path, ndims).filter(|_| {
+            dataset
+                .scale
+                .as_ref()
+                .is_some_and(|scale| scale.len() == ndims)
+        });
+        let Some(transform) = transform else {
+            anyhow::bail!(
+                "Level {} ({}) has no scale of {} values in its coordinateTransformations, \
+                 needed to find the same region in each level",
+                level,
+                dataset.path,
+                ndims
+            );
+        };
+        transforms.push(transform);
+    }
+    // The slice in physical coordinates, at the index along each other axis and from the
+    // start to the end of the crop along Y and X
+    let (scale, translation) = &transforms[shown];
+    let physical = |d: usize, index: u64| index as f64 * scale[d] + translation[d];
+    let start = subset.start();
+    let end = subset.end_exc();
+    let mut checks = vec![];
+    for fine in 0..datasets.len() - 1 {
+        let coarse = fine + 1;
+        let paths = (datasets[fine].path.clone(), datasets[coarse].path.clone());
+        let name = format!("Levels {} and {} ({}, {})", fine, coarse, paths.0, paths.1);
+        let fine_array = open_array(store.clone(), &join_path(group, &paths.0))?;
+        let coarse_array = open_array(store.clone(), &join_path(group, &paths.1))?;
+        let (fine_shape, coarse_shape) = (fine_array.shape(), coarse_array.shape());
+        if fine_shape.len() != ndims || coarse_shape.len() != ndims {
+            anyhow::bail!("{}: the arrays have other dimensions than {}", name, path);
+        }
+        let ((fine_scale, fine_translation), (coarse_scale, coarse_translation)) =
+            (&transforms[fine], &transforms[coarse]);
+        let mut factors = vec![];
+        let mut offsets = vec![];
+        for d in 0..ndims {
+            let factor = coarse_scale[d] / fine_scale[d];
+            let whole = factor.round();
+            if whole < 1.0 || (factor - whole).abs() > 1e-6 * factor {
+                anyhow::bail!(
+                    "{}: the scale factor {} along dimension {} is not a whole number",
+                    name,
+                    factor,
+                    d
+                );
+            }
+            // Pixels of the finer level before the first of the coarser one, whether the
+            // translations put the centers or the corners of the pixels at the same place
+            let shift = (coarse_translation[d] - fine_translation[d]) / fine_scale[d];
+            factors.push(whole as u64);
+            offsets.push((shift - (whole - 1.0) / 2.0 + 0.5).floor() as i64);
+        }
+        // The region in pixels of the coarser level, within the finer level wherever its
+        // blocks are read, and at most the crop size of finer pixels along Y and X
+        let covered = |d: usize| {
+            let first = (-offsets[d]).max(0) as u64;
+            let first = first.div_ceil(factors[d]);
+            let last = ((fine_shape[d] as i64 - offsets[d]).max(0) as u64 / factors[d])
+                .min(coarse_shape[d]);
+            (first, last)
+        };
+        let to_coarse =
+            |d: usize, index: u64| (physical(d, index) - coarse_translation[d]) / coarse_scale[d];
+        let mut coarse_start = vec![0; ndims];
+        let mut coarse_end = vec![0; ndims];
+        for d in 0..ndims {
+            let (first, last) = covered(d);
+            if first >= last {
+                anyhow::bail!("{}: the levels do not overlap along dimension {}", name, d);
+            }
+            if d + 2 < ndims {
+                let index = (to_coarse(d, start[d]).round().max(0.0) as u64).clamp(first, last - 1);
+                (coarse_start[d], coarse_end[d]) = (index, index + 1);
+                continue;
+            }
+            let mut from = (to_coarse(d, start[d]).floor().max(0.0) as u64).clamp(first, last - 1);
+            let mut to = (to_coarse(d, end[d]).ceil().max(0.0) as u64).clamp(from + 1, last);
+            let most = (options.crop_size / factors[d]).max(1);
+            if to - from > most {
+                from += (to - from - most) / 2;
+                to = from + most;
+            }
+            (coarse_start[d], coarse_end[d]) = (from, to);
+        }
+        let fine_start: Vec<u64> = (0..ndims)
+            .map(|d| (coarse_start[d] * factors[d]) as i64 + offsets[d])
+            .map(|index| index as u64)
+            .collect();
+        let fine_lengths: Vec<u64> = (0..ndims)
+            .map(|d| (coarse_end[d] - coarse_start[d]) * factors[d])
+            .collect();
+        let plane_dims = [ndims - 2, ndims - 1];
+        // The mean of the planes of the finer level within the block along other axes,
+        // e.g. two Z planes of a 3D pyramid
+        let mut planes = 0;
+        let mut sum: Option<Array2<f32>> = None;
+        let mut index = fine_start[..ndims - 2].to_vec();
+        loop {
+            let mut plane_start = index.clone();
+            plane_start.extend(&fine_start[ndims - 2..]);
+            let mut plane_shape = vec![1; ndims - 2];
+            plane_shape.extend(&fine_lengths[ndims - 2..]);
+            let subset = ArraySubset::new_with_start_shape(plane_start, plane_shape)?;
+            let plane = decode_plane_with_options(&fine_array, &subset, options, plane_dims)?;
+            sum = Some(match sum {
+                Some(sum) => sum + plane,
+                None => plane,
+            });
+            planes += 1;
+            // The next index within the block, the last axis fastest
+            let next = (0..ndims - 2)
+                .rev()
+                .find(|&d| index[d] + 1 < fine_start[d] + fine_lengths[d]);
+            let Some(d) = next else {
+                break;
+            };
+            index[d] += 1;
+            index[d + 1..].copy_from_slice(&fine_start[d + 1..ndims - 2]);
+        }
+        let mean = sum.unwrap_or_default() / planes as f32;
+        let coarse_subset =
+            ArraySubset::new_with_start_end_exc(coarse_start.clone(), coarse_end.clone())?;
+        let expected =
+            decode_plane_with_options(&coarse_array, &coarse_subset, options, plane_dims)?;
+        let downsampled = resample(&mean, expected.nrows(), expected.ncols(), Filter::Area);
+        let (mse, ssim) = compare(&downsampled, &expected);
+        checks.push(LevelCheck {
+            levels: (fine, coarse),
+            paths,
+            factor: [factors[ndims - 2], factors[ndims - 1]],
+            extent: [
+                (coarse_start[ndims - 2], coarse_end[ndims - 2]),
+                (coarse_start[ndims - 1], coarse_end[ndims - 1]),
+            ],
+            mse,
+            ssim,
+            passed: ssim >= min_ssim,
+        });
+    }
+    Ok(checks)
+}
+
+/// The mean squared error and mean SSIM of two planes of the same shape,
+/// over windows of [`SSIM_WINDOW`] pixels (or the whole plane if smaller) at the
+/// range of values of both; pixels not finite in either count as the same
+pub fn compare(a: &Array2<f32>, b: &Array2<f32>) -> (f64, f64) {
+    let finite = |a: f32, b: f32| a.is_finite() && b.is_finite();
+    let [a, b] = [(a, b), (b, a)].map(|(this, other)| {
+        ndarray::Zip::from(this)
+            .and(other)
+            .map_collect(|&x, &y| match finite(x, y) {
+                true => x as f64,
+                false => 0.0,
+            })
+    });
+    let (rows, columns) = a.dim();
+    if rows == 0 || columns == 0 {
+        return (0.0, 1.0);
+    }
+    let mse = (&a - &b).mapv(|d| d * d).mean().unwrap_or(0.0);
+    let (min, max) = a
+        .iter()
+        .chain(&b)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        });
+    let range = match max > min {
+        true => max - min,
+        false => 1.0,
+    };
+    let (c1, c2) = ((0.01 * range).powi(2), (0.03 * range).powi(2));
+    // Sums over the windows from integral images of each moment
+    let integral = |values: Array2<f64>| {
+        let mut sums = Array2::<f64>::zeros((rows + 1, columns + 1));
+        for ((row, column), value) in values.indexed_iter() {
+            sums[[row + 1, column + 1]] =
+                value + sums[[row, column + 1]] + sums[[row + 1, column]] - sums[[row, column]];
+        }
+        sums
+    };
+    let moments = [
+        integral(a.clone()),
+        integral(b.clone()),
+        integral(&a * &a),
+        integral(&b * &b),
+        integral(&a * &b),
+    ];
+    let (height, width) = (SSIM_WINDOW.min(rows), SSIM_WINDOW.min(columns));
+    let count = (height * width) as f64;
+    let mut total = 0.0;
+    let mut windows = 0;
+    for row in 0..=rows - height {
+        for column in 0..=columns - width {
+            let [ma, mb, maa, mbb, mab] = moments.each_ref().map(|sums| {
+                (sums[[row + height, column + width]]
+                    - sums[[row, column + width]]
+                    - sums[[row + height, column]]
+                    + sums[[row, column]])
+                    / count
+            });
+            let (va, vb, cov) = (maa - ma * ma, mbb - mb * mb, mab - ma * mb);
+            total += ((2.0 * ma * mb + c1) * (2.0 * cov + c2))
+                / ((ma * ma + mb * mb + c1) * (va + vb + c2));
+            windows += 1;
+        }
+    }
+    (mse, total / windows as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_group;
+    use crate::{SliceIndex, SliceValue};
+    use ndarray::{ArrayD, IxDyn};
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    /// A 2x16x16 image (z, y, x) and a 1x8x8 level averaged from it, or from other data
+    fn store(stale: bool) -> Result<Arc<MemoryStore>> {
+        let store = Arc::new(MemoryStore::new());
+        let attributes = serde_json::json!({
+            "multiscales": [{
+                "axes": [
+                    {"name": "z", "type": "space", "unit": "micrometer"},
+                    {"name": "y", "type": "space", "unit": "micrometer"},
+                    {"name": "x", "type": "space", "unit": "micrometer"}
+                ],
+                "datasets": [
+                    {
+                        "path": "0",
+                        "coordinateTransformations": [{"type": "scale", "scale": [1.0, 0.5, 0.5]}]
+                    },
+                    {
+                        "path": "1",
+                        "coordinateTransformations": [
+                            {"type": "scale", "scale": [2.0, 1.0, 1.0]},
+                            {"type": "translation", "translation": [0.5, 0.25, 0.25]}
+                        ]
+                    }
+                ]
+            }]
+        });
+        store_group(&store, "/", attributes)?;
+        let value = |z: usize, y: usize, x: usize| (z * 40 + (y * x) % 13 * 10 + y) as f32;
+        let fine = ArrayD::from_shape_fn(IxDyn(&[2, 16, 16]), |i| value(i[0], i[1], i[2]));
+        let coarse = ArrayD::from_shape_fn(IxDyn(&[1, 8, 8]), |i| {
+            let (y, x) = (i[1] * 2, i[2] * 2);
+            match stale {
+                true => ((x * 7) % 5 * 40) as f32,
+                false => {
+                    let block = [(0, y, x), (0, y, x + 1), (0, y + 1, x), (0, y + 1, x + 1)];
+                    let sum: f32 = block
+                        .iter()
+                        .flat_map(|&(_, y, x)| [value(0, y, x), value(1, y, x)])
+                        .sum();
+                    sum / 8.0
+                }
+            }
+        });
+        for (path, data) in [("/0", fine), ("/1", coarse)] {
+            let shape: Vec<u64> = data.shape().iter().map(|&n| n as u64).collect();
+            let array = ArrayBuilder::new(
+                shape,
+                vec![1, 4, 4],
+                DataType::Float32,
+                FillValue::from(0f32),
+            )
+            .build(store.clone(), path)?;
+            array.store_metadata()?;
+            array.store_array_subset_ndarray(&[0, 0, 0], data)?;
+        }
+        Ok(store)
+    }
+
+    #[test]
+    fn test_check_pyramid() -> Result<()> {
+        let options = ReadOptions {
+            slice_indices: Some(vec![SliceIndex::Axis("z".to_string(), SliceValue::First)]),
+            level: Some(0),
+            ..Default::default()
+        };
+        let checks = check_pyramid(store(false)?, None, &options, 0.9)?;
+        let [check] = checks.as_slice() else {
+            panic!("{:?}", checks);
+        };
+        assert_eq!(check.levels, (0, 1));
+        assert_eq!(check.factor, [2, 2]);
+        assert_eq!(check.extent, [(0, 8), (0, 8)]);
+        assert!(check.mse < 1e-6 && check.ssim > 0.999, "{}", check);
+        assert!(check.passed);
+        // Regenerated from other data
+        let checks = check_pyramid(store(true)?, None, &options, 0.9)?;
+        assert!(!checks[0].passed && checks[0].mse > 100.0, "{}", checks[0]);
+        assert!(checks[0]
+            .to_string()
+            .starts_with("levels 0 and 1 (0, 1)  2x2  y=0..8 x=0..8"));
+        // Within a crop of the finer level
+        let options = ReadOptions {
+            crop_size: 8,
+            ..options
+        };
+        let checks = check_pyramid(store(false)?, None, &options, 0.9)?;
+        assert_eq!(checks[0].extent, [(2, 6), (2, 6)]);
+        assert!(checks[0].passed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare() {
+        let a = Array2::from_shape_fn((12, 10), |(y, x)| (y * 10 + x) as f32);
+        let (mse, ssim) = compare(&a, &a);
+        assert_eq!(mse, 0.0);
+        assert!((ssim - 1.0).abs() < 1e-9);
+        let (mse, ssim) = compare(&a, &(&a + 2.0));
+        assert_eq!(mse, 4.0);
+        assert!(ssim < 1.0 && ssim > 0.9);
+        let flipped = a.slice(ndarray::s![..;-1, ..]).to_owned();
+        assert!(compare(&a, &flipped).1 < 0.5);
+        // Not finite in either counts as the same
+        let mut b = a.clone();
+        b[[0, 0]] = f32::NAN;
+        assert_eq!(compare(&a, &b).0, 0.0);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{info, warn};
+use anyhow::Result;
+
+/// Half-open range of pixels along Y or X, with negative ends counting from the end
+/// of the axis and a missing end meaning its boundary
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RoiRange {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl RoiRange {
+    /// Start and end among `size` pixels, clamped to the axis with a warning
+    /// if the range overflows it, failing if nothing is left
+    pub fn resolve(&self, size: u64, axis: &str) -> Result<(u64, u64)> {
+        let position = |value: i64| match value < 0 {
+            true => size as i64 + value,
+            false => value,
+        };
+        let start = self.start.map_or(0, position);
+        let end = self.end.map_or(size as i64, position);
+        let clamp = |value: i64| value.clamp(0, size as i64) as u64;
+        let (clamped_start, clamped_end) = (clamp(start), clamp(end));
+        if clamped_start >= clamped_end {
+            anyhow::bail!(
+                "The ROI {}={} is empty or outside the {} pixels along {}",
+                axis,
+                self,
+                size,
+                axis
+            );
+        }
+        if (clamped_start as i64, clamped_end as i64) != (start, end) {
+            warn!(
+                "The ROI {}={} overflows the {} pixels along {}, clamping it to {}..{}",
+                axis, self, size, axis, clamped_start, clamped_end
+            );
+        }
+        Ok((clamped_start, clamped_end))
+    }
+}
+
+impl std::fmt::Display for RoiRange {
+    /// The range as it was given, e.g. `-512:`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let end = |value: Option<i64>| value.map_or_else(String::new, |value| value.to_string());
+        write!(f, "{}:{}", end(self.start), end(self.end))
+    }
+}
+
+/// Region of interest along Y and X in the pixels of the array, as given to `--roi`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Roi {
+    pub y: RoiRange,
+    pub x: RoiRange,
+}
+
+impl std::fmt::Display for Roi {
+    /// The region as `--roi` takes it, e.g. `100:600,-512:`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.y, self.x)
+    }
+}
+
+impl std::str::FromStr for RoiRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |part: &str| match part.trim() {
+            "" => Some(None),
+            part => part.parse().ok().map(Some),
+        };
+        match s.split_once(':') {
+            Some((start, end)) => match (parse(start), parse(end)) {
+                (Some(start), Some(end)) => Ok(Self { start, end }),
+                _ => anyhow::bail!("Invalid range '{}'", s),
+            },
+            None => anyhow::bail!("Invalid range '{}'", s),
+        }
+    }
+}
+
+impl std::str::FromStr for Roi {
+    type Err = anyhow::Error;
+
+    /// Parse `y0:y1,x0:x1`, e.g. `100:600,-512:`
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = s
+            .split_once(',')
+            .and_then(|(y, x)| Some((y.parse().ok()?, x.parse().ok()?)));
+        match parsed {
+            Some((y, x)) => Ok(Self { y, x }),
+            None => anyhow::bail!(
+                "Invalid ROI '{}', expected y0:y1,x0:x1 in pixels, e.g. 100:600,-512:",
+                s
+            ),
+        }
+    }
+}
+
+impl Roi {
+    /// Start and end along Y and X of an array with `height` by `width` pixels
+    pub fn resolve(&self, height: u64, width: u64) -> Result<[(u64, u64); 2]> {
+        Ok([self.y.resolve(height, "y")?, self.x.resolve(width, "x")?])
+    }
+}
+
+/// Where a crop is placed in the plane before its offset, as given to `--anchor`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Center,
+    BottomRight,
+}
+
+impl std::str::FromStr for Anchor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "topleft" => Ok(Self::TopLeft),
+            "center" => Ok(Self::Center),
+            "bottomright" => Ok(Self::BottomRight),
+            _ => anyhow::bail!("Unknown anchor '{}', use topleft, center or bottomright", s),
+        }
+    }
+}
+
+/// Shift of a crop from its anchor in pixels, as given to `--offset`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Offset {
+    pub y: i64,
+    pub x: i64,
+}
+
+impl std::str::FromStr for Offset {
+    type Err = anyhow::Error;
+
+    /// Parse `dy,dx`, e.g. `-100,250`
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = s
+            .split_once(',')
+            .and_then(|(y, x)| Some((y.trim().parse().ok()?, x.trim().parse().ok()?)));
+        match parsed {
+            Some((y, x)) => Ok(Self { y, x }),
+            None => anyhow::bail!(
+                "Invalid offset '{}', expected dy,dx in pixels, e.g. -100,250",
+                s
+            ),
+        }
+    }
+}
+
+/// Where the YX window of a slice is taken from: the region of interest if there is one,
+/// or a crop within `limits` placed at the anchor and shifted by the offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub limits: [u64; 2],
+    pub roi: Option<Roi>,
+    pub anchor: Anchor,
+    pub offset: Offset,
+}
+
+impl Placement {
+    /// A crop within `limits` from the top left corner
+    pub fn crop(limits: [u64; 2]) -> Self {
+        Self {
+            limits,
+            roi: None,
+            anchor: Anchor::default(),
+            offset: Offset::default(),
+        }
+    }
+
+    /// Start and length of the window along Y and X of a `height` by `width` plane,
+    /// with a crop shifted outside the plane clamped to it
+    pub fn resolve(&self, height: u64, width: u64) -> Result<[(u64, u64); 2]> {
+        if let Some(roi) = &self.roi {
+            return Ok(roi
+                .resolve(height, width)?
+                .map(|(start, end)| (start, end - start)));
+        }
+        let mut window = [(0, height), (0, width)];
+        let offsets = [self.offset.y, self.offset.x];
+        for (i, (axis, size)) in [("Y", height), ("X", width)].into_iter().enumerate() {
+            let length = self.limits[i].min(size);
+            if length < size {
+                info!("Cropping dimension {:?} size {:?}", axis, length);
+            }
+            let anchored = match self.anchor {
+                Anchor::TopLeft => 0,
+                Anchor::Center => (size - length) / 2,
+                Anchor::BottomRight => size - length,
+            };
+            let start = anchored as i64 + offsets[i];
+            let clamped = start.clamp(0, (size - length) as i64) as u64;
+            if clamped as i64 != start {
+                warn!(
+                    "The offset moves the crop outside the array along {}, clamping its start to {}",
+                    axis, clamped
+                );
+            }
+            window[i] = (clamped, length);
+        }
+        Ok(window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_roi() -> Result<()> {
+        let roi: Roi = "100:600,-512:".parse()?;
+        assert_eq!(roi.resolve(1000, 2048)?, [(100, 600), (1536, 2048)]);
+        assert_eq!(
+            ":,:-10".parse::<Roi>()?.resolve(50, 40)?,
+            [(0, 50), (0, 30)]
+        );
+        // Clamped when overflowing a little
+        assert_eq!(
+            "-80:70,5:10".parse::<Roi>()?.resolve(64, 64)?,
+            [(0, 64), (5, 10)]
+        );
+        let error = "70:80,0:10"
+            .parse::<Roi>()?
+            .resolve(64, 64)
+            .expect_err("outside");
+        assert_eq!(
+            error.to_string(),
+            "The ROI y=70:80 is empty or outside the 64 pixels along y"
+        );
+        assert!("0:10".parse::<Roi>().is_err());
+        assert!("0:10,a:b".parse::<Roi>().is_err());
+        assert!("0-10,0:10".parse::<Roi>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_place_crop() -> Result<()> {
+        assert_eq!(Placement::crop([4, 8]).resolve(10, 6)?, [(0, 4), (0, 6)]);
+        let centered = Placement {
+            anchor: "center".parse()?,
+            ..Placement::crop([4, 4])
+        };
+        assert_eq!(centered.resolve(10, 7)?, [(3, 4), (1, 4)]);
+        let shifted = Placement {
+            offset: "-2,100".parse()?,
+            ..centered
+        };
+        assert_eq!(shifted.resolve(10, 7)?, [(1, 4), (3, 4)]);
+        let corner = Placement {
+            anchor: "bottomright".parse()?,
+            offset: "1,-1".parse()?,
+            ..Placement::crop([4, 4])
+        };
+        assert_eq!(corner.resolve(10, 7)?, [(6, 4), (2, 4)]);
+        let region = Placement {
+            roi: Some("2:5,:".parse()?),
+            ..corner
+        };
+        assert_eq!(region.resolve(10, 7)?, [(2, 3), (0, 7)]);
+        assert!("middle".parse::<Anchor>().is_err());
+        assert!("3".parse::<Offset>().is_err());
+        Ok(())
+    }
+}
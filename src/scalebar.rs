@@ -0,0 +1,239 @@
+use crate::ngff::{parse_unit, Quantity};
+use anyhow::Result;
+use image::{Rgb, RgbImage};
+
+/// When and how long to draw a scale bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScaleBar {
+    Off,
+    /// A round length spanning about a fifth of the image width
+    Auto,
+    /// A fixed length in metres
+    Length(f64),
+}
+
+impl std::str::FromStr for ScaleBar {
+    type Err = anyhow::Error;
+
+    /// Parse `off`, `auto` or a length with its unit, e.g. `50um`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        match s {
+            "off" => return Ok(Self::Off),
+            "auto" => return Ok(Self::Auto),
+            _ => {}
+        }
+        let unit_start = s
+            .find(|c: char| c.is_alphabetic() || "µμÅ".contains(c))
+            .ok_or_else(|| anyhow::anyhow!("Missing unit in scale bar length '{}'", s))?;
+        let (value, unit) = s.split_at(unit_start);
+        let value: f64 = value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid scale bar length '{}'", s))?;
+        match parse_unit(unit) {
+            Some((Quantity::Length, size)) if value > 0.0 => Ok(Self::Length(value * size)),
+            Some((Quantity::Length, _)) => anyhow::bail!("Scale bar length must be positive"),
+            _ => anyhow::bail!("Unknown length unit '{}' in scale bar '{}'", unit, s),
+        }
+    }
+}
+
+/// The largest 1, 2 or 5 times a power of ten not exceeding `length`
+pub fn round_length(length: f64) -> f64 {
+    let base = 10f64.powf(length.log10().floor());
+    [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|step| step * base)
+        .find(|&rounded| rounded <= length * (1.0 + 1e-9))
+        .unwrap_or(base)
+}
+
+/// Label a length in metres with the largest unit keeping it at least 1, e.g. `50 µm`
+pub fn format_length(length: f64) -> String {
+    let (size, unit) = [(1.0, "m"), (1e-3, "mm"), (1e-6, "µm"), (1e-9, "nm")]
+        .into_iter()
+        .find(|&(size, _)| length >= size * (1.0 - 1e-9))
+        .unwrap_or((1e-9, "nm"));
+    let value = format!("{:.3}", length / size);
+    let value = value.trim_end_matches('0').trim_end_matches('.');
+    format!("{} {}", value, unit)
+}
+
+/// Rows of a glyph of the bitmap font, 5 pixels tall and up to 5 wide,
+/// in capitals apart from the lowercase letters of units and colorbar labels
+fn glyph(c: char) -> (usize, [u8; 5]) {
+    match c {
+        '0' => (3, [0b111, 0b101, 0b101, 0b101, 0b111]),
+        '1' => (3, [0b010, 0b110, 0b010, 0b010, 0b111]),
+        '2' => (3, [0b111, 0b001, 0b111, 0b100, 0b111]),
+        '3' => (3, [0b111, 0b001, 0b111, 0b001, 0b111]),
+        '4' => (3, [0b101, 0b101, 0b111, 0b001, 0b001]),
+        '5' => (3, [0b111, 0b100, 0b111, 0b001, 0b111]),
+        '6' => (3, [0b111, 0b100, 0b111, 0b101, 0b111]),
+        '7' => (3, [0b111, 0b001, 0b001, 0b001, 0b001]),
+        '8' => (3, [0b111, 0b101, 0b111, 0b101, 0b111]),
+        '9' => (3, [0b111, 0b101, 0b111, 0b001, 0b111]),
+        '.' => (1, [0, 0, 0, 0, 1]),
+        '-' => (3, [0, 0, 0b111, 0, 0]),
+        '+' => (3, [0, 0b010, 0b111, 0b010, 0]),
+        'e' => (3, [0b111, 0b101, 0b111, 0b100, 0b111]),
+        'g' => (3, [0b111, 0b101, 0b111, 0b001, 0b111]),
+        'l' => (1, [1, 1, 1, 1, 1]),
+        'o' => (3, [0, 0b111, 0b101, 0b101, 0b111]),
+        'm' => (5, [0, 0, 0b11110, 0b10101, 0b10101]),
+        'n' => (3, [0, 0, 0b110, 0b101, 0b101]),
+        'µ' => (3, [0, 0b101, 0b101, 0b111, 0b100]),
+        'A' => (3, [0b010, 0b101, 0b111, 0b101, 0b101]),
+        'B' => (3, [0b110, 0b101, 0b110, 0b101, 0b110]),
+        'C' => (3, [0b011, 0b100, 0b100, 0b100, 0b011]),
+        'D' => (3, [0b110, 0b101, 0b101, 0b101, 0b110]),
+        'E' => (3, [0b111, 0b100, 0b110, 0b100, 0b111]),
+        'F' => (3, [0b111, 0b100, 0b110, 0b100, 0b100]),
+        'G' => (3, [0b011, 0b100, 0b101, 0b101, 0b011]),
+        'H' => (3, [0b101, 0b101, 0b111, 0b101, 0b101]),
+        'I' => (3, [0b111, 0b010, 0b010, 0b010, 0b111]),
+        'J' => (3, [0b001, 0b001, 0b001, 0b101, 0b010]),
+        'K' => (3, [0b101, 0b101, 0b110, 0b101, 0b101]),
+        'L' => (3, [0b100, 0b100, 0b100, 0b100, 0b111]),
+        'M' => (5, [0b10001, 0b11011, 0b10101, 0b10001, 0b10001]),
+        'N' => (4, [0b1001, 0b1101, 0b1011, 0b1001, 0b1001]),
+        'O' => (3, [0b010, 0b101, 0b101, 0b101, 0b010]),
+        'P' => (3, [0b110, 0b101, 0b110, 0b100, 0b100]),
+        'Q' => (3, [0b010, 0b101, 0b101, 0b110, 0b011]),
+        'R' => (3, [0b110, 0b101, 0b110, 0b101, 0b101]),
+        'S' => (3, [0b011, 0b100, 0b010, 0b001, 0b110]),
+        'T' => (3, [0b111, 0b010, 0b010, 0b010, 0b010]),
+        'U' => (3, [0b101, 0b101, 0b101, 0b101, 0b111]),
+        'V' => (3, [0b101, 0b101, 0b101, 0b101, 0b010]),
+        'W' => (5, [0b10001, 0b10001, 0b10101, 0b11011, 0b10001]),
+        'X' => (3, [0b101, 0b101, 0b010, 0b101, 0b101]),
+        'Y' => (3, [0b101, 0b101, 0b010, 0b010, 0b010]),
+        'Z' => (3, [0b111, 0b001, 0b010, 0b100, 0b111]),
+        '_' => (3, [0, 0, 0, 0, 0b111]),
+        '=' => (3, [0, 0b111, 0, 0b111, 0]),
+        ':' => (1, [0, 0b1, 0, 0b1, 0]),
+        _ => (2, [0; 5]),
+    }
+}
+
+pub(crate) fn text_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| glyph(c).0 + 1)
+        .sum::<usize>()
+        .saturating_sub(1)
+}
+
+pub(crate) fn fill(
+    image: &mut RgbImage,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    color: Rgb<u8>,
+) {
+    for row in y..(y + height).min(image.height() as usize) {
+        for column in x..(x + width).min(image.width() as usize) {
+            image.put_pixel(column as u32, row as u32, color);
+        }
+    }
+}
+
+pub(crate) fn draw_text(image: &mut RgbImage, text: &str, x: usize, y: usize, scale: usize) {
+    let mut x = x;
+    for c in text.chars() {
+        let (width, rows) = glyph(c);
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..width {
+                if bits >> (width - 1 - column) & 1 == 1 {
+                    let (px, py) = (x + column * scale, y + row * scale);
+                    fill(image, px, py, scale, scale, Rgb([255, 255, 255]));
+                }
+            }
+        }
+        x += (width + 1) * scale;
+    }
+}
+
+/// Draw a labelled scale bar in the bottom right corner,
+/// given the physical size of a pixel in metres,
+/// returning the length drawn or `None` if it does not fit
+pub fn draw_scale_bar(image: &mut RgbImage, pixel_size: f64, bar: ScaleBar) -> Option<f64> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let length = match bar {
+        ScaleBar::Off => return None,
+        ScaleBar::Auto => round_length(0.2 * width as f64 * pixel_size),
+        ScaleBar::Length(length) => length,
+    };
+    let bar_width = (length / pixel_size).round() as usize;
+    let label = format_length(length);
+    // Sized relative to the image, which is shrunk to fit the terminal
+    let scale = (width / 120).max(1);
+    let margin = 2 * scale;
+    let thickness = 2 * scale;
+    let box_width = bar_width.max(text_width(&label) * scale) + 2 * margin;
+    let box_height = 5 * scale + thickness + 3 * margin;
+    if bar_width == 0 || box_width > width || box_height > height {
+        return None;
+    }
+    let (left, top) = (width - box_width, height - box_height);
+    fill(image, left, top, box_width, box_height, Rgb([0, 0, 0]));
+    let text_left = left + (box_width - text_width(&label) * scale) / 2;
+    draw_text(image, &label, text_left, top + margin, scale);
+    let bar_left = left + (box_width - bar_width) / 2;
+    let bar_top = top + 2 * margin + 5 * scale;
+    fill(
+        image,
+        bar_left,
+        bar_top,
+        bar_width,
+        thickness,
+        Rgb([255, 255, 255]),
+    );
+    Some(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_bar_length_and_label() -> Result<()> {
+        assert_eq!(round_length(37.0), 20.0);
+        assert_eq!(round_length(0.6), 0.5);
+        assert_eq!(round_length(100.0), 100.0);
+        assert_eq!(format_length(50e-6), "50 µm");
+        assert_eq!(format_length(2.5e-3), "2.5 mm");
+        assert_eq!(format_length(200e-9), "200 nm");
+        let ScaleBar::Length(length) = "50um".parse::<ScaleBar>()? else {
+            anyhow::bail!("expected a length");
+        };
+        assert!((length - 50e-6).abs() < 1e-12);
+        assert_eq!("off".parse::<ScaleBar>()?, ScaleBar::Off);
+        assert!("50s".parse::<ScaleBar>().is_err());
+        assert!("50".parse::<ScaleBar>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_draw_scale_bar() {
+        let mut image = RgbImage::from_pixel(200, 100, Rgb([128, 128, 128]));
+        // 0.5 µm pixels, so a fifth of the width is 20 µm
+        let length = draw_scale_bar(&mut image, 0.5e-6, ScaleBar::Auto).unwrap_or_default();
+        assert!((length - 20e-6).abs() < 1e-12);
+        let white = image
+            .pixels()
+            .filter(|p| **p == Rgb([255, 255, 255]))
+            .count();
+        // The 40 x 2 bar plus the label
+        assert!(white > 80);
+        assert_eq!(*image.get_pixel(0, 0), Rgb([128, 128, 128]));
+        assert_eq!(*image.get_pixel(199, 99), Rgb([0, 0, 0]));
+
+        let mut small = RgbImage::new(20, 20);
+        assert_eq!(
+            draw_scale_bar(&mut small, 1e-6, ScaleBar::Length(1e-3)),
+            None
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::store::ConsolidatedStore;
+use crate::{decode_values, ngff, open_array, resolve_array_path, slice_subset};
+use crate::{info, ReadOptions, SliceIndex, SliceView};
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use zarrs::array::Array;
+use zarrs::array_subset::ArraySubset;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// One combination of slice indices of `--slices`, e.g. `t=0,c=1`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SliceSpec(pub Vec<SliceIndex>);
+
+impl std::str::FromStr for SliceSpec {
+    type Err = anyhow::Error;
+
+    /// Parse slice indices separated by commas, as given to `--slice`
+    fn from_str(s: &str) -> Result<Self> {
+        if s.trim().is_empty() {
+            anyhow::bail!("Empty slice combination, expected e.g. t=0,c=1");
+        }
+        let slices = s
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Invalid slice combination '{}'", s.trim()))?;
+        Ok(Self(slices))
+    }
+}
+
+impl std::fmt::Display for SliceSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slices: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        write!(f, "{}", slices.join(","))
+    }
+}
+
+/// Slices of one array at several combinations of slice indices,
+/// all resolved against the array before any is read
+pub struct SliceSequence<TStore: ?Sized> {
+    array: Array<ConsolidatedStore<Arc<TStore>>>,
+    path: String,
+    attributes: serde_json::Map<String, serde_json::Value>,
+    axes: Option<Vec<String>>,
+    options: ReadOptions,
+    /// Subset and step of each combination, in order
+    subsets: Vec<(ArraySubset, u64)>,
+}
+
+/// Open the array that [`crate::read_slice`] would read once for all of `specs`, failing
+/// on the first combination that does not fit it (e.g. an index out of bounds) with its position
+pub fn open_sequence<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+    specs: &[SliceSpec],
+) -> Result<SliceSequence<TStore>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(ConsolidatedStore::new(store, options.consolidated));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let attributes = ngff::group_attributes(&store, ngff::parent_path(&path))?;
+    let array = open_array(store, &path)?;
+    let mut axes = None;
+    let subsets = specs
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| {
+            let spec_options = ReadOptions {
+                slice_indices: Some(spec.0.clone()),
+                ..options.clone()
+            };
+            let (names, subset, step) =
+                slice_subset(&array, &path, attributes.as_ref(), &spec_options).with_context(
+                    || format!("Slice combination {} of {} ({})", i + 1, specs.len(), spec),
+                )?;
+            if step > 1 {
+                let [height, width] = options.size_limits();
+                info!(
+                    "Downsampling Y and X by {} to fit within {}x{}",
+                    step, height, width
+                );
+            }
+            axes = names;
+            Ok((subset, step))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(SliceSequence {
+        array,
+        path,
+        attributes: attributes.unwrap_or_default(),
+        axes,
+        options: options.clone(),
+        subsets,
+    })
+}
+
+impl<TStore> SliceSequence<TStore>
+where
+    TStore: ReadableStorageTraits + ?Sized + 'static,
+{
+    /// Number of combinations
+    pub fn len(&self) -> usize {
+        self.subsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subsets.is_empty()
+    }
+
+    /// Read the slice of the combination at `index`,
+    /// through the chunk cache of the store if it has one
+    pub fn read(&self, index: usize) -> Result<SliceView> {
+        let (subset, step) = &self.subsets[index];
+        let (data, exact) = decode_values(&self.array, subset, *step, &self.options)?;
+        Ok(SliceView {
+            data,
+            path: self.path.clone(),
+            axes: self.axes.clone(),
+            start: subset.start().to_vec(),
+            shape: self.array.shape().to_vec(),
+            data_type: self.array.data_type().to_string(),
+            attributes: self.attributes.clone(),
+            step: *step,
+            exact,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    #[test]
+    fn test_sequence() -> Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![3, 4, 4],
+            vec![1, 4, 4],
+            DataType::UInt8,
+            FillValue::from(0u8),
+        )
+        .build(store.clone(), "/data")?;
+        array.store_metadata()?;
+        for t in 0..3u8 {
+            array.store_chunk_elements(&[t as u64, 0, 0], &[t * 10; 16])?;
+        }
+        let specs: Vec<SliceSpec> = ["2", "0", "last"]
+            .iter()
+            .map(|s| s.parse())
+            .collect::<Result<_>>()?;
+        let sequence = open_sequence(
+            store.clone(),
+            Some("/data"),
+            &ReadOptions::default(),
+            &specs,
+        )?;
+        assert_eq!(sequence.len(), 3);
+        let values: Vec<f32> = (0..sequence.len())
+            .map(|i| Ok(sequence.read(i)?.data[[0, 0]]))
+            .collect::<Result<_>>()?;
+        assert_eq!(values, vec![20.0, 0.0, 20.0]);
+        assert_eq!(sequence.read(1)?.start, vec![0, 0, 0]);
+
+        // A later combination out of bounds fails before anything is read
+        let specs = [specs[0].clone(), "5".parse()?];
+        let error = open_sequence(store, Some("/data"), &ReadOptions::default(), &specs)
+            .err()
+            .expect("out of bounds");
+        assert_eq!(error.to_string(), "Slice combination 2 of 2 (5)");
+        assert!("t=0,".parse::<SliceSpec>().is_err());
+        assert_eq!("t=0,c=last".parse::<SliceSpec>()?.to_string(), "t=0,c=last");
+        Ok(())
+    }
+}
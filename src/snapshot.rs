@@ -0,0 +1,149 @@
+use crate::info;
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Format of the image streamed to stdout when it is not a terminal, as given to `--stdout-format`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StreamFormat {
+    #[default]
+    Png,
+    /// Binary PPM, 8-bit RGB without transparency
+    Ppm,
+}
+
+impl std::str::FromStr for StreamFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "png" => Ok(Self::Png),
+            "ppm" => Ok(Self::Ppm),
+            _ => anyhow::bail!("Unknown stdout format '{}', use png or ppm", s),
+        }
+    }
+}
+
+/// The bytes of `image` encoded in `format`
+pub fn encode(image: &DynamicImage, format: StreamFormat) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    match format {
+        StreamFormat::Png => image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?,
+        StreamFormat::Ppm => {
+            let rgb = image.to_rgb8();
+            bytes.extend(format!("P6\n{} {}\n255\n", rgb.width(), rgb.height()).bytes());
+            bytes.extend_from_slice(rgb.as_raw());
+        }
+    }
+    Ok(bytes)
+}
+
+/// Format of a still image file, from its extension
+pub fn image_format(path: &Path) -> Result<ImageFormat> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("png") => Ok(ImageFormat::Png),
+        Some("jpg" | "jpeg") => Ok(ImageFormat::Jpeg),
+        Some("bmp") => Ok(ImageFormat::Bmp),
+        Some("tif" | "tiff") => Ok(ImageFormat::Tiff),
+        _ => anyhow::bail!(
+            "Unknown image format of {}, use a .png, .jpg, .bmp or .tif file \
+             (or animate to a .gif or .webp file)",
+            path.display()
+        ),
+    }
+}
+
+/// The format to write `path` in, failing before anything is read if it is unknown
+/// or if the file exists and is not to be overwritten
+pub fn check_output(path: &Path, force: bool) -> Result<ImageFormat> {
+    let format = image_format(path)?;
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        );
+    }
+    Ok(format)
+}
+
+/// Write `image` to `path` in the format of its extension, creating the directories
+/// above it, and report its size
+pub fn write_image(image: &DynamicImage, path: &Path, force: bool) -> Result<()> {
+    let format = check_output(path, force)?;
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    // JPEG has no alpha channel, transparent pixels are written in their color
+    let image = match format {
+        ImageFormat::Jpeg => &DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => image,
+    };
+    image
+        .save_with_format(path, format)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    info!(
+        "Wrote {}x{} pixels to {}",
+        image.width(),
+        image.height(),
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_write_image() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("peekzarr-snapshot-{}", std::process::id()));
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(5, 3, |x, y| {
+            Rgba([x as u8 * 50, y as u8 * 100, 7, if x == 0 { 0 } else { 255 }])
+        }));
+        for name in ["nested/preview.png", "preview.TIF", "preview.bmp"] {
+            let path = directory.join(name);
+            write_image(&image, &path, false)?;
+            assert_eq!(image::open(&path)?.to_rgba8(), image.to_rgba8());
+        }
+        let path = directory.join("preview.jpg");
+        write_image(&image, &path, false)?;
+        assert_eq!(image::open(&path)?.to_rgb8().dimensions(), (5, 3));
+        // Overwritten only when forced
+        let error = write_image(&image, &path, false).expect_err("exists");
+        assert!(error
+            .to_string()
+            .ends_with("already exists, use --force to overwrite it"));
+        write_image(&image, &path, true)?;
+        let error = check_output(Path::new("preview.gif"), true).expect_err("gif");
+        assert!(error
+            .to_string()
+            .contains("use a .png, .jpg, .bmp or .tif file"));
+        std::fs::remove_dir_all(directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode() -> Result<()> {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(3, 2, |x, y| {
+            image::Rgb([x as u8, y as u8, 9])
+        }));
+        let png = encode(&image, "png".parse()?)?;
+        assert_eq!(image::load_from_memory(&png)?, image);
+        let ppm = encode(&image, StreamFormat::Ppm)?;
+        assert!(ppm.starts_with(b"P6\n3 2\n255\n"));
+        assert_eq!(&ppm[ppm.len() - 3..], &[2, 1, 9]);
+        assert!("gif".parse::<StreamFormat>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+use crate::info::join_dims;
+use crate::store::is_metadata_key;
+use crate::{join_path, ngff, open_array, resolve_array_path, ReadOptions};
+use anyhow::Result;
+use serde_json::Value;
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::Arc;
+use zarrs::array_subset::ArraySubset;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits, StorageError, StorePrefix};
+
+/// Chunks sized between two updates of the progress line
+const PROGRESS_STEP: u64 = 256;
+
+/// Size of an array as stored and as decoded, from the sizes of its chunk objects
+#[derive(Debug, PartialEq)]
+pub struct StorageStats {
+    pub path: String,
+    pub shape: Vec<u64>,
+    pub data_type: String,
+    /// Total size of the chunk (or shard) objects, without the metadata
+    pub stored_bytes: u64,
+    /// Size of every element decoded, including those of missing chunks
+    pub uncompressed_bytes: u64,
+    /// Number of chunks (shards, if sharded) in the chunk grid
+    pub chunks: u64,
+    /// Chunks without an object in the store, read as the fill value
+    pub missing_chunks: u64,
+}
+
+/// A number of bytes in binary units, e.g. `1.5 MiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", value, UNITS[unit]),
+    }
+}
+
+impl StorageStats {
+    /// Uncompressed over stored bytes, unknown if nothing is stored
+    pub fn ratio(&self) -> Option<f64> {
+        (self.stored_bytes > 0).then(|| self.uncompressed_bytes as f64 / self.stored_bytes as f64)
+    }
+
+    /// JSON object with the sizes in bytes and the chunk counts
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "path": self.path,
+            "shape": self.shape,
+            "data_type": self.data_type,
+            "stored_bytes": self.stored_bytes,
+            "uncompressed_bytes": self.uncompressed_bytes,
+            "compression_ratio": self.ratio(),
+            "chunks": self.chunks,
+            "missing_chunks": self.missing_chunks,
+        })
+    }
+}
+
+impl fmt::Display for StorageStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Array: {} ({} {})",
+            self.path,
+            join_dims(&self.shape),
+            self.data_type
+        )?;
+        writeln!(
+            f,
+            "Stored: {} in {} of {} chunks",
+            format_bytes(self.stored_bytes),
+            self.chunks - self.missing_chunks,
+            self.chunks
+        )?;
+        if self.missing_chunks > 0 {
+            writeln!(f, "Missing chunks: {} (fill value)", self.missing_chunks)?;
+        }
+        writeln!(f, "Uncompressed: {}", format_bytes(self.uncompressed_bytes))?;
+        match self.ratio() {
+            Some(ratio) => write!(f, "Compression ratio: {:.2}", ratio),
+            None => write!(f, "Compression ratio: unknown, nothing is stored"),
+        }
+    }
+}
+
+/// Total size and number of the chunk objects under `prefix`, from listing it
+fn listed_chunks<TStore>(store: &TStore, prefix: &StorePrefix) -> Result<Option<(u64, u64)>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized,
+{
+    let keys = match store.list_prefix(prefix) {
+        Ok(keys) => keys,
+        Err(StorageError::Unsupported(_)) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mut metadata_bytes = 0;
+    for key in keys.iter().filter(|key| is_metadata_key(key)) {
+        metadata_bytes += store.size_key(key)?.unwrap_or(0);
+    }
+    let chunks = keys.iter().filter(|key| !is_metadata_key(key)).count();
+    let stored = store.size_prefix(prefix)?.saturating_sub(metadata_bytes);
+    Ok(Some((stored, chunks as u64)))
+}
+
+/// Sizes of the chunks and number of missing ones of the array at `path`,
+/// from listing its objects, or from the size of each chunk (e.g. HEAD requests over HTTP)
+/// if the store cannot list keys, with a progress line on a terminal
+pub fn storage_stats<TStore>(store: &Arc<TStore>, path: &str) -> Result<StorageStats>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let array = open_array(store.clone(), path)?;
+    let element_size = array.data_type().fixed_size().ok_or_else(|| {
+        anyhow::anyhow!(
+            "The data type {} of {} has no fixed size",
+            array.data_type(),
+            path
+        )
+    })?;
+    let grid = array.chunk_grid_shape().clone();
+    let chunks: u64 = grid.iter().product();
+    let prefix = match path.trim_matches('/') {
+        "" => StorePrefix::root(),
+        path => StorePrefix::new(format!("{}/", path))?,
+    };
+    let (stored_bytes, present) = match listed_chunks(store.as_ref(), &prefix)? {
+        Some(listed) => listed,
+        None => {
+            let progress = std::io::stderr().is_terminal() && chunks > PROGRESS_STEP;
+            let (mut stored, mut present) = (0, 0);
+            for (i, indices) in ArraySubset::new_with_shape(grid)
+                .indices()
+                .iter()
+                .enumerate()
+            {
+                if let Some(size) = store.size_key(&array.chunk_key(&indices))? {
+                    stored += size;
+                    present += 1;
+                }
+                if progress && (i as u64 + 1).is_multiple_of(PROGRESS_STEP) {
+                    eprint!("\rSizing the chunks of {}: {}/{}", path, i + 1, chunks);
+                }
+            }
+            if progress {
+                eprint!("\r\x1b[K");
+            }
+            (stored, present)
+        }
+    };
+    Ok(StorageStats {
+        path: path.to_string(),
+        shape: array.shape().to_vec(),
+        data_type: array.data_type().to_string(),
+        stored_bytes,
+        uncompressed_bytes: array.shape().iter().product::<u64>() * element_size as u64,
+        chunks,
+        missing_chunks: chunks.saturating_sub(present),
+    })
+}
+
+/// Storage statistics of the array that [`crate::read_slice`] would read,
+/// or with `all_levels` of every resolution level of its multiscale image
+pub fn image_storage_stats<TStore>(
+    store: Arc<TStore>,
+    array_name: Option<&str>,
+    options: &ReadOptions,
+    all_levels: bool,
+) -> Result<Vec<StorageStats>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let path = resolve_array_path(&store, array_name, options)?;
+    let group = ngff::parent_path(&path);
+    let levels = match all_levels {
+        true => ngff::group_attributes(&store, group)?
+            .as_ref()
+            .and_then(ngff::dataset_paths),
+        false => None,
+    };
+    let paths = match levels {
+        Some(levels) => levels.iter().map(|level| join_path(group, level)).collect(),
+        None => vec![path],
+    };
+    paths
+        .iter()
+        .map(|path| storage_stats(&store, path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    #[test]
+    fn test_storage_stats() -> Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![4, 6],
+            vec![2, 3],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .build(store.clone(), "/0")?;
+        array.store_metadata()?;
+        // Two of the four chunks, the others are left to the fill value
+        array.store_chunk_elements(&[0, 0], &[1u16; 6])?;
+        array.store_chunk_elements(&[1, 1], &[2u16; 6])?;
+        let stats = storage_stats(&store, "/0")?;
+        assert_eq!((stats.chunks, stats.missing_chunks), (4, 2));
+        assert_eq!(stats.uncompressed_bytes, 48);
+        assert_eq!(stats.stored_bytes, 24);
+        assert_eq!(stats.ratio(), Some(2.0));
+        assert_eq!(stats.to_json()["compression_ratio"], 2.0);
+        let text = stats.to_string();
+        assert!(text.contains("Stored: 24 B in 2 of 4 chunks\nMissing chunks: 2"));
+        // Sized chunk by chunk without listing
+        let unlisted = Arc::new(crate::store::Unlisted::new(store.clone()));
+        assert_eq!(storage_stats(&unlisted, "/0")?, stats);
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 << 30), "3.0 GiB");
+        Ok(())
+    }
+}
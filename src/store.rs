@@ -0,0 +1,1766 @@
+use crate::error::Error;
+use crate::storagestats::format_bytes;
+use crate::timings::{self, Stage};
+use crate::{debug, verbose};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use zarrs::filesystem::FilesystemStore;
+use zarrs::storage::byte_range::{extract_byte_ranges, ByteRange, ByteRangeIterator};
+use zarrs::storage::storage_adapter::async_to_sync::{
+    AsyncToSyncBlockOn, AsyncToSyncStorageAdapter,
+};
+use zarrs::storage::{
+    Bytes, ListableStorageTraits, MaybeBytesIterator, ReadableListableStorageTraits,
+    ReadableStorageTraits, ReadableWritableListableStorageTraits, StorageError, StoreKey,
+    StoreKeys, StoreKeysPrefixes, StorePrefix,
+};
+use zarrs_object_store::object_store::aws::{AmazonS3, AmazonS3Builder};
+use zarrs_object_store::object_store::azure::{
+    AzureConfigKey, MicrosoftAzure, MicrosoftAzureBuilder,
+};
+use zarrs_object_store::object_store::gcp::{GoogleCloudStorage, GoogleCloudStorageBuilder};
+use zarrs_object_store::object_store::prefix::PrefixStore;
+use zarrs_object_store::AsyncObjectStore;
+use zarrs_zip::ZipStorageAdapter;
+
+/// Blocks on futures from object store backends with a dedicated runtime
+pub struct TokioBlockOn(tokio::runtime::Runtime);
+
+impl TokioBlockOn {
+    pub fn new() -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self(runtime))
+    }
+}
+
+impl AsyncToSyncBlockOn for TokioBlockOn {
+    fn block_on<F: core::future::Future>(&self, future: F) -> F::Output {
+        self.0.block_on(future)
+    }
+}
+
+/// Synchronous zarr store rooted at a prefix of an object store bucket
+pub type ObjectStore<T> = AsyncToSyncStorageAdapter<AsyncObjectStore<PrefixStore<T>>, TokioBlockOn>;
+
+/// Split a URL like `s3://bucket/some/prefix` into the bucket and the prefix
+pub fn parse_bucket_url(url: &str, scheme: &str) -> Result<(String, String)> {
+    let marker = format!("{}://", scheme);
+    let rest = url
+        .get(..marker.len())
+        .filter(|head| head.eq_ignore_ascii_case(&marker))
+        .map(|_| &url[marker.len()..])
+        .ok_or_else(|| anyhow::anyhow!("Expected a {} URL, got {}", marker, url))?;
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        anyhow::bail!("Missing bucket name in {}", url);
+    }
+    Ok((bucket.to_string(), prefix.trim_matches('/').to_string()))
+}
+
+/// Parse an AWS shared config/credentials file into sections of key-value pairs
+fn parse_ini(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.trim();
+            let name = name.strip_prefix("profile ").unwrap_or(name).trim();
+            current = Some(name.to_string());
+            sections.entry(name.to_string()).or_default();
+        } else if let (Some(section), Some((key, value))) = (&current, line.split_once('=')) {
+            sections
+                .get_mut(section)
+                .expect("section was inserted")
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+fn aws_file(env_var: &str, file_name: &str) -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(env_var) {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".aws").join(file_name))
+}
+
+fn read_profile(env_var: &str, file_name: &str, profile: &str) -> HashMap<String, String> {
+    aws_file(env_var, file_name)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| parse_ini(&content).remove(profile))
+        .unwrap_or_default()
+}
+
+/// Fill in credentials and region from the shared AWS profile
+/// when they are not already set by environment variables
+fn with_aws_profile(mut builder: AmazonS3Builder) -> AmazonS3Builder {
+    let profile = std::env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string());
+    if std::env::var_os("AWS_ACCESS_KEY_ID").is_none() {
+        let credentials = read_profile("AWS_SHARED_CREDENTIALS_FILE", "credentials", &profile);
+        if let (Some(key), Some(secret)) = (
+            credentials.get("aws_access_key_id"),
+            credentials.get("aws_secret_access_key"),
+        ) {
+            builder = builder
+                .with_access_key_id(key)
+                .with_secret_access_key(secret);
+            if let Some(token) = credentials.get("aws_session_token") {
+                builder = builder.with_token(token);
+            }
+        }
+    }
+    if std::env::var_os("AWS_REGION").is_none() && std::env::var_os("AWS_DEFAULT_REGION").is_none()
+    {
+        let config = read_profile("AWS_CONFIG_FILE", "config", &profile);
+        if let Some(region) = config.get("region") {
+            builder = builder.with_region(region);
+        }
+    }
+    builder
+}
+
+/// Open an `s3://bucket/prefix` URL, returning the bucket name and the store
+pub fn open_s3(url: &str, anonymous: bool) -> Result<(String, Arc<ObjectStore<AmazonS3>>)> {
+    let (bucket, prefix) = parse_bucket_url(url, "s3")?;
+    let builder = AmazonS3Builder::from_env().with_bucket_name(&bucket);
+    let builder = if anonymous {
+        builder.with_skip_signature(true)
+    } else {
+        with_aws_profile(builder)
+    };
+    let s3 = builder
+        .build()
+        .with_context(|| format!("Failed to configure S3 bucket '{}'", bucket))?;
+    let store = AsyncObjectStore::new(PrefixStore::new(s3, prefix));
+    let store = AsyncToSyncStorageAdapter::new(Arc::new(store), TokioBlockOn::new()?);
+    Ok((bucket, Arc::new(store)))
+}
+
+/// Whether Google application default or service account credentials are configured
+fn has_gcs_credentials() -> bool {
+    let env_vars = [
+        "GOOGLE_APPLICATION_CREDENTIALS",
+        "GOOGLE_SERVICE_ACCOUNT",
+        "GOOGLE_SERVICE_ACCOUNT_PATH",
+        "GOOGLE_SERVICE_ACCOUNT_KEY",
+        "SERVICE_ACCOUNT",
+    ];
+    if env_vars.iter().any(|var| std::env::var_os(var).is_some()) {
+        return true;
+    }
+    std::env::var_os("HOME")
+        .map(|home| {
+            PathBuf::from(home)
+                .join(".config/gcloud/application_default_credentials.json")
+                .exists()
+        })
+        .unwrap_or(false)
+}
+
+/// Open a `gs://bucket/prefix` URL, returning the bucket name and the store
+pub fn open_gcs(
+    url: &str,
+    anonymous: bool,
+) -> Result<(String, Arc<LocatedErrors<ObjectStore<GoogleCloudStorage>>>)> {
+    let (bucket, prefix) = parse_bucket_url(url, "gs")?;
+    let builder = GoogleCloudStorageBuilder::from_env().with_bucket_name(&bucket);
+    let builder = if anonymous || !has_gcs_credentials() {
+        builder.with_skip_signature(true)
+    } else {
+        builder
+    };
+    let gcs = builder
+        .build()
+        .with_context(|| format!("Failed to configure GCS bucket '{}'", bucket))?;
+    let store = AsyncObjectStore::new(PrefixStore::new(gcs, prefix.as_str()));
+    let store = AsyncToSyncStorageAdapter::new(Arc::new(store), TokioBlockOn::new()?);
+    let location = format!("gs://{}/{}", bucket, prefix);
+    Ok((bucket, Arc::new(LocatedErrors::new(store, location))))
+}
+
+/// Container location parsed from an `az://` or `https://<account>.blob.core.windows.net` URL
+#[derive(Debug, PartialEq)]
+pub struct AzureLocation {
+    pub account: Option<String>,
+    pub container: String,
+    pub prefix: String,
+}
+
+/// Whether a URL points at Azure Blob Storage
+pub fn is_azure_url(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    if lower.starts_with("az://") {
+        return true;
+    }
+    lower
+        .strip_prefix("https://")
+        .and_then(|rest| rest.split('/').next())
+        .is_some_and(|host| host.ends_with(".blob.core.windows.net"))
+}
+
+pub fn parse_azure_url(url: &str) -> Result<AzureLocation> {
+    if url.to_ascii_lowercase().starts_with("az://") {
+        let (container, prefix) = parse_bucket_url(url, "az")?;
+        return Ok(AzureLocation {
+            account: None,
+            container,
+            prefix,
+        });
+    }
+    let (host, path) = parse_bucket_url(url, "https")?;
+    let account = host
+        .split('.')
+        .next()
+        .filter(|account| !account.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Missing storage account in {}", url))?;
+    let (container, prefix) = path.split_once('/').unwrap_or((&path, ""));
+    if container.is_empty() {
+        anyhow::bail!("Missing container name in {}", url);
+    }
+    Ok(AzureLocation {
+        account: Some(account.to_string()),
+        container: container.to_string(),
+        prefix: prefix.to_string(),
+    })
+}
+
+/// Parse an Azure storage connection string like `AccountName=...;AccountKey=...`
+fn parse_connection_string(connection_string: &str) -> HashMap<String, String> {
+    connection_string
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Apply `AZURE_STORAGE_CONNECTION_STRING` to the builder,
+/// returning whether it carried credentials
+fn with_azure_connection_string(
+    mut builder: MicrosoftAzureBuilder,
+) -> (MicrosoftAzureBuilder, bool) {
+    let Some(connection_string) = std::env::var("AZURE_STORAGE_CONNECTION_STRING").ok() else {
+        return (builder, false);
+    };
+    let fields = parse_connection_string(&connection_string);
+    let mut has_credentials = false;
+    if let Some(account) = fields.get("AccountName") {
+        builder = builder.with_account(account);
+    }
+    if let Some(key) = fields.get("AccountKey") {
+        builder = builder.with_access_key(key);
+        has_credentials = true;
+    }
+    if let Some(sas) = fields.get("SharedAccessSignature") {
+        builder = builder.with_config(AzureConfigKey::SasKey, sas);
+        has_credentials = true;
+    }
+    if let Some(endpoint) = fields.get("BlobEndpoint") {
+        builder = builder.with_endpoint(endpoint.clone());
+    }
+    (builder, has_credentials)
+}
+
+/// Whether Azure credentials are configured through environment variables
+fn has_azure_credentials() -> bool {
+    let env_vars = [
+        "AZURE_STORAGE_ACCOUNT_KEY",
+        "AZURE_STORAGE_ACCESS_KEY",
+        "AZURE_STORAGE_SAS_KEY",
+        "AZURE_STORAGE_SAS_TOKEN",
+        "AZURE_STORAGE_TOKEN",
+        "AZURE_STORAGE_CLIENT_SECRET",
+    ];
+    env_vars.iter().any(|var| std::env::var_os(var).is_some())
+}
+
+/// Open an Azure Blob Storage URL, returning the container name and the store
+pub fn open_azure(
+    url: &str,
+    anonymous: bool,
+) -> Result<(String, Arc<ObjectStore<MicrosoftAzure>>)> {
+    let location = parse_azure_url(url)?;
+    let (builder, connection_credentials) =
+        with_azure_connection_string(MicrosoftAzureBuilder::from_env());
+    let mut builder = builder.with_container_name(&location.container);
+    if let Some(account) = &location.account {
+        builder = builder.with_account(account);
+    }
+    if anonymous || !(connection_credentials || has_azure_credentials()) {
+        builder = builder.with_skip_signature(true);
+    }
+    let azure = builder.build().with_context(|| {
+        format!(
+            "Failed to configure Azure container '{}'",
+            location.container
+        )
+    })?;
+    let store = AsyncObjectStore::new(PrefixStore::new(azure, location.prefix));
+    let store = AsyncToSyncStorageAdapter::new(Arc::new(store), TokioBlockOn::new()?);
+    Ok((location.container, Arc::new(store)))
+}
+
+/// Split `archive.zip#inner/group` into the archive path and the group path inside it,
+/// returning `None` if the path does not point at a zip archive
+pub fn split_zip_path(path: &str) -> Option<(PathBuf, String)> {
+    let (archive, inner) = path.split_once('#').unwrap_or((path, ""));
+    if !archive.to_ascii_lowercase().ends_with(".zip") {
+        return None;
+    }
+    Some((PathBuf::from(archive), inner.trim_matches('/').to_string()))
+}
+
+/// Implement listing for a store wrapper by delegating to its `store` field
+macro_rules! delegate_listable {
+    ($wrapper:ident) => {
+        impl<TStore: ListableStorageTraits> ListableStorageTraits for $wrapper<TStore> {
+            fn list(&self) -> Result<StoreKeys, StorageError> {
+                self.store.list()
+            }
+
+            fn list_prefix(&self, prefix: &StorePrefix) -> Result<StoreKeys, StorageError> {
+                self.store.list_prefix(prefix)
+            }
+
+            fn list_dir(&self, prefix: &StorePrefix) -> Result<StoreKeysPrefixes, StorageError> {
+                self.store.list_dir(prefix)
+            }
+
+            fn size_prefix(&self, prefix: &StorePrefix) -> Result<u64, StorageError> {
+                self.store.size_prefix(prefix)
+            }
+        }
+    };
+}
+
+/// Store wrapper for backends that cannot list keys (e.g. plain HTTP servers),
+/// reporting listing as unsupported so callers can fall back to metadata
+pub struct Unlisted<TStore> {
+    store: TStore,
+}
+
+impl<TStore> Unlisted<TStore> {
+    pub fn new(store: TStore) -> Self {
+        Self { store }
+    }
+}
+
+impl<TStore: ReadableStorageTraits> ReadableStorageTraits for Unlisted<TStore> {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        self.store.get_partial_many(key, byte_ranges)
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.store.size_key(key)
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.store.supports_get_partial()
+    }
+}
+
+fn listing_unsupported() -> StorageError {
+    StorageError::Unsupported("this store cannot list keys".to_string())
+}
+
+impl<TStore: Send + Sync> ListableStorageTraits for Unlisted<TStore> {
+    fn list(&self) -> Result<StoreKeys, StorageError> {
+        Err(listing_unsupported())
+    }
+
+    fn list_prefix(&self, _prefix: &StorePrefix) -> Result<StoreKeys, StorageError> {
+        Err(listing_unsupported())
+    }
+
+    fn list_dir(&self, _prefix: &StorePrefix) -> Result<StoreKeysPrefixes, StorageError> {
+        Err(listing_unsupported())
+    }
+
+    fn size_prefix(&self, _prefix: &StorePrefix) -> Result<u64, StorageError> {
+        Err(listing_unsupported())
+    }
+}
+
+/// Zip archive store, sizing entries relative to the group inside the archive
+/// (the adapter itself resolves `size_key` from the archive root)
+pub struct ZipStore(ZipStorageAdapter<FilesystemStore>);
+
+impl ReadableStorageTraits for ZipStore {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        self.0.get_partial_many(key, byte_ranges)
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        Ok(self.0.get(key)?.map(|bytes| bytes.len() as u64))
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.0.supports_get_partial()
+    }
+}
+
+impl ListableStorageTraits for ZipStore {
+    fn list(&self) -> Result<StoreKeys, StorageError> {
+        self.0.list()
+    }
+
+    fn list_prefix(&self, prefix: &StorePrefix) -> Result<StoreKeys, StorageError> {
+        self.0.list_prefix(prefix)
+    }
+
+    fn list_dir(&self, prefix: &StorePrefix) -> Result<StoreKeysPrefixes, StorageError> {
+        self.0.list_dir(prefix)
+    }
+
+    fn size_prefix(&self, prefix: &StorePrefix) -> Result<u64, StorageError> {
+        let mut size = 0;
+        for key in self.0.list_prefix(prefix)? {
+            size += self.size_key(&key)?.unwrap_or(0);
+        }
+        Ok(size)
+    }
+}
+
+/// Open a zarr hierarchy stored in a zip archive,
+/// reading entries through the central directory without extracting the archive
+pub fn open_zip(archive: &Path, inner: &str) -> Result<Arc<ZipStore>> {
+    let file_name = archive
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid zip archive path {}", archive.display()))?;
+    let parent = match archive.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let fs_store = Arc::new(FilesystemStore::new(parent)?);
+    // The adapter strips the inner path from entry names as is, without a separator
+    let inner = match inner {
+        "" => String::new(),
+        inner => format!("{}/", inner),
+    };
+    let store = ZipStorageAdapter::new_with_path(fs_store, StoreKey::new(file_name)?, inner)
+        .with_context(|| format!("Failed to open zip archive {}", archive.display()))?;
+    Ok(Arc::new(ZipStore(store)))
+}
+
+/// Where a zarr hierarchy lives, as detected from a path or URL
+#[derive(Debug, PartialEq)]
+pub enum Location {
+    Filesystem(PathBuf),
+    Http(String),
+    S3(String),
+    Gcs(String),
+    Azure(String),
+    Zip(PathBuf, String),
+}
+
+const SUPPORTED_SCHEMES: &str =
+    "a local path, file://, http://, https://, s3://, gs://, az:// or a .zip archive";
+
+/// Detect the kind of store from a local path or URL
+pub fn parse_location(path_or_url: &str) -> Result<Location> {
+    let Some((scheme, rest)) = path_or_url.split_once("://") else {
+        return Ok(match split_zip_path(path_or_url) {
+            Some((archive, inner)) => Location::Zip(archive, inner),
+            None => Location::Filesystem(PathBuf::from(path_or_url)),
+        });
+    };
+    let scheme = scheme.to_ascii_lowercase();
+    if scheme == "file" {
+        return parse_location(rest);
+    }
+    if !["http", "https", "s3", "gs", "az"].contains(&scheme.as_str()) {
+        anyhow::bail!(
+            "Unsupported scheme '{}://' in {}, expected {}",
+            scheme,
+            path_or_url,
+            SUPPORTED_SCHEMES
+        );
+    }
+    if rest.contains('?') {
+        anyhow::bail!("Query strings are not supported in {}", path_or_url);
+    }
+    let url = path_or_url.trim_end_matches('/').to_string();
+    if is_azure_url(&url) {
+        return Ok(Location::Azure(url));
+    }
+    Ok(match scheme.as_str() {
+        "s3" => Location::S3(url),
+        "gs" => Location::Gcs(url),
+        _ => Location::Http(url),
+    })
+}
+
+/// Each of `paths`, with the local glob patterns that do not exist as they are
+/// (quoted, or left to the program by the shell) replaced by the paths they match in order
+pub fn expand_globs(paths: &[String]) -> Result<Vec<String>> {
+    let mut expanded = vec![];
+    for path in paths {
+        let local = match parse_location(path)? {
+            Location::Filesystem(local) => Some(local),
+            Location::Zip(archive, inner) if inner.is_empty() => Some(archive),
+            _ => None,
+        };
+        let glob = |local: &PathBuf| !local.exists() && path.contains(['*', '?', '[']);
+        let Some(pattern) = local.filter(glob) else {
+            expanded.push(path.clone());
+            continue;
+        };
+        let matches = glob::glob(&pattern.to_string_lossy())
+            .with_context(|| format!("Invalid glob pattern {}", path))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            anyhow::bail!(Error::StoreNotFound {
+                location: path.clone()
+            });
+        }
+        expanded.extend(matches.iter().map(|path| path.display().to_string()));
+    }
+    Ok(expanded)
+}
+
+/// The latest modification time of a local store, of every directory and file below its
+/// root, to notice chunks written or overwritten anywhere in it; `None` for remote stores,
+/// which cannot be checked cheaply
+pub fn last_modified(path_or_url: &str) -> Result<Option<std::time::SystemTime>> {
+    let root = match parse_location(path_or_url)? {
+        Location::Filesystem(path) => path,
+        Location::Zip(archive, _) => archive,
+        _ => return Ok(None),
+    };
+    let modified = |path: &Path| {
+        path.metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+    let mut latest = modified(&root);
+    // Overwriting a chunk in place touches only the chunk file, not its directory
+    let mut directories = vec![root];
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(&directory)
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let path = entry.path();
+            latest = latest.max(modified(&path));
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                directories.push(path);
+            }
+        }
+    }
+    Ok(latest)
+}
+
+/// A short name for the image at `path_or_url`, to name files after: the last component
+/// of its path, inside the archive for zip paths, without the .zarr or .zip extension
+pub fn image_name(path_or_url: &str) -> String {
+    let path = match split_zip_path(path_or_url) {
+        Some((_, inner)) if !inner.is_empty() => inner,
+        _ => path_or_url
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    };
+    let last = path
+        .trim_end_matches('/')
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default();
+    let name = [".ome.zarr", ".zarr", ".zip"]
+        .iter()
+        .find_map(|extension| last.strip_suffix(extension))
+        .unwrap_or(last);
+    match name.is_empty() {
+        true => "image".to_string(),
+        false => name.to_string(),
+    }
+}
+
+/// Options for opening a store with [`open_store`]
+#[derive(Debug, Clone)]
+pub struct StoreOptions {
+    /// Access object storage without credentials (public buckets)
+    pub anonymous: bool,
+    /// Number of times to retry a read after a transient failure
+    pub retries: u32,
+}
+
+impl Default for StoreOptions {
+    fn default() -> Self {
+        Self {
+            anonymous: false,
+            retries: 3,
+        }
+    }
+}
+
+/// Open the store at a local path or URL, detecting the backend from its scheme
+pub fn open_store(
+    path_or_url: &str,
+    options: &StoreOptions,
+) -> Result<Arc<dyn ReadableListableStorageTraits>> {
+    let location = parse_location(path_or_url)?;
+    match &location {
+        Location::Filesystem(path) => verbose!("Opening the local store {}", path.display()),
+        Location::Zip(archive, inner) if inner.is_empty() => {
+            verbose!("Opening the zip archive {}", archive.display())
+        }
+        Location::Zip(archive, inner) => {
+            verbose!("Opening {} in the zip archive {}", inner, archive.display())
+        }
+        Location::Http(url) | Location::S3(url) | Location::Gcs(url) | Location::Azure(url) => {
+            verbose!("Opening the remote store {}", url)
+        }
+    }
+    match &location {
+        Location::Filesystem(path) | Location::Zip(path, _) if !path.exists() => {
+            anyhow::bail!(Error::StoreNotFound {
+                location: path.display().to_string(),
+            })
+        }
+        _ => {}
+    }
+    let store: Arc<dyn ReadableListableStorageTraits> = match location {
+        Location::Filesystem(path) => Arc::new(FilesystemStore::new(&path)?),
+        Location::Http(url) => Arc::new(Unlisted::new(zarrs_http::HTTPStore::new(&url)?)),
+        Location::S3(url) => open_s3(&url, options.anonymous)?.1,
+        Location::Gcs(url) => open_gcs(&url, options.anonymous)?.1,
+        Location::Azure(url) => open_azure(&url, options.anonymous)?.1,
+        Location::Zip(archive, inner) => open_zip(&archive, &inner)?,
+    };
+    let store = RetryStore::new(store, options.retries);
+    Ok(Arc::new(LoggedStore::new(store)))
+}
+
+/// Open the store at a local path or `s3://` URL for writing, e.g. a thumbnail,
+/// failing on backends that are read-only or opened read-only here
+pub fn open_writable_store(
+    path_or_url: &str,
+    options: &StoreOptions,
+) -> Result<Arc<dyn ReadableWritableListableStorageTraits>> {
+    Ok(match parse_location(path_or_url)? {
+        Location::Filesystem(path) => Arc::new(FilesystemStore::new(&path)?),
+        Location::S3(url) if !options.anonymous => open_s3(&url, false)?.1,
+        Location::S3(_) => anyhow::bail!(
+            "Cannot write to {} without credentials, remove --anonymous",
+            path_or_url
+        ),
+        Location::Http(_) => {
+            anyhow::bail!("Cannot write to {}, HTTP stores are read-only", path_or_url)
+        }
+        Location::Zip(..) => anyhow::bail!(
+            "Cannot write to {}, zip archives are read-only",
+            path_or_url
+        ),
+        Location::Gcs(_) | Location::Azure(_) => anyhow::bail!(
+            "Cannot write to {}, only local stores and s3:// are writable",
+            path_or_url
+        ),
+    })
+}
+
+/// Whether a key holds zarr metadata rather than chunk data
+pub(crate) fn is_metadata_key(key: &StoreKey) -> bool {
+    let name = key.as_str().rsplit('/').next().unwrap_or_default();
+    matches!(
+        name,
+        "zarr.json" | ".zarray" | ".zgroup" | ".zattrs" | ".zmetadata"
+    )
+}
+
+/// Store wrapper naming the full object location (bucket and prefix) in read errors
+pub struct LocatedErrors<TStore> {
+    store: TStore,
+    location: String,
+}
+
+impl<TStore> LocatedErrors<TStore> {
+    pub fn new(store: TStore, location: String) -> Self {
+        Self { store, location }
+    }
+
+    fn with_key(&self, key: &StoreKey, err: StorageError) -> StorageError {
+        let location = self.location.trim_end_matches('/');
+        StorageError::Other(format!("Failed to read {}/{}: {}", location, key, err))
+    }
+}
+
+impl<TStore: ReadableStorageTraits> ReadableStorageTraits for LocatedErrors<TStore> {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        self.store
+            .get_partial_many(key, byte_ranges)
+            .map_err(|err| self.with_key(key, err))
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.store
+            .size_key(key)
+            .map_err(|err| self.with_key(key, err))
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.store.supports_get_partial()
+    }
+}
+
+delegate_listable!(LocatedErrors);
+
+/// Store wrapper recording chunk keys that are absent from the store,
+/// which zarrs decodes as the fill value
+pub struct MissingChunks<TStore> {
+    store: TStore,
+    missing: Mutex<std::collections::HashSet<String>>,
+}
+
+impl<TStore> MissingChunks<TStore> {
+    pub fn new(store: TStore) -> Self {
+        Self {
+            store,
+            missing: Default::default(),
+        }
+    }
+
+    /// Number of distinct chunk keys found missing so far
+    pub fn missing(&self) -> usize {
+        self.missing.lock().map_or(0, |missing| missing.len())
+    }
+
+    fn record<T>(&self, key: &StoreKey, value: &Option<T>) {
+        if value.is_none() && !is_metadata_key(key) {
+            if let Ok(mut missing) = self.missing.lock() {
+                missing.insert(key.as_str().to_string());
+            }
+        }
+    }
+}
+
+impl<TStore: ReadableStorageTraits> ReadableStorageTraits for MissingChunks<TStore> {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        let value = self.store.get_partial_many(key, byte_ranges)?;
+        self.record(key, &value);
+        Ok(value)
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        let value = self.store.size_key(key)?;
+        self.record(key, &value);
+        Ok(value)
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.store.supports_get_partial()
+    }
+}
+
+const V2_METADATA_FILES: [&str; 3] = [".zarray", ".zgroup", ".zattrs"];
+const V3_METADATA_FILES: [&str; 1] = ["zarr.json"];
+
+/// Read the node metadata documents embedded in consolidated metadata at the store root,
+/// keyed by their store keys, with the metadata file names of the other zarr version
+fn read_consolidated<TStore: ReadableStorageTraits>(
+    store: &TStore,
+) -> Option<(HashMap<String, Bytes>, &'static [&'static str])> {
+    let parse = |key: &str| -> Option<serde_json::Value> {
+        let bytes = store.get(&StoreKey::new(key).ok()?).ok()??;
+        serde_json::from_slice(&bytes).ok()
+    };
+    let to_bytes = |value: &serde_json::Value| Bytes::from(value.to_string().into_bytes());
+    if let Some(root) = parse("zarr.json") {
+        let entries = root
+            .pointer("/consolidated_metadata/metadata")?
+            .as_object()?;
+        let mut metadata: HashMap<String, Bytes> = entries
+            .iter()
+            .map(|(path, node)| {
+                (
+                    format!("{}/zarr.json", path.trim_matches('/')),
+                    to_bytes(node),
+                )
+            })
+            .collect();
+        metadata.insert("zarr.json".to_string(), to_bytes(&root));
+        return Some((metadata, &V2_METADATA_FILES));
+    }
+    let root = parse(".zmetadata")?;
+    let entries = root.get("metadata")?.as_object()?;
+    let metadata = entries
+        .iter()
+        .map(|(key, value)| (key.clone(), to_bytes(value)))
+        .collect();
+    Some((metadata, &V3_METADATA_FILES))
+}
+
+/// Store wrapper that serves node metadata from consolidated metadata
+/// (`.zmetadata` for v2, inline `consolidated_metadata` for v3) to avoid per-key reads.
+/// Keys missing from the consolidated metadata are read from the underlying store.
+pub struct ConsolidatedStore<TStore> {
+    store: TStore,
+    metadata: HashMap<String, Bytes>,
+    absent: &'static [&'static str],
+}
+
+impl<TStore: ReadableStorageTraits> ConsolidatedStore<TStore> {
+    pub fn new(store: TStore, enabled: bool) -> Self {
+        let (metadata, absent) = enabled
+            .then(|| read_consolidated(&store))
+            .flatten()
+            .unwrap_or_default();
+        Self {
+            store,
+            metadata,
+            absent,
+        }
+    }
+
+    fn lookup(&self, key: &StoreKey) -> Option<Option<&Bytes>> {
+        if let Some(bytes) = self.metadata.get(key.as_str()) {
+            return Some(Some(bytes));
+        }
+        let name = key.as_str().rsplit('/').next().unwrap_or_default();
+        self.absent.contains(&name).then_some(None)
+    }
+}
+
+impl<TStore: ReadableStorageTraits> ReadableStorageTraits for ConsolidatedStore<TStore> {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        match self.lookup(key) {
+            Some(Some(bytes)) => {
+                let parts = extract_byte_ranges(bytes, byte_ranges)?;
+                Ok(Some(Box::new(
+                    parts.into_iter().map(|part| Ok(Bytes::from(part))),
+                )))
+            }
+            Some(None) => Ok(None),
+            None => self.store.get_partial_many(key, byte_ranges),
+        }
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        match self.lookup(key) {
+            Some(bytes) => Ok(bytes.map(|bytes| bytes.len() as u64)),
+            None => self.store.size_key(key),
+        }
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.store.supports_get_partial()
+    }
+}
+
+delegate_listable!(ConsolidatedStore);
+
+/// A cached read: the store key and the requested byte ranges
+type CacheKey = (String, Vec<(bool, u64, Option<u64>)>);
+
+struct Lru {
+    entries: lru::LruCache<CacheKey, Option<Vec<Bytes>>>,
+    size: u64,
+}
+
+/// Store wrapper caching chunk reads in memory with least-recently-used eviction,
+/// keyed by the chunk key (which encodes the chunk indices) and byte ranges,
+/// so repeated reads of the same chunks in one run go to the store only once
+pub struct CachedStore<TStore> {
+    store: TStore,
+    capacity: u64,
+    cache: Mutex<Lru>,
+}
+
+impl<TStore> CachedStore<TStore> {
+    /// Cache up to `capacity` bytes, a capacity of 0 disables caching
+    pub fn new(store: TStore, capacity: u64) -> Self {
+        Self {
+            store,
+            capacity,
+            cache: Mutex::new(Lru {
+                entries: lru::LruCache::unbounded(),
+                size: 0,
+            }),
+        }
+    }
+
+    fn lookup(&self, key: &CacheKey) -> Option<Option<Vec<Bytes>>> {
+        let mut cache = self.cache.lock().ok()?;
+        cache.entries.get(key).cloned()
+    }
+
+    fn insert(&self, key: CacheKey, value: Option<Vec<Bytes>>) {
+        let size = cached_size(&value);
+        if size > self.capacity {
+            return;
+        }
+        let Ok(mut cache) = self.cache.lock() else {
+            return;
+        };
+        if let Some(old) = cache.entries.put(key, value) {
+            cache.size -= cached_size(&old);
+        }
+        cache.size += size;
+        while cache.size > self.capacity {
+            let Some((_, evicted)) = cache.entries.pop_lru() else {
+                break;
+            };
+            cache.size -= cached_size(&evicted);
+        }
+    }
+}
+
+fn cached_size(value: &Option<Vec<Bytes>>) -> u64 {
+    value.iter().flatten().map(|bytes| bytes.len() as u64).sum()
+}
+
+impl<TStore: ReadableStorageTraits> ReadableStorageTraits for CachedStore<TStore> {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        if self.capacity == 0 || is_metadata_key(key) {
+            return self.store.get_partial_many(key, byte_ranges);
+        }
+        let byte_ranges: Vec<ByteRange> = byte_ranges.collect();
+        let cache_key = (
+            key.as_str().to_string(),
+            byte_ranges
+                .iter()
+                .map(|range| match *range {
+                    ByteRange::FromStart(offset, length) => (false, offset, length),
+                    ByteRange::Suffix(length) => (true, length, None),
+                })
+                .collect(),
+        );
+        let value = match self.lookup(&cache_key) {
+            Some(value) => value,
+            None => {
+                let value = self
+                    .store
+                    .get_partial_many(key, Box::new(byte_ranges.into_iter()))?
+                    .map(|parts| parts.collect::<Result<Vec<_>, _>>())
+                    .transpose()?;
+                self.insert(cache_key, value.clone());
+                value
+            }
+        };
+        match value {
+            Some(parts) => Ok(Some(Box::new(parts.into_iter().map(Ok)))),
+            None => Ok(None),
+        }
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.store.size_key(key)
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.store.supports_get_partial()
+    }
+}
+
+delegate_listable!(CachedStore);
+
+/// Store wrapper timing reads for `--timings`, metadata documents apart from chunks
+pub struct TimedStore<TStore> {
+    store: TStore,
+}
+
+impl<TStore> TimedStore<TStore> {
+    pub fn new(store: TStore) -> Self {
+        Self { store }
+    }
+}
+
+impl<TStore: ReadableStorageTraits> ReadableStorageTraits for TimedStore<TStore> {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        if !timings::enabled() {
+            return self.store.get_partial_many(key, byte_ranges);
+        }
+        let started = std::time::Instant::now();
+        // Collected so that the time includes the transfer of lazily read parts
+        let value = self
+            .store
+            .get_partial_many(key, byte_ranges)?
+            .map(|parts| parts.collect::<Result<Vec<_>, _>>())
+            .transpose()?;
+        let stage = match is_metadata_key(key) {
+            true => Stage::Metadata,
+            false => Stage::Retrieval,
+        };
+        timings::record(stage, started.elapsed(), cached_size(&value));
+        match value {
+            Some(parts) => Ok(Some(Box::new(parts.into_iter().map(Ok)))),
+            None => Ok(None),
+        }
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.store.size_key(key)
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.store.supports_get_partial()
+    }
+}
+
+delegate_listable!(TimedStore);
+
+/// Store wrapper printing the metadata documents read with `-v` and the chunks with `-vv`
+pub struct LoggedStore<TStore> {
+    store: TStore,
+}
+
+impl<TStore> LoggedStore<TStore> {
+    pub fn new(store: TStore) -> Self {
+        Self { store }
+    }
+}
+
+impl<TStore: ReadableStorageTraits> ReadableStorageTraits for LoggedStore<TStore> {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        let log = |message: std::fmt::Arguments| match is_metadata_key(key) {
+            true => verbose!("{}", message),
+            false => debug!("{}", message),
+        };
+        let started = std::time::Instant::now();
+        let value = self
+            .store
+            .get_partial_many(key, byte_ranges)?
+            .map(|parts| parts.collect::<Result<Vec<_>, _>>())
+            .transpose()?;
+        match &value {
+            Some(_) => log(format_args!(
+                "Read {}: {} in {:.1} ms",
+                key,
+                format_bytes(cached_size(&value)),
+                started.elapsed().as_secs_f64() * 1e3
+            )),
+            None => log(format_args!("Read {}: missing", key)),
+        }
+        match value {
+            Some(parts) => Ok(Some(Box::new(parts.into_iter().map(Ok)))),
+            None => Ok(None),
+        }
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.store.size_key(key)
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.store.supports_get_partial()
+    }
+}
+
+delegate_listable!(LoggedStore);
+
+/// Whether a read error is likely to go away on retry:
+/// timeouts, dropped connections, throttling and server (5xx) errors
+pub fn is_transient(err: &StorageError) -> bool {
+    use std::io::ErrorKind;
+    let message = match err {
+        StorageError::IOError(err) => {
+            if matches!(
+                err.kind(),
+                ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+            err.to_string()
+        }
+        StorageError::Other(message) => message.clone(),
+        _ => return false,
+    };
+    let message = message.to_ascii_lowercase();
+    let permanent = [
+        "not found",
+        "forbidden",
+        "unauthorized",
+        "permission denied",
+        "access denied",
+    ];
+    if permanent.iter().any(|pattern| message.contains(pattern)) {
+        return false;
+    }
+    let transient = [
+        "timed out",
+        "timeout",
+        "connection reset",
+        "connection closed",
+        "connection aborted",
+        "broken pipe",
+        "too many requests",
+        "slow down",
+    ];
+    transient.iter().any(|pattern| message.contains(pattern))
+        || message
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| {
+                word == "429"
+                    || (word.len() == 3 && word.starts_with('5') && word.parse::<u16>().is_ok())
+            })
+}
+
+/// Store wrapper retrying reads that fail with transient errors,
+/// with exponential backoff and jitter between attempts
+pub struct RetryStore<TStore> {
+    store: TStore,
+    retries: u32,
+    base_delay: std::time::Duration,
+}
+
+impl<TStore> RetryStore<TStore> {
+    pub fn new(store: TStore, retries: u32) -> Self {
+        Self {
+            store,
+            retries,
+            base_delay: std::time::Duration::from_millis(100),
+        }
+    }
+
+    /// Wait before retry `attempt` (from 1), doubling up to a few seconds,
+    /// with a random factor so concurrent chunk reads don't retry in lockstep
+    fn backoff(&self, attempt: u32) {
+        use std::hash::{BuildHasher, Hasher};
+        let delay = self.base_delay * 2u32.pow(attempt.min(6) - 1);
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        let jitter = 0.5 + (random % 1000) as f64 / 2000.0;
+        std::thread::sleep(delay.mul_f64(jitter));
+    }
+
+    fn retry<T>(
+        &self,
+        key: &StoreKey,
+        read: impl Fn() -> Result<T, StorageError>,
+    ) -> Result<T, StorageError> {
+        let mut attempt = 0;
+        loop {
+            match read() {
+                Err(err) if is_transient(&err) => {
+                    if attempt == self.retries {
+                        return Err(StorageError::Other(format!(
+                            "Failed to read {} after {} attempts: {}",
+                            key,
+                            attempt + 1,
+                            err
+                        )));
+                    }
+                    attempt += 1;
+                    verbose!(
+                        "Retrying {} ({} of {}) after {}",
+                        key,
+                        attempt,
+                        self.retries,
+                        err
+                    );
+                    self.backoff(attempt);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<TStore: ReadableStorageTraits> ReadableStorageTraits for RetryStore<TStore> {
+    fn get_partial_many<'a>(
+        &'a self,
+        key: &StoreKey,
+        byte_ranges: ByteRangeIterator<'a>,
+    ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+        let byte_ranges: Vec<ByteRange> = byte_ranges.collect();
+        let value = self.retry(key, || {
+            self.store
+                .get_partial_many(key, Box::new(byte_ranges.clone().into_iter()))?
+                .map(|parts| parts.collect::<Result<Vec<_>, _>>())
+                .transpose()
+        })?;
+        match value {
+            Some(parts) => Ok(Some(Box::new(parts.into_iter().map(Ok)))),
+            None => Ok(None),
+        }
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.retry(key, || self.store.size_key(key))
+    }
+
+    fn supports_get_partial(&self) -> bool {
+        self.store.supports_get_partial()
+    }
+}
+
+delegate_listable!(RetryStore);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bucket_url() -> Result<()> {
+        let (bucket, prefix) = parse_bucket_url("s3://my-bucket/data/image.zarr/", "s3")?;
+        assert_eq!(bucket, "my-bucket");
+        assert_eq!(prefix, "data/image.zarr");
+
+        let (bucket, prefix) = parse_bucket_url("S3://my-bucket", "s3")?;
+        assert_eq!(bucket, "my-bucket");
+        assert_eq!(prefix, "");
+
+        assert!(parse_bucket_url("s3:///prefix", "s3").is_err());
+        assert!(parse_bucket_url("gs://bucket", "s3").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_aws_profiles() {
+        let content = "\
+[default]
+aws_access_key_id = AKIA
+aws_secret_access_key=secret
+
+# comment
+[profile imaging]
+region = us-west-2
+";
+        let sections = parse_ini(content);
+        assert_eq!(sections["default"]["aws_access_key_id"], "AKIA");
+        assert_eq!(sections["default"]["aws_secret_access_key"], "secret");
+        assert_eq!(sections["imaging"]["region"], "us-west-2");
+    }
+
+    #[test]
+    fn test_parse_azure_url() -> Result<()> {
+        assert!(is_azure_url("az://container/image.zarr"));
+        assert!(is_azure_url(
+            "https://account.blob.core.windows.net/container/image.zarr"
+        ));
+        assert!(!is_azure_url("https://example.com/image.zarr"));
+
+        let location = parse_azure_url("az://container/data/image.zarr")?;
+        assert_eq!(
+            location,
+            AzureLocation {
+                account: None,
+                container: "container".to_string(),
+                prefix: "data/image.zarr".to_string(),
+            }
+        );
+
+        let location =
+            parse_azure_url("https://account.blob.core.windows.net/container/image.zarr/")?;
+        assert_eq!(location.account.as_deref(), Some("account"));
+        assert_eq!(location.container, "container");
+        assert_eq!(location.prefix, "image.zarr");
+
+        assert!(parse_azure_url("https://account.blob.core.windows.net/").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_connection_string() {
+        let fields = parse_connection_string(
+            "DefaultEndpointsProtocol=https;AccountName=acct;AccountKey=a2V5==;EndpointSuffix=core.windows.net",
+        );
+        assert_eq!(fields["AccountName"], "acct");
+        assert_eq!(fields["AccountKey"], "a2V5==");
+    }
+
+    #[test]
+    fn test_split_zip_path() {
+        assert_eq!(
+            split_zip_path("data/image.ome.zarr.zip"),
+            Some((PathBuf::from("data/image.ome.zarr.zip"), String::new()))
+        );
+        assert_eq!(
+            split_zip_path("export.ZIP#image.ome.zarr/"),
+            Some((PathBuf::from("export.ZIP"), "image.ome.zarr".to_string()))
+        );
+        assert_eq!(split_zip_path("data/image.ome.zarr"), None);
+    }
+
+    #[test]
+    fn test_last_modified() -> Result<()> {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("peekzarr-modified-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("0/c"))?;
+        let before = last_modified(&dir.display().to_string())?.expect("a local store");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::create_dir_all(dir.join("0/c/1/0"))?;
+        let after = last_modified(&dir.display().to_string())?.expect("a local store");
+        assert!(after > before);
+        // A chunk two levels below c/, then overwritten, leaving the directories unchanged
+        let chunk = dir.join("0/c/1/0/0");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&chunk, [0u8; 4])?;
+        let written = last_modified(&dir.display().to_string())?.expect("a local store");
+        assert!(written > after);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&chunk)?
+            .write_all(&[1u8; 4])?;
+        let overwritten = last_modified(&dir.display().to_string())?.expect("a local store");
+        std::fs::remove_dir_all(&dir)?;
+        assert!(overwritten > written);
+        assert_eq!(last_modified("s3://bucket/image.zarr")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_name() {
+        assert_eq!(image_name("data/plate.ome.zarr/A/1/0/"), "0");
+        assert_eq!(image_name("fov.zarr"), "fov");
+        assert_eq!(image_name("s3://bucket/sets/fov.ome.zarr"), "fov");
+        assert_eq!(image_name("https://host/a/fov.zarr?token=x"), "fov");
+        assert_eq!(image_name("images/fov.zip"), "fov");
+        assert_eq!(image_name("images/fov.zip#inner/b.zarr"), "b");
+        assert_eq!(image_name("/"), "image");
+    }
+
+    #[test]
+    fn test_expand_globs() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("peekzarr-glob-{}", std::process::id()));
+        for name in ["b.zarr", "a.zarr", "c.txt"] {
+            std::fs::create_dir_all(directory.join(name))?;
+        }
+        let pattern = directory.join("*.zarr").display().to_string();
+        let paths = [pattern.clone(), "s3://bucket/*.zarr".to_string()];
+        let expanded = expand_globs(&paths)?;
+        let found = |name: &str| directory.join(name).display().to_string();
+        assert_eq!(
+            expanded,
+            [found("a.zarr"), found("b.zarr"), paths[1].clone()]
+        );
+        let missing = directory.join("*.zip").display().to_string();
+        let error = expand_globs(&[missing]).expect_err("no matches");
+        assert!(matches!(
+            crate::error::find(&error),
+            Some(Error::StoreNotFound { .. })
+        ));
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_location_dispatch() -> Result<()> {
+        let cases = [
+            (
+                "/data/image.zarr",
+                Location::Filesystem(PathBuf::from("/data/image.zarr")),
+            ),
+            (
+                "file:///data/image.zarr/",
+                Location::Filesystem(PathBuf::from("/data/image.zarr/")),
+            ),
+            (
+                "file:///data/export.zip#image.zarr",
+                Location::Zip(PathBuf::from("/data/export.zip"), "image.zarr".to_string()),
+            ),
+            (
+                "HTTPS://example.com/image.zarr/",
+                Location::Http("HTTPS://example.com/image.zarr".to_string()),
+            ),
+            (
+                "http://example.com/",
+                Location::Http("http://example.com".to_string()),
+            ),
+            (
+                "s3://bucket/image.zarr/",
+                Location::S3("s3://bucket/image.zarr".to_string()),
+            ),
+            ("gs://bucket", Location::Gcs("gs://bucket".to_string())),
+            (
+                "az://container/image.zarr",
+                Location::Azure("az://container/image.zarr".to_string()),
+            ),
+            (
+                "https://account.blob.core.windows.net/container/image.zarr/",
+                Location::Azure(
+                    "https://account.blob.core.windows.net/container/image.zarr".to_string(),
+                ),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_location(input)?, expected, "{}", input);
+        }
+
+        let err = parse_location("ftp://host/image.zarr").expect_err("unknown scheme");
+        assert!(err.to_string().contains("s3://"));
+        assert!(parse_location("https://example.com/image.zarr?token=abc").is_err());
+        // Read-only backends are refused before connecting
+        let options = StoreOptions::default();
+        let err = open_writable_store("https://example.com/image.zarr", &options)
+            .err()
+            .expect("http is read-only");
+        assert_eq!(
+            err.to_string(),
+            "Cannot write to https://example.com/image.zarr, HTTP stores are read-only"
+        );
+        assert!(open_writable_store("data/export.zip#image.zarr", &options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_consolidated_v2_metadata() -> Result<()> {
+        use zarrs::storage::store::MemoryStore;
+        use zarrs::storage::WritableStorageTraits;
+
+        let memory = Arc::new(MemoryStore::new());
+        let consolidated = r#"{"zarr_consolidated_format": 1, "metadata": {
+            ".zgroup": {"zarr_format": 2},
+            "0/.zarray": {"zarr_format": 2, "shape": [4]}}}"#;
+        memory.set(
+            &StoreKey::new(".zmetadata")?,
+            consolidated.as_bytes().to_vec().into(),
+        )?;
+
+        let store = ConsolidatedStore::new(memory.clone(), true);
+        let zarray = store
+            .get(&StoreKey::new("0/.zarray")?)?
+            .expect("consolidated");
+        let zarray: serde_json::Value = serde_json::from_slice(&zarray)?;
+        assert_eq!(zarray["shape"][0], 4);
+        assert!(store.size_key(&StoreKey::new("0/zarr.json")?)?.is_none());
+        assert!(store.get(&StoreKey::new("1/.zarray")?)?.is_none());
+
+        let store = ConsolidatedStore::new(memory.clone(), false);
+        assert!(store.get(&StoreKey::new("0/.zarray")?)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_consolidated_v3_metadata() -> Result<()> {
+        use zarrs::storage::store::MemoryStore;
+        use zarrs::storage::WritableStorageTraits;
+
+        let memory = Arc::new(MemoryStore::new());
+        let root = r#"{"zarr_format": 3, "node_type": "group", "consolidated_metadata": {
+            "kind": "inline", "must_understand": false, "metadata": {
+            "0": {"zarr_format": 3, "node_type": "array", "shape": [4]}}}}"#;
+        memory.set(
+            &StoreKey::new("zarr.json")?,
+            root.as_bytes().to_vec().into(),
+        )?;
+
+        let store = ConsolidatedStore::new(memory.clone(), true);
+        let array = store
+            .get(&StoreKey::new("0/zarr.json")?)?
+            .expect("consolidated");
+        let array: serde_json::Value = serde_json::from_slice(&array)?;
+        assert_eq!(array["node_type"], "array");
+        assert!(store.get(&StoreKey::new("0/.zarray")?)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_located_errors_name_object() -> Result<()> {
+        let store = LocatedErrors::new(
+            FlakyStore {
+                failures: 1.into(),
+                error: "403 Forbidden",
+            },
+            "gs://bucket/image.zarr".to_string(),
+        );
+        let err = store
+            .get(&StoreKey::new("0/c/0/0/0/0")?)
+            .expect_err("read should fail");
+        assert!(err
+            .to_string()
+            .contains("gs://bucket/image.zarr/0/c/0/0/0/0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_chunks_are_recorded() -> Result<()> {
+        use zarrs::storage::store::MemoryStore;
+        use zarrs::storage::WritableStorageTraits;
+
+        let memory = MemoryStore::new();
+        memory.set(&StoreKey::new("0/c/0/0")?, vec![0, 0].into())?;
+        let store = MissingChunks::new(memory);
+
+        assert!(store.get(&StoreKey::new("0/zarr.json")?)?.is_none());
+        assert!(store.get(&StoreKey::new("0/c/0/0")?)?.is_some());
+        assert!(store.get(&StoreKey::new("0/c/0/1")?)?.is_none());
+        assert!(store.get(&StoreKey::new("0/c/0/1")?)?.is_none());
+        assert_eq!(store.missing(), 1);
+        Ok(())
+    }
+
+    /// Counts chunk reads and bytes reaching the underlying store
+    struct CountingStore {
+        store: Arc<zarrs::storage::store::MemoryStore>,
+        chunk_reads: std::sync::atomic::AtomicUsize,
+        bytes_read: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ReadableStorageTraits for CountingStore {
+        fn get_partial_many<'a>(
+            &'a self,
+            key: &StoreKey,
+            byte_ranges: ByteRangeIterator<'a>,
+        ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+            use std::sync::atomic::Ordering;
+            let Some(parts) = self.store.get_partial_many(key, byte_ranges)? else {
+                return Ok(None);
+            };
+            let parts = parts.collect::<Result<Vec<_>, _>>()?;
+            if !is_metadata_key(key) {
+                self.chunk_reads.fetch_add(1, Ordering::Relaxed);
+                let bytes: usize = parts.iter().map(|part| part.len()).sum();
+                self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+            }
+            Ok(Some(Box::new(parts.into_iter().map(Ok))))
+        }
+
+        fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+            self.store.size_key(key)
+        }
+
+        fn supports_get_partial(&self) -> bool {
+            true
+        }
+    }
+
+    impl ListableStorageTraits for CountingStore {
+        fn list(&self) -> Result<StoreKeys, StorageError> {
+            self.store.list()
+        }
+
+        fn list_prefix(&self, prefix: &StorePrefix) -> Result<StoreKeys, StorageError> {
+            self.store.list_prefix(prefix)
+        }
+
+        fn list_dir(&self, prefix: &StorePrefix) -> Result<StoreKeysPrefixes, StorageError> {
+            self.store.list_dir(prefix)
+        }
+
+        fn size_prefix(&self, prefix: &StorePrefix) -> Result<u64, StorageError> {
+            self.store.size_prefix(prefix)
+        }
+    }
+
+    #[test]
+    fn test_cached_store_reads_chunks_once() -> Result<()> {
+        use std::sync::atomic::Ordering;
+        use zarrs::array::codec::ShardingCodecBuilder;
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+
+        let memory = Arc::new(zarrs::storage::store::MemoryStore::new());
+        let array = ArrayBuilder::new(
+            vec![2, 16, 16],
+            vec![1, 16, 16],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .array_to_bytes_codec(Arc::new(
+            ShardingCodecBuilder::new(vec![1, 8, 8].try_into()?).build(),
+        ))
+        .build(memory.clone(), "/0")?;
+        array.store_metadata()?;
+        let data = ndarray::ArrayD::from_elem(ndarray::IxDyn(&[2, 16, 16]), 7u16);
+        array.store_array_subset_ndarray(&[0, 0, 0], data)?;
+        let counting = Arc::new(CountingStore {
+            store: memory,
+            chunk_reads: Default::default(),
+            bytes_read: Default::default(),
+        });
+
+        let options = crate::ReadOptions::default();
+        for (capacity, expected) in [(1 << 20, 1), (0, 2)] {
+            counting.chunk_reads.store(0, Ordering::Relaxed);
+            let store = Arc::new(CachedStore::new(counting.clone(), capacity));
+            crate::read_slice(store.clone(), Some("/0"), &options)?;
+            let first = counting.chunk_reads.load(Ordering::Relaxed);
+            assert!(first > 0);
+            crate::read_slice(store, Some("/0"), &options)?;
+            let second = counting.chunk_reads.load(Ordering::Relaxed);
+            assert_eq!(second, first * expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let other = |message: &str| StorageError::Other(message.to_string());
+        assert!(is_transient(&other(
+            "http unexpected status code: 503 Service Unavailable"
+        )));
+        assert!(is_transient(&other(
+            "error sending request: operation timed out"
+        )));
+        assert!(is_transient(&other("Connection reset by peer")));
+        assert!(!is_transient(&other(
+            "http unexpected status code: 403 Forbidden"
+        )));
+        assert!(!is_transient(&other("Object not found: gs://bucket/0/c/0")));
+        assert!(is_transient(&StorageError::IOError(Arc::new(
+            std::io::ErrorKind::TimedOut.into()
+        ))));
+        assert!(!is_transient(&StorageError::IOError(Arc::new(
+            std::io::ErrorKind::PermissionDenied.into()
+        ))));
+    }
+
+    /// Fails reads with the given error a number of times before succeeding
+    struct FlakyStore {
+        failures: std::sync::atomic::AtomicU32,
+        error: &'static str,
+    }
+
+    impl ReadableStorageTraits for FlakyStore {
+        fn get_partial_many<'a>(
+            &'a self,
+            _key: &StoreKey,
+            _byte_ranges: ByteRangeIterator<'a>,
+        ) -> Result<MaybeBytesIterator<'a>, StorageError> {
+            use std::sync::atomic::Ordering;
+            if self
+                .failures
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(StorageError::Other(self.error.to_string()));
+            }
+            Ok(Some(Box::new(std::iter::once(Ok(Bytes::from_static(
+                b"chunk",
+            ))))))
+        }
+
+        fn size_key(&self, _key: &StoreKey) -> Result<Option<u64>, StorageError> {
+            Ok(None)
+        }
+
+        fn supports_get_partial(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_retry_store() -> Result<()> {
+        let flaky = |failures: u32, error| {
+            let mut store = RetryStore::new(
+                FlakyStore {
+                    failures: failures.into(),
+                    error,
+                },
+                3,
+            );
+            store.base_delay = std::time::Duration::from_millis(1);
+            store
+        };
+        let key = StoreKey::new("0/c/1/2")?;
+        let unavailable = "http unexpected status code: 503 Service Unavailable";
+
+        let store = flaky(3, unavailable);
+        assert_eq!(store.get(&key)?, Some(Bytes::from_static(b"chunk")));
+
+        let store = flaky(4, unavailable);
+        let err = store.get(&key).expect_err("retries exhausted");
+        assert!(err.to_string().contains("0/c/1/2 after 4 attempts"));
+
+        let store = flaky(1, "http unexpected status code: 403 Forbidden");
+        let err = store.get(&key).expect_err("permission errors fail fast");
+        assert!(!err.to_string().contains("attempts"));
+        assert_eq!(store.store.failures.into_inner(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_decode_reads_intersecting_inner_chunks() -> Result<()> {
+        use std::sync::atomic::Ordering;
+        use zarrs::array::codec::ShardingCodecBuilder;
+        use zarrs::array::{ArrayBuilder, DataType, FillValue};
+
+        let memory = Arc::new(zarrs::storage::store::MemoryStore::new());
+        // One 128x128 shard of 16x16 inner chunks
+        let array = ArrayBuilder::new(
+            vec![128, 128],
+            vec![128, 128],
+            DataType::UInt16,
+            FillValue::from(0u16),
+        )
+        .array_to_bytes_codec(Arc::new(
+            ShardingCodecBuilder::new(vec![16, 16].try_into()?).build(),
+        ))
+        .build(memory.clone(), "/0")?;
+        array.store_metadata()?;
+        let data = ndarray::ArrayD::from_shape_fn(ndarray::IxDyn(&[128, 128]), |idx| {
+            (idx[0] * 128 + idx[1]) as u16
+        });
+        array.store_array_subset_ndarray(&[0, 0], data)?;
+        let shard_bytes = 128 * 128 * 2;
+        let store = Arc::new(CountingStore {
+            store: memory,
+            chunk_reads: Default::default(),
+            bytes_read: Default::default(),
+        });
+
+        let mut options = crate::ReadOptions {
+            crop_size: 32,
+            fit: crate::Fit::Crop,
+            ..Default::default()
+        };
+        let partial = crate::read_slice(store.clone(), Some("/0"), &options)?;
+        let partial_bytes = store.bytes_read.swap(0, Ordering::Relaxed);
+        options.partial_decode = false;
+        let whole = crate::read_slice(store.clone(), Some("/0"), &options)?;
+        let whole_bytes = store.bytes_read.swap(0, Ordering::Relaxed);
+
+        assert_eq!(partial, whole);
+        assert_eq!(partial[[31, 31]], (31 * 128 + 31) as f32);
+        // 4 of 64 inner chunks plus the shard index
+        assert!(partial_bytes < shard_bytes / 8, "{}", partial_bytes);
+        assert!(whole_bytes >= shard_bytes, "{}", whole_bytes);
+
+        Ok(())
+    }
+}
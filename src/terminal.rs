@@ -0,0 +1,382 @@
+use crate::blocks::Palette;
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::fmt;
+use std::io::IsTerminal;
+
+/// Rows left free below the image for the caption and the prompt
+const RESERVED_ROWS: u16 = 4;
+
+/// Pixels of a terminal cell when the terminal does not report its pixel size,
+/// the width and height of a common monospace font
+const CELL_PIXELS: (u32, u32) = (10, 20);
+
+/// Size of the terminal window in cells, and in pixels if the terminal reports it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerminalSize {
+    pub columns: u16,
+    pub rows: u16,
+    /// Width and height in pixels
+    pub pixels: Option<(u32, u32)>,
+}
+
+/// The size of the terminal on stdout, `None` if stdout is not a terminal (e.g. piped)
+pub fn terminal_size() -> Option<TerminalSize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        // The window size of the terminal, and of its pixels where the emulator fills them in
+        let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        if result == 0 && size.ws_col > 0 && size.ws_row > 0 {
+            let pixels = (size.ws_xpixel > 0 && size.ws_ypixel > 0)
+                .then_some((size.ws_xpixel as u32, size.ws_ypixel as u32));
+            return Some(TerminalSize {
+                columns: size.ws_col,
+                rows: size.ws_row,
+                pixels,
+            });
+        }
+    }
+    None
+}
+
+/// Largest height and width in pixels to fill most of the terminal with an image,
+/// in the pixels of the terminal with a `graphics` protocol (estimated from the cells if
+/// it does not report them), or at 1 by 2 pixels per cell with block characters
+pub fn display_limits(size: &TerminalSize, graphics: bool) -> [u64; 2] {
+    let rows = size.rows.saturating_sub(RESERVED_ROWS).max(1) as u64;
+    let columns = size.columns as u64;
+    if !graphics {
+        return [2 * rows, columns];
+    }
+    let (width, height) = size.pixels.unwrap_or((
+        size.columns as u32 * CELL_PIXELS.0,
+        size.rows as u32 * CELL_PIXELS.1,
+    ));
+    let height = height as u64 * rows / size.rows as u64;
+    [height.max(1), (width as u64).max(1)]
+}
+
+/// Backend to print images with, as given to `--protocol`
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum Protocol {
+    /// The first supported of sixel, iTerm and kitty, as viuer picks them, or blocks
+    #[default]
+    Auto,
+    /// The kitty graphics protocol
+    Kitty,
+    /// The iTerm2 inline images protocol
+    Iterm,
+    /// Sixel graphics
+    Sixel,
+    /// Half-block characters in true or 256 colors, in any terminal
+    Blocks,
+}
+
+impl std::str::FromStr for Protocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "auto" => Ok(Self::Auto),
+            "kitty" => Ok(Self::Kitty),
+            "iterm" => Ok(Self::Iterm),
+            "sixel" => Ok(Self::Sixel),
+            "blocks" => Ok(Self::Blocks),
+            _ => anyhow::bail!(
+                "Unknown protocol '{}', use auto, kitty, iterm, sixel or blocks",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Auto => "auto",
+            Self::Kitty => "kitty",
+            Self::Iterm => "iterm",
+            Self::Sixel => "sixel",
+            Self::Blocks => "blocks",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Graphics protocols the terminal supports, as detected by viuer
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Support {
+    pub kitty: bool,
+    pub iterm: bool,
+    pub sixel: bool,
+}
+
+impl Support {
+    /// Query the terminal, which viuer does once per process
+    pub fn detect() -> Self {
+        Self {
+            kitty: viuer::get_kitty_support() != viuer::KittySupport::None,
+            iterm: viuer::is_iterm_supported(),
+            sixel: viuer::is_sixel_supported(),
+        }
+    }
+
+    /// Names of the supported protocols, e.g. `kitty, sixel`, or `none`
+    pub fn names(&self) -> String {
+        let names: Vec<&str> = [
+            (self.kitty, "kitty"),
+            (self.iterm, "iterm"),
+            (self.sixel, "sixel"),
+        ]
+        .into_iter()
+        .filter_map(|(supported, name)| supported.then_some(name))
+        .collect();
+        match names.is_empty() {
+            true => "none".to_string(),
+            false => names.join(", "),
+        }
+    }
+}
+
+impl Protocol {
+    /// The protocol images are printed with given the `support` of the terminal
+    pub fn chosen(self, support: &Support) -> Self {
+        match self {
+            Self::Auto if support.sixel => Self::Sixel,
+            Self::Auto if support.iterm => Self::Iterm,
+            Self::Auto if support.kitty => Self::Kitty,
+            Self::Auto => Self::Blocks,
+            protocol => protocol,
+        }
+    }
+
+    /// Fail if the protocol is forced but the terminal named `terminal` does not support it,
+    /// instead of printing escape sequences it would show as text
+    pub fn check(self, support: &Support, terminal: &str) -> Result<()> {
+        let supported = match self {
+            Self::Kitty => support.kitty,
+            Self::Iterm => support.iterm,
+            Self::Sixel => support.sixel,
+            Self::Auto | Self::Blocks => true,
+        };
+        if !supported {
+            anyhow::bail!(
+                "The terminal {} does not support the {} protocol (detected: {}), \
+                 use --protocol auto or blocks, or --diagnose-terminal for details",
+                terminal,
+                self,
+                support.names()
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether images are printed in pixels rather than block characters
+    pub fn is_graphics(self, support: &Support) -> bool {
+        self.chosen(support) != Self::Blocks
+    }
+
+    /// The viuer config printing with only this protocol, or any with `Auto`
+    pub fn viuer_config(self) -> viuer::Config {
+        let only = |protocol: Self| matches!(self, Self::Auto) || self == protocol;
+        viuer::Config {
+            use_kitty: only(Self::Kitty),
+            use_iterm: only(Self::Iterm),
+            use_sixel: only(Self::Sixel),
+            ..Default::default()
+        }
+    }
+}
+
+/// Self-contained escape sequence to print an image with, as given to `--emit`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    /// The iTerm2 inline image protocol, also understood by WezTerm and VS Code
+    InlineIterm,
+    Kitty,
+}
+
+impl std::str::FromStr for Emit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "inline-iterm" => Ok(Self::InlineIterm),
+            "kitty" => Ok(Self::Kitty),
+            _ => anyhow::bail!("Unknown escape sequence '{}', use inline-iterm or kitty", s),
+        }
+    }
+}
+
+/// Base64 bytes per chunk of a kitty graphics command, the most the protocol allows
+const KITTY_CHUNK: usize = 4096;
+
+impl Emit {
+    /// The escape sequence transmitting and displaying a PNG image at the cursor,
+    /// kitty's split in chunks and asking the terminal not to reply, ending with a newline
+    pub fn sequence(self, png: &[u8]) -> String {
+        let payload = BASE64.encode(png);
+        match self {
+            Self::InlineIterm => format!(
+                "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07\n",
+                png.len(),
+                payload
+            ),
+            Self::Kitty => {
+                let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+                let mut sequence = String::new();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = (i + 1 < chunks.len()) as u8;
+                    // Only the first chunk carries the keys of the image
+                    let keys = match i {
+                        0 => format!("a=T,f=100,q=2,m={}", more),
+                        _ => format!("m={}", more),
+                    };
+                    sequence.push_str(&format!(
+                        "\x1b_G{};{}\x1b\\",
+                        keys,
+                        String::from_utf8_lossy(chunk)
+                    ));
+                }
+                sequence.push('\n');
+                sequence
+            }
+        }
+    }
+}
+
+/// Name of the terminal emulator from `TERM_PROGRAM`, or the terminal type from `TERM`
+pub fn terminal_name() -> String {
+    ["TERM_PROGRAM", "TERM"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// What `--diagnose-terminal` prints: the terminal, its size, the protocols detected,
+/// the colors of block characters and the protocol images are printed with
+pub fn diagnose(protocol: Protocol) -> String {
+    let support = Support::detect();
+    let env = |name: &str| std::env::var(name).unwrap_or_default();
+    let mut lines = vec![format!(
+        "Terminal: {} (TERM={}, TERM_PROGRAM={}{})",
+        terminal_name(),
+        env("TERM"),
+        env("TERM_PROGRAM"),
+        match env("TMUX").is_empty() {
+            true => "",
+            false => ", inside tmux",
+        }
+    )];
+    lines.push(match terminal_size() {
+        Some(size) => {
+            let pixels = size
+                .pixels
+                .map_or("pixels not reported".to_string(), |(w, h)| {
+                    format!("{}x{} pixels", w, h)
+                });
+            format!("Size: {}x{} cells, {}", size.columns, size.rows, pixels)
+        }
+        None => "Size: stdout is not a terminal".to_string(),
+    });
+    lines.push(format!("Detected: {}", support.names()));
+    lines.push(match Palette::detect() {
+        Palette::TrueColor => format!("Colors: true color (COLORTERM={})", env("COLORTERM")),
+        Palette::Ansi256 => "Colors: 256, COLORTERM is not truecolor or 24bit".to_string(),
+    });
+    let used = protocol.chosen(&support);
+    lines.push(match protocol.check(&support, &terminal_name()) {
+        Ok(()) if protocol == Protocol::Auto => format!("Using: {} (auto)", used),
+        Ok(()) => format!("Using: {}", used),
+        Err(err) => format!("Using: none, {}", err),
+    });
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_limits() {
+        let ssh = TerminalSize {
+            columns: 80,
+            rows: 24,
+            pixels: None,
+        };
+        assert_eq!(display_limits(&ssh, false), [40, 80]);
+        assert_eq!(display_limits(&ssh, true), [400, 800]);
+        let kitty = TerminalSize {
+            columns: 320,
+            rows: 80,
+            pixels: Some((3840, 2000)),
+        };
+        assert_eq!(display_limits(&kitty, true), [1900, 3840]);
+        let tiny = TerminalSize {
+            columns: 10,
+            rows: 2,
+            pixels: None,
+        };
+        assert_eq!(display_limits(&tiny, false), [2, 10]);
+    }
+
+    #[test]
+    fn test_choose_protocol() -> Result<()> {
+        let support = Support {
+            kitty: true,
+            iterm: false,
+            sixel: true,
+        };
+        assert_eq!(Protocol::Auto.chosen(&support), Protocol::Sixel);
+        assert_eq!(
+            "kitty".parse::<Protocol>()?.chosen(&support),
+            Protocol::Kitty
+        );
+        assert_eq!(Protocol::Auto.chosen(&Support::default()), Protocol::Blocks);
+        assert!(!Protocol::Blocks.is_graphics(&support));
+        assert_eq!(support.names(), "kitty, sixel");
+        Protocol::Kitty.check(&support, "xterm")?;
+        Protocol::Blocks.check(&Support::default(), "xterm")?;
+        let error = Protocol::Iterm
+            .check(&support, "tmux")
+            .expect_err("no iTerm protocol");
+        assert!(error.to_string().starts_with(
+            "The terminal tmux does not support the iterm protocol (detected: kitty, sixel)"
+        ));
+        let config = Protocol::Sixel.viuer_config();
+        assert!(config.use_sixel && !config.use_kitty && !config.use_iterm);
+        let config = Protocol::Blocks.viuer_config();
+        assert!(!config.use_sixel && !config.use_kitty && !config.use_iterm);
+        assert!("png".parse::<Protocol>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_emit_sequence() -> Result<()> {
+        let iterm = "inline-iterm".parse::<Emit>()?.sequence(b"png");
+        assert_eq!(
+            iterm,
+            "\x1b]1337;File=inline=1;size=3;preserveAspectRatio=1:cG5n\x07\n"
+        );
+        assert_eq!(
+            Emit::Kitty.sequence(b"png"),
+            "\x1b_Ga=T,f=100,q=2,m=0;cG5n\x1b\\\n"
+        );
+        // 3 bytes per 4 base64 characters, split in two full chunks and the rest
+        let kitty = Emit::Kitty.sequence(&[0; 3 * 2048 + 3]);
+        let chunks: Vec<&str> = kitty.trim_end().split("\x1b\\").collect();
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks[0].starts_with("\x1b_Ga=T,f=100,q=2,m=1;AAAA"));
+        assert_eq!(chunks[0].len(), "\x1b_Ga=T,f=100,q=2,m=1;".len() + 4096);
+        assert!(chunks[1].starts_with("\x1b_Gm=1;"));
+        assert_eq!(chunks[2], "\x1b_Gm=0;AAAA");
+        assert_eq!(chunks[3], "");
+        assert!("sixel".parse::<Emit>().is_err());
+        Ok(())
+    }
+}
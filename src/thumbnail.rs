@@ -0,0 +1,120 @@
+use crate::join_path;
+use crate::snapshot::{self, StreamFormat};
+use anyhow::Result;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbImage};
+use std::sync::Arc;
+use zarrs::array::{ArrayBuilder, DataType, FillValue};
+use zarrs::storage::{ReadableWritableStorageTraits, StoreKey};
+
+/// Shrink a rendered image so that its longer edge is at most `max_edge` pixels
+pub fn thumbnail(image: &DynamicImage, max_edge: u32) -> RgbImage {
+    let (width, height) = (image.width(), image.height());
+    let scale = (max_edge as f64 / width.max(height).max(1) as f64).min(1.0);
+    let size = |length: u32| ((length as f64 * scale).round() as u32).max(1);
+    imageops::resize(
+        &image.to_rgb8(),
+        size(width),
+        size(height),
+        FilterType::Triangle,
+    )
+}
+
+/// Write a thumbnail into the image group at `group` under `name`, as a PNG file if the
+/// name ends with `.png`, or as a Zarr v3 array of Y, X and RGB otherwise, refusing to
+/// replace an existing one unless `force`; returns where it was written
+pub fn write_thumbnail<TStore: ReadableWritableStorageTraits + ?Sized + 'static>(
+    store: &Arc<TStore>,
+    group: &str,
+    name: &str,
+    image: &RgbImage,
+    source: &str,
+    force: bool,
+) -> Result<String> {
+    let name = name.trim_matches('/');
+    if name.is_empty() || name.split('/').any(|part| part == ".." || part == ".") {
+        anyhow::bail!(
+            "Invalid thumbnail path '{}', expected a name inside the image group",
+            name
+        );
+    }
+    let path = join_path(group, name);
+    let is_png = name.to_lowercase().ends_with(".png");
+    let key = match is_png {
+        true => path.trim_start_matches('/').to_string(),
+        false => join_path(&path, "zarr.json")
+            .trim_start_matches('/')
+            .to_string(),
+    };
+    if !force && store.get(&StoreKey::new(key.as_str())?)?.is_some() {
+        anyhow::bail!(
+            "A thumbnail already exists at {}, use --force to overwrite it",
+            path
+        );
+    }
+    if is_png {
+        let png = snapshot::encode(&DynamicImage::ImageRgb8(image.clone()), StreamFormat::Png)?;
+        store.set(&StoreKey::new(key.as_str())?, png.into())?;
+        return Ok(path);
+    }
+    // A v3 array would not be found among the children of a v2 group
+    let v2 = join_path(group, ".zgroup")
+        .trim_start_matches('/')
+        .to_string();
+    if store.get(&StoreKey::new(v2.as_str())?)?.is_some() {
+        anyhow::bail!(
+            "The image group {} is Zarr v2, write the thumbnail as a .png file instead",
+            group
+        );
+    }
+    let (width, height) = image.dimensions();
+    let shape = vec![height as u64, width as u64, 3];
+    let attributes = serde_json::json!({"thumbnail": {"source": source}});
+    let array = ArrayBuilder::new(shape.clone(), shape, DataType::UInt8, FillValue::from(0u8))
+        .dimension_names(Some(["y", "x", "c"]))
+        .attributes(attributes.as_object().cloned().unwrap_or_default())
+        .build(store.clone(), &path)?;
+    array.store_metadata()?;
+    array.store_chunk_elements(&[0, 0, 0], image.as_raw())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+    use zarrs::array::Array;
+    use zarrs::storage::store::MemoryStore;
+    use zarrs::storage::{ReadableStorageTraits, WritableStorageTraits};
+
+    #[test]
+    fn test_write_thumbnail() -> Result<()> {
+        let rendered = DynamicImage::ImageRgb8(RgbImage::from_pixel(1024, 512, Rgb([10, 20, 30])));
+        let image = thumbnail(&rendered, 256);
+        assert_eq!(image.dimensions(), (256, 128));
+        assert_eq!(thumbnail(&rendered, 4096).dimensions(), (1024, 512));
+
+        let store = Arc::new(MemoryStore::new());
+        let path = write_thumbnail(&store, "/", "thumbnail.png", &image, "/0", false)?;
+        assert_eq!(path, "/thumbnail.png");
+        let png = store
+            .get(&StoreKey::new("thumbnail.png")?)?
+            .expect("thumbnail.png");
+        assert_eq!(image::load_from_memory(&png)?.to_rgb8(), image);
+        let error =
+            write_thumbnail(&store, "/", "thumbnail.png", &image, "/0", false).expect_err("exists");
+        assert!(error.to_string().contains("use --force to overwrite it"));
+        write_thumbnail(&store, "/", "thumbnail.png", &image, "/0", true)?;
+
+        let path = write_thumbnail(&store, "/well/0", "thumbnail", &image, "/well/0/0", false)?;
+        let array = Array::open(store.clone(), &path)?;
+        assert_eq!(array.shape(), [128, 256, 3]);
+        let pixels = array.retrieve_chunk_elements::<u8>(&[0, 0, 0])?;
+        assert_eq!(&pixels[..3], &[10, 20, 30]);
+        assert_eq!(array.attributes()["thumbnail"]["source"], "/well/0/0");
+        assert!(write_thumbnail(&store, "/", "../up.png", &image, "/0", false).is_err());
+        store.set(&StoreKey::new("v2/.zgroup")?, b"{}".to_vec().into())?;
+        assert!(write_thumbnail(&store, "/v2", "thumbnail", &image, "/v2/0", false).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use crate::storagestats::format_bytes;
+use serde_json::Value;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// A stage of showing a slice, timed with `--timings`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    /// Opening the store and reading metadata documents
+    Metadata,
+    /// Reading chunk objects from the store, summed over concurrent requests
+    Retrieval,
+    /// Decoding subsets to f32, including the wait for their chunks
+    Decode,
+    Quantiles,
+    /// Mapping values to colors, apart from the quantiles computed on the way
+    Normalization,
+    /// Drawing the image in the terminal or writing it out
+    Rendering,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Self::Metadata,
+        Self::Retrieval,
+        Self::Decode,
+        Self::Quantiles,
+        Self::Normalization,
+        Self::Rendering,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Metadata => "metadata",
+            Self::Retrieval => "chunk retrieval",
+            Self::Decode => "decode",
+            Self::Quantiles => "quantiles",
+            Self::Normalization => "normalization",
+            Self::Rendering => "rendering",
+        }
+    }
+}
+
+struct Total {
+    nanos: AtomicU64,
+    bytes: AtomicU64,
+    calls: AtomicU64,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static TOTALS: [Total; 6] = [const {
+    Total {
+        nanos: AtomicU64::new(0),
+        bytes: AtomicU64::new(0),
+        calls: AtomicU64::new(0),
+    }
+}; 6];
+
+thread_local! {
+    /// Time spent in stages nested in the one running on this thread, not counted twice
+    static NESTED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// Measure the stages from now on
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Add `elapsed` and `bytes` to the totals of `stage`
+pub fn record(stage: Stage, elapsed: Duration, bytes: u64) {
+    if !enabled() {
+        return;
+    }
+    let total = &TOTALS[stage as usize];
+    total
+        .nanos
+        .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    total.bytes.fetch_add(bytes, Ordering::Relaxed);
+    total.calls.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Run `f` as part of `stage`, without the time of the stages timed within it on this thread
+pub fn time<T>(stage: Stage, f: impl FnOnce() -> T) -> T {
+    time_bytes(stage, f, |_| 0)
+}
+
+/// Like [`time`], counting the bytes of the result given by `bytes`
+pub fn time_bytes<T>(stage: Stage, f: impl FnOnce() -> T, bytes: impl FnOnce(&T) -> u64) -> T {
+    if !enabled() {
+        return f();
+    }
+    let outer = NESTED.with(|nested| nested.replace(Duration::ZERO));
+    let started = Instant::now();
+    let value = f();
+    let elapsed = started.elapsed();
+    let inner = NESTED.with(|nested| nested.replace(outer + elapsed));
+    record(stage, elapsed.saturating_sub(inner), bytes(&value));
+    value
+}
+
+/// Time, bytes and number of calls of each stage so far
+pub fn totals() -> Vec<(Stage, Duration, u64, u64)> {
+    Stage::ALL
+        .iter()
+        .map(|&stage| {
+            let total = &TOTALS[stage as usize];
+            (
+                stage,
+                Duration::from_nanos(total.nanos.load(Ordering::Relaxed)),
+                total.bytes.load(Ordering::Relaxed),
+                total.calls.load(Ordering::Relaxed),
+            )
+        })
+        .collect()
+}
+
+/// A table with the time and bytes of each stage
+pub fn format_report() -> String {
+    let mut lines = vec![format!(
+        "{:<16}{:>12}{:>12}{:>8}",
+        "Stage", "Time", "Bytes", "Calls"
+    )];
+    for (stage, elapsed, bytes, calls) in totals() {
+        let bytes = match bytes {
+            0 => "-".to_string(),
+            bytes => format_bytes(bytes),
+        };
+        lines.push(format!(
+            "{:<16}{:>12}{:>12}{:>8}",
+            stage.name(),
+            format!("{:.1} ms", elapsed.as_secs_f64() * 1e3),
+            bytes,
+            calls
+        ));
+    }
+    lines.join("\n")
+}
+
+/// JSON array of the stages with their time in seconds, bytes and calls
+pub fn to_json() -> Value {
+    totals()
+        .into_iter()
+        .map(|(stage, elapsed, bytes, calls)| {
+            serde_json::json!({
+                "stage": stage.name(),
+                "seconds": elapsed.as_secs_f64(),
+                "bytes": bytes,
+                "calls": calls,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_stages() {
+        enable();
+        let before = totals();
+        let value = time(Stage::Normalization, || {
+            time_bytes(
+                Stage::Quantiles,
+                || {
+                    std::thread::sleep(Duration::from_millis(20));
+                    7
+                },
+                |_| 100,
+            )
+        });
+        assert_eq!(value, 7);
+        let after = totals();
+        let spent = |stage: Stage| {
+            let i = stage as usize;
+            (after[i].1 - before[i].1, after[i].2 - before[i].2)
+        };
+        // The quantiles are not counted again in the normalization around them
+        let (quantiles, bytes) = spent(Stage::Quantiles);
+        assert!(quantiles >= Duration::from_millis(20));
+        assert!(bytes >= 100);
+        assert!(spent(Stage::Normalization).0 < Duration::from_millis(20));
+        assert!(format_report().contains("quantiles"));
+        assert_eq!(to_json()[3]["stage"], "quantiles");
+    }
+}
@@ -0,0 +1,406 @@
+use crate::{join_path, ngff, omero, open_array, resolve_image_path, ReadOptions};
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::sync::Arc;
+use zarrs::storage::{ListableStorageTraits, ReadableStorageTraits};
+
+/// OME-NGFF versions with a multiscales specification
+const KNOWN_VERSIONS: [&str; 5] = ["0.1", "0.2", "0.3", "0.4", "0.5"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Metadata readers may tolerate, but that is likely a mistake
+    Warning,
+    /// Metadata that does not match the arrays or the specification
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in the OME-NGFF metadata of an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    /// JSON object with the severity and message
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({"severity": self.severity.to_string(), "message": self.message})
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Findings collected while checking the metadata
+#[derive(Default)]
+struct Findings(Vec<Finding>);
+
+impl Findings {
+    fn error(&mut self, message: String) {
+        self.0.push(Finding {
+            severity: Severity::Error,
+            message,
+        });
+    }
+
+    fn warning(&mut self, message: String) {
+        self.0.push(Finding {
+            severity: Severity::Warning,
+            message,
+        });
+    }
+}
+
+/// Check the lengths and values of `coordinateTransformations` against the dimensionality
+fn check_transformations(
+    findings: &mut Findings,
+    transformations: &Value,
+    ndims: usize,
+    context: &str,
+) {
+    let Some(transformations) = transformations.as_array() else {
+        findings.error(format!(
+            "{}: coordinateTransformations is not a list",
+            context
+        ));
+        return;
+    };
+    for transformation in transformations {
+        let kind = transformation.get("type").and_then(Value::as_str);
+        let Some(kind @ ("scale" | "translation")) = kind else {
+            if kind != Some("identity") {
+                findings.warning(format!(
+                    "{}: unknown coordinate transformation type {}",
+                    context,
+                    transformation.get("type").unwrap_or(&Value::Null)
+                ));
+            }
+            continue;
+        };
+        let Some(values) = transformation.get(kind).and_then(Value::as_array) else {
+            // A transformation may refer to a file of values instead
+            if transformation.get("path").is_none() {
+                findings.error(format!("{}: {} has no values", context, kind));
+            }
+            continue;
+        };
+        if values.len() != ndims {
+            findings.error(format!(
+                "{}: {} has {} values for {} dimensions",
+                context,
+                kind,
+                values.len(),
+                ndims
+            ));
+        }
+        if values.iter().any(|value| !value.is_number()) {
+            findings.error(format!("{}: {} has non-numeric values", context, kind));
+        } else if kind == "scale"
+            && values
+                .iter()
+                .any(|value| value.as_f64().is_some_and(|value| value <= 0.0))
+        {
+            findings.error(format!("{}: scale has non-positive values", context));
+        }
+    }
+}
+
+/// Check the version field, which moved from each multiscale to the `ome` namespace in 0.5
+fn check_version(findings: &mut Findings, attributes: &Map<String, Value>, multiscale: &Value) {
+    let namespaced = attributes.get("ome").and_then(Value::as_object);
+    let version = match namespaced {
+        Some(ome) => ome.get("version").or_else(|| multiscale.get("version")),
+        None => multiscale.get("version"),
+    };
+    match version {
+        None => findings.warning("No OME-NGFF version in the metadata".to_string()),
+        Some(Value::String(version)) if !KNOWN_VERSIONS.contains(&version.as_str()) => {
+            findings.warning(format!("Unknown OME-NGFF version '{}'", version))
+        }
+        Some(Value::String(version)) if version == "0.5" && namespaced.is_none() => {
+            findings.error("Version 0.5 metadata must be under the 'ome' attribute".to_string())
+        }
+        Some(Value::String(version)) if version != "0.5" && namespaced.is_some() => findings
+            .warning(format!(
+                "Version {} metadata is under the 'ome' attribute, which was added in 0.5",
+                version
+            )),
+        Some(Value::String(_)) => {}
+        Some(version) => findings.error(format!("Version {} is not a string", version)),
+    }
+}
+
+/// Check the axes list, returning the number of axes if it is well formed
+fn check_axes(findings: &mut Findings, multiscale: &Value) -> Option<usize> {
+    let Some(axes) = multiscale.get("axes") else {
+        findings.warning("No axes in the multiscales metadata".to_string());
+        return None;
+    };
+    let Some(axes) = axes.as_array() else {
+        findings.error("Axes are not a list".to_string());
+        return None;
+    };
+    let names: Vec<Option<&str>> = axes
+        .iter()
+        .map(|axis| match axis {
+            Value::String(name) => Some(name.as_str()),
+            axis => axis.get("name")?.as_str(),
+        })
+        .collect();
+    if names.iter().any(Option::is_none) {
+        findings.error("An axis has no name".to_string());
+    }
+    let mut unique: Vec<_> = names.iter().flatten().collect();
+    unique.sort();
+    unique.dedup();
+    if unique.len() < names.iter().flatten().count() {
+        findings.error("Axis names are not unique".to_string());
+    }
+    let spatial = axes
+        .iter()
+        .filter(|axis| axis.get("type").and_then(Value::as_str) == Some("space"))
+        .count();
+    let typed = axes.iter().any(|axis| axis.get("type").is_some());
+    if typed && !(2..=3).contains(&spatial) {
+        findings.error(format!("{} space axes, expected 2 or 3", spatial));
+    }
+    for axis in axes {
+        if let Some(unit) = axis.get("unit").and_then(Value::as_str) {
+            if ngff::parse_unit(unit).is_none() {
+                findings.warning(format!(
+                    "Axis '{}' has unknown unit '{}'",
+                    axis.get("name").and_then(Value::as_str).unwrap_or("?"),
+                    unit
+                ));
+            }
+        }
+    }
+    Some(axes.len())
+}
+
+/// Check the OME-NGFF metadata of the image that would be read against its arrays:
+/// version, axes, resolution levels, coordinate transformations and omero channels
+pub fn validate<TStore>(store: Arc<TStore>, options: &ReadOptions) -> Result<Vec<Finding>>
+where
+    TStore: ReadableStorageTraits + ListableStorageTraits + ?Sized + 'static,
+{
+    let store = Arc::new(crate::store::ConsolidatedStore::new(
+        store,
+        options.consolidated,
+    ));
+    let group = resolve_image_path(&store, options)?;
+    let mut findings = Findings::default();
+    let Some(attributes) = ngff::group_attributes(&store, &group)? else {
+        findings.error(format!("No group metadata at {}", group));
+        return Ok(findings.0);
+    };
+    let ome = ngff::ome_attributes(&attributes);
+    let Some(multiscale) = ome
+        .get("multiscales")
+        .and_then(Value::as_array)
+        .and_then(|multiscales| multiscales.first())
+    else {
+        findings.error(format!("No multiscales metadata in group {}", group));
+        return Ok(findings.0);
+    };
+    check_version(&mut findings, &attributes, multiscale);
+    let naxes = check_axes(&mut findings, multiscale);
+    if let Some(transformations) = multiscale.get("coordinateTransformations") {
+        if let Some(ndims) = naxes {
+            check_transformations(&mut findings, transformations, ndims, "multiscales");
+        }
+    }
+
+    let datasets = multiscale
+        .get("datasets")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    if datasets.is_empty() {
+        findings.error("No datasets in the multiscales metadata".to_string());
+    }
+    let mut shapes = vec![];
+    for (level, dataset) in datasets.iter().enumerate() {
+        let Some(path) = dataset.get("path").and_then(Value::as_str) else {
+            findings.error(format!("Dataset {} has no path", level));
+            continue;
+        };
+        let context = format!("Dataset '{}'", path);
+        let array = match open_array(store.clone(), &join_path(&group, path)) {
+            Ok(array) => array,
+            Err(_) => {
+                findings.error(format!(
+                    "{}: no array at {}",
+                    context,
+                    join_path(&group, path)
+                ));
+                continue;
+            }
+        };
+        let shape = array.shape().to_vec();
+        if let Some(naxes) = naxes.filter(|&naxes| naxes != shape.len()) {
+            findings.error(format!(
+                "{}: {} axes in the metadata but the array has {} dimensions",
+                context,
+                naxes,
+                shape.len()
+            ));
+        }
+        match dataset.get("coordinateTransformations") {
+            Some(transformations) => {
+                check_transformations(&mut findings, transformations, shape.len(), &context)
+            }
+            None => findings.warning(format!("{}: no coordinateTransformations", context)),
+        }
+        if let Some(finer) = shapes.last() {
+            if shape.iter().zip(finer).any(|(size, finer)| size > finer) {
+                findings.warning(format!(
+                    "{}: larger than the previous level in some dimension",
+                    context
+                ));
+            }
+        }
+        shapes.push(shape);
+    }
+
+    if ome.contains_key("omero") {
+        let channels = omero::omero_channels(&attributes).len();
+        let axes = ngff::axis_names(&attributes).unwrap_or_default();
+        let size = ngff::channel_axis(&attributes, &axes)
+            .zip(shapes.first())
+            .and_then(|(axis, shape)| shape.get(axis));
+        match size {
+            Some(&size) if size != channels as u64 => findings.error(format!(
+                "{} omero channels but the channel axis has size {}",
+                channels, size
+            )),
+            None if channels > 1 => findings.warning(format!(
+                "{} omero channels but the image has no channel axis",
+                channels
+            )),
+            _ => {}
+        }
+    }
+    Ok(findings.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_group;
+    use zarrs::array::{ArrayBuilder, DataType, FillValue};
+    use zarrs::storage::store::MemoryStore;
+
+    fn image(attributes: Value) -> Result<Arc<MemoryStore>> {
+        let store = Arc::new(MemoryStore::new());
+        store_group(&store, "/", attributes)?;
+        for (path, size) in [("/0", 64), ("/1", 32)] {
+            ArrayBuilder::new(
+                vec![2, size, size],
+                vec![1, 32, 32],
+                DataType::UInt8,
+                FillValue::from(0u8),
+            )
+            .build(store.clone(), path)?
+            .store_metadata()?;
+        }
+        Ok(store)
+    }
+
+    fn messages(findings: &[Finding], severity: Severity) -> Vec<&str> {
+        findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .map(|finding| finding.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_valid_image() -> Result<()> {
+        let scale = |s: f64| serde_json::json!([{"type": "scale", "scale": [1.0, s, s]}]);
+        let store = image(serde_json::json!({"multiscales": [{
+            "version": "0.4",
+            "axes": [
+                {"name": "c", "type": "channel"},
+                {"name": "y", "type": "space", "unit": "micrometer"},
+                {"name": "x", "type": "space", "unit": "micrometer"}
+            ],
+            "datasets": [
+                {"path": "0", "coordinateTransformations": scale(0.5)},
+                {"path": "1", "coordinateTransformations": scale(1.0)}
+            ]
+        }], "omero": {"channels": [{"label": "DAPI"}, {"label": "GFP"}]}}))?;
+        assert_eq!(validate(store, &ReadOptions::default())?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_broken_image() -> Result<()> {
+        let store = image(serde_json::json!({"multiscales": [{
+            "version": "0.7",
+            "axes": [{"name": "y", "type": "space"}, {"name": "x", "type": "space"}],
+            "datasets": [
+                {"path": "0", "coordinateTransformations": [{"type": "scale", "scale": [1.0, 0.5]}]},
+                {"path": "1"},
+                {"path": "2"}
+            ]
+        }], "omero": {"channels": [{}, {}, {}]}}))?;
+        let findings = validate(store, &ReadOptions::default())?;
+        assert_eq!(
+            messages(&findings, Severity::Error),
+            vec![
+                "Dataset '0': 2 axes in the metadata but the array has 3 dimensions",
+                "Dataset '0': scale has 2 values for 3 dimensions",
+                "Dataset '1': 2 axes in the metadata but the array has 3 dimensions",
+                "Dataset '2': no array at /2",
+            ]
+        );
+        assert_eq!(
+            messages(&findings, Severity::Warning),
+            vec![
+                "Unknown OME-NGFF version '0.7'",
+                "Dataset '1': no coordinateTransformations",
+                "3 omero channels but the image has no channel axis",
+            ]
+        );
+        assert_eq!(
+            findings[0].to_string(),
+            "warning: Unknown OME-NGFF version '0.7'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_omero_channel_count_and_version() -> Result<()> {
+        let store = image(
+            serde_json::json!({"ome": {"version": "0.5", "multiscales": [{
+            "axes": [{"name": "c"}, {"name": "y"}, {"name": "x"}],
+            "datasets": [{"path": "0", "coordinateTransformations": [{"type": "scale", "scale": [1, 1, 1]}]}]
+        }], "omero": {"channels": [{}]}}}),
+        )?;
+        let findings = validate(store, &ReadOptions::default())?;
+        assert_eq!(
+            messages(&findings, Severity::Error),
+            vec!["1 omero channels but the channel axis has size 2"]
+        );
+        let store = image(serde_json::json!({"multiscales": [{"version": "0.5"}]}))?;
+        let findings = validate(store, &ReadOptions::default())?;
+        assert!(messages(&findings, Severity::Error)
+            .contains(&"Version 0.5 metadata must be under the 'ome' attribute"));
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much is printed to stderr besides errors, warnings and requested reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Nothing but warnings, with `-q`
+    Quiet,
+    /// What is read and how it is shown, by default
+    Normal,
+    /// Also the store opened and the metadata documents read, with `-v`
+    Verbose,
+    /// Also every chunk read, with `-vv`
+    Debug,
+}
+
+impl Verbosity {
+    /// The verbosity of `-q` or the number of `-v`
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Self::Quiet,
+            (false, 0) => Self::Normal,
+            (false, 1) => Self::Verbose,
+            (false, _) => Self::Debug,
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Print the messages up to `verbosity` from now on
+pub fn set(verbosity: Verbosity) {
+    LEVEL.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// Whether messages at `verbosity` are printed
+pub fn enabled(verbosity: Verbosity) -> bool {
+    verbosity as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Print an informational message to stderr, unless `-q`
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::verbosity::enabled($crate::verbosity::Verbosity::Normal) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Print a message about the store to stderr with `-v`
+#[macro_export]
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if $crate::verbosity::enabled($crate::verbosity::Verbosity::Verbose) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Print a message about a chunk to stderr with `-vv`
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::verbosity::enabled($crate::verbosity::Verbosity::Debug) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Print a warning to stderr, even with `-q`
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        eprintln!($($arg)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_flags() {
+        assert_eq!(Verbosity::from_flags(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(true, 2), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, 1), Verbosity::Verbose);
+        assert_eq!(Verbosity::from_flags(false, 3), Verbosity::Debug);
+        assert!(Verbosity::Quiet < Verbosity::Normal);
+    }
+}